@@ -0,0 +1,27 @@
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum AirdropError {
+    #[error("Failed to read recipients file '{0}': {1}")]
+    FailedToReadRecipients(String, String),
+
+    #[error("Found {0} malformed row(s) in the recipients file, fix them or use --skip-bad-rows to airdrop to the valid rows only.")]
+    MalformedRows(usize),
+
+    #[error("No recipients found in the recipients file '{0}'.")]
+    NoRecipients(String),
+
+    #[error("{0} item(s) available, the airdrop requires {1}.")]
+    NotEnoughItems(u64, u64),
+
+    #[error("Airdropped {0} of {1} NFT(s), see '{2}' for the result of each recipient. Re-run the airdrop to retry the failed mints.")]
+    Incomplete(u64, u64, String),
+
+    #[error("Failed to read result file '{0}': {1}")]
+    FailedToReadResults(String, String),
+
+    #[error(
+        "Invalid row at line {1} of result file '{0}': fix or remove the file to airdrop again."
+    )]
+    InvalidResults(String, usize),
+}
@@ -0,0 +1,418 @@
+use anchor_client::solana_sdk::{pubkey::Pubkey, signature::Signature};
+use anyhow::Result;
+use console::style;
+use std::{
+    collections::HashMap,
+    fs::{self, File, OpenOptions},
+    io::Write,
+    path::Path,
+    str::FromStr,
+    sync::Arc,
+};
+
+use crate::airdrop::AirdropError;
+use crate::cache::load_cache;
+use crate::candy_machine::get_candy_machine_state;
+use crate::common::*;
+use crate::config::data::parse_pubkey;
+use crate::mint::mint;
+
+pub struct AirdropArgs {
+    pub keypair: Option<String>,
+    pub rpc_url: Option<String>,
+    pub cache: String,
+    pub candy_machine: Option<String>,
+    pub recipients: String,
+    pub output: String,
+    pub skip_bad_rows: bool,
+    pub priority_fee: Option<u64>,
+}
+
+/// A row of the recipients file.
+#[derive(Debug)]
+pub struct Recipient {
+    /// Line number of the row in the recipients file.
+    pub line: usize,
+    pub address: Pubkey,
+    pub amount: u64,
+}
+
+/// Status of an airdropped NFT that was minted.
+const MINTED: &str = "minted";
+
+/// Result of an airdropped NFT, written as a row of the result file.
+#[derive(Debug, PartialEq)]
+struct AirdropResult {
+    address: Pubkey,
+    mint: Option<Pubkey>,
+    signature: Option<Signature>,
+    status: &'static str,
+}
+
+pub fn process_airdrop(args: AirdropArgs) -> Result<()> {
+    println!(
+        "{} {}Reading recipients",
+        style("[1/2]").bold().dim(),
+        LOOKING_GLASS_EMOJI
+    );
+
+    let content = fs::read_to_string(&args.recipients).map_err(|err| {
+        AirdropError::FailedToReadRecipients(args.recipients.clone(), err.to_string())
+    })?;
+    let (recipients, bad_rows) = parse_recipients(&content);
+
+    if !bad_rows.is_empty() {
+        for (line, reason) in &bad_rows {
+            warn!("Malformed row at line {}: {}", line, reason);
+            println!("{}line {}: {}", style("=> ").dim(), line, reason);
+        }
+
+        if !args.skip_bad_rows {
+            return Err(AirdropError::MalformedRows(bad_rows.len()).into());
+        }
+
+        println!(
+            "{} skipping {} malformed row(s)",
+            style("WARNING:").yellow().bold(),
+            bad_rows.len()
+        );
+    }
+
+    if recipients.is_empty() {
+        return Err(AirdropError::NoRecipients(args.recipients).into());
+    }
+
+    let total: u64 = recipients.iter().map(|r| r.amount).sum();
+    println!(
+        "Airdropping {} NFT(s) to {} recipient(s)",
+        total,
+        recipients.len()
+    );
+
+    // the NFTs minted by a previous run are not sent again
+    let previous = if Path::new(&args.output).exists() {
+        load_results(&args.output)?
+    } else {
+        Vec::new()
+    };
+    let mut pending = pending_amounts(&recipients, &previous);
+    let remaining: u64 = pending.values().sum();
+    let already_minted = total - remaining;
+
+    if already_minted > 0 {
+        println!(
+            "Skipping {} NFT(s) already airdropped (see '{}')",
+            already_minted, args.output
+        );
+    }
+
+    let sugar_config = sugar_setup(args.keypair, args.rpc_url)?;
+    let client = Arc::new(setup_client(&sugar_config, None)?);
+
+    // the candy machine id specified takes precedence over the one from the cache
+
+    let candy_machine_id = match args.candy_machine {
+        Some(candy_machine_id) => candy_machine_id,
+        None => {
+            let cache = load_cache(&args.cache, false)?;
+            cache.program.candy_machine
+        }
+    };
+
+    let candy_pubkey = match Pubkey::from_str(&candy_machine_id) {
+        Ok(candy_pubkey) => candy_pubkey,
+        Err(_) => {
+            let error = anyhow!("Failed to parse candy machine id: {}", candy_machine_id);
+            error!("{:?}", error);
+            return Err(error);
+        }
+    };
+
+    let candy_machine_state = Arc::new(get_candy_machine_state(&sugar_config, &candy_pubkey)?);
+    let available = candy_machine_state.data.items_available - candy_machine_state.items_redeemed;
+
+    if remaining > available {
+        return Err(AirdropError::NotEnoughItems(available, remaining).into());
+    }
+
+    println!(
+        "\n{} {}Minting to recipients",
+        style("[2/2]").bold().dim(),
+        CANDY_EMOJI
+    );
+    println!("Candy machine ID: {}", candy_pubkey);
+
+    let priority_fee = args.priority_fee.unwrap_or(0);
+    let pb = task_progress("airdrop", remaining);
+    // each result is written as soon as it is known, so an interrupted run keeps the
+    // record of the NFTs already minted
+    let mut output = create_results(&args.output, &previous)?;
+    let mut minted = already_minted;
+
+    for recipient in &recipients {
+        let amount = pending.get_mut(&recipient.address).map_or(0, |pending| {
+            let amount = (*pending).min(recipient.amount);
+            *pending -= amount;
+            amount
+        });
+
+        for _i in 0..amount {
+            // the NFT is minted to the associated token account of the recipient;
+            // 'mint' retries the transient errors (without minting a second NFT when
+            // a transaction lands unconfirmed), so a failed mint is reported and the
+            // airdrop continues with the next one
+            let result = match mint(
+                Arc::clone(&client),
+                candy_pubkey,
+                Arc::clone(&candy_machine_state),
+                recipient.address,
+                priority_fee,
+            ) {
+                Ok((nft_mint, signature)) => {
+                    info!(
+                        recipient = %recipient.address,
+                        mint = %nft_mint,
+                        signature = %signature,
+                        "Airdropped NFT"
+                    );
+                    minted += 1;
+                    pb.inc(1);
+                    AirdropResult {
+                        address: recipient.address,
+                        mint: Some(nft_mint),
+                        signature: Some(signature),
+                        status: MINTED,
+                    }
+                }
+                Err(err) => {
+                    error!(
+                        "Failed to airdrop to {} (line {}): {:?}",
+                        recipient.address, recipient.line, err
+                    );
+                    println!(
+                        "{}failed to airdrop to {} (line {}): {}",
+                        style("=> ").dim(),
+                        recipient.address,
+                        recipient.line,
+                        err
+                    );
+                    AirdropResult {
+                        address: recipient.address,
+                        mint: None,
+                        signature: None,
+                        status: "failed",
+                    }
+                }
+            };
+
+            write_result(&mut output, &result)?;
+        }
+    }
+
+    if minted == total {
+        pb.finish_with_message(format!("{}", style("Airdrop successful ").green().bold()));
+    } else {
+        pb.abandon_with_message(format!("{}", style("Airdrop failed ").red().bold()));
+    }
+
+    println!("\nResult of each recipient saved to '{}'.", args.output);
+
+    if minted != total {
+        return Err(AirdropError::Incomplete(minted, total, args.output).into());
+    }
+
+    Ok(())
+}
+
+/// Parse the `address,amount` rows of the recipients file, returning the valid rows and
+/// the line number and reason of each malformed row. Empty lines and a header row are
+/// ignored.
+pub fn parse_recipients(content: &str) -> (Vec<Recipient>, Vec<(usize, String)>) {
+    let mut recipients = Vec::new();
+    let mut bad_rows = Vec::new();
+
+    for (index, row) in content.lines().enumerate() {
+        let line = index + 1;
+        let row = row.trim();
+
+        if row.is_empty() {
+            continue;
+        }
+
+        let columns: Vec<&str> = row.split(',').map(str::trim).collect();
+
+        if index == 0 && columns[0].eq_ignore_ascii_case("address") {
+            continue;
+        }
+
+        if columns.len() != 2 {
+            bad_rows.push((
+                line,
+                format!(
+                    "expected 2 columns (address,amount), found {}",
+                    columns.len()
+                ),
+            ));
+            continue;
+        }
+
+        let address = match parse_pubkey("address", columns[0]) {
+            Ok(address) => address,
+            Err(err) => {
+                bad_rows.push((line, err.to_string()));
+                continue;
+            }
+        };
+
+        match columns[1].parse::<u64>() {
+            Ok(amount) if amount > 0 => recipients.push(Recipient {
+                line,
+                address,
+                amount,
+            }),
+            _ => bad_rows.push((
+                line,
+                format!(
+                    "invalid amount '{}': the amount must be a positive integer",
+                    columns[1]
+                ),
+            )),
+        }
+    }
+
+    (recipients, bad_rows)
+}
+
+/// Return the number of NFTs still to airdrop to each address, not counting the NFTs
+/// already minted to it.
+fn pending_amounts(recipients: &[Recipient], minted: &[AirdropResult]) -> HashMap<Pubkey, u64> {
+    let mut pending: HashMap<Pubkey, u64> = HashMap::new();
+
+    for recipient in recipients {
+        *pending.entry(recipient.address).or_default() += recipient.amount;
+    }
+
+    for result in minted {
+        if let Some(amount) = pending.get_mut(&result.address) {
+            *amount = amount.saturating_sub(1);
+        }
+    }
+
+    pending
+}
+
+/// Load the NFTs minted by a previous run from its result file, ignoring the rows of
+/// the NFTs that were not minted.
+fn load_results(path: &str) -> Result<Vec<AirdropResult>> {
+    let invalid = |line: usize| AirdropError::InvalidResults(path.to_string(), line);
+    let content = fs::read_to_string(path)
+        .map_err(|err| AirdropError::FailedToReadResults(path.to_string(), err.to_string()))?;
+    let mut results = Vec::new();
+
+    for (index, row) in content.lines().enumerate().skip(1) {
+        let columns: Vec<&str> = row.trim().split(',').collect();
+
+        match columns.as_slice() {
+            [address, mint, signature, status] if *status == MINTED => {
+                results.push(AirdropResult {
+                    address: Pubkey::from_str(address).map_err(|_| invalid(index + 1))?,
+                    mint: Some(Pubkey::from_str(mint).map_err(|_| invalid(index + 1))?),
+                    signature: Some(
+                        Signature::from_str(signature).map_err(|_| invalid(index + 1))?,
+                    ),
+                    status: MINTED,
+                })
+            }
+            [_, _, _, _] => (),
+            [""] => (),
+            _ => return Err(invalid(index + 1).into()),
+        }
+    }
+
+    Ok(results)
+}
+
+/// Create the result file, with the NFTs minted by a previous run.
+fn create_results(path: &str, previous: &[AirdropResult]) -> Result<File> {
+    fs::write(path, "address,mint,signature,status\n")?;
+    let mut file = OpenOptions::new().append(true).open(path)?;
+
+    for result in previous {
+        write_result(&mut file, result)?;
+    }
+
+    Ok(file)
+}
+
+/// Append the `address,mint,signature,status` result of an airdropped NFT.
+fn write_result(file: &mut File, result: &AirdropResult) -> Result<()> {
+    writeln!(
+        file,
+        "{},{},{},{}",
+        result.address,
+        result.mint.map(|m| m.to_string()).unwrap_or_default(),
+        result.signature.map(|s| s.to_string()).unwrap_or_default(),
+        result.status
+    )?;
+    file.sync_data()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    fn minted(address: Pubkey) -> AirdropResult {
+        AirdropResult {
+            address,
+            mint: Some(Pubkey::new_unique()),
+            signature: Some(Signature::new_unique()),
+            status: MINTED,
+        }
+    }
+
+    #[test]
+    fn resume_from_previous_results() {
+        let path = env::temp_dir().join(format!("sugar-airdrop-{}.csv", std::process::id()));
+        let path = path.to_str().unwrap();
+        let first = Pubkey::new_unique();
+        let second = Pubkey::new_unique();
+
+        let previous = vec![minted(first), minted(first), minted(second)];
+        let mut file = create_results(path, &previous).unwrap();
+        write_result(
+            &mut file,
+            &AirdropResult {
+                address: second,
+                mint: None,
+                signature: None,
+                status: "failed",
+            },
+        )
+        .unwrap();
+
+        // the failed mint is airdropped again
+        let loaded = load_results(path).unwrap();
+        assert_eq!(loaded, previous);
+
+        let recipients = vec![
+            Recipient {
+                line: 1,
+                address: first,
+                amount: 2,
+            },
+            Recipient {
+                line: 2,
+                address: second,
+                amount: 3,
+            },
+        ];
+        let pending = pending_amounts(&recipients, &loaded);
+        assert_eq!(pending[&first], 0);
+        assert_eq!(pending[&second], 2);
+
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -1,17 +1,40 @@
 use anchor_client::solana_sdk::pubkey::Pubkey;
 use anyhow::Result;
+use data_encoding::HEXLOWER;
 use serde::{Deserialize, Serialize};
-use std::{fs, io::Write, path::Path};
+use serde_json::{Map, Value};
+use std::{collections::BTreeMap, fs, io::Write, path::Path};
 
 use mpl_candy_machine::ConfigLine;
 
 use crate::common::*;
+use crate::config::ContentEncoding;
 use crate::mint::pdas::get_candy_machine_creator_pda;
 
+/// Version of the cache file format, written on every save.
+///
+/// Cache files without a version are version 0.
+pub const CACHE_VERSION: u32 = 1;
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct Cache {
+    #[serde(default)]
+    pub version: u32,
     pub program: CacheProgram,
     pub items: CacheItems,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub checkpoint: Option<DeployCheckpoint>,
+    /// Links of the uploaded media files by content hash, so identical media files are
+    /// only uploaded once.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub media_links: BTreeMap<String, String>,
+    /// Content hash of the config of the last deploy, used to detect config changes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub config_hash: Option<String>,
+    /// Timestamps of the relative start dates of the schedule (e.g., "+1d"), resolved
+    /// once so the schedule does not move between runs.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub schedule_dates: BTreeMap<String, i64>,
     #[serde(skip_deserializing, skip_serializing)]
     pub file_path: String,
 }
@@ -19,16 +42,109 @@ pub struct Cache {
 impl Cache {
     pub fn new() -> Self {
         Cache {
+            version: CACHE_VERSION,
             program: CacheProgram::new(),
             items: CacheItems::new(),
+            checkpoint: None,
+            media_links: BTreeMap::new(),
+            config_hash: None,
+            schedule_dates: BTreeMap::new(),
             file_path: String::new(),
         }
     }
 
+    /// Load the cache file, migrating files of older versions to the current format.
+    /// When the file does not exist, a new cache is created if `create` is `true`.
+    pub fn load(cache_file_path: &str, create: bool) -> Result<Cache> {
+        let cache_file_path = Path::new(cache_file_path);
+        if !cache_file_path.exists() {
+            if create {
+                // if the cache file does not exist, creates a new Cache object
+                let mut cache = Cache::new();
+                cache.file_path = path_to_string(cache_file_path)?;
+                Ok(cache)
+            } else {
+                let cache_file_string = path_to_string(cache_file_path)?;
+                let error = CacheError::CacheFileNotFound(cache_file_string).into();
+                error!("{:?}", error);
+                Err(error)
+            }
+        } else {
+            info!("Cache exists, loading...");
+            let file = match File::open(cache_file_path) {
+                Ok(file) => file,
+                Err(err) => {
+                    let cache_file_string = path_to_string(cache_file_path)?;
+                    let error =
+                        CacheError::FailedToOpenCacheFile(cache_file_string, err.to_string())
+                            .into();
+                    error!("{:?}", error);
+                    return Err(error);
+                }
+            };
+
+            let mut cache = match serde_json::from_reader(file) {
+                Ok(value) => Cache::from_value(value)?,
+                Err(err) => {
+                    let error = CacheError::CacheFileWrongFormat(err.to_string()).into();
+                    error!("{:?}", error);
+                    return Err(error);
+                }
+            };
+            cache.file_path = path_to_string(cache_file_path)?;
+
+            Ok(cache)
+        }
+    }
+
+    /// Create the cache from its JSON value, migrating older versions of the format.
+    pub fn from_value(mut value: Value) -> Result<Cache> {
+        let version = value.get("version").and_then(Value::as_u64).unwrap_or(0);
+
+        if version > CACHE_VERSION as u64 {
+            let error = CacheError::UnsupportedCacheVersion(version, CACHE_VERSION).into();
+            error!("{:?}", error);
+            return Err(error);
+        }
+
+        if version < 1 {
+            migrate_v0(&mut value);
+        }
+
+        let mut cache: Cache = match serde_json::from_value(value) {
+            Ok(cache) => cache,
+            Err(err) => {
+                let error = CacheError::CacheFileWrongFormat(err.to_string()).into();
+                error!("{:?}", error);
+                return Err(error);
+            }
+        };
+        cache.version = CACHE_VERSION;
+
+        Ok(cache)
+    }
+
     pub fn write_to_file<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        self.version = CACHE_VERSION;
         let c = serde_json::to_string(&self)?;
-        let mut f = fs::File::create(path)?;
+
+        // writes to a temporary file first and then replaces the cache, so an exit
+        // in the middle of the write does not leave a truncated cache behind
+        let path = path.as_ref();
+
+        if let Some(parent) = path.parent() {
+            if !parent.as_os_str().is_empty() {
+                fs::create_dir_all(parent)?;
+            }
+        }
+
+        let mut tmp_path = path.as_os_str().to_owned();
+        tmp_path.push(".tmp");
+
+        let mut f = fs::File::create(&tmp_path)?;
         f.write_all(c.as_bytes())?;
+        f.sync_all()?;
+        fs::rename(&tmp_path, path)?;
 
         Ok(())
     }
@@ -37,6 +153,50 @@ impl Cache {
         let file_path = self.file_path.clone();
         self.write_to_file(Path::new(&file_path))
     }
+
+    /// Discard the deployed candy machine: the program section and the checkpoint are
+    /// cleared and all items are marked as not on-chain.
+    pub fn reset_deploy(&mut self) {
+        self.program = CacheProgram::new();
+        self.checkpoint = None;
+        self.config_hash = None;
+        self.schedule_dates.clear();
+
+        for item in self.items.0.values_mut() {
+            item.on_chain = false;
+        }
+    }
+
+    /// Record the content hash of the config of a deploy (see
+    /// [`crate::config::ConfigData::content_hash`]).
+    pub fn set_config_hash(&mut self, content_hash: &[u8; 32]) {
+        self.config_hash = Some(HEXLOWER.encode(content_hash));
+    }
+
+    /// Return `true` if the config changed since the last deploy. Caches of deploys
+    /// made before the hash was recorded are never reported as changed.
+    pub fn config_changed(&self, content_hash: &[u8; 32]) -> bool {
+        match &self.config_hash {
+            Some(config_hash) => *config_hash != HEXLOWER.encode(content_hash),
+            None => false,
+        }
+    }
+
+    /// Update the index of the last config line of the checkpoint: this is the highest
+    /// index for which all config lines up to it are on-chain.
+    pub fn update_checkpoint(&mut self) {
+        if let Some(checkpoint) = &mut self.checkpoint {
+            checkpoint.last_config_line = (0..checkpoint.number)
+                .take_while(|index| {
+                    self.items
+                        .0
+                        .get(&index.to_string())
+                        .map_or(false, |item| item.on_chain)
+                })
+                .last()
+                .map(|index| index as u32);
+        }
+    }
 }
 
 impl Default for Cache {
@@ -77,6 +237,33 @@ impl Default for CacheProgram {
     }
 }
 
+/// Progress of a deploy, used to resume it when it did not complete.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeployCheckpoint {
+    /// Number of items of the config used to create the candy machine.
+    pub number: u64,
+    /// Hash of the config used to create the candy machine.
+    pub config_hash: String,
+    /// Highest index for which all config lines up to it were added.
+    pub last_config_line: Option<u32>,
+}
+
+impl DeployCheckpoint {
+    pub fn new(number: u64, config_hash: String) -> Self {
+        DeployCheckpoint {
+            number,
+            config_hash,
+            last_config_line: None,
+        }
+    }
+
+    /// Return `true` if the checkpoint was created with the same config.
+    pub fn matches(&self, number: u64, config_hash: &str) -> bool {
+        self.number == number && self.config_hash == config_hash
+    }
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct CacheItems(pub IndexMap<String, CacheItem>);
 
@@ -84,6 +271,13 @@ impl CacheItems {
     pub fn new() -> Self {
         CacheItems(IndexMap::new())
     }
+
+    /// Return the indices in the range `0..number` without a cache item.
+    pub fn missing_indices(&self, number: u64) -> Vec<u64> {
+        (0..number)
+            .filter(|index| !self.0.contains_key(&index.to_string()))
+            .collect()
+    }
 }
 impl Default for CacheItems {
     fn default() -> Self {
@@ -100,13 +294,37 @@ pub struct CacheItem {
     pub metadata_link: String,
     #[serde(rename = "onChain")]
     pub on_chain: bool,
+    /// Name of the config line, replacing the name from the metadata.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name_override: Option<String>,
+    /// Symbol of the item, replacing the symbol of the uploaded metadata (set it before
+    /// the metadata is uploaded). Config lines only have a name and uri, so the minted
+    /// NFTs get the symbol of the config on-chain.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub symbol_override: Option<String>,
+    /// Content encoding of the uploaded metadata file, when it was compressed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata_encoding: Option<ContentEncoding>,
 }
 
 impl CacheItem {
+    /// Return the name written to the config line: the override when there is one,
+    /// otherwise the name from the metadata.
+    pub fn config_name(&self) -> &str {
+        self.name_override.as_deref().unwrap_or(&self.name)
+    }
+
+    /// Copy the name and symbol overrides of another item.
+    pub fn with_overrides_of(mut self, item: &CacheItem) -> Self {
+        self.name_override = item.name_override.clone();
+        self.symbol_override = item.symbol_override.clone();
+        self
+    }
+
     pub fn into_config_line(&self) -> Option<ConfigLine> {
         if !self.on_chain {
             Some(ConfigLine {
-                name: self.name.clone(),
+                name: self.config_name().to_string(),
                 uri: self.metadata_link.clone(),
             })
         } else {
@@ -116,42 +334,218 @@ impl CacheItem {
 }
 
 pub fn load_cache(cache_file_path: &str, create: bool) -> Result<Cache> {
-    let cache_file_path = Path::new(cache_file_path);
-    if !cache_file_path.exists() {
-        if create {
-            // if the cache file does not exist, creates a new Cache object
-            let mut cache = Cache::new();
-            cache.file_path = path_to_string(cache_file_path)?;
-            Ok(cache)
-        } else {
-            let cache_file_string = path_to_string(cache_file_path)?;
-            let error = CacheError::CacheFileNotFound(cache_file_string).into();
-            error!("{:?}", error);
-            Err(error)
+    Cache::load(cache_file_path, create)
+}
+
+/// Migrate a version 0 cache: the program section and the link fields of the items
+/// might be missing, so they are added as empty values (items without a link are
+/// uploaded again).
+fn migrate_v0(value: &mut Value) {
+    let cache = match value.as_object_mut() {
+        Some(cache) => cache,
+        None => return,
+    };
+
+    let program = cache
+        .entry("program")
+        .or_insert_with(|| Value::Object(Map::new()));
+
+    if let Some(program) = program.as_object_mut() {
+        for key in ["candyMachine", "candyMachineCreator"] {
+            program
+                .entry(key)
+                .or_insert_with(|| Value::String(String::new()));
         }
-    } else {
-        info!("Cache exists, loading...");
-        let file = match File::open(cache_file_path) {
-            Ok(file) => file,
-            Err(err) => {
-                let cache_file_string = path_to_string(cache_file_path)?;
-                let error =
-                    CacheError::FailedToOpenCacheFile(cache_file_string, err.to_string()).into();
-                error!("{:?}", error);
-                return Err(error);
+    }
+
+    let items = cache
+        .entry("items")
+        .or_insert_with(|| Value::Object(Map::new()));
+
+    if let Some(items) = items.as_object_mut() {
+        for item in items.values_mut().filter_map(Value::as_object_mut) {
+            for key in [
+                "name",
+                "media_hash",
+                "media_link",
+                "metadata_hash",
+                "metadata_link",
+            ] {
+                item.entry(key)
+                    .or_insert_with(|| Value::String(String::new()));
             }
-        };
+            item.entry("onChain").or_insert(Value::Bool(false));
+        }
+    }
+}
 
-        let mut cache: Cache = match serde_json::from_reader(file) {
-            Ok(cache) => cache,
-            Err(err) => {
-                let error = CacheError::CacheFileWrongFormat(err.to_string()).into();
-                error!("{:?}", error);
-                return Err(error);
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use std::env;
+
+    use super::*;
+
+    fn item(index: u64, on_chain: bool) -> CacheItem {
+        CacheItem {
+            name: format!("Item #{}", index),
+            media_hash: String::new(),
+            media_link: format!("https://example.com/{}.png", index),
+            metadata_hash: String::new(),
+            metadata_link: format!("https://example.com/{}.json", index),
+            on_chain,
+            name_override: None,
+            symbol_override: None,
+            metadata_encoding: None,
+        }
+    }
+
+    #[test]
+    fn migrate_v0_cache() {
+        // version 0 caches have no version, program section or link fields
+        let v0 = json!({
+            "items": {
+                "0": { "name": "Item #1", "media_hash": "a", "metadata_hash": "b" },
+                "1": {
+                    "name": "Item #2",
+                    "media_hash": "c",
+                    "media_link": "https://example.com/1.png",
+                    "metadata_hash": "d",
+                    "metadata_link": "https://example.com/1.json",
+                    "onChain": true
+                }
             }
-        };
-        cache.file_path = path_to_string(cache_file_path)?;
+        });
 
-        Ok(cache)
+        let cache = Cache::from_value(v0).unwrap();
+
+        assert_eq!(cache.version, CACHE_VERSION);
+        assert!(cache.program.candy_machine.is_empty());
+        assert!(cache.checkpoint.is_none());
+
+        let first = cache.items.0.get("0").unwrap();
+        assert_eq!(first.name, "Item #1");
+        assert!(first.media_link.is_empty());
+        assert!(first.metadata_link.is_empty());
+        assert!(!first.on_chain);
+
+        let second = cache.items.0.get("1").unwrap();
+        assert_eq!(second.metadata_link, "https://example.com/1.json");
+        assert!(second.on_chain);
+    }
+
+    #[test]
+    fn future_cache_version() {
+        let value = json!({
+            "version": CACHE_VERSION + 1,
+            "program": { "candyMachine": "", "candyMachineCreator": "" },
+            "items": {}
+        });
+
+        let error = Cache::from_value(value).unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<CacheError>(),
+            Some(CacheError::UnsupportedCacheVersion(version, CACHE_VERSION))
+                if *version == CACHE_VERSION as u64 + 1
+        ));
+    }
+
+    #[test]
+    fn save_writes_current_version() {
+        let path = env::temp_dir().join(format!("sugar-cache-version-{}.json", std::process::id()));
+        let mut cache = Cache::new();
+        cache.version = 0;
+        cache.write_to_file(&path).unwrap();
+
+        let saved: Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(saved["version"], json!(CACHE_VERSION));
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn checkpoint_stops_at_first_gap() {
+        let mut cache = Cache::new();
+        cache.checkpoint = Some(DeployCheckpoint::new(5, "hash".to_string()));
+
+        cache.update_checkpoint();
+        assert_eq!(cache.checkpoint.as_ref().unwrap().last_config_line, None);
+
+        for (index, on_chain) in [(0, true), (1, true), (2, false), (3, true), (4, true)] {
+            cache
+                .items
+                .0
+                .insert(index.to_string(), item(index, on_chain));
+        }
+        cache.update_checkpoint();
+        assert_eq!(cache.checkpoint.as_ref().unwrap().last_config_line, Some(1));
+
+        cache.items.0.get_mut("2").unwrap().on_chain = true;
+        cache.update_checkpoint();
+        assert_eq!(cache.checkpoint.as_ref().unwrap().last_config_line, Some(4));
+    }
+
+    #[test]
+    fn checkpoint_matches_config() {
+        let checkpoint = DeployCheckpoint::new(10, "hash".to_string());
+
+        assert!(checkpoint.matches(10, "hash"));
+        assert!(!checkpoint.matches(11, "hash"));
+        assert!(!checkpoint.matches(10, "other"));
+    }
+
+    #[test]
+    fn missing_indices_report_gaps() {
+        let mut items = CacheItems::new();
+        for index in [0, 1, 3, 5] {
+            items.0.insert(index.to_string(), item(index, false));
+        }
+
+        assert_eq!(items.missing_indices(7), vec![2, 4, 6]);
+        assert!(items.missing_indices(2).is_empty());
+    }
+
+    #[test]
+    fn config_changed_since_deploy() {
+        let mut cache = Cache::new();
+        // caches without a hash are never reported as changed
+        assert!(!cache.config_changed(&[1; 32]));
+
+        cache.set_config_hash(&[1; 32]);
+        assert!(!cache.config_changed(&[1; 32]));
+        assert!(cache.config_changed(&[2; 32]));
+    }
+
+    #[test]
+    fn caches_do_not_interfere() {
+        let dir = env::temp_dir().join(format!("sugar-caches-{}", std::process::id()));
+        let first_path = path_to_string(&dir.join("first.json")).unwrap();
+        // missing folders are created when the cache is saved
+        let second_path = path_to_string(&dir.join("drops").join("second.json")).unwrap();
+
+        let mut first = Cache::load(&first_path, true).unwrap();
+        first.items.0.insert("0".to_string(), item(0, false));
+        first.sync_file().unwrap();
+
+        let mut second = Cache::load(&second_path, true).unwrap();
+        assert!(second.items.0.is_empty());
+        second.items.0.insert("0".to_string(), item(10, true));
+        second.items.0.insert("1".to_string(), item(11, true));
+        second.program.candy_machine = "candy".to_string();
+        second.sync_file().unwrap();
+
+        let first = Cache::load(&first_path, false).unwrap();
+        assert_eq!(first.file_path, first_path);
+        assert_eq!(first.items.0.len(), 1);
+        assert_eq!(first.items.0["0"].name, "Item #0");
+        assert!(first.program.candy_machine.is_empty());
+
+        let second = Cache::load(&second_path, false).unwrap();
+        assert_eq!(second.file_path, second_path);
+        assert_eq!(second.items.0.len(), 2);
+        assert_eq!(second.items.0["0"].name, "Item #10");
+        assert_eq!(second.program.candy_machine, "candy");
+
+        fs::remove_dir_all(dir).unwrap();
     }
 }
@@ -1,11 +1,29 @@
-use anchor_client::solana_sdk::pubkey::Pubkey;
+use anchor_client::{solana_sdk::pubkey::Pubkey, Program};
 use anchor_lang::AccountDeserialize;
 use anyhow::Result;
+use mpl_token_metadata::{
+    state::{Metadata, MAX_NAME_LENGTH, MAX_SYMBOL_LENGTH, MAX_URI_LENGTH},
+    ID as TOKEN_METADATA_ID,
+};
+use solana_account_decoder::UiAccountEncoding;
+use solana_client::{
+    rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig},
+    rpc_filter::{Memcmp, MemcmpEncodedBytes, RpcFilterType},
+};
+use solana_program::borsh::try_from_slice_unchecked;
+use tracing::warn;
 
-use mpl_candy_machine::{CandyMachine, CandyMachineData, WhitelistMintMode, WhitelistMintSettings};
+use mpl_candy_machine::{
+    CandyMachine, CandyMachineData, ConfigLine, WhitelistMintMode, WhitelistMintSettings,
+};
 
 use crate::config::data::SugarConfig;
-use crate::setup::setup_client;
+use crate::constants::{
+    CONFIG_ARRAY_START, CONFIG_LINE_SIZE, CONFIG_NAME_OFFSET, CONFIG_URI_OFFSET, STRING_LEN_SIZE,
+};
+use crate::mint::pdas::get_candy_machine_creator_pda;
+use crate::rpc_calls::timed;
+use crate::setup::{setup_rpc_client, TimedRpc};
 
 pub use mpl_candy_machine::ID;
 // To test a custom candy machine program, comment the line above and use the
@@ -23,10 +41,11 @@ pub fn get_candy_machine_state(
     sugar_config: &SugarConfig,
     candy_machine_id: &Pubkey,
 ) -> Result<CandyMachine> {
-    let client = setup_client(sugar_config)?;
-    let program = client.program(ID);
+    let rpc_client = setup_rpc_client(&sugar_config.rpc_url, None)?;
 
-    let data = program.rpc().get_account_data(candy_machine_id)?;
+    let data = timed("Get candy machine", || {
+        rpc_client.get_account_data(candy_machine_id)
+    })?;
     let candy_machine: CandyMachine = CandyMachine::try_deserialize(&mut data.as_slice())?;
 
     Ok(candy_machine)
@@ -40,6 +59,101 @@ pub fn get_candy_machine_data(
     Ok(candy_machine.data)
 }
 
+/// Return the size (in bytes) of a candy machine account holding `items_available` items.
+/// Offset of the address of the first creator in the metadata account: key, update
+/// authority, mint, (padded) name, symbol and uri, seller fee basis points, creators
+/// option and vector length.
+const FIRST_CREATOR_OFFSET: usize = 1
+    + 32
+    + 32
+    + STRING_LEN_SIZE
+    + MAX_NAME_LENGTH
+    + STRING_LEN_SIZE
+    + MAX_SYMBOL_LENGTH
+    + STRING_LEN_SIZE
+    + MAX_URI_LENGTH
+    + 2
+    + 1
+    + 4;
+
+/// Return the metadata accounts of the NFTs minted by the candy machine, which have
+/// the candy machine creator PDA as their first creator. Accounts that cannot be
+/// deserialized are skipped.
+pub fn get_minted_metadata(
+    program: &Program,
+    candy_machine_id: &Pubkey,
+) -> Result<Vec<(Pubkey, Metadata)>> {
+    let (candy_machine_creator, _creator_bump) = get_candy_machine_creator_pda(candy_machine_id);
+
+    let config = RpcProgramAccountsConfig {
+        filters: Some(vec![RpcFilterType::Memcmp(Memcmp {
+            offset: FIRST_CREATOR_OFFSET,
+            bytes: MemcmpEncodedBytes::Base58(candy_machine_creator.to_string()),
+            encoding: None,
+        })]),
+        account_config: RpcAccountInfoConfig {
+            encoding: Some(UiAccountEncoding::Base64),
+            data_slice: None,
+            commitment: Some(program.timed_rpc().commitment()),
+        },
+        with_context: None,
+    };
+
+    let accounts = timed("Get minted metadata", || {
+        program
+            .timed_rpc()
+            .get_program_accounts_with_config(&TOKEN_METADATA_ID, config)
+    })?;
+
+    let mut minted = Vec::new();
+
+    for (pubkey, account) in accounts {
+        match try_from_slice_unchecked::<Metadata>(&account.data) {
+            Ok(metadata) => minted.push((pubkey, metadata)),
+            Err(err) => warn!("Failed to deserialize metadata {}: {}", pubkey, err),
+        }
+    }
+
+    Ok(minted)
+}
+
+pub fn get_candy_machine_account_size(items_available: u64) -> usize {
+    CONFIG_ARRAY_START
+        + 4
+        + items_available as usize * CONFIG_LINE_SIZE
+        + 8
+        + 2 * (items_available as usize / 8 + 1)
+}
+
+/// Return the config line at the index from the candy machine account data, if the
+/// config line was added.
+pub fn get_config_line(data: &[u8], items_available: u64, index: usize) -> Option<ConfigLine> {
+    // the bit mask of the added config lines follows the config lines
+    let bit_mask_start =
+        CONFIG_ARRAY_START + STRING_LEN_SIZE + items_available as usize * CONFIG_LINE_SIZE + 4;
+    let mask = 1u8 << (7 - index % 8);
+
+    match data.get(bit_mask_start + index / 8) {
+        Some(bits) if bits & mask != 0 => (),
+        _ => return None,
+    }
+
+    let line_start = CONFIG_ARRAY_START + STRING_LEN_SIZE + CONFIG_LINE_SIZE * index;
+    let decode = |start: usize, len: usize| {
+        data.get(line_start + start..line_start + start + len)
+            .map(|bytes| {
+                String::from_utf8_lossy(bytes)
+                    .trim_matches(char::from(0))
+                    .to_string()
+            })
+    };
+
+    Some(ConfigLine {
+        name: decode(CONFIG_NAME_OFFSET, MAX_NAME_LENGTH)?,
+        uri: decode(CONFIG_URI_OFFSET, MAX_URI_LENGTH)?,
+    })
+}
+
 pub fn uuid_from_pubkey(pubkey: &Pubkey) -> String {
     pubkey.to_string()[0..6].to_string()
 }
@@ -1,6 +1,12 @@
 use clap::{Parser, Subcommand};
+use std::str::FromStr;
 
-use crate::constants::{DEFAULT_ASSETS, DEFAULT_CACHE, DEFAULT_CONFIG};
+use crate::config::{Commitment, ConfigFormat};
+use crate::constants::{
+    CACHE_ENV, DEFAULT_AIRDROP_RESULTS, DEFAULT_ASSETS, DEFAULT_CACHE, DEFAULT_CONFIG,
+};
+use crate::deploy::{ConfigLineRange, OutputFormat};
+use crate::setup::Network;
 
 #[derive(Parser)]
 #[clap(author, version, about)]
@@ -9,12 +15,243 @@ pub struct Cli {
     #[clap(short, long, global = true)]
     pub log_level: Option<String>,
 
+    /// Format of the config file: json, toml, yaml [default: from the file extension]
+    #[clap(long, global = true)]
+    pub config_format: Option<ConfigFormat>,
+
+    /// Format of the log records: human, json
+    #[clap(long, global = true, env = "SUGAR_LOG_FORMAT", default_value = "human")]
+    pub log_format: LogFormat,
+
+    /// Priority fee of the transactions in micro-lamports per compute unit [default: from the config file or 0]
+    #[clap(long, global = true)]
+    pub priority_fee: Option<u64>,
+
+    /// Derive the keypair from a BIP39 seed phrase, read from SUGAR_SEED_PHRASE or prompted for, instead of a keypair file
+    #[clap(long, global = true)]
+    pub keypair_seed: bool,
+
+    /// Derivation path of the seed phrase keypair in the solana CLI key format, e.g., "0/0" for m/44'/501'/0'/0' [default: m/44'/501']
+    #[clap(long, global = true, requires = "keypair-seed")]
+    pub derivation_path: Option<String>,
+
+    /// Commitment of the RPC requests and transaction confirmations: processed, confirmed, finalized [default: from the solana config or confirmed]
+    #[clap(long, global = true)]
+    pub commitment: Option<Commitment>,
+
+    /// Timeout of the RPC requests in seconds [default: from the config file or 30]
+    #[clap(long, global = true)]
+    pub rpc_timeout: Option<u64>,
+
+    /// Skip the check of the RPC health and version when a command starts
+    #[clap(long, global = true)]
+    pub skip_rpc_check: bool,
+
+    /// Print the number of calls, retries and the p50/p95 duration of the RPC calls when the command finishes
+    #[clap(long, global = true)]
+    pub rpc_calls: bool,
+
     #[clap(subcommand)]
     pub command: Commands,
 }
 
+/// Output format of the log records.
+///
+/// `human` only writes the log file (`sugar.log`); `json` also writes every log
+/// record as a JSON line to stderr, so they can be collected when running in
+/// automation.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LogFormat {
+    Human,
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "human" => Ok(LogFormat::Human),
+            "json" => Ok(LogFormat::Json),
+            _ => Err(format!(
+                "Invalid log format: {}. Valid formats are: human, json.",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(Subcommand)]
+pub enum ConfigCommands {
+    /// Interactive process to create the config file
+    Create {
+        /// Path to the config file
+        #[clap(short, long)]
+        config: Option<String>,
+
+        /// RPC Url
+        #[clap(short, long)]
+        rpc_url: Option<String>,
+
+        /// Path to the keypair file [default: solana config or "~/.config/solana/id.json"]
+        #[clap(short, long)]
+        keypair: Option<String>,
+
+        /// Path to the directory with the assets
+        #[clap(default_value = DEFAULT_ASSETS)]
+        assets_dir: String,
+    },
+    /// Convert a legacy (JS CLI) config file into the Sugar format
+    Convert {
+        /// Path to the legacy config file
+        legacy_config: String,
+
+        /// Path to the converted config file
+        #[clap(short, long, default_value = DEFAULT_CONFIG)]
+        output: String,
+
+        /// Path to the keypair file [default: solana config or "~/.config/solana/id.json"]
+        #[clap(short, long)]
+        keypair: Option<String>,
+
+        /// RPC Url
+        #[clap(short, long)]
+        rpc_url: Option<String>,
+
+        /// Path to the directory with the assets (used to read the symbol and seller fee basis points)
+        #[clap(short, long, default_value = DEFAULT_ASSETS)]
+        assets_dir: String,
+    },
+    /// Validate the config file without sending any transaction
+    Validate {
+        /// Path to the config file, use "-" to read it from stdin
+        #[clap(short, long, default_value = DEFAULT_CONFIG)]
+        config: String,
+
+        /// Path to the keypair file [default: solana config or "~/.config/solana/id.json"]
+        #[clap(short, long)]
+        keypair: Option<String>,
+
+        /// RPC Url
+        #[clap(short, long)]
+        rpc_url: Option<String>,
+
+        /// Print the validation report as JSON
+        #[clap(long)]
+        json: bool,
+    },
+    /// Print the JSON schema of the config file
+    Schema {
+        /// Path to write the schema to, printed to stdout by default
+        #[clap(short, long)]
+        output: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum CacheCommands {
+    /// Rebuild the cache file from the candy machine account
+    Sync {
+        /// Address of the candy machine
+        #[clap(long)]
+        candy_machine: String,
+
+        /// Path to the keypair file, uses Sol config or defaults to "~/.config/solana/id.json"
+        #[clap(short, long)]
+        keypair: Option<String>,
+
+        /// RPC Url
+        #[clap(short, long)]
+        rpc_url: Option<String>,
+
+        /// Path to the cache file, defaults to "cache.json"
+        #[clap(long, env = CACHE_ENV, default_value = DEFAULT_CACHE)]
+        cache: String,
+
+        /// Replace the cache file if it already exists
+        #[clap(long)]
+        force: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum CollectionCommands {
+    /// Set the collection NFT of the candy machine
+    Set {
+        /// Mint of the collection NFT [default: the 'collection' value of the config file]
+        #[clap(long)]
+        collection_mint: Option<String>,
+
+        /// Path to the config file, defaults to "config.json"
+        #[clap(short, long, default_value = DEFAULT_CONFIG)]
+        config: String,
+
+        /// Address of the candy machine
+        #[clap(long)]
+        candy_machine: Option<String>,
+
+        /// Path to the keypair file, uses Sol config or defaults to "~/.config/solana/id.json"
+        #[clap(short, long)]
+        keypair: Option<String>,
+
+        /// RPC Url
+        #[clap(short, long)]
+        rpc_url: Option<String>,
+
+        /// Path to the cache file, defaults to "cache.json"
+        #[clap(long, env = CACHE_ENV, default_value = DEFAULT_CACHE)]
+        cache: String,
+    },
+}
+
 #[derive(Subcommand)]
 pub enum Commands {
+    /// Mint NFTs to the recipients of a CSV file with address,amount rows
+    Airdrop {
+        /// Path to the CSV file with the address and amount of each recipient
+        recipients: String,
+
+        /// Path to the keypair file, uses Sol config or defaults to "~/.config/solana/id.json"
+        #[clap(short, long)]
+        keypair: Option<String>,
+
+        /// RPC Url
+        #[clap(short, long)]
+        rpc_url: Option<String>,
+
+        /// Path to the cache file, defaults to "cache.json"
+        #[clap(long, env = CACHE_ENV, default_value = DEFAULT_CACHE)]
+        cache: String,
+
+        /// Address of candy machine to mint from.
+        #[clap(long)]
+        candy_machine: Option<String>,
+
+        /// Path to the CSV file with the address, mint, signature and status of each airdropped NFT; the NFTs it records as minted are not airdropped again
+        #[clap(short, long, default_value = DEFAULT_AIRDROP_RESULTS)]
+        output: String,
+
+        /// Airdrop to the valid rows when the recipients file has malformed rows
+        #[clap(long)]
+        skip_bad_rows: bool,
+    },
+
+    /// Manage the cache file
+    Cache {
+        #[clap(subcommand)]
+        command: CacheCommands,
+    },
+
+    /// Manage the collection NFT of the candy machine
+    Collection {
+        #[clap(subcommand)]
+        command: CollectionCommands,
+    },
+    /// Manage the config file
+    Config {
+        #[clap(subcommand)]
+        command: ConfigCommands,
+    },
     /// Interactive process to create the config file
     CreateConfig {
         /// Path to the config file
@@ -51,13 +288,21 @@ pub enum Commands {
         #[clap(short, long)]
         rpc_url: Option<String>,
 
-        /// Path to the cache file
-        #[clap(long, default_value = DEFAULT_CACHE)]
+        /// Path to the cache file, defaults to "cache.json"
+        #[clap(long, env = CACHE_ENV, default_value = DEFAULT_CACHE)]
         cache: String,
 
         /// Strict mode: validate against JSON metadata standard exactly
         #[clap(long)]
         strict: bool,
+
+        /// Skip all confirmations: deploying to mainnet-beta, not retaining the update authority and funding the storage
+        #[clap(long)]
+        yes: bool,
+
+        /// Confirm deploying to mainnet-beta without prompting, keeping the other confirmations
+        #[clap(long)]
+        confirm_mainnet: bool,
     },
     /// Mint one NFT from candy machine
     Mint {
@@ -70,7 +315,7 @@ pub enum Commands {
         rpc_url: Option<String>,
 
         /// Path to the cache file, defaults to "cache.json"
-        #[clap(long, default_value = DEFAULT_CACHE)]
+        #[clap(long, env = CACHE_ENV, default_value = DEFAULT_CACHE)]
         cache: String,
 
         /// Amount of NFTs to be minted in bulk
@@ -80,11 +325,23 @@ pub enum Commands {
         /// Address of candy machine to mint from.
         #[clap(long)]
         candy_machine: Option<String>,
+
+        /// Address of the wallet that receives the NFTs, defaults to the keypair
+        #[clap(long)]
+        receiver: Option<String>,
+
+        /// Path to the log of the minted NFTs (index,mint,signature), written after each mint
+        #[clap(long)]
+        mints_out: Option<String>,
+
+        /// Resume a failed mint from the log of the minted NFTs, skipping the items already minted
+        #[clap(long, requires = "mints-out")]
+        resume: bool,
     },
 
     /// Update the candy machine config on-chain
     Update {
-        /// Path to the config file, defaults to "config.json"
+        /// Path to the config file, defaults to "config.json" (use "-" to read it from stdin)
         #[clap(short, long, default_value = DEFAULT_CONFIG)]
         config: String,
 
@@ -97,7 +354,7 @@ pub enum Commands {
         rpc_url: Option<String>,
 
         /// Path to the cache file, defaults to "cache.json"
-        #[clap(long, default_value = DEFAULT_CACHE)]
+        #[clap(long, env = CACHE_ENV, default_value = DEFAULT_CACHE)]
         cache: String,
 
         /// Pubkey for the new authority
@@ -107,11 +364,15 @@ pub enum Commands {
         /// Address of candy machine to update.
         #[clap(long)]
         candy_machine: Option<String>,
+
+        /// Set the price of the 'schedule' entry active now; run it periodically (e.g., from cron) to follow the schedule
+        #[clap(long)]
+        apply_schedule: bool,
     },
 
     /// Deploy cache items into candy machine config on-chain
     Deploy {
-        /// Path to the config file, defaults to "config.json"
+        /// Path to the config file, defaults to "config.json" (use "-" to read it from stdin)
         #[clap(short, long, default_value = DEFAULT_CONFIG)]
         config: String,
 
@@ -119,13 +380,76 @@ pub enum Commands {
         #[clap(short, long)]
         keypair: Option<String>,
 
-        /// RPC Url
+        /// RPC Url (takes precedence over the network)
         #[clap(short, long)]
         rpc_url: Option<String>,
 
+        /// Network to deploy to: localnet, devnet, testnet, mainnet [default: solana config or devnet]
+        #[clap(long)]
+        network: Option<Network>,
+
         /// Path to the cache file, defaults to "cache.json"
-        #[clap(long, default_value = DEFAULT_CACHE)]
+        #[clap(long, env = CACHE_ENV, default_value = DEFAULT_CACHE)]
         cache: String,
+
+        /// Skip all confirmations: deploying to mainnet-beta and not retaining the update authority
+        #[clap(long)]
+        yes: bool,
+
+        /// Confirm deploying to mainnet-beta without prompting, keeping the other confirmations
+        #[clap(long)]
+        confirm_mainnet: bool,
+
+        /// Deploy even if the number of items does not match the cache items
+        #[clap(long)]
+        allow_partial: bool,
+
+        /// Ignore the deploy checkpoint and create a new candy machine
+        #[clap(long)]
+        restart: bool,
+
+        /// Validate the deploy and print the transactions and the estimated cost without sending anything
+        #[clap(long)]
+        dry_run: bool,
+
+        /// Only set the collection of the deployed candy machine, without adding config lines
+        #[clap(long)]
+        collection_only: bool,
+
+        /// Write the unsigned transactions to this directory instead of sending them, to sign them elsewhere and broadcast them with 'submit'
+        #[clap(long, requires = "authority", conflicts_with_all = &["keypair", "dry-run", "collection-only"])]
+        unsigned_out: Option<String>,
+
+        /// Authority of the candy machine that signs the unsigned transactions (the payer of the deploy)
+        #[clap(long, requires = "unsigned-out")]
+        authority: Option<String>,
+
+        /// Durable nonce account to use instead of a recent blockhash, so the unsigned transactions do not expire (one per transaction, with the authority as the nonce authority)
+        #[clap(long, requires = "unsigned-out")]
+        nonce_account: Vec<String>,
+
+        /// Only deploy the config lines of this range of indices, written as 'start..end' (both included)
+        #[clap(long, conflicts_with = "collection-only")]
+        only: Option<ConfigLineRange>,
+
+        /// Output format: human, json (prints a JSON summary to stdout and the progress to stderr)
+        #[clap(long, default_value = "human")]
+        output: OutputFormat,
+    },
+
+    /// Compute the hidden settings hash of the cache file
+    Hash {
+        /// Path to the config file, defaults to "config.json"
+        #[clap(short, long, default_value = DEFAULT_CONFIG)]
+        config: String,
+
+        /// Path to the cache file, defaults to "cache.json"
+        #[clap(long, env = CACHE_ENV, default_value = DEFAULT_CACHE)]
+        cache: String,
+
+        /// Write the hash to the 'hiddenSettings.hash' value of the config file
+        #[clap(long)]
+        update_config: bool,
     },
 
     /// Upload assets to storage and creates the cache config
@@ -134,7 +458,7 @@ pub enum Commands {
         #[clap(default_value = DEFAULT_ASSETS)]
         assets_dir: String,
 
-        /// Path to the config file
+        /// Path to the config file, use "-" to read it from stdin
         #[clap(short, long, default_value = DEFAULT_CONFIG)]
         config: String,
 
@@ -146,14 +470,92 @@ pub enum Commands {
         #[clap(short, long)]
         rpc_url: Option<String>,
 
-        /// Path to the cache file
-        #[clap(long, default_value = DEFAULT_CACHE)]
+        /// Path to the cache file, defaults to "cache.json"
+        #[clap(long, env = CACHE_ENV, default_value = DEFAULT_CACHE)]
+        cache: String,
+
+        /// Skip the validation of the assets against the config before uploading (the
+        /// symbol and seller fee basis points are always checked)
+        #[clap(long)]
+        skip_validation: bool,
+
+        /// Upload every media file, even when several files have the same content
+        #[clap(long)]
+        no_dedup: bool,
+
+        /// Upload all files again, including the ones already uploaded
+        #[clap(long)]
+        force: bool,
+
+        /// Check that the uploaded files exist in the storage, uploading the missing ones again
+        #[clap(long)]
+        verify_remote: bool,
+
+        /// Skip the confirmation when the upload cost exceeds the funded balance of the storage
+        #[clap(long)]
+        yes: bool,
+    },
+
+    /// Reveal the minted NFTs of a hidden settings drop, updating their name and uri from the cache file
+    Reveal {
+        /// Address of candy machine of the minted NFTs.
+        #[clap(long)]
+        candy_machine: Option<String>,
+
+        /// Path to the config file, only used when the hidden settings uri is a template
+        #[clap(short, long, default_value = DEFAULT_CONFIG)]
+        config: String,
+
+        /// Path to the update authority keypair file, uses Sol config or defaults to "~/.config/solana/id.json"
+        #[clap(short, long)]
+        keypair: Option<String>,
+
+        /// RPC Url
+        #[clap(short, long)]
+        rpc_url: Option<String>,
+
+        /// Path to the cache file with the revealed metadata, defaults to "cache.json"
+        #[clap(long, env = CACHE_ENV, default_value = DEFAULT_CACHE)]
+        cache: String,
+    },
+
+    /// Sign the minted NFTs as a creator, verifying the creator in each NFT
+    Sign {
+        /// Address of candy machine of the minted NFTs.
+        #[clap(long)]
+        candy_machine: Option<String>,
+
+        /// Path to the creator keypair file, uses Sol config or defaults to "~/.config/solana/id.json"
+        #[clap(short, long)]
+        keypair: Option<String>,
+
+        /// RPC Url
+        #[clap(short, long)]
+        rpc_url: Option<String>,
+
+        /// Path to the cache file, defaults to "cache.json"
+        #[clap(long, env = CACHE_ENV, default_value = DEFAULT_CACHE)]
+        cache: String,
+    },
+
+    /// Broadcast the signed transactions of an unsigned deploy
+    Submit {
+        /// Transaction files written by 'deploy --unsigned-out', or directories with them (sent in file name order)
+        #[clap(required = true)]
+        files: Vec<String>,
+
+        /// RPC Url
+        #[clap(short, long)]
+        rpc_url: Option<String>,
+
+        /// Path to the cache file, defaults to "cache.json"
+        #[clap(long, env = CACHE_ENV, default_value = DEFAULT_CACHE)]
         cache: String,
     },
 
     /// Withdraw funds from candy machine account closing it
     Withdraw {
-        /// Address of candy machine to withdraw funds from.
+        /// Address of candy machine to withdraw funds from, defaults to the candy machine of the cache file
         #[clap(long)]
         candy_machine: Option<String>,
 
@@ -168,6 +570,18 @@ pub enum Commands {
         /// List available candy machines, no withdraw performed
         #[clap(long)]
         list: bool,
+
+        /// Withdraw funds from all candy machines of the keypair
+        #[clap(long, conflicts_with = "candy-machine")]
+        all: bool,
+
+        /// Path to the cache file, defaults to "cache.json"
+        #[clap(long, env = CACHE_ENV, default_value = DEFAULT_CACHE)]
+        cache: String,
+
+        /// Report the amount that would be reclaimed, no withdraw performed
+        #[clap(long)]
+        dry_run: bool,
     },
 
     /// Validate JSON metadata files
@@ -183,6 +597,10 @@ pub enum Commands {
 
     /// Verify uploaded data
     Verify {
+        /// Path to the config file, only used when the hidden settings uri is a template
+        #[clap(short, long, default_value = DEFAULT_CONFIG)]
+        config: String,
+
         /// Path to the keypair file, uses Sol config or defaults to "~/.config/solana/id.json"
         #[clap(short, long)]
         keypair: Option<String>,
@@ -192,8 +610,16 @@ pub enum Commands {
         rpc_url: Option<String>,
 
         /// Path to the cache file, defaults to "cache.json"
-        #[clap(long, default_value = DEFAULT_CACHE)]
+        #[clap(long, env = CACHE_ENV, default_value = DEFAULT_CACHE)]
         cache: String,
+
+        /// Skip fetching the metadata URIs
+        #[clap(long)]
+        skip_uris: bool,
+
+        /// Number of metadata URIs fetched in parallel
+        #[clap(long, default_value = "10")]
+        concurrency: usize,
     },
 
     /// Show the on-chain config of an existing candy machine
@@ -207,10 +633,18 @@ pub enum Commands {
         rpc_url: Option<String>,
 
         /// Path to the cache file, defaults to "cache.json"
-        #[clap(long, default_value = DEFAULT_CACHE)]
+        #[clap(long, env = CACHE_ENV, default_value = DEFAULT_CACHE)]
         cache: String,
 
         /// Address of candy machine
         candy_machine: Option<String>,
+
+        /// Print the on-chain config in the config file format (JSON)
+        #[clap(long)]
+        as_config: bool,
+
+        /// Output format: human, json (prints the candy machine details as JSON)
+        #[clap(long, default_value = "human")]
+        output: OutputFormat,
     },
 }
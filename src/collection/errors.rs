@@ -0,0 +1,24 @@
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum CollectionError {
+    #[error(
+        "Missing collection mint: set 'collection' in the config file or use --collection-mint."
+    )]
+    MissingCollection,
+
+    #[error("Collection NFT {0} not found: check the collection mint address and that you are connected to the right cluster.")]
+    CollectionNotFound(String),
+
+    #[error("The mint {0} is not a collection NFT: {1}.")]
+    NotACollectionNft(String, String),
+
+    #[error("The keypair {2} is not the update authority of the collection NFT {0} (update authority is {1}).")]
+    AuthorityMismatch(String, String, String),
+
+    #[error("Invalid collection PDA {0}: expected at least 40 bytes of data, found {1}.")]
+    InvalidCollectionPda(String, usize),
+
+    #[error("Candy machine {0} has {1} minted item(s): the collection can only be set before any item is minted.")]
+    ItemsAlreadyMinted(String, u64),
+}
@@ -0,0 +1,5 @@
+pub mod errors;
+pub mod process;
+
+pub use errors::*;
+pub use process::*;
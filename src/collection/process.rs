@@ -0,0 +1,289 @@
+use anchor_client::{
+    solana_sdk::{
+        hash::hash,
+        instruction::{AccountMeta, Instruction},
+        pubkey::Pubkey,
+        signature::Signature,
+        system_program, sysvar,
+    },
+    Program,
+};
+use console::style;
+use mpl_token_metadata::{
+    state::{Key, MasterEditionV2, Metadata},
+    ID as TOKEN_METADATA_ID,
+};
+use solana_program::borsh::try_from_slice_unchecked;
+use std::str::FromStr;
+
+use crate::cache::load_cache;
+use crate::candy_machine::get_candy_machine_state;
+use crate::candy_machine::ID as CANDY_MACHINE_ID;
+use crate::collection::CollectionError;
+use crate::common::*;
+use crate::config::{get_config_data, ConfigFormat, RetryConfig};
+use crate::mint::pdas::{
+    get_collection_authority_record_pda, get_collection_pda, get_master_edition_pda,
+    get_metadata_pda,
+};
+use crate::rpc_calls::timed;
+use crate::utils::*;
+
+pub struct SetCollectionArgs {
+    pub keypair: Option<String>,
+    pub rpc_url: Option<String>,
+    pub cache: String,
+    pub config: String,
+    pub config_format: Option<ConfigFormat>,
+    pub candy_machine: Option<String>,
+    pub collection_mint: Option<String>,
+    pub priority_fee: Option<u64>,
+}
+
+pub fn process_set_collection(args: SetCollectionArgs) -> Result<()> {
+    let sugar_config = sugar_setup(args.keypair, args.rpc_url)?;
+
+    // the collection mint specified takes precedence over the one from the config,
+    // which is only loaded when needed
+
+    let (collection_mint, retry_config, config_fee, rpc_timeout_secs) = match args.collection_mint {
+        Some(collection_mint) => {
+            let mint = Pubkey::from_str(&collection_mint)
+                .map_err(|_| anyhow!("Failed to parse collection mint: {}", collection_mint))?;
+            (mint, RetryConfig::default(), None, None)
+        }
+        None => {
+            let config_data = get_config_data(&args.config, args.config_format)?;
+            let mint = config_data
+                .collection
+                .ok_or(CollectionError::MissingCollection)?;
+            (
+                mint,
+                config_data.retry_config.unwrap_or_default(),
+                config_data.priority_fee,
+                config_data.rpc_timeout_secs,
+            )
+        }
+    };
+    let priority_fee = args.priority_fee.or(config_fee).unwrap_or(0);
+    let client = setup_client(&sugar_config, rpc_timeout_secs)?;
+    let program = client.program(CANDY_MACHINE_ID);
+
+    // the candy machine id specified takes precedence over the one from the cache
+
+    let candy_machine_id = match args.candy_machine {
+        Some(candy_machine_id) => candy_machine_id,
+        None => {
+            let cache = load_cache(&args.cache, false)?;
+            cache.program.candy_machine
+        }
+    };
+
+    let candy_pubkey = match Pubkey::from_str(&candy_machine_id) {
+        Ok(candy_pubkey) => candy_pubkey,
+        Err(_) => {
+            let error = anyhow!("Failed to parse candy machine id: {}", candy_machine_id);
+            error!("{:?}", error);
+            return Err(error);
+        }
+    };
+
+    println!(
+        "{} {}Loading collection NFT",
+        style("[1/2]").bold().dim(),
+        LOOKING_GLASS_EMOJI
+    );
+    println!("{} {}", style("Candy machine ID:").bold(), candy_pubkey);
+    println!("{} {}", style("Collection mint:").bold(), collection_mint);
+
+    let pb = spinner_with_style();
+    pb.set_message("Connecting...");
+
+    let candy_machine_state = get_candy_machine_state(&sugar_config, &candy_pubkey)?;
+
+    if candy_machine_state.items_redeemed > 0 {
+        pb.finish_and_clear();
+        return Err(CollectionError::ItemsAlreadyMinted(
+            candy_pubkey.to_string(),
+            candy_machine_state.items_redeemed,
+        )
+        .into());
+    }
+
+    check_collection(&program, &collection_mint)?;
+
+    pb.finish_and_clear();
+
+    println!(
+        "\n{} {}Setting collection",
+        style("[2/2]").bold().dim(),
+        CANDY_EMOJI
+    );
+
+    if get_collection_mint(&program, &candy_pubkey)? == Some(collection_mint) {
+        println!("The collection is already set, skipping the transaction.");
+        return Ok(());
+    }
+
+    let pb = spinner_with_style();
+    pb.set_message("Sending set collection transaction...");
+
+    let signature = set_collection(
+        &program,
+        &candy_pubkey,
+        &collection_mint,
+        &retry_config,
+        priority_fee,
+    )?;
+
+    pb.finish_with_message(format!(
+        "{} {}",
+        style("Set collection signature:").bold(),
+        signature
+    ));
+
+    Ok(())
+}
+
+/// Check that the mint is a collection NFT (it has a master edition with a maximum
+/// supply of 0) and that the payer is its update authority.
+pub fn check_collection(program: &Program, collection_mint: &Pubkey) -> Result<()> {
+    let mint = collection_mint.to_string();
+
+    let metadata_account = timed("Get collection metadata", || {
+        program
+            .timed_rpc()
+            .get_account(&get_metadata_pda(collection_mint))
+    })
+    .map_err(|_| CollectionError::CollectionNotFound(mint.clone()))?;
+    let metadata: Metadata = try_from_slice_unchecked(&metadata_account.data)
+        .map_err(|err| CollectionError::NotACollectionNft(mint.clone(), err.to_string()))?;
+
+    if metadata.update_authority != program.payer() {
+        return Err(CollectionError::AuthorityMismatch(
+            mint,
+            metadata.update_authority.to_string(),
+            program.payer().to_string(),
+        )
+        .into());
+    }
+
+    let edition_account = timed("Get master edition", || {
+        program
+            .timed_rpc()
+            .get_account(&get_master_edition_pda(collection_mint))
+    })
+    .map_err(|_| {
+        CollectionError::NotACollectionNft(mint.clone(), "missing master edition".to_string())
+    })?;
+    let edition: MasterEditionV2 = try_from_slice_unchecked(&edition_account.data)
+        .map_err(|err| CollectionError::NotACollectionNft(mint.clone(), err.to_string()))?;
+
+    if edition.key != Key::MasterEditionV2 || edition.max_supply != Some(0) {
+        return Err(CollectionError::NotACollectionNft(
+            mint,
+            "the master edition must have a max supply of 0".to_string(),
+        )
+        .into());
+    }
+
+    Ok(())
+}
+
+/// Return the collection mint set on the candy machine, if there is one. Only a missing
+/// collection PDA means that there is no collection, RPC errors are returned.
+pub fn get_collection_mint(program: &Program, candy_machine: &Pubkey) -> Result<Option<Pubkey>> {
+    let collection_pda = get_collection_pda(candy_machine);
+    let rpc_client = program.timed_rpc();
+
+    let account = timed("Get collection", || {
+        rpc_client.get_account_with_commitment(&collection_pda, rpc_client.commitment())
+    })?
+    .value;
+
+    parse_collection_mint(&collection_pda, account.as_ref().map(|a| a.data.as_slice()))
+}
+
+/// Return the collection mint of the data of a collection PDA: the data is the
+/// discriminator followed by the mint and the candy machine.
+fn parse_collection_mint(collection_pda: &Pubkey, data: Option<&[u8]>) -> Result<Option<Pubkey>> {
+    match data {
+        Some(data) if data.len() >= 40 => Ok(Some(Pubkey::new(&data[8..40]))),
+        Some(data) => Err(CollectionError::InvalidCollectionPda(
+            collection_pda.to_string(),
+            data.len(),
+        )
+        .into()),
+        None => Ok(None),
+    }
+}
+
+/// Send the `set_collection` instruction to the candy machine program.
+///
+/// The instruction is built by hand since it is not part of the candy machine crate
+/// used by Sugar.
+pub fn set_collection(
+    program: &Program,
+    candy_machine: &Pubkey,
+    collection_mint: &Pubkey,
+    retry_config: &RetryConfig,
+    priority_fee: u64,
+) -> Result<Signature> {
+    let payer = program.payer();
+    let collection_pda = get_collection_pda(candy_machine);
+
+    let instruction = Instruction {
+        program_id: CANDY_MACHINE_ID,
+        accounts: vec![
+            AccountMeta::new(*candy_machine, false),
+            AccountMeta::new_readonly(payer, true),
+            AccountMeta::new(collection_pda, false),
+            AccountMeta::new(payer, true),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(sysvar::rent::ID, false),
+            AccountMeta::new_readonly(get_metadata_pda(collection_mint), false),
+            AccountMeta::new_readonly(*collection_mint, false),
+            AccountMeta::new_readonly(get_master_edition_pda(collection_mint), false),
+            AccountMeta::new(
+                get_collection_authority_record_pda(collection_mint, &collection_pda),
+                false,
+            ),
+            AccountMeta::new_readonly(TOKEN_METADATA_ID, false),
+        ],
+        // anchor instruction discriminator
+        data: hash(b"global:set_collection").to_bytes()[..8].to_vec(),
+    };
+
+    with_priority_fee_retry(retry_config, priority_fee, "Set collection", |fee| {
+        with_priority_fee(program.request(), fee)
+            .instruction(instruction.clone())
+            .send_timed(&[])
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn collection_mint_of_pda_data() {
+        let collection_pda = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let mut data = vec![0; 8];
+        data.extend_from_slice(mint.as_ref());
+        data.extend_from_slice(Pubkey::new_unique().as_ref());
+
+        assert_eq!(
+            parse_collection_mint(&collection_pda, Some(&data)).unwrap(),
+            Some(mint)
+        );
+        // no collection PDA, no collection
+        assert_eq!(parse_collection_mint(&collection_pda, None).unwrap(), None);
+
+        let error = parse_collection_mint(&collection_pda, Some(&data[..20])).unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<CollectionError>(),
+            Some(CollectionError::InvalidCollectionPda(_, 20))
+        ));
+    }
+}
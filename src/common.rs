@@ -20,6 +20,7 @@ pub use std::{
     fs::File,
     path::{Path, PathBuf},
     str::FromStr,
+    time::Instant,
 };
 pub use tracing::{debug, error, info, warn};
 
@@ -31,4 +32,5 @@ pub use crate::cache::{Cache, CacheItem};
 pub use crate::constants::*;
 pub use crate::errors::*;
 pub use crate::parse::path_to_string;
-pub use crate::setup::{setup_client, sugar_setup};
+pub use crate::progress::task_progress;
+pub use crate::setup::{setup_client, setup_rpc_client, sugar_setup, TimedRpc, TimedSend};
@@ -1,19 +1,49 @@
 use anchor_client::solana_sdk::signature::Keypair;
-use anchor_client::solana_sdk::{native_token::LAMPORTS_PER_SOL, pubkey::Pubkey};
+use anchor_client::solana_sdk::{
+    commitment_config::CommitmentConfig, native_token::LAMPORTS_PER_SOL, pubkey::Pubkey,
+    system_instruction::MAX_PERMITTED_DATA_LENGTH,
+};
 pub use anyhow::{anyhow, Result};
-use chrono::DateTime;
+use chrono::{DateTime, NaiveDateTime, SecondsFormat, Utc};
+use console::style;
+use data_encoding::{HEXLOWER, HEXLOWER_PERMISSIVE};
+use rand::Rng;
+use reqwest::Url;
+use ring::digest::{Context, SHA256};
+use schemars::{
+    gen::SchemaGenerator,
+    schema::{InstanceType, RootSchema, Schema, SchemaObject},
+    schema_for, JsonSchema,
+};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use spl_associated_token_account::get_associated_token_address;
+use std::cmp;
+use std::collections::BTreeMap;
+use std::env;
 use std::fmt::Display;
+use std::fs::File;
+use std::io::{ErrorKind, Read};
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
+use tracing::{error, warn};
 
 use mpl_candy_machine::{
-    Creator as CandyCreator, EndSettingType as CandyEndSettingType,
+    CandyMachineData, ConfigLine, Creator as CandyCreator, EndSettingType as CandyEndSettingType,
     EndSettings as CandyEndSettings, GatekeeperConfig as CandyGatekeeperConfig,
     HiddenSettings as CandyHiddenSettings, WhitelistMintMode as CandyWhitelistMintMode,
     WhitelistMintSettings as CandyWhitelistMintSettings,
 };
 
+use crate::cache::load_cache;
+use crate::candy_machine::{get_candy_machine_account_size, ID as CANDY_MACHINE_ID};
 use crate::config::errors::*;
+use crate::config::parser::{expand_env_vars, expand_env_vars_in_value};
+use crate::constants::{
+    AWS_MAX_FILE_SIZE, CIVIC_ID_VERIFICATION_NETWORK, CIVIC_NETWORK, CIVIC_UNIQUENESS_NETWORK,
+    ENCORE_NETWORK, MAX_CREATOR_LIMIT, MAX_FREEZE_TIME, MAX_NAME_LENGTH, MAX_SYMBOL_LENGTH,
+    MAX_URI_LENGTH, MIN_MINT_DURATION, NFT_STORAGE_MAX_FILE_SIZE, SOL_DECIMALS,
+};
+use crate::parse::{default_solana_config_path, path_to_string, SOLANA_CONFIG_ENV};
 
 pub struct SugarConfig {
     pub keypair: Keypair,
@@ -24,351 +54,4060 @@ pub struct SugarConfig {
 pub struct SolanaConfig {
     pub json_rpc_url: String,
     pub keypair_path: String,
-    pub commitment: String,
+    pub commitment: Commitment,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Commitment {
+    Processed,
+    #[default]
+    Confirmed,
+    Finalized,
+}
+
+impl FromStr for Commitment {
+    type Err = ConfigError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "processed" => Ok(Commitment::Processed),
+            "confirmed" => Ok(Commitment::Confirmed),
+            "finalized" => Ok(Commitment::Finalized),
+            _ => match Commitment::from_deprecated(s) {
+                Some(commitment) => {
+                    warn!(
+                        "Commitment '{}' is deprecated, using '{}'",
+                        s,
+                        commitment.to_string()
+                    );
+                    Ok(commitment)
+                }
+                None => Err(ConfigError::InvalidCommitment(s.to_string())),
+            },
+        }
+    }
+}
+
+impl Commitment {
+    /// Return the commitment matching a deprecated commitment of older Solana CLI
+    /// versions, which the solana config file may still have.
+    pub fn from_deprecated(s: &str) -> Option<Commitment> {
+        match s.to_lowercase().as_str() {
+            "recent" => Some(Commitment::Processed),
+            "single" | "singlegossip" => Some(Commitment::Confirmed),
+            "root" | "max" => Some(Commitment::Finalized),
+            _ => None,
+        }
+    }
+}
+
+impl ToString for Commitment {
+    fn to_string(&self) -> String {
+        match self {
+            Commitment::Processed => "processed".to_string(),
+            Commitment::Confirmed => "confirmed".to_string(),
+            Commitment::Finalized => "finalized".to_string(),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Commitment {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s: String = Deserialize::deserialize(deserializer)?;
+        FromStr::from_str(&s).map_err(serde::de::Error::custom)
+    }
+}
+
+impl From<Commitment> for CommitmentConfig {
+    fn from(commitment: Commitment) -> Self {
+        match commitment {
+            Commitment::Processed => CommitmentConfig::processed(),
+            Commitment::Confirmed => CommitmentConfig::confirmed(),
+            Commitment::Finalized => CommitmentConfig::finalized(),
+        }
+    }
+}
+
+impl SolanaConfig {
+    /// Load the solana CLI config file from its default location, falling back to the
+    /// file specified by the `SOLANA_CONFIG` environment variable.
+    pub fn load_default() -> Result<SolanaConfig, ConfigError> {
+        let mut config_path = default_solana_config_path();
+
+        if !config_path.is_file() {
+            match env::var_os(SOLANA_CONFIG_ENV) {
+                Some(path) => config_path = PathBuf::from(path),
+                None => {
+                    return Err(ConfigError::SolanaConfigNotFound(
+                        config_path.display().to_string(),
+                    ))
+                }
+            }
+        }
+
+        SolanaConfig::load(&config_path)
+    }
+
+    /// Load a solana CLI config file.
+    pub fn load(config_path: &Path) -> Result<SolanaConfig, ConfigError> {
+        let file = match File::open(config_path) {
+            Ok(file) => file,
+            Err(err) if err.kind() == ErrorKind::NotFound => {
+                return Err(ConfigError::SolanaConfigNotFound(
+                    config_path.display().to_string(),
+                ))
+            }
+            Err(_) => {
+                return Err(ConfigError::PermissionError(
+                    config_path.display().to_string(),
+                ))
+            }
+        };
+
+        let mut sol_config: SolanaConfig = serde_yaml::from_reader(file)
+            .map_err(|err| ConfigError::ParseError(err.to_string()))?;
+        sol_config.json_rpc_url = expand_env_vars(&sol_config.json_rpc_url)?;
+        sol_config.keypair_path = expand_env_vars(&sol_config.keypair_path)?;
+
+        Ok(sol_config)
+    }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+/// Config of the candy machine.
+///
+/// Only `price`, `number`, `uploadMethod` and the `creators` are required; the
+/// optional settings default to `None`, `goLiveDate` to `null`, `symbol` to an empty
+/// symbol, `sellerFeeBasisPoints` to 0, and `retainAuthority` and `isMutable` to
+/// `true`.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Default, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct ConfigData {
     pub price: f64,
     pub number: u64,
+    #[serde(default)]
     pub gatekeeper: Option<GatekeeperConfig>,
+    /// A missing list of creators is reported by `validate()`.
+    #[serde(default)]
     pub creators: Vec<Creator>,
 
-    #[serde(deserialize_with = "to_option_pubkey")]
-    #[serde(serialize_with = "to_option_string")]
-    pub sol_treasury_account: Option<Pubkey>,
-
-    #[serde(deserialize_with = "to_option_pubkey")]
-    #[serde(serialize_with = "to_option_string")]
-    pub spl_token_account: Option<Pubkey>,
-
-    #[serde(deserialize_with = "to_option_pubkey")]
-    #[serde(serialize_with = "to_option_string")]
-    pub spl_token: Option<Pubkey>,
+    /// Treasury set with the `solTreasuryAccount`, `splToken` and `splTokenAccount` keys.
+    #[serde(flatten)]
+    pub treasury: TreasuryConfig,
 
-    pub go_live_date: String,
+    /// A `null` go live date means the candy machine is not live yet: only the
+    /// authority (or whitelisted users during presale) can mint until it is set.
+    #[serde(default)]
+    pub go_live_date: Option<String>,
 
+    #[serde(default)]
     pub end_settings: Option<EndSettings>,
 
+    /// Price steps of the drop. The candy machine only has a single price, so the
+    /// active price is set by running `sugar update --apply-schedule` periodically.
+    #[serde(default)]
+    pub schedule: Option<Vec<ScheduleEntry>>,
+
+    #[serde(default)]
     pub whitelist_mint_settings: Option<WhitelistMintSettings>,
 
+    #[serde(default)]
     pub hidden_settings: Option<HiddenSettings>,
 
+    /// Freeze of the minted NFTs, which is not supported by the candy machine program
+    /// (a config that sets it does not validate).
+    #[serde(default)]
+    pub freeze_settings: Option<FreezeSettings>,
+
+    /// Candy guard settings, which replace the mint settings of the candy machine
+    /// on programs that support candy guards. The candy machine program does not, so
+    /// a config that sets them does not validate.
+    #[serde(default)]
+    pub guards: Option<GuardConfig>,
+
+    /// Mint of the collection NFT that the minted NFTs join.
+    #[serde(default, deserialize_with = "to_collection")]
+    #[schemars(with = "Option<String>")]
+    #[serde(serialize_with = "to_option_string")]
+    pub collection: Option<Pubkey>,
+
     pub upload_method: UploadMethod,
 
+    #[serde(default)]
+    pub parallel_limit: Option<u16>,
+
+    /// Maximum number of upload requests per second (unlimited by default).
+    #[serde(default)]
+    pub requests_per_second: Option<f64>,
+
+    #[serde(default = "default_true")]
     pub retain_authority: bool,
 
+    #[serde(default = "default_true")]
     pub is_mutable: bool,
 
-    #[serde(serialize_with = "to_option_string")]
+    #[serde(default, serialize_with = "to_option_string")]
     pub aws_s3_bucket: Option<String>,
 
+    #[serde(default)]
+    pub aws_config: Option<AwsConfig>,
+
+    #[serde(default, serialize_with = "to_option_string")]
+    pub nft_storage_auth_token: Option<String>,
+
+    #[serde(default)]
+    pub pinata_config: Option<PinataConfig>,
+
+    #[serde(default)]
+    pub shdw_config: Option<ShdwConfig>,
+
+    #[serde(default)]
+    pub retry_config: Option<RetryConfig>,
+
+    /// Timeout (in seconds) of the RPC requests, overridden by `--rpc-timeout`.
+    #[serde(default)]
+    pub rpc_timeout_secs: Option<u64>,
+
+    /// Maximum number of idle connections kept open to the storage by the parallel
+    /// upload to nft.storage, Pinata and Shadow Drive (by default, one per concurrent
+    /// upload).
+    #[serde(default)]
+    pub upload_pool_size: Option<u16>,
+
+    /// Priority fee of the transactions, in micro-lamports per compute unit.
+    #[serde(default)]
+    pub priority_fee: Option<u64>,
+
+    #[serde(default)]
     pub symbol: String,
 
+    #[serde(default)]
     pub seller_fee_basis_points: u16,
-}
 
-pub fn to_string<T, S>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
-where
-    T: Display,
-    S: Serializer,
-{
-    serializer.collect_str(value)
-}
+    /// Maximum number of prints of each minted master edition (0 means no prints).
+    #[serde(default)]
+    pub max_supply: Option<u64>,
 
-pub fn to_option_string<T, S>(value: &Option<T>, serializer: S) -> Result<S::Ok, S::Error>
-where
-    T: Display,
-    S: Serializer,
-{
-    match value {
-        Some(v) => serializer.collect_str(&v),
-        None => serializer.serialize_none(),
-    }
-}
+    /// Default `animation_url` of the metadata files, where `{index}` is replaced by
+    /// the index of the item. The value of a metadata file takes precedence.
+    #[serde(default)]
+    pub animation_url_template: Option<String>,
 
-pub fn parse_string_as_date(go_live_date: &str) -> Result<String> {
-    let date = DateTime::parse_from_str(go_live_date, "%Y-%m-%d %H:%M:%S %z")?;
-    Ok(date.to_rfc2822())
+    /// Default `external_url` of the metadata files, where `{index}` is replaced by
+    /// the index of the item. The value of a metadata file takes precedence.
+    #[serde(default)]
+    pub external_url_template: Option<String>,
+
+    /// Encoding of the uploaded metadata files, which are compressed to reduce the
+    /// storage cost (the media files are not compressed).
+    #[serde(default)]
+    pub metadata_encoding: Option<ContentEncoding>,
 }
 
-pub fn go_live_date_as_timestamp(go_live_date: &str) -> Result<i64> {
-    let format;
-    if let Ok(date) = chrono::DateTime::parse_from_rfc2822(go_live_date) {
-        format = date.timestamp();
-    } else if let Ok(date) = chrono::DateTime::parse_from_rfc3339(go_live_date) {
-        format = date.timestamp();
-    } else if let Ok(timestamp) = go_live_date.parse::<i64>() {
-        format = timestamp;
-    } else {
-        return Err(anyhow!("Invalid date format. Format must be: RFC2822(Fri, 14 Jul 2022 02:40:00 -0400), RFC3339(2022-02-25T13:00:00Z), or UNIX timestamp."));
-    };
+/// Warning shown when the update authority is not retained.
+pub const RETAIN_AUTHORITY_WARNING: &str = "'retainAuthority' is set to false: the update authority of each minted NFT will be transferred to the minter. This cannot be undone once the NFTs are minted.";
 
-    Ok(format)
-}
+/// Warning shown when presale is enabled without a discount price.
+pub const PRESALE_WITHOUT_DISCOUNT_WARNING: &str = "'whitelistMintSettings.presale' is enabled without a 'discountPrice': whitelisted users will pay the full price during presale.";
 
-pub fn price_as_lamports(price: f64) -> u64 {
-    (price * LAMPORTS_PER_SOL as f64) as u64
-}
+/// Warning shown when presale is enabled without a go live date, so the public mint
+/// never starts.
+pub const NULL_GO_LIVE_WITH_PRESALE_WARNING: &str = "'goLiveDate' is null and 'whitelistMintSettings.presale' is enabled: whitelisted users will be able to mint, but the public mint will not start until a go live date is set.";
 
-fn to_pubkey<'de, D>(deserializer: D) -> Result<Pubkey, D::Error>
-where
-    D: Deserializer<'de>,
-{
-    let s: String = Deserialize::deserialize(deserializer)?;
-    Pubkey::from_str(&s).map_err(serde::de::Error::custom)
-}
+/// Warning shown when whitelisted users mint for free but only after the go live date.
+pub const FREE_WITHOUT_PRESALE_WARNING: &str = "'whitelistMintSettings.discountPrice' is 0 (free mint) but 'presale' is disabled: whitelisted users will only be able to mint for free after the go live date.";
 
-fn to_option_pubkey<'de, D>(deserializer: D) -> Result<Option<Pubkey>, D::Error>
-where
-    D: Deserializer<'de>,
-{
-    let s: String = match Deserialize::deserialize(deserializer) {
-        Ok(s) => s,
-        Err(_) => return Ok(None),
-    };
+/// Warning shown when a whitelist mint limit is set, since the program cannot enforce it.
+pub const WHITELIST_MINT_LIMIT_WARNING: &str = "'whitelistMintSettings.mintLimit' is not enforced by the candy machine v2 program: with 'burnEveryTime' each whitelist token allows one mint (distribute as many tokens as mints allowed per holder) and with 'neverBurn' holders can mint without limit.";
 
-    let pubkey = Pubkey::from_str(&s).map_err(serde::de::Error::custom)?;
-    Ok(Some(pubkey))
-}
+/// Warning shown when prints are allowed on the placeholder NFTs of a hidden mint.
+pub const HIDDEN_WITH_MAX_SUPPLY_WARNING: &str = "'maxSupply' is greater than 0 with 'hiddenSettings': prints of each NFT can be made from the hidden placeholder before it is revealed.";
 
-fn discount_price_to_lamports(discount_price: Option<f64>) -> Option<u64> {
-    discount_price.map(|price| (price * LAMPORTS_PER_SOL as f64) as u64)
-}
+/// Parses and validates the JSON config data, without expanding environment variables.
+impl FromStr for ConfigData {
+    type Err = ConfigError;
 
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
-pub struct GatekeeperConfig {
-    /// The network for the gateway token required
-    #[serde(deserialize_with = "to_pubkey")]
-    #[serde(serialize_with = "to_string")]
-    gatekeeper_network: Pubkey,
-    /// Whether or not the token should expire after minting.
-    /// The gatekeeper network must support this if true.
-    expire_on_use: bool,
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ConfigData::parse_reader(s.as_bytes())?.into_validated("<string>")
+    }
 }
 
-impl GatekeeperConfig {
-    pub fn new(gatekeeper_network: Pubkey, expire_on_use: bool) -> GatekeeperConfig {
-        GatekeeperConfig {
-            gatekeeper_network,
-            expire_on_use,
-        }
+impl ConfigData {
+    /// Parses and validates the JSON config data, without expanding environment variables.
+    pub fn from_reader<R: Read>(reader: R) -> Result<ConfigData, ConfigError> {
+        ConfigData::parse_reader(reader)?.into_validated("<reader>")
     }
 
-    pub fn into_candy_format(&self) -> CandyGatekeeperConfig {
-        CandyGatekeeperConfig {
-            gatekeeper_network: self.gatekeeper_network,
-            expire_on_use: self.expire_on_use,
-        }
+    /// Parses the JSON config data as is, without expanding environment variables or
+    /// validating its values (see [`ConfigData::validate`]).
+    pub fn parse_reader<R: Read>(reader: R) -> Result<ConfigData, ConfigError> {
+        serde_json::from_reader(reader).map_err(|err| ConfigError::ParseError(err.to_string()))
     }
-}
 
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
-pub enum EndSettingType {
-    Date,
-    Amount,
-}
+    /// Parses the config data in the specified format, replacing `${VAR}` references in
+    /// string values by the value of the corresponding environment variable. The values
+    /// are not validated.
+    pub fn parse_reader_with_env<R: Read>(
+        mut reader: R,
+        format: ConfigFormat,
+    ) -> Result<ConfigData, ConfigError> {
+        let mut contents = String::new();
+        reader
+            .read_to_string(&mut contents)
+            .map_err(|err| ConfigError::ParseError(err.to_string()))?;
 
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
-pub struct EndSettings {
-    #[serde(rename = "endSettingType")]
-    end_setting_type: EndSettingType,
-    number: u64,
-}
+        ConfigData::parse_str_with_env(&contents, format)
+    }
 
-impl EndSettings {
-    pub fn new(end_setting_type: EndSettingType, number: u64) -> EndSettings {
-        EndSettings {
-            end_setting_type,
-            number,
+    /// Parses the config data in the specified format, replacing `${VAR}` references in
+    /// string values by the value of the corresponding environment variable. The values
+    /// are not validated.
+    pub fn parse_str_with_env(
+        contents: &str,
+        format: ConfigFormat,
+    ) -> Result<ConfigData, ConfigError> {
+        // parses directly when there is nothing to expand, so that errors point to the
+        // line and column of the offending value
+        if format == ConfigFormat::Json && !contents.contains("${") {
+            return ConfigData::parse_reader(contents.as_bytes());
         }
+
+        // other formats are read into a JSON value first, so that all formats go through
+        // the same (custom) deserializers
+        let mut value: serde_json::Value = match format {
+            ConfigFormat::Json => serde_json::from_str(contents)
+                .map_err(|err| ConfigError::ParseError(err.to_string())),
+            ConfigFormat::Toml => {
+                toml::from_str(contents).map_err(|err| ConfigError::ParseError(err.to_string()))
+            }
+            ConfigFormat::Yaml => serde_yaml::from_str(contents)
+                .map_err(|err| ConfigError::ParseError(err.to_string())),
+        }?;
+        expand_env_vars_in_value(&mut value)?;
+
+        serde_json::from_value(value).map_err(|err| ConfigError::ParseError(err.to_string()))
     }
-    pub fn into_candy_format(&self) -> CandyEndSettings {
-        CandyEndSettings {
-            end_setting_type: match self.end_setting_type {
-                EndSettingType::Date => CandyEndSettingType::Date,
-                EndSettingType::Amount => CandyEndSettingType::Amount,
-            },
-            number: self.number,
-        }
+
+    /// Reads the config file, using the format of its extension unless a format is
+    /// specified. The values are not validated.
+    pub fn parse_path(
+        path: &Path,
+        format: Option<ConfigFormat>,
+    ) -> Result<ConfigData, ConfigError> {
+        let format = format.unwrap_or_else(|| ConfigFormat::from_path(path));
+        let file = File::open(path)
+            .map_err(|_| ConfigError::PermissionError(path.display().to_string()))?;
+
+        ConfigData::parse_reader_with_env(file, format)
     }
-}
 
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
-pub struct WhitelistMintSettings {
-    mode: WhitelistMintMode,
-    #[serde(deserialize_with = "to_pubkey")]
-    #[serde(serialize_with = "to_string")]
-    mint: Pubkey,
-    presale: bool,
-    discount_price: Option<f64>,
-}
+    /// Validates the config data, reporting all invalid values at once. The `source`
+    /// (e.g., the path of the config file) is included in the error message.
+    pub fn into_validated(self, source: &str) -> Result<ConfigData, ConfigError> {
+        if let Err(errors) = self.validate() {
+            let mut message = format!(
+                "Invalid config file '{}', {} error(s) found:",
+                source,
+                errors.len()
+            );
 
-impl WhitelistMintSettings {
-    pub fn new(
-        mode: WhitelistMintMode,
-        mint: Pubkey,
-        presale: bool,
-        discount_price: Option<f64>,
-    ) -> WhitelistMintSettings {
-        WhitelistMintSettings {
-            mode,
-            mint,
-            presale,
-            discount_price,
+            for err in errors {
+                error!("{:?}", err);
+                message.push_str(&style("\n=> ").dim().to_string());
+                message.push_str(&err.to_string());
+            }
+
+            return Err(ConfigError::InvalidConfig(message));
         }
+
+        Ok(self)
     }
-    pub fn into_candy_format(&self) -> CandyWhitelistMintSettings {
-        CandyWhitelistMintSettings {
-            mode: self.mode.into_candy_format(),
-            mint: self.mint,
-            presale: self.presale,
-            discount_price: discount_price_to_lamports(self.discount_price),
-        }
+
+    /// Serialize the config data to JSON with the keys of all objects sorted, so that the
+    /// output is the same regardless of the order of the fields.
+    pub fn canonical_json(&self) -> Result<String> {
+        let value = sort_json_keys(serde_json::to_value(self)?);
+        Ok(serde_json::to_string_pretty(&value)?)
     }
-}
 
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
-pub enum WhitelistMintMode {
-    BurnEveryTime,
-    NeverBurn,
-}
+    /// Check that the (nonzero) prices are not rounded to a zero amount of a token with
+    /// the given decimals, e.g., a price of `0.001` with a 2-decimals token.
+    pub fn check_price_precision(&self, decimals: u8) -> Result<(), ConfigError> {
+        let min_price = format!(
+            "{:.*}",
+            decimals as usize,
+            token_amount_as_price(1, decimals)
+        );
 
-impl WhitelistMintMode {
-    pub fn into_candy_format(&self) -> CandyWhitelistMintMode {
-        match self {
-            WhitelistMintMode::BurnEveryTime => CandyWhitelistMintMode::BurnEveryTime,
-            WhitelistMintMode::NeverBurn => CandyWhitelistMintMode::NeverBurn,
-        }
-    }
-}
+        let mut prices = vec![("price", self.price)];
 
-impl FromStr for WhitelistMintMode {
-    type Err = anyhow::Error;
+        if let Some(discount_price) = self
+            .whitelist_mint_settings
+            .as_ref()
+            .and_then(|settings| settings.discount_price)
+        {
+            prices.push(("whitelistMintSettings.discountPrice", discount_price));
+        }
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s.to_lowercase().as_str() {
-            "burneverytime" => Ok(WhitelistMintMode::BurnEveryTime),
-            "neverburn" => Ok(WhitelistMintMode::NeverBurn),
-            _ => Err(anyhow::anyhow!("Invalid whitelist mint mode: {}", s)),
+        for (field, price) in prices {
+            if price > 0.0 && price_as_token_amount(price, decimals) == 0 {
+                return Err(ConfigError::PriceBelowTokenPrecision(
+                    field.to_string(),
+                    price,
+                    decimals,
+                    min_price,
+                ));
+            }
         }
+
+        Ok(())
     }
-}
 
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
-pub struct HiddenSettings {
-    name: String,
-    uri: String,
-    hash: String,
-}
+    /// Create the candy machine data struct. The price is converted to lamports, or to
+    /// the spl-token amount when the token decimals are specified.
+    pub fn to_candy_machine_data(
+        &self,
+        uuid: &str,
+        token_decimals: Option<u8>,
+    ) -> Result<CandyMachineData> {
+        let decimals = token_decimals.unwrap_or(SOL_DECIMALS);
+        self.check_price_precision(decimals)?;
+        let price = price_as_token_amount(self.price, decimals);
 
-impl HiddenSettings {
-    pub fn new(name: String, uri: String, hash: String) -> HiddenSettings {
-        HiddenSettings { name, uri, hash }
-    }
-    pub fn into_candy_format(&self) -> CandyHiddenSettings {
-        CandyHiddenSettings {
-            name: self.name.clone(),
-            uri: self.uri.clone(),
-            hash: self
-                .hash
-                .as_bytes()
-                .try_into()
-                .expect("Hidden settings hash has to be 32 characters long!"),
+        let go_live_date = go_live_date_as_timestamp(&self.go_live_date)?;
+
+        if let Some(err) = check_creators(&self.creators).into_iter().next() {
+            return Err(err.into());
         }
-    }
-}
 
-#[derive(Debug, Clone, PartialEq, Serialize)]
-pub enum UploadMethod {
-    Bundlr,
-    AWS,
-}
+        let creators = self
+            .creators
+            .iter()
+            .map(|creator| creator.into_candy_format())
+            .collect::<Result<Vec<CandyCreator>>>()?;
 
-impl Default for UploadMethod {
-    fn default() -> UploadMethod {
-        UploadMethod::Bundlr
+        Ok(CandyMachineData {
+            uuid: uuid.to_string(),
+            price,
+            symbol: self.symbol.clone(),
+            seller_fee_basis_points: self.seller_fee_basis_points,
+            max_supply: self.max_supply.unwrap_or(0),
+            is_mutable: self.is_mutable,
+            retain_authority: self.retain_authority,
+            go_live_date,
+            end_settings: self.end_settings.as_ref().map(|s| s.into_candy_format()),
+            creators,
+            whitelist_mint_settings: self
+                .whitelist_mint_settings
+                .as_ref()
+                .map(|s| s.into_candy_format_with_decimals(decimals)),
+            hidden_settings: self
+                .onchain_hidden_settings()
+                .map(|s| s.into_candy_format()),
+            items_available: self.number,
+            gatekeeper: self.gatekeeper.as_ref().map(|g| g.into_candy_format()),
+        })
     }
-}
 
-impl FromStr for UploadMethod {
-    type Err = ConfigError;
+    /// Create the config from the candy machine data struct, the inverse of
+    /// `to_candy_machine_data`. The price is converted from lamports to SOL.
+    ///
+    /// The settings that are not stored in the candy machine data (treasury, upload
+    /// method, etc.) are left with their default values.
+    pub fn from_candy_machine_data(data: &CandyMachineData) -> ConfigData {
+        ConfigData::from_candy_machine_data_with_decimals(data, SOL_DECIMALS)
+    }
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s.to_lowercase().as_str() {
-            "bundlr" => Ok(UploadMethod::Bundlr),
-            "aws" => Ok(UploadMethod::AWS),
-            _ => Err(ConfigError::InvalidUploadMethod(s.to_string())),
+    /// Create the config from the candy machine data struct, with the prices specified
+    /// in units of a token with the given decimals.
+    pub fn from_candy_machine_data_with_decimals(
+        data: &CandyMachineData,
+        decimals: u8,
+    ) -> ConfigData {
+        ConfigData {
+            price: token_amount_as_price(data.price, decimals),
+            number: data.items_available,
+            gatekeeper: data
+                .gatekeeper
+                .as_ref()
+                .map(GatekeeperConfig::from_candy_format),
+            creators: data
+                .creators
+                .iter()
+                .map(Creator::from_candy_format)
+                .collect(),
+            go_live_date: data.go_live_date.map(timestamp_as_date),
+            end_settings: data
+                .end_settings
+                .as_ref()
+                .map(EndSettings::from_candy_format),
+            whitelist_mint_settings: data
+                .whitelist_mint_settings
+                .as_ref()
+                .map(|s| WhitelistMintSettings::from_candy_format_with_decimals(s, decimals)),
+            hidden_settings: data
+                .hidden_settings
+                .as_ref()
+                .map(HiddenSettings::from_candy_format),
+            retain_authority: data.retain_authority,
+            is_mutable: data.is_mutable,
+            symbol: data.symbol.clone(),
+            seller_fee_basis_points: data.seller_fee_basis_points,
+            max_supply: Some(data.max_supply),
+            ..Default::default()
         }
     }
-}
 
-impl ToString for UploadMethod {
-    fn to_string(&self) -> String {
-        match self {
-            UploadMethod::Bundlr => "bundlr".to_string(),
-            UploadMethod::AWS => "aws".to_string(),
-        }
+    /// Return the JSON schema of the config file.
+    pub fn json_schema() -> RootSchema {
+        schema_for!(ConfigData)
     }
-}
 
-impl<'de> Deserialize<'de> for UploadMethod {
-    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
-    where
-        D: Deserializer<'de>,
-    {
-        let s: String = Deserialize::deserialize(deserializer)?;
-        FromStr::from_str(&s).map_err(serde::de::Error::custom)
+    /// Return a copy of the config without the settings that only affect how Sugar sends
+    /// transactions and uploads (retries, fees, throttling and timeouts), which are not
+    /// part of the config hashes.
+    fn without_client_settings(&self) -> ConfigData {
+        let mut config_data = self.clone();
+        config_data.retry_config = None;
+        config_data.priority_fee = None;
+        config_data.parallel_limit = None;
+        config_data.requests_per_second = None;
+        config_data.rpc_timeout_secs = None;
+        config_data.upload_pool_size = None;
+
+        if let Some(aws_config) = &mut config_data.aws_config {
+            aws_config.parallel_limit = None;
+        }
+        if let Some(pinata_config) = &mut config_data.pinata_config {
+            pinata_config.parallel_limit = None;
+            pinata_config.requests_per_second = None;
+        }
+        if let Some(shdw_config) = &mut config_data.shdw_config {
+            shdw_config.parallel_limit = None;
+            shdw_config.requests_per_second = None;
+        }
+
+        config_data
+    }
+
+    /// Return the hidden settings stored in the candy machine. The program uses the same
+    /// hidden uri for all items, so when the uri is a template the candy machine has no
+    /// hidden settings: each item gets a placeholder config line with the expanded uri.
+    pub fn onchain_hidden_settings(&self) -> Option<&HiddenSettings> {
+        self.hidden_settings
+            .as_ref()
+            .filter(|hidden_settings| !hidden_settings.is_uri_template())
+    }
+
+    /// Return the hidden settings of the placeholder config lines, used when the hidden
+    /// settings uri is a template.
+    pub fn placeholder_settings(&self) -> Option<&HiddenSettings> {
+        self.hidden_settings
+            .as_ref()
+            .filter(|hidden_settings| hidden_settings.is_uri_template())
+    }
+
+    /// Compute the hash of the config used to create a candy machine, stored in the
+    /// deploy checkpoint. Settings that only affect how transactions are sent are not
+    /// included, so they can change between deploy attempts.
+    pub fn deploy_hash(&self) -> Result<String> {
+        let config_data = self.without_client_settings();
+
+        let mut context = Context::new(&SHA256);
+        context.update(serde_json::to_string(&config_data)?.as_bytes());
+
+        Ok(HEXLOWER.encode(context.finish().as_ref()))
+    }
+
+    /// Compute the content hash of the effective config, stored in the cache on deploy to
+    /// detect when the config changed since the last deploy.
+    ///
+    /// The hash is computed over the canonical JSON serialization (sorted keys), with
+    /// the prices resolved to lamports and the absolute go live date resolved to its
+    /// timestamp, so equivalent configs have the same hash on every run and machine.
+    /// Settings that only affect how transactions and uploads are sent are not included.
+    pub fn content_hash(&self) -> Result<[u8; 32]> {
+        let config_data = self.without_client_settings();
+
+        let mut value = serde_json::to_value(&config_data)?;
+
+        let mut price_pointers = vec![
+            "/price".to_string(),
+            "/whitelistMintSettings/discountPrice".to_string(),
+            "/guards/solPayment/value".to_string(),
+        ];
+        if let Some(schedule) = &self.schedule {
+            price_pointers.extend((0..schedule.len()).map(|i| format!("/schedule/{}/price", i)));
+        }
+
+        for pointer in price_pointers {
+            if let Some(price) = value.pointer_mut(&pointer) {
+                if let Some(amount) = price.as_f64() {
+                    *price = serde_json::Value::from(price_as_lamports(amount));
+                }
+            }
+        }
+
+        // relative dates are resolved against the current time, so they are kept as is
+        if let Some(go_live_date) = &self.go_live_date {
+            if !is_relative_date(go_live_date) {
+                value["goLiveDate"] = serde_json::Value::from(date_as_timestamp(go_live_date)?);
+            }
+        }
+
+        let mut context = Context::new(&SHA256);
+        context.update(serde_json::to_string(&sort_json_keys(value))?.as_bytes());
+
+        let mut hash = [0u8; 32];
+        hash.copy_from_slice(context.finish().as_ref());
+
+        Ok(hash)
+    }
+
+    /// Return the list of values that are most likely a mistake on the specified cluster.
+    pub fn cluster_warnings(&self, cluster: &Cluster) -> Vec<String> {
+        let mut warnings = Vec::new();
+
+        if let Cluster::Devnet = cluster {
+            if let Some(network) = self.gatekeeper.as_ref().and_then(|g| g.known_network()) {
+                warnings.push(format!(
+                    "'gatekeeper.gatekeeperNetwork' is the mainnet '{}' network: gatekeeper networks differ per cluster, make sure it is also available on devnet.",
+                    network.to_string()
+                ));
+            }
+        }
+
+        warnings
+    }
+
+    /// Check the guard values and that guards do not overlap the candy machine
+    /// settings they replace.
+    fn validate_guards(&self, guards: &GuardConfig) -> Vec<ConfigError> {
+        let mut errors = Vec::new();
+
+        let conflicts = [
+            (
+                "startDate",
+                guards.start_date.is_some(),
+                "goLiveDate",
+                self.go_live_date.is_some(),
+            ),
+            (
+                "solPayment",
+                guards.sol_payment.is_some(),
+                "splToken",
+                self.treasury.spl_token().is_some(),
+            ),
+            (
+                "allowList",
+                guards.allow_list.is_some(),
+                "whitelistMintSettings",
+                self.whitelist_mint_settings.is_some(),
+            ),
+        ];
+
+        for (guard, guard_set, setting, setting_set) in conflicts {
+            if guard_set && setting_set {
+                errors.push(ConfigError::ConflictingGuard(
+                    guard.to_string(),
+                    setting.to_string(),
+                ));
+            }
+        }
+
+        if let Some(start_date) = &guards.start_date {
+            match start_date.into_guard_format() {
+                Ok(start) => {
+                    // the mint must start before it ends
+                    if let Some(end_settings) = &self.end_settings {
+                        if end_settings.end_setting_type == EndSettingType::Date
+                            && end_settings.number as i64 <= start
+                        {
+                            errors.push(ConfigError::InvalidGuard(
+                                "startDate".to_string(),
+                                format!(
+                                    "the start date must be before the 'endSettings' date ({})",
+                                    end_settings.number
+                                ),
+                            ));
+                        }
+                    }
+                }
+                Err(err) => errors.push(ConfigError::InvalidGuard(
+                    "startDate".to_string(),
+                    err.to_string(),
+                )),
+            }
+        }
+
+        if let Some(sol_payment) = &guards.sol_payment {
+            if sol_payment.value.is_nan() || sol_payment.value < 0.0 {
+                errors.push(ConfigError::InvalidGuard(
+                    "solPayment".to_string(),
+                    format!("the value {} cannot be negative", sol_payment.value),
+                ));
+            }
+        }
+
+        if let Some(mint_limit) = &guards.mint_limit {
+            if mint_limit.limit == 0 {
+                errors.push(ConfigError::InvalidGuard(
+                    "mintLimit".to_string(),
+                    "the limit must be at least 1".to_string(),
+                ));
+            }
+        }
+
+        if let Some(allow_list) = &guards.allow_list {
+            if let Err(err) = allow_list.into_guard_format() {
+                errors.push(ConfigError::InvalidGuard(
+                    "allowList".to_string(),
+                    err.to_string(),
+                ));
+            }
+        }
+
+        errors
+    }
+
+    /// Check that the schedule entries are sorted by (distinct) start dates, starting
+    /// at the go live date.
+    fn validate_schedule(&self, schedule: &[ScheduleEntry]) -> Vec<ConfigError> {
+        let mut errors = Vec::new();
+        let mut previous: Option<i64> = None;
+
+        for (index, entry) in schedule.iter().enumerate() {
+            if entry.price.is_nan() || entry.price < 0.0 {
+                errors.push(ConfigError::InvalidSchedulePrice(index, entry.price));
+            }
+
+            let start = match date_as_timestamp(&entry.start) {
+                Ok(start) => start,
+                Err(_) => {
+                    errors.push(ConfigError::InvalidScheduleStart(
+                        index,
+                        entry.start.clone(),
+                    ));
+                    continue;
+                }
+            };
+
+            if let Some(previous) = previous {
+                if start <= previous {
+                    errors.push(ConfigError::UnsortedSchedule(index, entry.start.clone()));
+                }
+            }
+
+            previous = Some(start);
+        }
+
+        if let Some(first) = schedule.first() {
+            // an invalid go live date is reported when converting the config
+            let go_live_date = go_live_date_as_timestamp(&self.go_live_date);
+
+            if let (Ok(go_live_date), Ok(start)) = (go_live_date, date_as_timestamp(&first.start)) {
+                if go_live_date != Some(start) {
+                    errors.push(ConfigError::ScheduleGoLiveMismatch(first.start.clone()));
+                }
+            }
+        } else {
+            errors.push(ConfigError::EmptySchedule);
+        }
+
+        errors
+    }
+
+    /// Resolve the relative start dates of the schedule (e.g., "+1d") that are not in
+    /// `resolved` yet, adding their timestamps. Relative dates are resolved against the
+    /// current time, so they are resolved once and stored (in the cache) to keep the
+    /// schedule from moving between runs. Returns `true` if any date was added.
+    pub fn resolve_schedule_dates(
+        &self,
+        resolved: &mut BTreeMap<String, i64>,
+    ) -> Result<bool, ConfigError> {
+        let mut added = false;
+
+        for entry in self.schedule.iter().flatten() {
+            if is_relative_date(&entry.start) && !resolved.contains_key(&entry.start) {
+                resolved.insert(entry.start.clone(), date_as_timestamp(&entry.start)?);
+                added = true;
+            }
+        }
+
+        Ok(added)
+    }
+
+    /// Return the price of the schedule entry active at the specified time, or the
+    /// price of the first entry when the schedule has not started yet. Relative start
+    /// dates use their timestamp in `resolved` (see [`ConfigData::resolve_schedule_dates`]).
+    pub fn scheduled_price(
+        &self,
+        now: i64,
+        resolved: &BTreeMap<String, i64>,
+    ) -> Result<Option<f64>> {
+        let schedule = match &self.schedule {
+            Some(schedule) => schedule,
+            None => return Ok(None),
+        };
+
+        let mut price = schedule.first().map(|entry| entry.price);
+
+        for entry in schedule {
+            let start = match resolved.get(&entry.start) {
+                Some(timestamp) => *timestamp,
+                None => date_as_timestamp(&entry.start)?,
+            };
+
+            if start <= now {
+                price = Some(entry.price);
+            }
+        }
+
+        Ok(price)
+    }
+
+    /// Return the maximum number of upload requests per second: the rate set in the
+    /// config of the upload method, otherwise the `requestsPerSecond` setting.
+    pub fn upload_requests_per_second(&self) -> Option<f64> {
+        let method_rate = match self.upload_method {
+            UploadMethod::Pinata => self
+                .pinata_config
+                .as_ref()
+                .and_then(|c| c.requests_per_second),
+            UploadMethod::ShdwDrive => self
+                .shdw_config
+                .as_ref()
+                .and_then(|c| c.requests_per_second),
+            _ => None,
+        };
+
+        method_rate.or(self.requests_per_second)
+    }
+
+    /// Return the maximum number of concurrent uploads: the limit set in the config of
+    /// the upload method, otherwise the `parallelLimit` setting or the `default` limit.
+    pub fn upload_parallel_limit(&self, default: usize) -> usize {
+        let method_limit = match self.upload_method {
+            UploadMethod::AWS => self.aws_config.as_ref().and_then(|c| c.parallel_limit),
+            UploadMethod::Pinata => self.pinata_config.as_ref().and_then(|c| c.parallel_limit),
+            UploadMethod::ShdwDrive => self.shdw_config.as_ref().and_then(|c| c.parallel_limit),
+            _ => None,
+        };
+
+        method_limit
+            .or(self.parallel_limit)
+            .map(|limit| cmp::max(limit as usize, 1))
+            .unwrap_or(default)
+    }
+
+    /// Return the maximum number of idle connections to keep open to the storage: the
+    /// `uploadPoolSize` setting, otherwise the number of concurrent uploads.
+    pub fn upload_pool_size(&self, parallel_limit: usize) -> usize {
+        self.upload_pool_size
+            .map(|size| size as usize)
+            .unwrap_or(parallel_limit)
+    }
+
+    /// Return the list of values that are valid but most likely a mistake.
+    pub fn warnings(&self) -> Vec<String> {
+        let mut warnings = Vec::new();
+
+        if !self.retain_authority {
+            warnings.push(RETAIN_AUTHORITY_WARNING.to_string());
+        }
+
+        if let Some(whitelist_mint_settings) = &self.whitelist_mint_settings {
+            if whitelist_mint_settings.presale && whitelist_mint_settings.discount_price.is_none() {
+                warnings.push(PRESALE_WITHOUT_DISCOUNT_WARNING.to_string());
+            }
+
+            if whitelist_mint_settings.presale && self.go_live_date.is_none() {
+                warnings.push(NULL_GO_LIVE_WITH_PRESALE_WARNING.to_string());
+            }
+
+            if !whitelist_mint_settings.presale && whitelist_mint_settings.is_free() {
+                warnings.push(FREE_WITHOUT_PRESALE_WARNING.to_string());
+            }
+
+            if whitelist_mint_settings.mint_limit.is_some() {
+                warnings.push(WHITELIST_MINT_LIMIT_WARNING.to_string());
+            }
+        }
+
+        // "now" is live on deploy on purpose, but an absolute date in the past is most
+        // likely a typo
+        if let Some(go_live_date) = &self.go_live_date {
+            if !go_live_date.eq_ignore_ascii_case("now") {
+                if let Ok(Some((timestamp, date))) = resolve_go_live_date(&self.go_live_date) {
+                    if timestamp < Utc::now().timestamp() {
+                        warnings.push(format!("'goLiveDate' {} ({}) is in the past: the drop will be live as soon as the candy machine is deployed.", date, timestamp));
+                    }
+                }
+            }
+        }
+
+        if self.hidden_settings.is_some() && self.max_supply.unwrap_or(0) > 0 {
+            warnings.push(HIDDEN_WITH_MAX_SUPPLY_WARNING.to_string());
+        }
+
+        warnings
+    }
+
+    /// Check the config invariants, returning all violations found (not only the first one).
+    pub fn validate(&self) -> Result<(), Vec<ConfigError>> {
+        let mut errors = Vec::new();
+
+        if self.price.is_nan() || self.price <= 0.0 {
+            errors.push(ConfigError::InvalidPrice(self.price));
+        }
+
+        if self.number == 0
+            || get_candy_machine_account_size(self.number) > MAX_PERMITTED_DATA_LENGTH as usize
+        {
+            errors.push(ConfigError::InvalidNumber(self.number));
+        }
+
+        if self.symbol.len() > MAX_SYMBOL_LENGTH {
+            errors.push(ConfigError::InvalidSymbol(self.symbol.clone()));
+        }
+
+        if self.seller_fee_basis_points > 10000 {
+            errors.push(ConfigError::InvalidSellerFeeBasisPoints(
+                self.seller_fee_basis_points,
+            ));
+        }
+
+        errors.extend(check_creators(&self.creators));
+
+        if let Some(end_settings) = &self.end_settings {
+            match end_settings.end_setting_type {
+                EndSettingType::Amount => {
+                    if end_settings.number > self.number {
+                        errors.push(ConfigError::InvalidEndSettingsAmount(
+                            end_settings.number,
+                            self.number,
+                        ));
+                    }
+                }
+                EndSettingType::Date => {
+                    // an invalid go live date is reported when converting the config
+                    if let Ok(Some(go_live_date)) = go_live_date_as_timestamp(&self.go_live_date) {
+                        let duration = end_settings.number as i64 - go_live_date;
+
+                        if duration <= 0 {
+                            errors.push(ConfigError::InvalidEndSettingsDate(
+                                end_settings.number,
+                                go_live_date,
+                            ));
+                        } else if duration < MIN_MINT_DURATION {
+                            errors.push(ConfigError::EndSettingsDateTooClose(
+                                end_settings.number,
+                                go_live_date,
+                                duration,
+                            ));
+                        }
+                    }
+                }
+            }
+        }
+
+        if let Some(schedule) = &self.schedule {
+            errors.extend(self.validate_schedule(schedule));
+        }
+
+        if let Some(whitelist_mint_settings) = &self.whitelist_mint_settings {
+            if let Some(discount_price) = whitelist_mint_settings.discount_price {
+                if discount_price.is_nan() || discount_price < 0.0 {
+                    errors.push(ConfigError::NegativeDiscountPrice(discount_price));
+                } else if discount_price > self.price {
+                    errors.push(ConfigError::InvalidDiscountPrice(
+                        discount_price,
+                        self.price,
+                    ));
+                }
+            }
+
+            if whitelist_mint_settings.mint_limit == Some(0) {
+                errors.push(ConfigError::InvalidWhitelistMintLimit);
+            }
+        }
+
+        // a hidden settings drop is revealed by updating the metadata of the minted
+        // NFTs, which is not possible when they are immutable
+        if self.hidden_settings.is_some() && !self.is_mutable {
+            errors.push(ConfigError::ImmutableHiddenSettings);
+        }
+
+        if let Some(hidden_settings) = &self.hidden_settings {
+            if !hidden_settings.is_valid_name_template() {
+                errors.push(ConfigError::HiddenNameTemplateNotSupported(
+                    hidden_settings.name.clone(),
+                ));
+            } else if let Err(error) = hidden_settings.check_name_length(self.number) {
+                errors.push(error);
+            }
+
+            // the longest uri is the one expanded with the highest index
+            let uri = hidden_settings.uri_for_index(self.number.saturating_sub(1));
+            if uri.len() > MAX_URI_LENGTH {
+                errors.push(ConfigError::HiddenUriTooLong(
+                    uri.clone(),
+                    uri.len(),
+                    MAX_URI_LENGTH,
+                ));
+            }
+        }
+
+        if let Some(freeze_settings) = &self.freeze_settings {
+            if freeze_settings.freeze_time <= 0 || freeze_settings.freeze_time > MAX_FREEZE_TIME {
+                errors.push(ConfigError::InvalidFreezeTime(
+                    freeze_settings.freeze_time,
+                    MAX_FREEZE_TIME,
+                ));
+            }
+
+            // the linked candy machine program has no 'set_freeze' instruction, so the
+            // config is rejected before anything is deployed
+            errors.push(ConfigError::FreezeNotSupported(
+                CANDY_MACHINE_ID.to_string(),
+            ));
+        }
+
+        if let Some(guards) = &self.guards {
+            errors.extend(self.validate_guards(guards));
+            errors.push(ConfigError::GuardsNotSupported(
+                CANDY_MACHINE_ID.to_string(),
+            ));
+        }
+
+        let rates = [
+            ("requestsPerSecond", self.requests_per_second),
+            (
+                "pinataConfig.requestsPerSecond",
+                self.pinata_config
+                    .as_ref()
+                    .and_then(|c| c.requests_per_second),
+            ),
+            (
+                "shdwConfig.requestsPerSecond",
+                self.shdw_config
+                    .as_ref()
+                    .and_then(|c| c.requests_per_second),
+            ),
+        ];
+
+        for (field, rate) in rates {
+            if let Some(rate) = rate {
+                if rate.is_nan() || rate <= 0.0 {
+                    errors.push(ConfigError::InvalidRequestsPerSecond(
+                        field.to_string(),
+                        rate,
+                    ));
+                }
+            }
+        }
+
+        let url_templates = [
+            ("animationUrlTemplate", &self.animation_url_template),
+            ("externalUrlTemplate", &self.external_url_template),
+        ];
+
+        for (field, template) in url_templates {
+            if let Some(template) = template {
+                if let Err(err) = Url::parse(&expand_index_template(template, "0")) {
+                    errors.push(ConfigError::InvalidUrlTemplate(
+                        field.to_string(),
+                        template.clone(),
+                        err.to_string(),
+                    ));
+                }
+            }
+        }
+
+        if let Some(metadata_encoding) = &self.metadata_encoding {
+            if !self.upload_method.supports_content_encoding() {
+                errors.push(ConfigError::ContentEncodingNotSupported(
+                    metadata_encoding.to_string(),
+                    self.upload_method.to_string(),
+                ));
+            }
+        }
+
+        if self.rpc_timeout_secs == Some(0) {
+            errors.push(ConfigError::InvalidRpcTimeout);
+        }
+
+        if let Some(retry_config) = &self.retry_config {
+            if retry_config.base_delay_ms > retry_config.max_delay_ms {
+                errors.push(ConfigError::InvalidRetryConfig(
+                    retry_config.base_delay_ms,
+                    retry_config.max_delay_ms,
+                ));
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
     }
 }
 
-#[derive(Debug, Clone, Deserialize, Default, Serialize)]
-pub struct Creator {
-    #[serde(deserialize_with = "to_pubkey")]
-    #[serde(serialize_with = "to_string")]
-    pub address: Pubkey,
-    pub share: u8,
+fn sort_json_keys(value: serde_json::Value) -> serde_json::Value {
+    match value {
+        serde_json::Value::Object(map) => {
+            let mut entries: Vec<(String, serde_json::Value)> = map.into_iter().collect();
+            entries.sort_by(|a, b| a.0.cmp(&b.0));
+
+            serde_json::Value::Object(
+                entries
+                    .into_iter()
+                    .map(|(key, value)| (key, sort_json_keys(value)))
+                    .collect(),
+            )
+        }
+        serde_json::Value::Array(values) => {
+            serde_json::Value::Array(values.into_iter().map(sort_json_keys).collect())
+        }
+        value => value,
+    }
 }
 
-impl Creator {
-    pub fn into_candy_format(&self) -> Result<CandyCreator> {
-        let creator = CandyCreator {
-            address: self.address,
-            share: self.share,
-            verified: false,
-        };
+fn default_true() -> bool {
+    true
+}
 
-        Ok(creator)
+pub fn to_string<T, S>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+where
+    T: Display,
+    S: Serializer,
+{
+    serializer.collect_str(value)
+}
+
+pub fn to_option_string<T, S>(value: &Option<T>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    T: Display,
+    S: Serializer,
+{
+    match value {
+        Some(v) => serializer.collect_str(&v),
+        None => serializer.serialize_none(),
     }
 }
 
-#[derive(Debug, Clone, Serialize)]
-pub enum Cluster {
-    Devnet,
-    Mainnet,
+pub fn parse_string_as_date(go_live_date: &str) -> Result<String> {
+    let date = DateTime::parse_from_str(go_live_date, "%Y-%m-%d %H:%M:%S %z")?;
+    Ok(date.to_rfc2822())
 }
 
-impl FromStr for Cluster {
-    type Err = anyhow::Error;
+/// Convert the go live date into a timestamp, where `None` (not live yet) is kept
+/// as `None` since this is the value the candy machine expects.
+pub fn go_live_date_as_timestamp(
+    go_live_date: &Option<String>,
+) -> Result<Option<i64>, ConfigError> {
+    match go_live_date {
+        Some(date) => Ok(Some(date_as_timestamp(date)?)),
+        None => Ok(None),
+    }
+}
 
-    fn from_str(s: &str) -> Result<Self> {
-        match s {
-            "devnet" => Ok(Cluster::Devnet),
-            "mainnet" => Ok(Cluster::Mainnet),
-            _ => Err(ConfigError::InvalidCluster(s.to_string()).into()),
+/// Resolve the go live date into its timestamp and the normalized UTC (RFC3339) date,
+/// e.g., `(1704067200, "2024-01-01T00:00:00Z")`.
+pub fn resolve_go_live_date(
+    go_live_date: &Option<String>,
+) -> Result<Option<(i64, String)>, ConfigError> {
+    Ok(go_live_date_as_timestamp(go_live_date)?.map(|timestamp| {
+        let date = DateTime::<Utc>::from_utc(NaiveDateTime::from_timestamp(timestamp, 0), Utc);
+        (timestamp, date.to_rfc3339_opts(SecondsFormat::Secs, true))
+    }))
+}
+
+/// Convert a timestamp into a (RFC3339) go live date.
+pub fn timestamp_as_date(timestamp: i64) -> String {
+    DateTime::<Utc>::from_utc(NaiveDateTime::from_timestamp(timestamp, 0), Utc).to_rfc3339()
+}
+
+/// Return `true` if the date is relative to the current time ("now" or an offset such as
+/// "+1d").
+pub fn is_relative_date(date: &str) -> bool {
+    date.eq_ignore_ascii_case("now") || date.starts_with('+')
+}
+
+pub fn date_as_timestamp(go_live_date: &str) -> Result<i64, ConfigError> {
+    let format;
+    if go_live_date.eq_ignore_ascii_case("now") {
+        format = Utc::now().timestamp();
+    } else if let Some(offset) = go_live_date.strip_prefix('+') {
+        // relative dates are resolved against the current time
+        match relative_offset_as_seconds(offset) {
+            Some(seconds) => format = Utc::now().timestamp() + seconds,
+            None => return Err(ConfigError::InvalidRelativeDate(go_live_date.to_string())),
         }
+    } else if let Ok(date) = chrono::DateTime::parse_from_rfc2822(go_live_date) {
+        format = date.timestamp();
+    } else if let Ok(date) = chrono::DateTime::parse_from_rfc3339(go_live_date) {
+        format = date.timestamp();
+    } else if let Ok(timestamp) = go_live_date.parse::<i64>() {
+        format = timestamp;
+    } else {
+        return Err(ConfigError::InvalidDateFormat);
+    };
+
+    Ok(format)
+}
+
+/// Convert a relative offset (e.g., "30m", "2h", "7d") into seconds.
+fn relative_offset_as_seconds(offset: &str) -> Option<i64> {
+    let unit = offset.chars().last()?;
+    let amount = offset[..offset.len() - unit.len_utf8()]
+        .parse::<u32>()
+        .ok()? as i64;
+
+    match unit {
+        's' => Some(amount),
+        'm' => Some(amount * 60),
+        'h' => Some(amount * 60 * 60),
+        'd' => Some(amount * 60 * 60 * 24),
+        _ => None,
     }
 }
 
-impl ToString for Cluster {
-    fn to_string(&self) -> String {
-        match self {
-            Cluster::Devnet => "devnet".to_string(),
-            Cluster::Mainnet => "mainnet".to_string(),
+pub fn price_as_lamports(price: f64) -> u64 {
+    price_as_token_amount(price, SOL_DECIMALS)
+}
+
+/// Format lamports as SOL for display, with up to 9 decimals and no trailing zeros
+/// (e.g., "0.000000001", "1", "1.5"). The formatting uses integer arithmetic, so the
+/// value shown is exact.
+pub fn format_sol(lamports: u64) -> String {
+    let fraction = format!("{:09}", lamports % LAMPORTS_PER_SOL);
+    let fraction = fraction.trim_end_matches('0');
+
+    if fraction.is_empty() {
+        (lamports / LAMPORTS_PER_SOL).to_string()
+    } else {
+        format!("{}.{}", lamports / LAMPORTS_PER_SOL, fraction)
+    }
+}
+
+/// Convert a price into the base units of a token with the specified decimals,
+/// rounding half-up to the nearest unit.
+///
+/// The conversion uses `f64` arithmetic, so it is only exact while the resulting
+/// amount is below 2^53 (e.g., ~9 million tokens for a mint with 9 decimals);
+/// prices should also not specify more fractional digits than the token decimals,
+/// since any extra digits are rounded away.
+pub fn price_as_token_amount(price: f64, decimals: u8) -> u64 {
+    (price * 10f64.powi(decimals as i32)).round() as u64
+}
+
+/// Convert an amount in base units of a token with the specified decimals into a price,
+/// the inverse of `price_as_token_amount`.
+pub fn token_amount_as_price(amount: u64, decimals: u8) -> f64 {
+    amount as f64 / 10f64.powi(decimals as i32)
+}
+
+/// Parse the pubkey value of a config field. The error includes the name of the field
+/// and a hint of what is wrong with the value.
+pub fn parse_pubkey(field: &str, value: &str) -> Result<Pubkey, ConfigError> {
+    Pubkey::from_str(value).map_err(|_| {
+        ConfigError::InvalidPubkey(field.to_string(), value.to_string(), pubkey_hint(value))
+    })
+}
+
+/// Return a hint of why the value is not a valid (base58) pubkey.
+fn pubkey_hint(value: &str) -> String {
+    let mut not_base58: Vec<char> = Vec::new();
+
+    for c in value.chars().filter(|c| matches!(c, '0' | 'O' | 'I' | 'l')) {
+        if !not_base58.contains(&c) {
+            not_base58.push(c);
+        }
+    }
+
+    if !not_base58.is_empty() {
+        let chars: Vec<String> = not_base58.iter().map(|c| format!("'{}'", c)).collect();
+        return format!(
+            "contains {}, which is not valid in base58 (0, O, I and l are not used)",
+            chars.join(", ")
+        );
+    }
+
+    if let Some(c) = value.chars().find(|c| !c.is_ascii_alphanumeric()) {
+        return format!("contains the invalid character {:?}", c);
+    }
+
+    match bs58::decode(value).into_vec() {
+        Ok(bytes) if bytes.len() < 32 => format!(
+            "too short, decodes to {} bytes but a pubkey has 32 (is the value complete?)",
+            bytes.len()
+        ),
+        Ok(bytes) => format!(
+            "too long, decodes to {} bytes but a pubkey has 32",
+            bytes.len()
+        ),
+        Err(err) => err.to_string(),
+    }
+}
+
+fn to_pubkey<'de, D>(deserializer: D, field: &str) -> Result<Pubkey, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let s: String = Deserialize::deserialize(deserializer)?;
+    parse_pubkey(field, &s).map_err(serde::de::Error::custom)
+}
+
+/// Deserializes an optional pubkey: a `null` value or an empty string yields `None`, while
+/// a malformed value is an error (the field must also be marked with `#[serde(default)]`
+/// so that a missing field yields `None`).
+fn to_option_pubkey<'de, D>(deserializer: D, field: &str) -> Result<Option<Pubkey>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let s: Option<String> = Deserialize::deserialize(deserializer)?;
+
+    match s {
+        Some(s) if !s.is_empty() => parse_pubkey(field, &s)
+            .map(Some)
+            .map_err(serde::de::Error::custom),
+        _ => Ok(None),
+    }
+}
+
+// serde does not pass the name of the field to the deserializer, so each pubkey field
+// has its own function
+
+fn to_sol_treasury_account<'de, D>(deserializer: D) -> Result<Option<Pubkey>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    to_option_pubkey(deserializer, "solTreasuryAccount")
+}
+
+fn to_spl_token_account<'de, D>(deserializer: D) -> Result<Option<Pubkey>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    to_option_pubkey(deserializer, "splTokenAccount")
+}
+
+fn to_spl_token<'de, D>(deserializer: D) -> Result<Option<Pubkey>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    to_option_pubkey(deserializer, "splToken")
+}
+
+fn to_collection<'de, D>(deserializer: D) -> Result<Option<Pubkey>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    to_option_pubkey(deserializer, "collection")
+}
+
+fn to_shdw_storage_account<'de, D>(deserializer: D) -> Result<Option<Pubkey>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    to_option_pubkey(deserializer, "shdwConfig.storageAccount")
+}
+
+fn to_whitelist_mint<'de, D>(deserializer: D) -> Result<Pubkey, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    to_pubkey(deserializer, "whitelistMintSettings.mint")
+}
+
+/// Deserialize the end setting type ignoring the case (e.g., "date" or "Date").
+fn to_end_setting_type<'de, D>(deserializer: D) -> Result<EndSettingType, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = String::deserialize(deserializer)?;
+    EndSettingType::from_str(&value).map_err(serde::de::Error::custom)
+}
+
+fn to_sol_payment_destination<'de, D>(deserializer: D) -> Result<Pubkey, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    to_pubkey(deserializer, "guards.solPayment.destination")
+}
+
+fn to_creator_address<'de, D>(deserializer: D) -> Result<Pubkey, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    to_pubkey(deserializer, "creators.address")
+}
+
+/// Convert the discount price into a token amount. A discount price of zero is kept
+/// as `Some(0)` (whitelisted users mint for free), while `None` means that there is
+/// no discount and whitelisted users pay the full price.
+fn discount_price_to_token_amount(discount_price: Option<f64>, decimals: u8) -> Option<u64> {
+    discount_price.map(|price| price_as_token_amount(price, decimals))
+}
+
+/// Treasury settings of the config file, parsed from the `solTreasuryAccount`,
+/// `splToken` and `splTokenAccount` keys. Settings that conflict are rejected while
+/// the config is parsed.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(try_from = "TreasuryKeys", into = "TreasuryKeys")]
+pub enum TreasuryConfig {
+    /// No treasury set: the payer receives the proceeds in SOL
+    #[default]
+    Payer,
+    /// SOL wallet
+    Sol(Pubkey),
+    /// Spl-token used as payment and the token account that receives it (by default,
+    /// the associated token account of the payer)
+    Spl {
+        token: Pubkey,
+        account: Option<Pubkey>,
+    },
+}
+
+impl TreasuryConfig {
+    pub fn sol_treasury_account(&self) -> Option<Pubkey> {
+        match self {
+            TreasuryConfig::Sol(wallet) => Some(*wallet),
+            _ => None,
+        }
+    }
+
+    pub fn spl_token(&self) -> Option<Pubkey> {
+        match self {
+            TreasuryConfig::Spl { token, .. } => Some(*token),
+            _ => None,
+        }
+    }
+
+    pub fn spl_token_account(&self) -> Option<Pubkey> {
+        match self {
+            TreasuryConfig::Spl { account, .. } => *account,
+            _ => None,
+        }
+    }
+
+    /// Return the treasury that receives the proceeds of the mint: when no treasury is
+    /// set, the `payer` is the SOL treasury; when the spl-token account is not set, the
+    /// associated token account of the `payer` is used.
+    pub fn resolve(&self, payer: &Pubkey) -> Treasury {
+        match self {
+            TreasuryConfig::Payer => Treasury::Sol(*payer),
+            TreasuryConfig::Sol(wallet) => Treasury::Sol(*wallet),
+            TreasuryConfig::Spl { token, account } => Treasury::Spl {
+                token: *token,
+                account: account.unwrap_or_else(|| get_associated_token_address(payer, token)),
+            },
+        }
+    }
+}
+
+/// Treasury keys of the config file.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+struct TreasuryKeys {
+    #[serde(default, deserialize_with = "to_sol_treasury_account")]
+    #[schemars(with = "Option<String>")]
+    #[serde(serialize_with = "to_option_string")]
+    sol_treasury_account: Option<Pubkey>,
+
+    #[serde(default, deserialize_with = "to_spl_token_account")]
+    #[schemars(with = "Option<String>")]
+    #[serde(serialize_with = "to_option_string")]
+    spl_token_account: Option<Pubkey>,
+
+    #[serde(default, deserialize_with = "to_spl_token")]
+    #[schemars(with = "Option<String>")]
+    #[serde(serialize_with = "to_option_string")]
+    spl_token: Option<Pubkey>,
+}
+
+impl TryFrom<TreasuryKeys> for TreasuryConfig {
+    type Error = ConfigError;
+
+    fn try_from(keys: TreasuryKeys) -> Result<Self, Self::Error> {
+        match (
+            keys.sol_treasury_account,
+            keys.spl_token,
+            keys.spl_token_account,
+        ) {
+            (Some(_), Some(_), _) | (Some(_), _, Some(_)) => Err(ConfigError::ConflictingTreasury),
+            (None, None, Some(_)) => Err(ConfigError::SplTokenAccountWithoutToken),
+            (Some(wallet), None, None) if wallet == Pubkey::default() => {
+                Err(ConfigError::InvalidSolTreasuryAccount(wallet.to_string()))
+            }
+            (Some(wallet), None, None) => Ok(TreasuryConfig::Sol(wallet)),
+            (None, None, None) => Ok(TreasuryConfig::Payer),
+            (None, Some(token), account) => Ok(TreasuryConfig::Spl { token, account }),
+        }
+    }
+}
+
+impl From<TreasuryConfig> for TreasuryKeys {
+    fn from(treasury: TreasuryConfig) -> Self {
+        TreasuryKeys {
+            sol_treasury_account: treasury.sol_treasury_account(),
+            spl_token_account: treasury.spl_token_account(),
+            spl_token: treasury.spl_token(),
+        }
+    }
+}
+
+/// The treasury is written as the flat keys of the config file.
+impl JsonSchema for TreasuryConfig {
+    fn schema_name() -> String {
+        TreasuryKeys::schema_name()
+    }
+
+    fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+        TreasuryKeys::json_schema(gen)
+    }
+}
+
+/// Treasury that receives the proceeds of the mint.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Treasury {
+    /// SOL wallet
+    Sol(Pubkey),
+    /// Token account of the spl-token used as payment
+    Spl { token: Pubkey, account: Pubkey },
+}
+
+impl Treasury {
+    /// Return the account set as the wallet of the candy machine.
+    pub fn wallet(&self) -> Pubkey {
+        match self {
+            Treasury::Sol(wallet) => *wallet,
+            Treasury::Spl { account, .. } => *account,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct GatekeeperConfig {
+    /// The network for the gateway token required (either a pubkey or the name
+    /// of a known network)
+    #[serde(deserialize_with = "to_gatekeeper_network")]
+    #[schemars(with = "String")]
+    #[serde(serialize_with = "to_string")]
+    gatekeeper_network: Pubkey,
+    /// Whether or not the token should expire after minting.
+    /// The gatekeeper network must support this if true.
+    expire_on_use: bool,
+}
+
+impl GatekeeperConfig {
+    pub fn new(gatekeeper_network: Pubkey, expire_on_use: bool) -> GatekeeperConfig {
+        GatekeeperConfig {
+            gatekeeper_network,
+            expire_on_use,
+        }
+    }
+
+    pub fn into_candy_format(&self) -> CandyGatekeeperConfig {
+        CandyGatekeeperConfig {
+            gatekeeper_network: self.gatekeeper_network,
+            expire_on_use: self.expire_on_use,
+        }
+    }
+
+    pub fn from_candy_format(gatekeeper: &CandyGatekeeperConfig) -> GatekeeperConfig {
+        GatekeeperConfig::new(gatekeeper.gatekeeper_network, gatekeeper.expire_on_use)
+    }
+
+    /// Return the known network of the gateway token, if there is one.
+    pub fn known_network(&self) -> Option<GatekeeperNetwork> {
+        GatekeeperNetwork::from_pubkey(&self.gatekeeper_network)
+    }
+}
+
+/// Well-known (mainnet) gatekeeper networks.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GatekeeperNetwork {
+    CivicCaptcha,
+    CivicUniqueness,
+    CivicIdVerification,
+    EncoreVerify,
+}
+
+impl GatekeeperNetwork {
+    const ALL: [GatekeeperNetwork; 4] = [
+        GatekeeperNetwork::CivicCaptcha,
+        GatekeeperNetwork::CivicUniqueness,
+        GatekeeperNetwork::CivicIdVerification,
+        GatekeeperNetwork::EncoreVerify,
+    ];
+
+    pub fn pubkey(&self) -> Pubkey {
+        let address = match self {
+            GatekeeperNetwork::CivicCaptcha => CIVIC_NETWORK,
+            GatekeeperNetwork::CivicUniqueness => CIVIC_UNIQUENESS_NETWORK,
+            GatekeeperNetwork::CivicIdVerification => CIVIC_ID_VERIFICATION_NETWORK,
+            GatekeeperNetwork::EncoreVerify => ENCORE_NETWORK,
+        };
+
+        Pubkey::from_str(address).expect("Invalid gatekeeper network address.")
+    }
+
+    pub fn from_pubkey(pubkey: &Pubkey) -> Option<GatekeeperNetwork> {
+        GatekeeperNetwork::ALL
+            .iter()
+            .find(|network| network.pubkey() == *pubkey)
+            .copied()
+    }
+}
+
+impl FromStr for GatekeeperNetwork {
+    type Err = ConfigError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // accepts any case and separator (civicCaptcha, civic-captcha, CIVIC_CAPTCHA)
+        match s.to_lowercase().replace(&['-', '_'][..], "").as_str() {
+            "civiccaptcha" => Ok(GatekeeperNetwork::CivicCaptcha),
+            "civicuniqueness" => Ok(GatekeeperNetwork::CivicUniqueness),
+            "civicidverification" => Ok(GatekeeperNetwork::CivicIdVerification),
+            "encoreverify" => Ok(GatekeeperNetwork::EncoreVerify),
+            _ => Err(ConfigError::InvalidGatekeeperNetwork(s.to_string())),
+        }
+    }
+}
+
+impl ToString for GatekeeperNetwork {
+    fn to_string(&self) -> String {
+        match self {
+            GatekeeperNetwork::CivicCaptcha => "civicCaptcha".to_string(),
+            GatekeeperNetwork::CivicUniqueness => "civicUniqueness".to_string(),
+            GatekeeperNetwork::CivicIdVerification => "civicIdVerification".to_string(),
+            GatekeeperNetwork::EncoreVerify => "encoreVerify".to_string(),
+        }
+    }
+}
+
+fn to_gatekeeper_network<'de, D>(deserializer: D) -> Result<Pubkey, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let s: String = Deserialize::deserialize(deserializer)?;
+
+    match Pubkey::from_str(&s) {
+        Ok(pubkey) => Ok(pubkey),
+        Err(_) => GatekeeperNetwork::from_str(&s)
+            .map(|network| network.pubkey())
+            .map_err(serde::de::Error::custom),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub enum EndSettingType {
+    Date,
+    Amount,
+}
+
+impl FromStr for EndSettingType {
+    type Err = ConfigError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "date" => Ok(EndSettingType::Date),
+            "amount" => Ok(EndSettingType::Amount),
+            _ => Err(ConfigError::InvalidEndSettingType(s.to_string())),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct EndSettings {
+    #[serde(rename = "endSettingType", deserialize_with = "to_end_setting_type")]
+    end_setting_type: EndSettingType,
+    number: u64,
+}
+
+impl EndSettings {
+    pub fn new(end_setting_type: EndSettingType, number: u64) -> EndSettings {
+        EndSettings {
+            end_setting_type,
+            number,
+        }
+    }
+    pub fn into_candy_format(&self) -> CandyEndSettings {
+        CandyEndSettings {
+            end_setting_type: match self.end_setting_type {
+                EndSettingType::Date => CandyEndSettingType::Date,
+                EndSettingType::Amount => CandyEndSettingType::Amount,
+            },
+            number: self.number,
+        }
+    }
+
+    pub fn from_candy_format(end_settings: &CandyEndSettings) -> EndSettings {
+        EndSettings::new(
+            match end_settings.end_setting_type {
+                CandyEndSettingType::Date => EndSettingType::Date,
+                CandyEndSettingType::Amount => EndSettingType::Amount,
+            },
+            end_settings.number,
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct WhitelistMintSettings {
+    mode: WhitelistMintMode,
+    #[serde(deserialize_with = "to_whitelist_mint")]
+    #[schemars(with = "String")]
+    #[serde(serialize_with = "to_string")]
+    mint: Pubkey,
+    presale: bool,
+    /// Price paid by whitelisted users: `0.0` means that they mint for free and
+    /// `None` that they pay the full price.
+    discount_price: Option<f64>,
+    /// Number of whitelist mints of each holder. This is not enforced by the candy
+    /// machine v2 program: `burnEveryTime` allows one mint per whitelist token held
+    /// and `neverBurn` unlimited mints. It is only enforced by the `mintLimit` guard
+    /// of candy guard (candy machine v3).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    mint_limit: Option<u64>,
+}
+
+impl WhitelistMintSettings {
+    pub fn new(
+        mode: WhitelistMintMode,
+        mint: Pubkey,
+        presale: bool,
+        discount_price: Option<f64>,
+    ) -> WhitelistMintSettings {
+        WhitelistMintSettings {
+            mode,
+            mint,
+            presale,
+            discount_price,
+            mint_limit: None,
+        }
+    }
+
+    /// Return `true` when whitelisted users mint for free (discount price of zero).
+    pub fn is_free(&self) -> bool {
+        self.discount_price == Some(0.0)
+    }
+
+    pub fn into_candy_format(&self) -> CandyWhitelistMintSettings {
+        self.into_candy_format_with_decimals(SOL_DECIMALS)
+    }
+
+    /// Convert into the candy machine format, with the discount price specified in
+    /// units of a token with the given decimals.
+    pub fn into_candy_format_with_decimals(&self, decimals: u8) -> CandyWhitelistMintSettings {
+        CandyWhitelistMintSettings {
+            mode: self.mode.into_candy_format(),
+            mint: self.mint,
+            presale: self.presale,
+            discount_price: discount_price_to_token_amount(self.discount_price, decimals),
+        }
+    }
+
+    /// Convert from the candy machine format, with the discount price specified in
+    /// units of a token with the given decimals.
+    pub fn from_candy_format_with_decimals(
+        settings: &CandyWhitelistMintSettings,
+        decimals: u8,
+    ) -> WhitelistMintSettings {
+        WhitelistMintSettings::new(
+            WhitelistMintMode::from_candy_format(&settings.mode),
+            settings.mint,
+            settings.presale,
+            settings
+                .discount_price
+                .map(|amount| token_amount_as_price(amount, decimals)),
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum WhitelistMintMode {
+    BurnEveryTime,
+    NeverBurn,
+}
+
+impl WhitelistMintMode {
+    pub fn into_candy_format(&self) -> CandyWhitelistMintMode {
+        match self {
+            WhitelistMintMode::BurnEveryTime => CandyWhitelistMintMode::BurnEveryTime,
+            WhitelistMintMode::NeverBurn => CandyWhitelistMintMode::NeverBurn,
+        }
+    }
+
+    pub fn from_candy_format(mode: &CandyWhitelistMintMode) -> WhitelistMintMode {
+        match mode {
+            CandyWhitelistMintMode::BurnEveryTime => WhitelistMintMode::BurnEveryTime,
+            CandyWhitelistMintMode::NeverBurn => WhitelistMintMode::NeverBurn,
+        }
+    }
+}
+
+impl FromStr for WhitelistMintMode {
+    type Err = ConfigError;
+
+    /// Parse the mode ignoring the case and the separators, so "burnEveryTime",
+    /// "burn-every-time", "burn_every_time" and "burn every time" are the same mode.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mode: String = s
+            .chars()
+            .filter(|c| !matches!(c, '-' | '_' | ' '))
+            .collect::<String>()
+            .to_lowercase();
+
+        match mode.as_str() {
+            "burneverytime" => Ok(WhitelistMintMode::BurnEveryTime),
+            "neverburn" => Ok(WhitelistMintMode::NeverBurn),
+            _ => Err(ConfigError::InvalidWhitelistMintMode(s.to_string())),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for WhitelistMintMode {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s: String = Deserialize::deserialize(deserializer)?;
+        FromStr::from_str(&s).map_err(serde::de::Error::custom)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ScheduleEntry {
+    /// Date when the price becomes active
+    pub start: String,
+    /// Price from the start date (until the start of the next entry)
+    pub price: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FreezeSettings {
+    /// Number of seconds the minted NFTs stay frozen (unless the candy machine
+    /// sells out first).
+    freeze_time: i64,
+}
+
+impl FreezeSettings {
+    pub fn new(freeze_time: i64) -> FreezeSettings {
+        FreezeSettings { freeze_time }
+    }
+
+    /// Return the freeze time (in seconds) as expected by the program's
+    /// `set_freeze` instruction.
+    pub fn into_candy_format(&self) -> i64 {
+        self.freeze_time
+    }
+}
+
+/// Guards of a candy guard; every guard is optional.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct GuardConfig {
+    pub start_date: Option<StartDateGuard>,
+    pub sol_payment: Option<SolPaymentGuard>,
+    pub mint_limit: Option<MintLimitGuard>,
+    pub allow_list: Option<AllowListGuard>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct StartDateGuard {
+    /// Date when the mint starts
+    pub date: String,
+}
+
+impl StartDateGuard {
+    /// Return the start date as a timestamp.
+    pub fn into_guard_format(&self) -> Result<i64, ConfigError> {
+        date_as_timestamp(&self.date)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SolPaymentGuard {
+    /// Price of the mint in SOL
+    pub value: f64,
+    /// Account that receives the payment
+    #[serde(deserialize_with = "to_sol_payment_destination")]
+    #[schemars(with = "String")]
+    #[serde(serialize_with = "to_string")]
+    pub destination: Pubkey,
+}
+
+impl SolPaymentGuard {
+    /// Return the price (in lamports) and the destination of the payment.
+    pub fn into_guard_format(&self) -> (u64, Pubkey) {
+        (price_as_lamports(self.value), self.destination)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MintLimitGuard {
+    /// Identifier of the limit, so different limits can be tracked
+    pub id: u8,
+    /// Maximum number of mints per wallet
+    pub limit: u16,
+}
+
+impl MintLimitGuard {
+    pub fn into_guard_format(&self) -> (u8, u16) {
+        (self.id, self.limit)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AllowListGuard {
+    /// Hex encoded root of the merkle tree of the allowed wallets
+    pub merkle_root: String,
+}
+
+impl AllowListGuard {
+    /// Return the bytes of the merkle root.
+    pub fn into_guard_format(&self) -> Result<[u8; 32], ConfigError> {
+        let bytes = HEXLOWER_PERMISSIVE
+            .decode(self.merkle_root.as_bytes())
+            .map_err(|err| {
+                ConfigError::InvalidMerkleRoot(self.merkle_root.clone(), err.to_string())
+            })?;
+
+        bytes.try_into().map_err(|bytes: Vec<u8>| {
+            ConfigError::InvalidMerkleRootLength(self.merkle_root.clone(), bytes.len())
+        })
+    }
+}
+
+/// Token of the hidden settings uri replaced by the index of the item.
+pub const URI_INDEX_TOKEN: &str = "{index}";
+
+/// End of a hidden settings name template: the program appends '#' and the mint number
+/// to the name of each NFT, so this is where the number is placed.
+pub const NAME_INDEX_SUFFIX: &str = "#{index}";
+
+/// Replace the `{index}` tokens of a template by the index of an item.
+pub fn expand_index_template(template: &str, index: &str) -> String {
+    template.replace(URI_INDEX_TOKEN, index)
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct HiddenSettings {
+    /// Name of the placeholder NFTs. The mint number is appended to the name (e.g.,
+    /// "Mystery #1"), or it can be a template ending with `#{index}` (e.g.,
+    /// "Mystery #{index}").
+    name: String,
+    /// Uri of the placeholder metadata, which can be a template with an `{index}`
+    /// token for flows that need a distinct uri per item. The candy machine then has a
+    /// placeholder config line per item instead of hidden settings.
+    uri: String,
+    hash: String,
+}
+
+impl HiddenSettings {
+    pub fn new(name: String, uri: String, hash: String) -> HiddenSettings {
+        HiddenSettings { name, uri, hash }
+    }
+
+    /// Return `true` if the name is a template with an `{index}` token.
+    pub fn is_name_template(&self) -> bool {
+        self.name.contains(URI_INDEX_TOKEN)
+    }
+
+    /// Return `true` if the name is static or a template that the program can expand:
+    /// a single `{index}` token at the end, after a '#'.
+    pub fn is_valid_name_template(&self) -> bool {
+        !self.is_name_template()
+            || (self.name.ends_with(NAME_INDEX_SUFFIX)
+                && self.name.matches(URI_INDEX_TOKEN).count() == 1)
+    }
+
+    /// Return the name of the placeholder NFT of an item (using the index of the cache
+    /// file): the program appends '#' and the mint number (the index plus one) to the
+    /// on-chain name, which is what the `{index}` token of a template expands to.
+    pub fn name_for_index(&self, index: u64) -> String {
+        let number = (index + 1).to_string();
+
+        if self.is_name_template() {
+            expand_index_template(&self.name, &number)
+        } else {
+            expand_index_template(&format!("{}{}", self.name, NAME_INDEX_SUFFIX), &number)
+        }
+    }
+
+    /// Check that the names of the placeholder NFTs of `number` items fit in a config
+    /// line. The longest name is the one of the highest index.
+    pub fn check_name_length(&self, number: u64) -> Result<(), ConfigError> {
+        let name = self.name_for_index(number.saturating_sub(1));
+
+        if name.len() > MAX_NAME_LENGTH {
+            return Err(ConfigError::HiddenNameTooLong(
+                name.clone(),
+                name.len(),
+                MAX_NAME_LENGTH,
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Return the name stored in the candy machine: the name without the `#{index}`
+    /// suffix of a template, since the program appends the mint number itself.
+    pub fn onchain_name(&self) -> &str {
+        self.name
+            .strip_suffix(NAME_INDEX_SUFFIX)
+            .unwrap_or(&self.name)
+    }
+
+    /// Return `true` if the uri is a template with an `{index}` token.
+    pub fn is_uri_template(&self) -> bool {
+        self.uri.contains(URI_INDEX_TOKEN)
+    }
+
+    /// Return the uri of an item (using the index of the cache file): the `{index}`
+    /// tokens of a template are replaced by the index, otherwise the uri is the same
+    /// for all items.
+    pub fn uri_for_index(&self, index: u64) -> String {
+        expand_index_template(&self.uri, &index.to_string())
+    }
+
+    /// Return the placeholder config line of an item, used instead of the hidden settings
+    /// when the uri is a template: the name and uri of the placeholder NFT.
+    pub fn placeholder_config_line(&self, index: u64) -> ConfigLine {
+        ConfigLine {
+            name: self.name_for_index(index),
+            uri: self.uri_for_index(index),
+        }
+    }
+
+    /// Compute the hidden settings hash from the items of the cache file.
+    ///
+    /// The hash is the (hex) MD5 digest of the cache items, which is 32 characters
+    /// long, the size of the hash stored by the program. Unlike the MD5 of the whole
+    /// cache file, the program section of the cache is not included, so the hash does
+    /// not change when the candy machine address is written to the cache.
+    pub fn compute_hash(cache_path: &Path) -> Result<[u8; 32]> {
+        Ok(hash_of_digest(&HiddenSettings::compute_digest(cache_path)?))
+    }
+
+    /// Compute the (raw) MD5 digest of the items of the cache file.
+    pub fn compute_digest(cache_path: &Path) -> Result<[u8; 16]> {
+        Ok(md5::compute(cache_items_json(cache_path)?).0)
+    }
+
+    /// Compute the hash of the cache items used before the MD5 hash: the first 32
+    /// characters of their (hex) SHA256 digest. Candy machines deployed with this hash
+    /// can still be revealed.
+    pub fn compute_legacy_hash(cache_path: &Path) -> Result<[u8; 32]> {
+        Ok(legacy_hash(cache_items_json(cache_path)?.as_bytes()))
+    }
+
+    /// Return `true` if the hash matches the cache items, either as the MD5 hash or
+    /// as the hash used before it.
+    pub fn matches_cache(hash: &[u8; 32], cache_path: &Path) -> Result<bool> {
+        Ok(*hash == HiddenSettings::compute_hash(cache_path)?
+            || *hash == HiddenSettings::compute_legacy_hash(cache_path)?)
+    }
+
+    /// Return `true` if the hash was left unset (empty or all zeros) in the config
+    /// file and it should be computed from the cache.
+    pub fn is_hash_unset(&self) -> bool {
+        self.hash.chars().all(|c| c == '0')
+    }
+
+    pub fn set_hash(&mut self, hash: [u8; 32]) -> Result<()> {
+        self.hash = String::from_utf8(hash.to_vec())?;
+        Ok(())
+    }
+
+    pub fn into_candy_format(&self) -> CandyHiddenSettings {
+        CandyHiddenSettings {
+            name: self.onchain_name().to_string(),
+            uri: self.uri.clone(),
+            hash: self
+                .hash
+                .as_bytes()
+                .try_into()
+                .expect("Hidden settings hash has to be 32 characters long!"),
+        }
+    }
+
+    pub fn from_candy_format(hidden_settings: &CandyHiddenSettings) -> HiddenSettings {
+        HiddenSettings::new(
+            hidden_settings.name.clone(),
+            hidden_settings.uri.clone(),
+            String::from_utf8_lossy(&hidden_settings.hash).to_string(),
+        )
+    }
+}
+
+/// Return the serialized items of the cache file, which are the input of the hidden
+/// settings hash.
+fn cache_items_json(cache_path: &Path) -> Result<String> {
+    let cache = load_cache(&path_to_string(cache_path)?, false)?;
+    Ok(serde_json::to_string(&cache.items)?)
+}
+
+/// Return the hidden settings hash of a MD5 digest, i.e., its hex encoding.
+fn hash_of_digest(digest: &[u8; 16]) -> [u8; 32] {
+    let mut hash = [0u8; 32];
+    hash.copy_from_slice(HEXLOWER.encode(digest).as_bytes());
+    hash
+}
+
+/// Return the first 32 characters of the (hex) SHA256 digest of the data.
+fn legacy_hash(data: &[u8]) -> [u8; 32] {
+    let mut context = Context::new(&SHA256);
+    context.update(data);
+    let digest = HEXLOWER.encode(context.finish().as_ref());
+
+    let mut hash = [0u8; 32];
+    hash.copy_from_slice(&digest.as_bytes()[..32]);
+    hash
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ConfigFormat {
+    #[default]
+    Json,
+    Toml,
+    Yaml,
+}
+
+impl ConfigFormat {
+    /// Return the format matching the extension of the file (JSON when the extension
+    /// is not recognized).
+    pub fn from_path(path: &Path) -> ConfigFormat {
+        path.extension()
+            .and_then(|extension| extension.to_str())
+            .and_then(|extension| ConfigFormat::from_str(extension).ok())
+            .unwrap_or_default()
+    }
+}
+
+impl FromStr for ConfigFormat {
+    type Err = ConfigError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "json" => Ok(ConfigFormat::Json),
+            "toml" => Ok(ConfigFormat::Toml),
+            "yaml" | "yml" => Ok(ConfigFormat::Yaml),
+            _ => Err(ConfigError::InvalidConfigFormat(s.to_string())),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Default)]
+pub enum UploadMethod {
+    #[default]
+    Bundlr,
+    AWS,
+    NftStorage,
+    Pinata,
+    ShdwDrive,
+}
+
+impl FromStr for UploadMethod {
+    type Err = ConfigError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "bundlr" => Ok(UploadMethod::Bundlr),
+            "aws" => Ok(UploadMethod::AWS),
+            "nft.storage" | "nftstorage" => Ok(UploadMethod::NftStorage),
+            "pinata" => Ok(UploadMethod::Pinata),
+            "shdw" | "shdwdrive" | "shdw-drive" => Ok(UploadMethod::ShdwDrive),
+            _ => Err(ConfigError::InvalidUploadMethod(s.to_string())),
+        }
+    }
+}
+
+impl UploadMethod {
+    /// Return the maximum size (in bytes) of a single file accepted by the storage, if
+    /// there is a limit.
+    pub fn max_file_size(&self) -> Option<u64> {
+        match self {
+            UploadMethod::AWS => Some(AWS_MAX_FILE_SIZE),
+            UploadMethod::NftStorage => Some(NFT_STORAGE_MAX_FILE_SIZE),
+            UploadMethod::Bundlr | UploadMethod::Pinata | UploadMethod::ShdwDrive => None,
+        }
+    }
+
+    /// Return `true` if the storage serves the files with the content encoding set on
+    /// upload, so compressed files can be decompressed by the clients.
+    pub fn supports_content_encoding(&self) -> bool {
+        match self {
+            UploadMethod::AWS | UploadMethod::Bundlr => true,
+            UploadMethod::NftStorage | UploadMethod::Pinata | UploadMethod::ShdwDrive => false,
+        }
+    }
+}
+
+/// Content encoding (compression) of the uploaded files.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ContentEncoding {
+    Gzip,
+}
+
+impl Display for ContentEncoding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ContentEncoding::Gzip => write!(f, "gzip"),
+        }
+    }
+}
+
+impl ToString for UploadMethod {
+    fn to_string(&self) -> String {
+        match self {
+            UploadMethod::Bundlr => "bundlr".to_string(),
+            UploadMethod::AWS => "aws".to_string(),
+            UploadMethod::NftStorage => "nft.storage".to_string(),
+            UploadMethod::Pinata => "pinata".to_string(),
+            UploadMethod::ShdwDrive => "shdw".to_string(),
+        }
+    }
+}
+
+/// The upload method is parsed from its name (ignoring the case), so the schema lists
+/// the names accepted instead of the variants.
+impl JsonSchema for UploadMethod {
+    fn schema_name() -> String {
+        "UploadMethod".to_string()
+    }
+
+    fn json_schema(_gen: &mut SchemaGenerator) -> Schema {
+        let names = [
+            "bundlr",
+            "aws",
+            "nft.storage",
+            "nftstorage",
+            "pinata",
+            "shdw",
+            "shdwdrive",
+            "shdw-drive",
+        ];
+        let variants = ["Bundlr", "AWS", "NftStorage", "Pinata", "ShdwDrive"];
+
+        SchemaObject {
+            instance_type: Some(InstanceType::String.into()),
+            enum_values: Some(
+                names
+                    .iter()
+                    .chain(&variants)
+                    .map(|n| serde_json::Value::from(*n))
+                    .collect(),
+            ),
+            ..Default::default()
+        }
+        .into()
+    }
+}
+
+impl<'de> Deserialize<'de> for UploadMethod {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s: String = Deserialize::deserialize(deserializer)?;
+        FromStr::from_str(&s).map_err(serde::de::Error::custom)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AwsConfig {
+    /// Name of the S3 bucket
+    pub bucket: String,
+    /// Region of the bucket (defaults to the region of the AWS profile)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub region: Option<String>,
+    /// Name of the AWS profile used for credentials (defaults to the AWS_PROFILE)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+    /// Directory (key prefix) where the files are uploaded to
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub directory: Option<String>,
+    /// Maximum number of concurrent uploads
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parallel_limit: Option<u16>,
+}
+
+impl AwsConfig {
+    pub fn new(
+        bucket: String,
+        region: Option<String>,
+        profile: Option<String>,
+        directory: Option<String>,
+        parallel_limit: Option<u16>,
+    ) -> AwsConfig {
+        AwsConfig {
+            bucket,
+            region,
+            profile,
+            directory,
+            parallel_limit,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PinataConfig {
+    /// JWT used to authenticate with the Pinata API
+    pub jwt: String,
+    /// Gateway used to build the URIs of the pinned files
+    pub gateway_url: String,
+    /// Maximum number of concurrent uploads
+    pub parallel_limit: Option<u16>,
+    /// Maximum number of requests per second
+    pub requests_per_second: Option<f64>,
+}
+
+impl PinataConfig {
+    pub fn new(
+        jwt: String,
+        gateway_url: String,
+        parallel_limit: Option<u16>,
+        requests_per_second: Option<f64>,
+    ) -> PinataConfig {
+        PinataConfig {
+            jwt,
+            gateway_url,
+            parallel_limit,
+            requests_per_second,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ShdwConfig {
+    /// Address of the Shadow Drive storage account (owned by the keypair), a storage
+    /// account is created for the upload when it is not set
+    #[serde(default, deserialize_with = "to_shdw_storage_account")]
+    #[schemars(with = "Option<String>")]
+    #[serde(
+        serialize_with = "to_option_string",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub storage_account: Option<Pubkey>,
+    /// Size (in bytes) of the storage account created for the upload, which is never
+    /// less than the size of the files to upload
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+    /// Maximum number of concurrent uploads
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parallel_limit: Option<u16>,
+    /// Maximum number of requests per second
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub requests_per_second: Option<f64>,
+}
+
+impl ShdwConfig {
+    pub fn new(
+        storage_account: Option<Pubkey>,
+        size: Option<u64>,
+        parallel_limit: Option<u16>,
+        requests_per_second: Option<f64>,
+    ) -> ShdwConfig {
+        ShdwConfig {
+            storage_account,
+            size,
+            parallel_limit,
+            requests_per_second,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RetryConfig {
+    /// Maximum number of retries of a failed RPC call (0 disables retries)
+    pub max_retries: u32,
+    /// Delay before the first retry, doubled on every retry
+    pub base_delay_ms: u64,
+    /// Maximum delay between retries
+    pub max_delay_ms: u64,
+}
+
+impl Default for RetryConfig {
+    fn default() -> RetryConfig {
+        RetryConfig {
+            max_retries: 3,
+            base_delay_ms: 500,
+            max_delay_ms: 8000,
+        }
+    }
+}
+
+impl RetryConfig {
+    /// Return the delay (in ms) before the specified retry attempt (starting at 1).
+    ///
+    /// The delay grows exponentially up to `max_delay_ms` and a random jitter of up to
+    /// half of the delay is added, so parallel requests do not retry at the same time.
+    pub fn delay_ms(&self, attempt: u32) -> u64 {
+        let delay = self
+            .base_delay_ms
+            .saturating_mul(2u64.saturating_pow(attempt.saturating_sub(1)))
+            .min(self.max_delay_ms);
+
+        delay + rand::thread_rng().gen_range(0, delay / 2 + 1)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, JsonSchema, Default, Serialize, PartialEq)]
+pub struct Creator {
+    #[serde(deserialize_with = "to_creator_address")]
+    #[schemars(with = "String")]
+    #[serde(serialize_with = "to_string")]
+    pub address: Pubkey,
+    pub share: u8,
+}
+
+impl Creator {
+    pub fn into_candy_format(&self) -> Result<CandyCreator> {
+        let creator = CandyCreator {
+            address: self.address,
+            share: self.share,
+            verified: false,
+        };
+
+        Ok(creator)
+    }
+
+    pub fn from_candy_format(creator: &CandyCreator) -> Creator {
+        Creator {
+            address: creator.address,
+            share: creator.share,
+        }
+    }
+}
+
+/// Check the number of creators, their shares and that there are no duplicated
+/// addresses, returning all violations found.
+pub fn check_creators(creators: &[Creator]) -> Vec<ConfigError> {
+    let mut errors = Vec::new();
+
+    // the candy machine is added as the first creator, so there is one less
+    // creator available
+    if creators.is_empty() || creators.len() > (MAX_CREATOR_LIMIT - 1) {
+        errors.push(ConfigError::InvalidCreators(creators.len()));
+    }
+
+    let share: u32 = creators.iter().map(|c| c.share as u32).sum();
+
+    if share != 100 {
+        errors.push(ConfigError::InvalidCreatorShares(share));
+    }
+
+    for (index, creator) in creators.iter().enumerate() {
+        if creators[..index]
+            .iter()
+            .any(|c| c.address == creator.address)
+        {
+            errors.push(ConfigError::DuplicateCreator(creator.address.to_string()));
+        }
+    }
+
+    errors
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub enum Cluster {
+    Devnet,
+    Mainnet,
+}
+
+impl FromStr for Cluster {
+    type Err = ConfigError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "devnet" => Ok(Cluster::Devnet),
+            "mainnet" => Ok(Cluster::Mainnet),
+            _ => Err(ConfigError::InvalidCluster(s.to_string())),
+        }
+    }
+}
+
+impl ToString for Cluster {
+    fn to_string(&self) -> String {
+        match self {
+            Cluster::Devnet => "devnet".to_string(),
+            Cluster::Mainnet => "mainnet".to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_lang::AnchorSerialize;
+
+    /// Config with every field set, so that a round trip goes through all the custom
+    /// serializers and deserializers.
+    fn full_config() -> ConfigData {
+        ConfigData {
+            price: 1.5,
+            number: 10,
+            gatekeeper: Some(GatekeeperConfig::new(Pubkey::new_unique(), true)),
+            creators: vec![
+                Creator {
+                    address: Pubkey::new_unique(),
+                    share: 60,
+                },
+                Creator {
+                    address: Pubkey::new_unique(),
+                    share: 40,
+                },
+            ],
+            treasury: TreasuryConfig::Spl {
+                token: Pubkey::new_unique(),
+                account: Some(Pubkey::new_unique()),
+            },
+            go_live_date: Some("2022-06-01T12:00:00Z".to_string()),
+            end_settings: Some(EndSettings::new(EndSettingType::Amount, 5)),
+            schedule: Some(vec![ScheduleEntry {
+                start: "2022-06-02T12:00:00Z".to_string(),
+                price: 2.0,
+            }]),
+            whitelist_mint_settings: Some(WhitelistMintSettings {
+                mode: WhitelistMintMode::BurnEveryTime,
+                mint: Pubkey::new_unique(),
+                presale: true,
+                discount_price: Some(0.5),
+                mint_limit: Some(2),
+            }),
+            hidden_settings: Some(HiddenSettings::new(
+                "Mystery".to_string(),
+                "https://example.com/hidden.json".to_string(),
+                "0".repeat(32),
+            )),
+            freeze_settings: Some(FreezeSettings::new(86400)),
+            guards: Some(GuardConfig {
+                start_date: Some(StartDateGuard {
+                    date: "2022-06-01T12:00:00Z".to_string(),
+                }),
+                sol_payment: Some(SolPaymentGuard {
+                    value: 1.5,
+                    destination: Pubkey::new_unique(),
+                }),
+                mint_limit: Some(MintLimitGuard { id: 1, limit: 3 }),
+                allow_list: Some(AllowListGuard {
+                    merkle_root: "ab".repeat(32),
+                }),
+            }),
+            collection: Some(Pubkey::new_unique()),
+            upload_method: UploadMethod::ShdwDrive,
+            parallel_limit: Some(8),
+            requests_per_second: Some(4.0),
+            retain_authority: false,
+            is_mutable: false,
+            aws_s3_bucket: Some("bucket".to_string()),
+            aws_config: Some(AwsConfig::new(
+                "bucket".to_string(),
+                Some("us-east-1".to_string()),
+                Some("default".to_string()),
+                Some("assets".to_string()),
+                Some(4),
+            )),
+            nft_storage_auth_token: Some("token".to_string()),
+            pinata_config: Some(PinataConfig::new(
+                "jwt".to_string(),
+                "https://gateway.pinata.cloud".to_string(),
+                Some(2),
+                Some(3.0),
+            )),
+            shdw_config: Some(ShdwConfig::new(
+                Some(Pubkey::new_unique()),
+                Some(1024),
+                Some(2),
+                Some(3.0),
+            )),
+            retry_config: Some(RetryConfig {
+                max_retries: 5,
+                base_delay_ms: 100,
+                max_delay_ms: 1000,
+            }),
+            rpc_timeout_secs: Some(30),
+            upload_pool_size: Some(4),
+            priority_fee: Some(1000),
+            symbol: "SYM".to_string(),
+            seller_fee_basis_points: 500,
+            max_supply: Some(0),
+            animation_url_template: Some("https://example.com/{index}.mp4".to_string()),
+            external_url_template: Some("https://example.com/{index}".to_string()),
+            metadata_encoding: Some(ContentEncoding::Gzip),
+        }
+    }
+
+    #[test]
+    fn canonical_json_round_trip() {
+        let config = full_config();
+        let json = config.canonical_json().unwrap();
+        let parsed = ConfigData::parse_reader(json.as_bytes()).unwrap();
+
+        assert_eq!(parsed.treasury, config.treasury);
+        assert_eq!(parsed.collection, config.collection);
+        assert_eq!(parsed.creators, config.creators);
+        assert_eq!(parsed.gatekeeper, config.gatekeeper);
+        assert_eq!(
+            parsed.whitelist_mint_settings,
+            config.whitelist_mint_settings
+        );
+        assert_eq!(parsed.guards, config.guards);
+        assert_eq!(parsed.end_settings, config.end_settings);
+        assert_eq!(parsed.hidden_settings, config.hidden_settings);
+        assert_eq!(parsed.upload_method, config.upload_method);
+        assert_eq!(parsed, config);
+
+        // the output does not depend on the order of the fields
+        assert_eq!(parsed.canonical_json().unwrap(), json);
+    }
+
+    #[test]
+    fn canonical_json_round_trip_without_optional_pubkeys() {
+        let config = ConfigData {
+            creators: vec![Creator {
+                address: Pubkey::new_unique(),
+                share: 100,
+            }],
+            ..ConfigData::default()
+        };
+        let parsed = ConfigData::parse_reader(config.canonical_json().unwrap().as_bytes()).unwrap();
+
+        assert_eq!(parsed.treasury, TreasuryConfig::Payer);
+        assert_eq!(parsed.collection, None);
+        assert_eq!(parsed, config);
+    }
+
+    #[test]
+    fn canonical_json_sorts_keys() {
+        let json = full_config().canonical_json().unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        let keys: Vec<&String> = value.as_object().unwrap().keys().collect();
+        let mut sorted = keys.clone();
+        sorted.sort();
+
+        assert_eq!(keys, sorted);
+    }
+
+    /// Return the errors of a JSON value against a schema, checking the keywords that the
+    /// generated schema uses (`$ref`, `anyOf`, `type`, `enum`, `required`, `properties`
+    /// and `items`). Keys that are not in the properties of an object are reported, since
+    /// they are ignored when the config is parsed.
+    fn schema_errors(
+        value: &serde_json::Value,
+        schema: &serde_json::Value,
+        root: &serde_json::Value,
+        path: &str,
+    ) -> Vec<String> {
+        use serde_json::Value;
+
+        if let Some(reference) = schema["$ref"].as_str() {
+            let name = reference.trim_start_matches("#/definitions/");
+            return schema_errors(value, &root["definitions"][name], root, path);
+        }
+
+        if let Some(schemas) = schema["anyOf"].as_array() {
+            let valid = schemas
+                .iter()
+                .any(|schema| schema_errors(value, schema, root, path).is_empty());
+            return if valid {
+                Vec::new()
+            } else {
+                vec![format!("{}: {} does not match any schema", path, value)]
+            };
+        }
+
+        let mut errors = Vec::new();
+
+        let types: Vec<&str> = match &schema["type"] {
+            Value::String(instance_type) => vec![instance_type.as_str()],
+            Value::Array(types) => types.iter().filter_map(Value::as_str).collect(),
+            _ => Vec::new(),
+        };
+        let type_matches = |instance_type: &str| match instance_type {
+            "null" => value.is_null(),
+            "boolean" => value.is_boolean(),
+            "integer" => value.is_u64() || value.is_i64(),
+            "number" => value.is_number(),
+            "string" => value.is_string(),
+            "array" => value.is_array(),
+            "object" => value.is_object(),
+            _ => false,
+        };
+        if !types.is_empty() && !types.iter().any(|t| type_matches(t)) {
+            errors.push(format!("{}: {} is not of type {:?}", path, value, types));
+        }
+
+        if let Some(values) = schema["enum"].as_array() {
+            if !values.contains(value) {
+                errors.push(format!("{}: {} is not one of {:?}", path, value, values));
+            }
+        }
+
+        if let Value::Object(object) = value {
+            for key in schema["required"].as_array().into_iter().flatten() {
+                if !object.contains_key(key.as_str().unwrap()) {
+                    errors.push(format!("{}: missing required key {}", path, key));
+                }
+            }
+
+            if let Some(properties) = schema["properties"].as_object() {
+                for (key, value) in object {
+                    let path = format!("{}.{}", path, key);
+                    match properties.get(key) {
+                        Some(schema) => errors.extend(schema_errors(value, schema, root, &path)),
+                        None => errors.push(format!("{}: unknown key", path)),
+                    }
+                }
+            }
+        }
+
+        if let Value::Array(items) = value {
+            for (index, item) in items.iter().enumerate() {
+                let path = format!("{}[{}]", path, index);
+                errors.extend(schema_errors(item, &schema["items"], root, &path));
+            }
+        }
+
+        errors
+    }
+
+    /// Return the errors of a config, serialized as JSON, against the schema.
+    fn config_schema_errors(config: &ConfigData) -> Vec<String> {
+        let schema = serde_json::to_value(ConfigData::json_schema()).unwrap();
+        let value = serde_json::to_value(config).unwrap();
+        schema_errors(&value, &schema, &schema, "$")
+    }
+
+    #[test]
+    fn serialized_config_matches_schema() {
+        let config = full_config();
+        assert_eq!(config_schema_errors(&config), Vec::<String>::new());
+
+        let value = serde_json::to_value(&config).unwrap();
+        assert!(value.get("splToken").is_some());
+        assert!(value.get("splTokenAccount").is_some());
+        assert_eq!(value["whitelistMintSettings"]["mode"], "burnEveryTime");
+
+        // the treasury wallet is written with its config key
+        let config = ConfigData {
+            treasury: TreasuryConfig::Sol(Pubkey::new_unique()),
+            ..full_config()
+        };
+        assert_eq!(config_schema_errors(&config), Vec::<String>::new());
+        let value = serde_json::to_value(&config).unwrap();
+        assert!(value.get("solTreasuryAccount").is_some());
+        assert!(value.get("sol_treasury_account").is_none());
+
+        for upload_method in [
+            UploadMethod::Bundlr,
+            UploadMethod::AWS,
+            UploadMethod::NftStorage,
+            UploadMethod::Pinata,
+            UploadMethod::ShdwDrive,
+        ] {
+            let config = ConfigData {
+                upload_method: upload_method.clone(),
+                ..full_config()
+            };
+            assert_eq!(
+                config_schema_errors(&config),
+                Vec::<String>::new(),
+                "{:?}",
+                upload_method
+            );
+        }
+
+        for (mode, name) in [
+            (WhitelistMintMode::BurnEveryTime, "burnEveryTime"),
+            (WhitelistMintMode::NeverBurn, "neverBurn"),
+        ] {
+            let mut config = full_config();
+            config.whitelist_mint_settings.as_mut().unwrap().mode = mode;
+            assert_eq!(config_schema_errors(&config), Vec::<String>::new());
+            assert_eq!(
+                serde_json::to_value(&config).unwrap()["whitelistMintSettings"]["mode"],
+                name
+            );
+        }
+
+        // a key that is not in the schema is reported
+        let schema = serde_json::to_value(ConfigData::json_schema()).unwrap();
+        let mut value = serde_json::to_value(full_config()).unwrap();
+        value["sol_treasury_account"] = Pubkey::new_unique().to_string().into();
+        assert_eq!(
+            schema_errors(&value, &schema, &schema, "$"),
+            vec!["$.sol_treasury_account: unknown key".to_string()]
+        );
+    }
+
+    /// Assert that a relative date resolves to the current time plus an offset (allowing
+    /// for the clock to tick during the test).
+    fn assert_relative(date: &str, offset: i64) {
+        let before = Utc::now().timestamp();
+        let timestamp = date_as_timestamp(date).unwrap();
+        let after = Utc::now().timestamp();
+
+        assert!(
+            (before + offset..=after + offset).contains(&timestamp),
+            "'{}' resolved to {}",
+            date,
+            timestamp
+        );
+    }
+
+    #[test]
+    fn date_now() {
+        assert_relative("now", 0);
+        assert_relative("NOW", 0);
+    }
+
+    #[test]
+    fn date_relative_offsets() {
+        assert_relative("+45s", 45);
+        assert_relative("+30m", 30 * 60);
+        assert_relative("+2h", 2 * 60 * 60);
+        assert_relative("+7d", 7 * 24 * 60 * 60);
+    }
+
+    #[test]
+    fn date_invalid_relative_offsets() {
+        for date in ["+5x", "+h", "+", "+-2h", "+2.5h"] {
+            assert!(
+                matches!(
+                    date_as_timestamp(date),
+                    Err(ConfigError::InvalidRelativeDate(ref value)) if value == date
+                ),
+                "'{}' was accepted",
+                date
+            );
+        }
+    }
+
+    #[test]
+    fn date_rfc3339() {
+        assert_eq!(
+            date_as_timestamp("2022-02-25T13:00:00Z").unwrap(),
+            1645794000
+        );
+        assert_eq!(
+            date_as_timestamp("2022-02-25T08:00:00-05:00").unwrap(),
+            1645794000
+        );
+    }
+
+    #[test]
+    fn date_rfc2822() {
+        assert_eq!(
+            date_as_timestamp("Fri, 25 Feb 2022 13:00:00 +0000").unwrap(),
+            1645794000
+        );
+    }
+
+    #[test]
+    fn date_unix_timestamp() {
+        assert_eq!(date_as_timestamp("1645794000").unwrap(), 1645794000);
+    }
+
+    #[test]
+    fn date_invalid_format() {
+        for date in ["", "tomorrow", "2022-02-25", "25/02/2022 13:00"] {
+            assert!(
+                matches!(date_as_timestamp(date), Err(ConfigError::InvalidDateFormat)),
+                "'{}' was accepted",
+                date
+            );
+        }
+    }
+
+    #[test]
+    fn go_live_date_not_set() {
+        assert_eq!(go_live_date_as_timestamp(&None).unwrap(), None);
+        assert_eq!(resolve_go_live_date(&None).unwrap(), None);
+    }
+
+    #[test]
+    fn go_live_date_resolved_to_utc() {
+        let date = Some("2022-02-25T08:00:00-05:00".to_string());
+
+        assert_eq!(
+            resolve_go_live_date(&date).unwrap(),
+            Some((1645794000, "2022-02-25T13:00:00Z".to_string()))
+        );
+    }
+
+    /// Write a cache file with a single item to the temporary directory.
+    fn write_test_cache(name: &str, cache: &mut crate::cache::Cache) -> PathBuf {
+        let path = env::temp_dir().join(format!("sugar-{}-{}.json", name, std::process::id()));
+        cache.write_to_file(&path).unwrap();
+        path
+    }
+
+    fn test_cache() -> crate::cache::Cache {
+        let mut cache = crate::cache::Cache::new();
+        cache.items.0.insert(
+            "0".to_string(),
+            crate::cache::CacheItem {
+                name: "Item #1".to_string(),
+                media_hash: "media".to_string(),
+                media_link: "https://example.com/0.png".to_string(),
+                metadata_hash: "metadata".to_string(),
+                metadata_link: "https://example.com/0.json".to_string(),
+                on_chain: false,
+                name_override: None,
+                symbol_override: None,
+                metadata_encoding: None,
+            },
+        );
+        cache
+    }
+
+    #[test]
+    fn hash_of_md5_digest() {
+        assert_eq!(
+            &hash_of_digest(&md5::compute(b"").0),
+            b"d41d8cd98f00b204e9800998ecf8427e"
+        );
+    }
+
+    #[test]
+    fn legacy_hash_is_truncated_sha256() {
+        assert_eq!(&legacy_hash(b""), b"e3b0c44298fc1c149afbf4c8996fb924");
+    }
+
+    #[test]
+    fn cache_hash_is_md5_of_items() {
+        let mut cache = test_cache();
+        let path = write_test_cache("hash-md5", &mut cache);
+        let items = serde_json::to_string(&cache.items).unwrap();
+
+        let digest = HiddenSettings::compute_digest(&path).unwrap();
+        assert_eq!(digest, md5::compute(items.as_bytes()).0);
+        assert_eq!(
+            HiddenSettings::compute_hash(&path).unwrap(),
+            hash_of_digest(&digest)
+        );
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn cache_hash_ignores_program() {
+        let mut cache = test_cache();
+        let path = write_test_cache("hash-program", &mut cache);
+        let hash = HiddenSettings::compute_hash(&path).unwrap();
+
+        // the candy machine is written to the cache by the deploy
+        cache.program = crate::cache::CacheProgram::new_from_cm(&Pubkey::new_unique());
+        cache.write_to_file(&path).unwrap();
+        assert_eq!(HiddenSettings::compute_hash(&path).unwrap(), hash);
+
+        cache.items.0.get_mut("0").unwrap().metadata_link = "https://other.com".to_string();
+        cache.write_to_file(&path).unwrap();
+        assert_ne!(HiddenSettings::compute_hash(&path).unwrap(), hash);
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn cache_matches_current_and_legacy_hash() {
+        let mut cache = test_cache();
+        let path = write_test_cache("hash-legacy", &mut cache);
+        let hash = HiddenSettings::compute_hash(&path).unwrap();
+        let legacy = HiddenSettings::compute_legacy_hash(&path).unwrap();
+
+        assert_ne!(hash, legacy);
+        assert!(HiddenSettings::matches_cache(&hash, &path).unwrap());
+        assert!(HiddenSettings::matches_cache(&legacy, &path).unwrap());
+        assert!(!HiddenSettings::matches_cache(&[b'0'; 32], &path).unwrap());
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    /// Parse a minimal config with the extra (JSON) fields.
+    fn parse_config_with(fields: &str) -> Result<ConfigData, ConfigError> {
+        let separator = if fields.is_empty() { "" } else { "," };
+        let json = format!(
+            r#"{{"price": 1.0, "number": 10, "uploadMethod": "bundlr"{}{}}}"#,
+            separator, fields
+        );
+        ConfigData::parse_reader(json.as_bytes())
+    }
+
+    #[test]
+    fn optional_pubkey_missing_or_null() {
+        let config = parse_config_with("").unwrap();
+        assert_eq!(config.treasury.spl_token(), None);
+
+        let config = parse_config_with(r#""splToken": null"#).unwrap();
+        assert_eq!(config.treasury.spl_token(), None);
+    }
+
+    #[test]
+    fn optional_pubkey_empty() {
+        let config = parse_config_with(r#""splToken": """#).unwrap();
+        assert_eq!(config.treasury.spl_token(), None);
+    }
+
+    #[test]
+    fn optional_pubkey_valid() {
+        let pubkey = Pubkey::new_unique();
+        let config = parse_config_with(&format!(r#""splToken": "{}""#, pubkey)).unwrap();
+        assert_eq!(config.treasury.spl_token(), Some(pubkey));
+    }
+
+    #[test]
+    fn optional_pubkey_malformed() {
+        for (field, value) in [
+            ("splToken", "not-a-pubkey"),
+            ("splTokenAccount", "111"),
+            ("solTreasuryAccount", "0OIl"),
+            (
+                "collection",
+                "So11111111111111111111111111111111111111112222",
+            ),
+        ] {
+            match parse_config_with(&format!(r#""{}": "{}""#, field, value)) {
+                Err(ConfigError::ParseError(message)) => {
+                    assert!(message.contains(field), "{}", message)
+                }
+                other => panic!("'{}' was not rejected: {:?}", value, other),
+            }
+        }
+    }
+
+    #[test]
+    fn candy_machine_data_matches_hand_built_data() {
+        let mut config = full_config();
+        config.hidden_settings = None;
+        let creators = config.creators.clone();
+
+        let data = config.to_candy_machine_data("ABCDEF", None).unwrap();
+        let expected = CandyMachineData {
+            uuid: "ABCDEF".to_string(),
+            price: 1_500_000_000,
+            symbol: "SYM".to_string(),
+            seller_fee_basis_points: 500,
+            max_supply: 0,
+            is_mutable: false,
+            retain_authority: false,
+            go_live_date: Some(1654084800),
+            end_settings: Some(CandyEndSettings {
+                end_setting_type: CandyEndSettingType::Amount,
+                number: 5,
+            }),
+            creators: creators
+                .iter()
+                .map(|creator| CandyCreator {
+                    address: creator.address,
+                    share: creator.share,
+                    verified: false,
+                })
+                .collect(),
+            hidden_settings: None,
+            whitelist_mint_settings: Some(CandyWhitelistMintSettings {
+                mode: CandyWhitelistMintMode::BurnEveryTime,
+                mint: config.whitelist_mint_settings.as_ref().unwrap().mint,
+                presale: true,
+                discount_price: Some(500_000_000),
+            }),
+            items_available: 10,
+            gatekeeper: Some(CandyGatekeeperConfig {
+                gatekeeper_network: config.gatekeeper.as_ref().unwrap().gatekeeper_network,
+                expire_on_use: true,
+            }),
+        };
+
+        assert_eq!(data.try_to_vec().unwrap(), expected.try_to_vec().unwrap());
+    }
+
+    #[test]
+    fn candy_machine_data_round_trip() {
+        let config = full_config();
+        let data = config.to_candy_machine_data("ABCDEF", None).unwrap();
+        let parsed = ConfigData::from_candy_machine_data(&data);
+
+        assert_eq!(parsed.price, config.price);
+        assert_eq!(parsed.number, config.number);
+        assert_eq!(parsed.symbol, config.symbol);
+        assert_eq!(parsed.go_live_date, Some(timestamp_as_date(1654084800)));
+        assert_eq!(parsed.end_settings, config.end_settings);
+        assert_eq!(parsed.hidden_settings, config.hidden_settings);
+        assert_eq!(parsed.gatekeeper, config.gatekeeper);
+        assert_eq!(
+            parsed
+                .to_candy_machine_data("ABCDEF", None)
+                .unwrap()
+                .try_to_vec()
+                .unwrap(),
+            data.try_to_vec().unwrap()
+        );
+    }
+
+    #[test]
+    fn check_creators_reports_all_errors() {
+        let address = Pubkey::new_unique();
+        let creators = vec![
+            Creator { address, share: 50 },
+            Creator { address, share: 40 },
+        ];
+        let errors = check_creators(&creators);
+
+        assert_eq!(errors.len(), 2);
+        assert!(matches!(errors[0], ConfigError::InvalidCreatorShares(90)));
+        assert!(matches!(errors[1], ConfigError::DuplicateCreator(_)));
+        assert!(matches!(
+            check_creators(&[]).as_slice(),
+            [
+                ConfigError::InvalidCreators(0),
+                ConfigError::InvalidCreatorShares(0)
+            ]
+        ));
+
+        let config = ConfigData {
+            creators,
+            ..full_config()
+        };
+        assert!(config.to_candy_machine_data("ABCDEF", None).is_err());
+    }
+
+    #[test]
+    fn commitment_levels() {
+        assert_eq!(
+            Commitment::from_str("processed").unwrap(),
+            Commitment::Processed
+        );
+        assert_eq!(
+            Commitment::from_str("Confirmed").unwrap(),
+            Commitment::Confirmed
+        );
+        assert_eq!(
+            Commitment::from_str("finalized").unwrap(),
+            Commitment::Finalized
+        );
+        assert!(matches!(
+            Commitment::from_str("fast"),
+            Err(ConfigError::InvalidCommitment(_))
+        ));
+    }
+
+    #[test]
+    fn deprecated_commitment_levels() {
+        for (deprecated, commitment) in [
+            ("recent", Commitment::Processed),
+            ("single", Commitment::Confirmed),
+            ("singleGossip", Commitment::Confirmed),
+            ("root", Commitment::Finalized),
+            ("max", Commitment::Finalized),
+        ] {
+            assert_eq!(Commitment::from_str(deprecated).unwrap(), commitment);
+
+            let parsed: Commitment = serde_json::from_str(&format!("\"{}\"", deprecated)).unwrap();
+            assert_eq!(parsed, commitment);
+        }
+    }
+
+    #[test]
+    fn gatekeeper_network_names() {
+        for network in GatekeeperNetwork::ALL {
+            assert_eq!(
+                GatekeeperNetwork::from_pubkey(&network.pubkey()),
+                Some(network)
+            );
+            assert_eq!(
+                GatekeeperNetwork::from_str(&network.to_string()).unwrap(),
+                network
+            );
+        }
+
+        let config = parse_config_with(
+            r#""gatekeeper": {"gatekeeperNetwork": "civic-uniqueness", "expireOnUse": false}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.gatekeeper.unwrap().known_network(),
+            Some(GatekeeperNetwork::CivicUniqueness)
+        );
+    }
+
+    /// Config that passes the validation.
+    fn valid_config() -> ConfigData {
+        ConfigData {
+            price: 1.0,
+            number: 10,
+            creators: vec![Creator {
+                address: Pubkey::new_unique(),
+                share: 100,
+            }],
+            go_live_date: Some("2022-06-01T12:00:00Z".to_string()),
+            retain_authority: true,
+            is_mutable: true,
+            ..ConfigData::default()
+        }
+    }
+
+    fn validation_errors(config: &ConfigData) -> Vec<ConfigError> {
+        config.validate().err().unwrap_or_default()
+    }
+
+    #[test]
+    fn valid_config_has_no_errors() {
+        assert!(validation_errors(&valid_config()).is_empty());
+    }
+
+    #[test]
+    fn from_reader_and_from_str_validate() {
+        let json = serde_json::to_string(&valid_config()).unwrap();
+        assert_eq!(
+            ConfigData::from_reader(json.as_bytes()).unwrap(),
+            ConfigData::from_str(&json).unwrap()
+        );
+
+        // a config that parses but is not valid (no creators)
+        let json = r#"{"price": 1.0, "number": 10, "uploadMethod": "bundlr"}"#;
+        assert!(ConfigData::parse_reader(json.as_bytes()).is_ok());
+        assert!(matches!(
+            ConfigData::from_reader(json.as_bytes()),
+            Err(ConfigError::InvalidConfig(_))
+        ));
+        assert!(matches!(
+            ConfigData::from_str(json),
+            Err(ConfigError::InvalidConfig(_))
+        ));
+
+        assert!(matches!(
+            ConfigData::from_str("{"),
+            Err(ConfigError::ParseError(_))
+        ));
+    }
+
+    #[test]
+    fn hidden_settings_require_mutable() {
+        let hidden_settings = HiddenSettings::new(
+            "Mystery".to_string(),
+            "https://example.com/hidden.json".to_string(),
+            "0".repeat(32),
+        );
+
+        for (is_mutable, hidden, immutable_error) in [
+            (true, false, false),
+            (true, true, false),
+            (false, false, false),
+            (false, true, true),
+        ] {
+            let config = ConfigData {
+                is_mutable,
+                hidden_settings: hidden.then(|| hidden_settings.clone()),
+                ..valid_config()
+            };
+            let errors = validation_errors(&config);
+
+            assert_eq!(
+                errors
+                    .iter()
+                    .any(|error| matches!(error, ConfigError::ImmutableHiddenSettings)),
+                immutable_error,
+                "isMutable {} with hidden settings {}",
+                is_mutable,
+                hidden
+            );
+            if !immutable_error {
+                assert!(errors.is_empty());
+            }
+        }
+    }
+
+    #[test]
+    fn freeze_settings_not_supported() {
+        let config = ConfigData {
+            freeze_settings: Some(FreezeSettings::new(86400)),
+            ..valid_config()
+        };
+
+        assert!(matches!(
+            validation_errors(&config).as_slice(),
+            [ConfigError::FreezeNotSupported(_)]
+        ));
+    }
+
+    #[test]
+    fn guards_not_supported() {
+        let config = ConfigData {
+            guards: Some(GuardConfig {
+                mint_limit: Some(MintLimitGuard { id: 1, limit: 5 }),
+                ..GuardConfig::default()
+            }),
+            ..valid_config()
+        };
+
+        assert!(matches!(
+            validation_errors(&config).as_slice(),
+            [ConfigError::GuardsNotSupported(_)]
+        ));
+    }
+
+    #[test]
+    fn end_settings_amount_up_to_number() {
+        let mut config = valid_config();
+
+        config.end_settings = Some(EndSettings::new(EndSettingType::Amount, 10));
+        assert!(validation_errors(&config).is_empty());
+
+        config.end_settings = Some(EndSettings::new(EndSettingType::Amount, 11));
+        assert!(matches!(
+            validation_errors(&config).as_slice(),
+            [ConfigError::InvalidEndSettingsAmount(11, 10)]
+        ));
+    }
+
+    #[test]
+    fn end_settings_date_after_go_live_date() {
+        let mut config = valid_config();
+        let go_live_date = 1654084800;
+
+        for end_date in [go_live_date - 1, go_live_date] {
+            config.end_settings = Some(EndSettings::new(EndSettingType::Date, end_date as u64));
+            assert!(matches!(
+                validation_errors(&config).as_slice(),
+                [ConfigError::InvalidEndSettingsDate(_, 1654084800)]
+            ));
+        }
+
+        config.end_settings = Some(EndSettings::new(
+            EndSettingType::Date,
+            (go_live_date + 1) as u64,
+        ));
+        assert!(matches!(
+            validation_errors(&config).as_slice(),
+            [ConfigError::EndSettingsDateTooClose(_, 1654084800, 1)]
+        ));
+
+        config.end_settings = Some(EndSettings::new(
+            EndSettingType::Date,
+            (go_live_date + MIN_MINT_DURATION - 1) as u64,
+        ));
+        assert!(matches!(
+            validation_errors(&config).as_slice(),
+            [ConfigError::EndSettingsDateTooClose(_, 1654084800, duration)]
+                if *duration == MIN_MINT_DURATION - 1
+        ));
+
+        config.end_settings = Some(EndSettings::new(
+            EndSettingType::Date,
+            (go_live_date + MIN_MINT_DURATION) as u64,
+        ));
+        assert!(validation_errors(&config).is_empty());
+    }
+
+    #[test]
+    fn end_settings_date_after_relative_go_live_date() {
+        let mut config = valid_config();
+        config.go_live_date = Some("+1d".to_string());
+        let go_live_date = Utc::now().timestamp() + 86400;
+
+        // an end date valid for the absolute go live date is before the relative one
+        config.end_settings = Some(EndSettings::new(
+            EndSettingType::Date,
+            (go_live_date - 3600) as u64,
+        ));
+        assert!(matches!(
+            validation_errors(&config).as_slice(),
+            [ConfigError::InvalidEndSettingsDate(..)]
+        ));
+
+        config.end_settings = Some(EndSettings::new(
+            EndSettingType::Date,
+            (go_live_date + 3600) as u64,
+        ));
+        assert!(validation_errors(&config).is_empty());
+    }
+
+    #[test]
+    fn config_formats_parse_to_the_same_config() {
+        let creator = Pubkey::new_unique();
+        let spl_token = Pubkey::new_unique();
+
+        let json = format!(
+            r#"{{
+                "price": 0.5,
+                "number": 3,
+                "uploadMethod": "AWS",
+                "splToken": "{spl_token}",
+                "splTokenAccount": "",
+                "symbol": "SYM",
+                "creators": [{{"address": "{creator}", "share": 100}}],
+                "gatekeeper": {{"gatekeeperNetwork": "civicCaptcha", "expireOnUse": true}},
+                "awsConfig": {{"bucket": "bucket", "parallelLimit": 2}}
+            }}"#
+        );
+        let toml = format!(
+            r#"
+                price = 0.5
+                number = 3
+                uploadMethod = "AWS"
+                splToken = "{spl_token}"
+                splTokenAccount = ""
+                symbol = "SYM"
+
+                [[creators]]
+                address = "{creator}"
+                share = 100
+
+                [gatekeeper]
+                gatekeeperNetwork = "civicCaptcha"
+                expireOnUse = true
+
+                [awsConfig]
+                bucket = "bucket"
+                parallelLimit = 2
+            "#
+        );
+        let yaml = format!(
+            r#"
+price: 0.5
+number: 3
+uploadMethod: AWS
+splToken: "{spl_token}"
+splTokenAccount: ""
+symbol: SYM
+creators:
+  - address: "{creator}"
+    share: 100
+gatekeeper:
+  gatekeeperNetwork: civicCaptcha
+  expireOnUse: true
+awsConfig:
+  bucket: bucket
+  parallelLimit: 2
+"#
+        );
+
+        let from_json = ConfigData::parse_str_with_env(&json, ConfigFormat::Json).unwrap();
+        let from_toml = ConfigData::parse_str_with_env(&toml, ConfigFormat::Toml).unwrap();
+        let from_yaml = ConfigData::parse_str_with_env(&yaml, ConfigFormat::Yaml).unwrap();
+
+        assert_eq!(from_json.upload_method, UploadMethod::AWS);
+        assert_eq!(from_json.treasury.spl_token(), Some(spl_token));
+        assert_eq!(from_json.treasury.spl_token_account(), None);
+        assert_eq!(from_json.creators[0].address, creator);
+        assert_eq!(from_toml, from_json);
+        assert_eq!(from_yaml, from_json);
+    }
+
+    #[test]
+    fn config_format_from_extension() {
+        assert_eq!(
+            ConfigFormat::from_path(Path::new("config.toml")),
+            ConfigFormat::Toml
+        );
+        assert_eq!(
+            ConfigFormat::from_path(Path::new("config.yml")),
+            ConfigFormat::Yaml
+        );
+        assert_eq!(
+            ConfigFormat::from_path(Path::new("config.YAML")),
+            ConfigFormat::Yaml
+        );
+        assert_eq!(
+            ConfigFormat::from_path(Path::new("config.json")),
+            ConfigFormat::Json
+        );
+        assert_eq!(
+            ConfigFormat::from_path(Path::new("config")),
+            ConfigFormat::Json
+        );
+    }
+
+    fn whitelist_settings(presale: bool, discount_price: Option<f64>) -> WhitelistMintSettings {
+        WhitelistMintSettings::new(
+            WhitelistMintMode::BurnEveryTime,
+            Pubkey::new_unique(),
+            presale,
+            discount_price,
+        )
+    }
+
+    #[test]
+    fn whitelist_discount_price() {
+        // free mint
+        let settings = whitelist_settings(true, Some(0.0));
+        assert_eq!(settings.into_candy_format().discount_price, Some(0));
+
+        // full price
+        let settings = whitelist_settings(true, None);
+        assert_eq!(settings.into_candy_format().discount_price, None);
+
+        let settings = whitelist_settings(true, Some(0.25));
+        assert_eq!(
+            settings.into_candy_format().discount_price,
+            Some(250_000_000)
+        );
+        assert_eq!(
+            settings.into_candy_format_with_decimals(2).discount_price,
+            Some(25)
+        );
+    }
+
+    #[test]
+    fn whitelist_free_mint_without_presale_warning() {
+        let mut config = valid_config();
+
+        config.whitelist_mint_settings = Some(whitelist_settings(false, Some(0.0)));
+        assert!(config
+            .warnings()
+            .contains(&FREE_WITHOUT_PRESALE_WARNING.to_string()));
+
+        for settings in [
+            whitelist_settings(true, Some(0.0)),
+            whitelist_settings(false, None),
+            whitelist_settings(false, Some(0.5)),
+        ] {
+            config.whitelist_mint_settings = Some(settings);
+            assert!(!config
+                .warnings()
+                .contains(&FREE_WITHOUT_PRESALE_WARNING.to_string()));
+        }
+    }
+
+    #[test]
+    fn pubkey_too_short() {
+        let err = parse_pubkey("splToken", "So1111111111").unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "Invalid pubkey 'So1111111111' for 'splToken': too short, decodes to 9 bytes but a pubkey has 32 (is the value complete?)"
+        );
+    }
+
+    #[test]
+    fn pubkey_with_invalid_base58_characters() {
+        let err = parse_pubkey("solTreasuryAccount", "Ol0Ol0").unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "Invalid pubkey 'Ol0Ol0' for 'solTreasuryAccount': contains 'O', 'l', '0', which is not valid in base58 (0, O, I and l are not used)"
+        );
+
+        let err = parse_pubkey("whitelistMintSettings.mint", "abc-def").unwrap_err();
+        assert!(err
+            .to_string()
+            .ends_with("contains the invalid character '-'"));
+    }
+
+    #[test]
+    fn pubkey_field_in_parse_error() {
+        let mint = "So1111111111";
+        let err = parse_config_with(&format!(
+            r#""whitelistMintSettings": {{"mode": "neverBurn", "mint": "{}", "presale": false, "discountPrice": null}}"#,
+            mint
+        ))
+        .unwrap_err();
+
+        assert!(err
+            .to_string()
+            .contains("Invalid pubkey 'So1111111111' for 'whitelistMintSettings.mint': too short"));
+    }
+
+    #[test]
+    fn treasury_keys() {
+        let wallet = Pubkey::new_unique();
+        let token = Pubkey::new_unique();
+        let account = Pubkey::new_unique();
+        let payer = Pubkey::new_unique();
+
+        let config = parse_config_with("").unwrap();
+        assert_eq!(config.treasury, TreasuryConfig::Payer);
+        assert_eq!(config.treasury.resolve(&payer), Treasury::Sol(payer));
+
+        let config = parse_config_with(&format!(r#""solTreasuryAccount": "{}""#, wallet)).unwrap();
+        assert_eq!(config.treasury, TreasuryConfig::Sol(wallet));
+        assert_eq!(config.treasury.resolve(&payer), Treasury::Sol(wallet));
+
+        let config = parse_config_with(&format!(
+            r#""solTreasuryAccount": null, "splToken": "{}""#,
+            token
+        ))
+        .unwrap();
+        assert_eq!(
+            config.treasury.resolve(&payer),
+            Treasury::Spl {
+                token,
+                account: get_associated_token_address(&payer, &token)
+            }
+        );
+
+        let config = parse_config_with(&format!(
+            r#""splToken": "{}", "splTokenAccount": "{}""#,
+            token, account
+        ))
+        .unwrap();
+        assert_eq!(
+            config.treasury,
+            TreasuryConfig::Spl {
+                token,
+                account: Some(account)
+            }
+        );
+        assert_eq!(config.treasury.resolve(&payer).wallet(), account);
+    }
+
+    #[test]
+    fn invalid_treasury_keys() {
+        let wallet = Pubkey::new_unique();
+        let token = Pubkey::new_unique();
+
+        for (fields, message) in [
+            (
+                format!(
+                    r#""solTreasuryAccount": "{}", "splToken": "{}""#,
+                    wallet, token
+                ),
+                ConfigError::ConflictingTreasury.to_string(),
+            ),
+            (
+                format!(
+                    r#""solTreasuryAccount": "{}", "splTokenAccount": "{}""#,
+                    wallet, token
+                ),
+                ConfigError::ConflictingTreasury.to_string(),
+            ),
+            (
+                format!(r#""splTokenAccount": "{}""#, token),
+                ConfigError::SplTokenAccountWithoutToken.to_string(),
+            ),
+            (
+                format!(r#""solTreasuryAccount": "{}""#, Pubkey::default()),
+                ConfigError::InvalidSolTreasuryAccount(Pubkey::default().to_string()).to_string(),
+            ),
+        ] {
+            match parse_config_with(&fields) {
+                Err(ConfigError::ParseError(error)) => {
+                    assert!(error.contains(&message), "{}", error)
+                }
+                other => panic!("{} was not rejected: {:?}", fields, other),
+            }
+        }
+    }
+
+    #[test]
+    fn format_sol_values() {
+        assert_eq!(format_sol(0), "0");
+        assert_eq!(format_sol(1), "0.000000001");
+        assert_eq!(format_sol(LAMPORTS_PER_SOL), "1");
+        assert_eq!(format_sol(1_500_000_000), "1.5");
+        // large values are shown exactly, without float rounding
+        assert_eq!(format_sol(123_456_789_123_456_789), "123456789.123456789");
+        assert_eq!(format_sol(u64::MAX), "18446744073.709551615");
+    }
+
+    #[test]
+    fn hashes_ignore_client_settings() {
+        let config = full_config();
+        let deploy_hash = config.deploy_hash().unwrap();
+        let content_hash = config.content_hash().unwrap();
+
+        let mut client_changes = config.clone();
+        client_changes.retry_config = None;
+        client_changes.priority_fee = Some(1);
+        client_changes.parallel_limit = Some(1);
+        client_changes.requests_per_second = Some(1.0);
+        client_changes.rpc_timeout_secs = Some(1);
+        client_changes.upload_pool_size = Some(1);
+        client_changes.aws_config.as_mut().unwrap().parallel_limit = Some(1);
+        let pinata_config = client_changes.pinata_config.as_mut().unwrap();
+        pinata_config.parallel_limit = Some(1);
+        pinata_config.requests_per_second = Some(1.0);
+        let shdw_config = client_changes.shdw_config.as_mut().unwrap();
+        shdw_config.parallel_limit = Some(1);
+        shdw_config.requests_per_second = Some(1.0);
+
+        assert_eq!(client_changes.deploy_hash().unwrap(), deploy_hash);
+        assert_eq!(client_changes.content_hash().unwrap(), content_hash);
+
+        let mut config_changes = config;
+        config_changes.number += 1;
+
+        assert_ne!(config_changes.deploy_hash().unwrap(), deploy_hash);
+        assert_ne!(config_changes.content_hash().unwrap(), content_hash);
+    }
+
+    #[test]
+    fn content_hash_ignores_key_order() {
+        let config = full_config();
+        let value = serde_json::to_value(&config).unwrap();
+        let entries: Vec<String> = value
+            .as_object()
+            .unwrap()
+            .iter()
+            .map(|(key, value)| format!("{:?}: {}", key, value))
+            .collect();
+
+        // the same config with the keys in opposite orders
+        let sorted = format!("{{{}}}", entries.join(", "));
+        let reversed = format!(
+            "{{{}}}",
+            entries.iter().rev().cloned().collect::<Vec<_>>().join(", ")
+        );
+        let sorted = ConfigData::parse_reader(sorted.as_bytes()).unwrap();
+        let reversed = ConfigData::parse_reader(reversed.as_bytes()).unwrap();
+
+        assert_eq!(
+            sorted.content_hash().unwrap(),
+            reversed.content_hash().unwrap()
+        );
+        assert_eq!(
+            reversed.content_hash().unwrap(),
+            config.content_hash().unwrap()
+        );
+    }
+
+    fn schedule_config(schedule: &[(&str, f64)]) -> ConfigData {
+        ConfigData {
+            schedule: Some(
+                schedule
+                    .iter()
+                    .map(|(start, price)| ScheduleEntry {
+                        start: start.to_string(),
+                        price: *price,
+                    })
+                    .collect(),
+            ),
+            ..valid_config()
+        }
+    }
+
+    #[test]
+    fn scheduled_price_at_time() {
+        let config = schedule_config(&[("1000", 1.0), ("2000", 2.0), ("3000", 3.0)]);
+        let resolved = BTreeMap::new();
+
+        assert_eq!(config.scheduled_price(500, &resolved).unwrap(), Some(1.0));
+        assert_eq!(config.scheduled_price(1000, &resolved).unwrap(), Some(1.0));
+        assert_eq!(config.scheduled_price(2500, &resolved).unwrap(), Some(2.0));
+        assert_eq!(config.scheduled_price(4000, &resolved).unwrap(), Some(3.0));
+
+        assert_eq!(valid_config().scheduled_price(0, &resolved).unwrap(), None);
+    }
+
+    #[test]
+    fn schedule_dates_resolved_once() {
+        let config = schedule_config(&[("now", 1.0), ("+1h", 2.0), ("4102444800", 3.0)]);
+        let mut resolved = BTreeMap::new();
+
+        assert!(config.resolve_schedule_dates(&mut resolved).unwrap());
+        // only the relative dates are stored
+        assert_eq!(
+            resolved.keys().collect::<Vec<_>>(),
+            vec![&"+1h".to_string(), &"now".to_string()]
+        );
+
+        // later runs keep the stored timestamps
+        let start = Utc::now().timestamp() - 7200;
+        resolved.insert("now".to_string(), start);
+        resolved.insert("+1h".to_string(), start + 3600);
+        assert!(!config.resolve_schedule_dates(&mut resolved).unwrap());
+        assert_eq!(resolved["now"], start);
+
+        // the schedule is evaluated from the stored start, not from the current time
+        let now = Utc::now().timestamp();
+        assert_eq!(config.scheduled_price(now, &resolved).unwrap(), Some(2.0));
+        assert_eq!(
+            config.scheduled_price(start + 10, &resolved).unwrap(),
+            Some(1.0)
+        );
+    }
+
+    #[test]
+    fn hidden_settings_static_uri() {
+        let hidden_settings = HiddenSettings::new(
+            "Mystery".to_string(),
+            "https://example.com/hidden.json".to_string(),
+            "0".repeat(32),
+        );
+        assert!(!hidden_settings.is_uri_template());
+        assert_eq!(
+            hidden_settings.uri_for_index(7),
+            "https://example.com/hidden.json"
+        );
+        assert_eq!(hidden_settings.name_for_index(7), "Mystery#8");
+
+        let config = ConfigData {
+            hidden_settings: Some(hidden_settings.clone()),
+            ..valid_config()
+        };
+        assert_eq!(config.onchain_hidden_settings(), Some(&hidden_settings));
+        assert_eq!(config.placeholder_settings(), None);
+
+        let data = config.to_candy_machine_data("ABCDEF", None).unwrap();
+        assert_eq!(
+            data.hidden_settings.unwrap().uri,
+            "https://example.com/hidden.json"
+        );
+    }
+
+    #[test]
+    fn hidden_settings_uri_template() {
+        let hidden_settings = HiddenSettings::new(
+            "Mystery #{index}".to_string(),
+            "https://example.com/{index}.json".to_string(),
+            "0".repeat(32),
+        );
+        assert!(hidden_settings.is_uri_template());
+        assert_eq!(
+            hidden_settings.uri_for_index(7),
+            "https://example.com/7.json"
+        );
+
+        // the name has the mint number, the uri the index of the item
+        let config_line = hidden_settings.placeholder_config_line(7);
+        assert_eq!(config_line.name, "Mystery #8");
+        assert_eq!(config_line.uri, "https://example.com/7.json");
+
+        // the template is expanded in the config lines, not stored in the candy machine
+        let config = ConfigData {
+            hidden_settings: Some(hidden_settings.clone()),
+            ..valid_config()
+        };
+        assert_eq!(config.onchain_hidden_settings(), None);
+        assert_eq!(config.placeholder_settings(), Some(&hidden_settings));
+        assert!(config
+            .to_candy_machine_data("ABCDEF", None)
+            .unwrap()
+            .hidden_settings
+            .is_none());
+    }
+
+    #[test]
+    fn hidden_settings_uri_template_length() {
+        let config = ConfigData {
+            number: 1000,
+            hidden_settings: Some(HiddenSettings::new(
+                "Mystery".to_string(),
+                format!(
+                    "https://example.com/{}{{index}}",
+                    "a".repeat(MAX_URI_LENGTH - 22)
+                ),
+                "0".repeat(32),
+            )),
+            ..valid_config()
+        };
+
+        // the uri of the last item (999) is longer than the limit
+        assert!(validation_errors(&config)
+            .iter()
+            .any(|error| matches!(error, ConfigError::HiddenUriTooLong(_, _, _))));
+    }
+
+    #[test]
+    fn hidden_settings_name_template_length() {
+        let hidden_settings = HiddenSettings::new(
+            "VeryLongName #{index}".to_string(),
+            "https://example.com/hidden.json".to_string(),
+            "0".repeat(32),
+        );
+        assert_eq!(hidden_settings.name_for_index(9), "VeryLongName #10");
+        assert!(hidden_settings.check_name_length(10).is_ok());
+
+        // the name of the last item has 18 digits: exactly the limit
+        assert!(hidden_settings.check_name_length(10u64.pow(18) - 1).is_ok());
+
+        // the name of the last item has 19 digits
+        let error = hidden_settings
+            .check_name_length(10u64.pow(18))
+            .unwrap_err();
+        assert!(matches!(
+            &error,
+            ConfigError::HiddenNameTooLong(name, 33, MAX_NAME_LENGTH)
+                if *name == format!("VeryLongName #{}", 10u64.pow(18))
+        ));
+
+        // the check is part of the validation of the config
+        let config = ConfigData {
+            number: 1000,
+            hidden_settings: Some(HiddenSettings::new(
+                "A Very Long Mystery Box Name #{index}".to_string(),
+                "https://example.com/hidden.json".to_string(),
+                "0".repeat(32),
+            )),
+            ..valid_config()
+        };
+        assert!(validation_errors(&config)
+            .iter()
+            .any(|error| matches!(error, ConfigError::HiddenNameTooLong(_, _, _))));
+    }
+
+    #[test]
+    fn end_setting_type_any_case() {
+        for value in ["date", "Date", "DATE"] {
+            assert_eq!(
+                EndSettingType::from_str(value).unwrap(),
+                EndSettingType::Date
+            );
+        }
+        assert_eq!(
+            EndSettingType::from_str("AMOUNT").unwrap(),
+            EndSettingType::Amount
+        );
+
+        let end_settings: EndSettings =
+            serde_json::from_str(r#"{"endSettingType": "amount", "number": 10}"#).unwrap();
+        assert_eq!(end_settings, EndSettings::new(EndSettingType::Amount, 10));
+
+        // the serialized value does not depend on the case of the config
+        assert_eq!(
+            serde_json::to_string(&end_settings).unwrap(),
+            r#"{"endSettingType":"Amount","number":10}"#
+        );
+    }
+
+    #[test]
+    fn whitelist_mint_mode_spellings() {
+        for value in [
+            "burnEveryTime",
+            "burn-every-time",
+            "burn_every_time",
+            "burn every time",
+            "BURN_EVERY_TIME",
+        ] {
+            assert_eq!(
+                WhitelistMintMode::from_str(value).unwrap(),
+                WhitelistMintMode::BurnEveryTime
+            );
+        }
+        for value in ["neverBurn", "never-burn", "never_burn", "never burn"] {
+            assert_eq!(
+                WhitelistMintMode::from_str(value).unwrap(),
+                WhitelistMintMode::NeverBurn
+            );
+        }
+
+        // the serialized value is the canonical one, whatever the spelling of the config
+        let mode: WhitelistMintMode = serde_json::from_str(r#""never-burn""#).unwrap();
+        assert_eq!(serde_json::to_string(&mode).unwrap(), r#""neverBurn""#);
+    }
+
+    #[test]
+    fn invalid_whitelist_mint_mode() {
+        let error = WhitelistMintMode::from_str("burn-once").unwrap_err();
+        assert!(matches!(
+            &error,
+            ConfigError::InvalidWhitelistMintMode(mode) if mode == "burn-once"
+        ));
+
+        assert!(serde_json::from_str::<WhitelistMintMode>(r#""burn-once""#).is_err());
+    }
+
+    #[test]
+    fn invalid_end_setting_type() {
+        let error = EndSettingType::from_str("time").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Invalid end setting type: time. Valid types are: Date, Amount"
+        );
+
+        let error =
+            serde_json::from_str::<EndSettings>(r#"{"endSettingType": "time", "number": 10}"#)
+                .unwrap_err();
+        assert!(error.to_string().contains("Valid types are: Date, Amount"));
+    }
+
+    #[test]
+    fn max_supply_defaults_to_no_prints() {
+        let config = valid_config();
+        assert_eq!(config.max_supply, None);
+        assert_eq!(
+            config
+                .to_candy_machine_data("ABCDEF", None)
+                .unwrap()
+                .max_supply,
+            0
+        );
+
+        let mut value = serde_json::to_value(&config).unwrap();
+        value["maxSupply"] = serde_json::json!(5);
+        let config: ConfigData = serde_json::from_value(value).unwrap();
+        assert_eq!(config.max_supply, Some(5));
+        assert_eq!(
+            config
+                .to_candy_machine_data("ABCDEF", None)
+                .unwrap()
+                .max_supply,
+            5
+        );
+        assert!(!config
+            .warnings()
+            .contains(&HIDDEN_WITH_MAX_SUPPLY_WARNING.to_string()));
+
+        let hidden = ConfigData {
+            hidden_settings: Some(HiddenSettings::new(
+                "Mystery".to_string(),
+                "https://example.com/hidden.json".to_string(),
+                "0".repeat(32),
+            )),
+            ..config
+        };
+        assert!(hidden
+            .warnings()
+            .contains(&HIDDEN_WITH_MAX_SUPPLY_WARNING.to_string()));
+    }
+
+    #[test]
+    fn minimal_config_defaults() {
+        let config = parse_config_with("").unwrap();
+
+        assert!(config.retain_authority);
+        assert!(config.is_mutable);
+        assert_eq!(config.go_live_date, None);
+        assert_eq!(config.end_settings, None);
+        assert_eq!(config.whitelist_mint_settings, None);
+        assert_eq!(config.hidden_settings, None);
+        assert_eq!(config.gatekeeper, None);
+        assert_eq!(config.treasury, TreasuryConfig::Payer);
+        assert!(config.symbol.is_empty());
+        assert_eq!(config.seller_fee_basis_points, 0);
+
+        // the missing creators are still reported
+        assert!(matches!(
+            validation_errors(&config).as_slice(),
+            [
+                ConfigError::InvalidCreators(0),
+                ConfigError::InvalidCreatorShares(0)
+            ]
+        ));
+
+        // the fields without a default are required
+        for json in [
+            r#"{"number": 10, "uploadMethod": "bundlr"}"#,
+            r#"{"price": 1.0, "uploadMethod": "bundlr"}"#,
+            r#"{"price": 1.0, "number": 10}"#,
+        ] {
+            assert!(
+                ConfigData::parse_reader(json.as_bytes()).is_err(),
+                "{}",
+                json
+            );
         }
     }
 }
@@ -1,5 +1,7 @@
 use thiserror::Error;
 
+use crate::constants::MAX_CREATOR_LIMIT;
+
 #[derive(Debug, Error)]
 pub enum ConfigError {
     #[error("Could not parse the config file ({0})")]
@@ -14,9 +16,166 @@ pub enum ConfigError {
     #[error("Could not open config file '{0}'")]
     PermissionError(String),
 
+    #[error("Solana config file not found '{0}'")]
+    SolanaConfigNotFound(String),
+
     #[error("Invalid cluster '{0}'")]
     InvalidCluster(String),
 
+    #[error("Invalid config format '{0}': valid formats are json, toml and yaml")]
+    InvalidConfigFormat(String),
+
     #[error("Invalid upload method '{0}'")]
     InvalidUploadMethod(String),
+
+    #[error("Invalid gatekeeper network '{0}': expected a pubkey or one of civicCaptcha, civicUniqueness, civicIdVerification, encoreVerify")]
+    InvalidGatekeeperNetwork(String),
+
+    #[error("Invalid commitment '{0}': valid values are processed, confirmed and finalized")]
+    InvalidCommitment(String),
+
+    #[error("Environment variable '{0}' referenced in the config is not set")]
+    MissingEnvVariable(String),
+
+    #[error("{0}")]
+    InvalidConfig(String),
+
+    #[error("Invalid 'price' value {0}: price must be greater than 0")]
+    InvalidPrice(f64),
+
+    #[error("Invalid 'number' value {0}: number of items must be greater than 0 and fit in a candy machine account")]
+    InvalidNumber(u64),
+
+    #[error("Invalid 'symbol' value '{0}': symbol must be 10 bytes or less")]
+    InvalidSymbol(String),
+
+    #[error("Invalid 'sellerFeeBasisPoints' value {0}: basis points must be 10000 or less")]
+    InvalidSellerFeeBasisPoints(u16),
+
+    #[error("The number of creators must be between 1 and {}.", MAX_CREATOR_LIMIT - 1)]
+    InvalidCreators(usize),
+
+    #[error("Creator(s) share must add up to 100, current total {0}.")]
+    InvalidCreatorShares(u32),
+
+    #[error("Duplicated creator address {0}")]
+    DuplicateCreator(String),
+
+    #[error(
+        "Invalid 'solTreasuryAccount' value {0}: the default pubkey cannot be used as treasury"
+    )]
+    InvalidSolTreasuryAccount(String),
+
+    #[error("Invalid 'solTreasuryAccount': it cannot be set when 'splToken' or 'splTokenAccount' is set")]
+    ConflictingTreasury,
+
+    #[error("Invalid 'splTokenAccount': it can only be set together with 'splToken'")]
+    SplTokenAccountWithoutToken,
+
+    #[error("Invalid 'endSettings.number' value {0}: the end amount cannot be higher than the number of items ({1})")]
+    InvalidEndSettingsAmount(u64, u64),
+
+    #[error(
+        "Invalid 'endSettings.number' value {0}: the end date must be after the go live date ({1})"
+    )]
+    InvalidEndSettingsDate(u64, i64),
+
+    #[error("Invalid 'endSettings.number' value {0}: the end date is only {2} second(s) after the go live date ({1}), the mint would end almost as soon as it starts")]
+    EndSettingsDateTooClose(u64, i64, i64),
+
+    #[error("Invalid 'whitelistMintSettings.discountPrice' value {0}: discount price cannot be higher than the price ({1})")]
+    InvalidDiscountPrice(f64, f64),
+
+    #[error("Invalid '{0}' value {1}: the payment token has {2} decimals, so the price is rounded to 0 (the minimum price is {3})")]
+    PriceBelowTokenPrecision(String, f64, u8, String),
+
+    #[error("Invalid 'whitelistMintSettings.discountPrice' value {0}: discount price cannot be negative")]
+    NegativeDiscountPrice(f64),
+
+    #[error(
+        "Invalid 'whitelistMintSettings.mintLimit' value 0: the mint limit must be at least 1"
+    )]
+    InvalidWhitelistMintLimit,
+
+    #[error("Invalid 'schedule' value: the schedule must have at least one entry")]
+    EmptySchedule,
+
+    #[error("Invalid 'schedule[{0}].start' value '{1}': the date could not be parsed")]
+    InvalidScheduleStart(usize, String),
+
+    #[error("Invalid 'schedule[{0}].price' value {1}: price cannot be negative")]
+    InvalidSchedulePrice(usize, f64),
+
+    #[error("Invalid 'schedule[{0}].start' value '{1}': entries must be sorted by start date, without repeated dates")]
+    UnsortedSchedule(usize, String),
+
+    #[error(
+        "Invalid 'schedule[0].start' value '{0}': the first entry must start at the 'goLiveDate'"
+    )]
+    ScheduleGoLiveMismatch(String),
+
+    #[error("'hiddenSettings' requires 'isMutable' to be true: the NFTs of a hidden settings drop are revealed by updating their metadata, which cannot be done when they are immutable. Set 'isMutable' to true (or remove 'hiddenSettings')")]
+    ImmutableHiddenSettings,
+
+    #[error("Invalid 'hiddenSettings.name' value '{0}': the program appends the mint number to the name, so a template can only have one '{{index}}' token at the end, after a '#' (e.g., \"Mystery #{{index}}\")")]
+    HiddenNameTemplateNotSupported(String),
+
+    #[error("Invalid 'hiddenSettings.name' value: the name of the last item '{0}' has {1} bytes, but it can have at most {2}")]
+    HiddenNameTooLong(String, usize, usize),
+
+    #[error("Invalid 'hiddenSettings.uri' value '{0}': the uri has {1} characters, but it can have at most {2}")]
+    HiddenUriTooLong(String, usize, usize),
+
+    #[error("Invalid 'freezeSettings.freezeTime' value {0}: freeze time must be positive and at most {1} seconds")]
+    InvalidFreezeTime(i64, i64),
+
+    #[error("'freezeSettings' is not supported: the candy machine program ({0}) cannot freeze the minted NFTs, remove it from the config")]
+    FreezeNotSupported(String),
+
+    #[error("Conflicting 'guards.{0}' and '{1}': the guard replaces the candy machine setting, remove one of them")]
+    ConflictingGuard(String, String),
+
+    #[error("Invalid 'guards.{0}' value: {1}")]
+    InvalidGuard(String, String),
+
+    #[error("'guards' is not supported: the candy machine program ({0}) has no candy guards, remove it from the config")]
+    GuardsNotSupported(String),
+
+    #[error("Invalid '{0}' value {1}: the number of requests per second must be positive")]
+    InvalidRequestsPerSecond(String, f64),
+
+    #[error(
+        "Invalid 'retryConfig' value: 'baseDelayMs' ({0}) cannot be higher than 'maxDelayMs' ({1})"
+    )]
+    InvalidRetryConfig(u64, u64),
+
+    #[error("Invalid 'rpcTimeoutSecs' value 0: the timeout must be at least 1 second")]
+    InvalidRpcTimeout,
+
+    #[error("Invalid '{0}' value '{1}': {2}")]
+    InvalidUrlTemplate(String, String, String),
+
+    #[error("Invalid 'metadataEncoding' value '{0}': the {1} upload method does not set the content encoding of the files, use aws or bundlr")]
+    ContentEncodingNotSupported(String, String),
+
+    #[error("Invalid pubkey '{1}' for '{0}': {2}")]
+    InvalidPubkey(String, String, String),
+
+    #[error("Invalid whitelist mint mode: {0}. Valid modes are: burnEveryTime, neverBurn")]
+    InvalidWhitelistMintMode(String),
+
+    #[error("Invalid end setting type: {0}. Valid types are: Date, Amount")]
+    InvalidEndSettingType(String),
+
+    #[error("Invalid relative date '{0}'. Format must be: +<amount><unit>, where unit is one of s (seconds), m (minutes), h (hours) or d (days), e.g. +30m, +2h, +7d.")]
+    InvalidRelativeDate(String),
+
+    #[error("Invalid date format. Format must be: RFC2822(Fri, 14 Jul 2022 02:40:00 -0400), RFC3339(2022-02-25T13:00:00Z), UNIX timestamp, 'now' or relative to the current time (+30m, +2h, +7d).")]
+    InvalidDateFormat,
+
+    #[error("invalid merkle root '{0}': {1}")]
+    InvalidMerkleRoot(String, String),
+
+    #[error("the merkle root must have 32 bytes, but '{0}' has {1}")]
+    InvalidMerkleRootLength(String, usize),
 }
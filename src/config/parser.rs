@@ -1,26 +1,57 @@
 use anyhow::Result;
+use lazy_static::lazy_static;
+use regex::{Captures, Regex};
+use serde_json::Value;
+use std::env;
 use std::fs::metadata;
 use std::fs::OpenOptions;
-use std::io::ErrorKind;
-use tracing::error;
+use std::io::{self, ErrorKind, Read};
+use std::path::Path;
+use std::sync::Mutex;
+use tracing::{error, warn};
 
 use crate::config::data::*;
 use crate::config::errors::ConfigError;
 
-pub fn get_config_data(config_path: &str) -> Result<ConfigData, ConfigError> {
+/// Config path that reads the config data from the standard input.
+pub const STDIN_CONFIG: &str = "-";
+
+lazy_static! {
+    /// Reference to an environment variable, e.g., `${VAR}`.
+    static ref ENV_VAR_REGEX: Regex =
+        Regex::new(r"\$\{([A-Za-z_][A-Za-z0-9_]*)\}").expect("Failed to create regex.");
+
+    /// Config data read from the standard input, which can only be read once.
+    static ref STDIN_CONFIG_DATA: Mutex<Option<ConfigData>> = Mutex::new(None);
+}
+
+/// Reads and parses the config file, without validating its values. The config is read
+/// from the standard input (in JSON format, unless a format is specified) when the path
+/// is "-".
+pub fn parse_config_data(
+    config_path: &str,
+    format: Option<ConfigFormat>,
+) -> Result<ConfigData, ConfigError> {
+    if config_path == STDIN_CONFIG {
+        return match parse_once(&STDIN_CONFIG_DATA, io::stdin, format.unwrap_or_default()) {
+            Ok(config_data) => Ok(config_data),
+            Err(error) => {
+                error!("{:?}", error);
+                Err(error)
+            }
+        };
+    }
+
     // checks that the config file exists and it is readable
-    let f = match OpenOptions::new().read(true).open(config_path) {
-        Ok(f) => f,
-        Err(err) => {
-            let error = match err.kind() {
-                ErrorKind::NotFound => ConfigError::MissingFileError(config_path.to_string()),
-                _ => ConfigError::PermissionError(config_path.to_string()),
-            };
+    if let Err(err) = OpenOptions::new().read(true).open(config_path) {
+        let error = match err.kind() {
+            ErrorKind::NotFound => ConfigError::MissingFileError(config_path.to_string()),
+            _ => ConfigError::PermissionError(config_path.to_string()),
+        };
 
-            error!("{:?}", error);
-            return Err(error);
-        }
-    };
+        error!("{:?}", error);
+        return Err(error);
+    }
     // checks that the config is a file and not a directory
     if metadata(config_path).unwrap().is_dir() {
         let error = ConfigError::InvalidPathError(config_path.to_string());
@@ -28,13 +59,181 @@ pub fn get_config_data(config_path: &str) -> Result<ConfigData, ConfigError> {
         return Err(error);
     }
 
-    let config_data: ConfigData = match serde_json::from_reader(f) {
-        Ok(config_data) => config_data,
-        Err(err) => {
-            let error = ConfigError::ParseError(err.to_string());
+    match ConfigData::parse_path(Path::new(config_path), format) {
+        Ok(config_data) => Ok(config_data),
+        Err(error) => {
             error!("{:?}", error);
-            return Err(error);
+            Err(error)
         }
-    };
+    }
+}
+
+/// Parses the config data from the reader on the first call, returning the same config
+/// data on later calls: commands that run other commands (e.g., `launch`) load the
+/// config more than once, but the standard input can only be read once.
+fn parse_once<R: Read>(
+    parsed: &Mutex<Option<ConfigData>>,
+    reader: impl FnOnce() -> R,
+    format: ConfigFormat,
+) -> Result<ConfigData, ConfigError> {
+    let mut parsed = parsed.lock().unwrap();
+
+    if let Some(config_data) = parsed.as_ref() {
+        return Ok(config_data.clone());
+    }
+
+    let config_data = ConfigData::parse_reader_with_env(reader(), format)?;
+    *parsed = Some(config_data.clone());
+
+    Ok(config_data)
+}
+
+pub fn get_config_data(
+    config_path: &str,
+    format: Option<ConfigFormat>,
+) -> Result<ConfigData, ConfigError> {
+    let config_data = parse_config_data(config_path, format)?.into_validated(config_path)?;
+
+    for warning in config_data.warnings() {
+        warn!("{}", warning);
+    }
+
     Ok(config_data)
 }
+
+/// Return the placeholder settings of the config file (see
+/// `ConfigData::placeholder_settings`). Commands that also work without a config file
+/// get `None` when the file does not exist.
+pub fn get_placeholder_settings(
+    config_path: &str,
+    format: Option<ConfigFormat>,
+) -> Result<Option<HiddenSettings>, ConfigError> {
+    if config_path != STDIN_CONFIG && !Path::new(config_path).exists() {
+        return Ok(None);
+    }
+
+    Ok(get_config_data(config_path, format)?
+        .placeholder_settings()
+        .cloned())
+}
+
+/// Replaces every `${VAR}` reference in the input by the value of the environment
+/// variable `VAR`. Any other use of `$` is left untouched.
+pub fn expand_env_vars(input: &str) -> Result<String, ConfigError> {
+    let mut missing = None;
+
+    let expanded = ENV_VAR_REGEX.replace_all(input, |captures: &Captures| {
+        let name = &captures[1];
+        match env::var(name) {
+            Ok(value) => value,
+            Err(_) => {
+                missing.get_or_insert_with(|| name.to_string());
+                String::new()
+            }
+        }
+    });
+
+    match missing {
+        Some(name) => Err(ConfigError::MissingEnvVariable(name)),
+        None => Ok(expanded.into_owned()),
+    }
+}
+
+/// Expands the environment variable references of all string values (recursively).
+pub fn expand_env_vars_in_value(value: &mut Value) -> Result<(), ConfigError> {
+    match value {
+        Value::String(s) => *s = expand_env_vars(s)?,
+        Value::Array(values) => {
+            for value in values {
+                expand_env_vars_in_value(value)?;
+            }
+        }
+        Value::Object(map) => {
+            for value in map.values_mut() {
+                expand_env_vars_in_value(value)?;
+            }
+        }
+        _ => (),
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn expand_env_var() {
+        env::set_var("SUGAR_TEST_RPC", "https://rpc.example.com");
+
+        assert_eq!(
+            expand_env_vars("${SUGAR_TEST_RPC}/path").unwrap(),
+            "https://rpc.example.com/path"
+        );
+    }
+
+    #[test]
+    fn expand_env_var_leaves_other_dollar_signs() {
+        for input in ["$5", "$SUGAR_TEST_VAR", "${}", "${1VAR}", "price: $ {x}"] {
+            assert_eq!(expand_env_vars(input).unwrap(), input);
+        }
+    }
+
+    #[test]
+    fn expand_missing_env_var() {
+        env::remove_var("SUGAR_TEST_MISSING");
+
+        assert!(matches!(
+            expand_env_vars("${SUGAR_TEST_MISSING}"),
+            Err(ConfigError::MissingEnvVariable(name)) if name == "SUGAR_TEST_MISSING"
+        ));
+    }
+
+    #[test]
+    fn expand_env_vars_of_nested_values() {
+        env::set_var("SUGAR_TEST_BUCKET", "bucket");
+        let mut value = json!({
+            "number": 10,
+            "awsConfig": { "bucket": "${SUGAR_TEST_BUCKET}" },
+            "creators": [{ "address": "${SUGAR_TEST_BUCKET}" }],
+        });
+        expand_env_vars_in_value(&mut value).unwrap();
+
+        assert_eq!(
+            value,
+            json!({
+                "number": 10,
+                "awsConfig": { "bucket": "bucket" },
+                "creators": [{ "address": "bucket" }],
+            })
+        );
+    }
+
+    #[test]
+    fn parse_reader_once() {
+        let parsed = Mutex::new(None);
+        let json = r#"{"price": 1.5, "number": 10, "uploadMethod": "bundlr"}"#;
+
+        let first = parse_once(&parsed, || json.as_bytes(), ConfigFormat::Json).unwrap();
+        assert_eq!(first.price, 1.5);
+
+        // later calls do not read again
+        let second = parse_once(
+            &parsed,
+            || -> &[u8] { panic!("the reader was read twice") },
+            ConfigFormat::Json,
+        )
+        .unwrap();
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn parse_reader_once_does_not_cache_errors() {
+        let parsed = Mutex::new(None);
+
+        assert!(parse_once(&parsed, || "{".as_bytes(), ConfigFormat::Json).is_err());
+        assert!(parsed.lock().unwrap().is_none());
+    }
+}
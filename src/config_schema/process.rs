@@ -0,0 +1,24 @@
+use std::fs;
+
+use crate::common::*;
+use crate::config::ConfigData;
+
+pub struct ConfigSchemaArgs {
+    pub output: Option<String>,
+}
+
+/// Print (or write to the output file) the JSON schema of the config file, to be used
+/// by editors and external tools to validate config files.
+pub fn process_config_schema(args: ConfigSchemaArgs) -> Result<()> {
+    let schema = serde_json::to_string_pretty(&ConfigData::json_schema())?;
+
+    match args.output {
+        Some(output) => {
+            fs::write(&output, schema)?;
+            println!("Config schema saved to '{}'.", output);
+        }
+        None => println!("{}", schema),
+    }
+
+    Ok(())
+}
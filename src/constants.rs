@@ -7,6 +7,15 @@ use console::Emoji;
 /// Metaplex program id.
 pub const METAPLEX_PROGRAM_ID: &str = "metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s";
 
+/// Shadow Drive storage program id.
+pub const SHDW_DRIVE_PROGRAM_ID: &str = "2e1wdyNhUvE76y6yUCvah2KaviavMJYKoRun8acMRBZZ";
+
+/// Mint of the SHDW token, which pays for the Shadow Drive storage.
+pub const SHDW_TOKEN_MINT: &str = "SHDWyBxihqiCj6YekG2GUr7wqKLeLAMK1gHZck9pL6y";
+
+/// Shadow Drive uploader, which co-signs the creation of storage accounts.
+pub const SHDW_UPLOADER: &str = "972oJTFyjmVNsWM4GHEGPWUomAiJf2qrVotLtwnKmWem";
+
 /// Civic gateway program id.
 pub const CIVIC: &str = "gatem74V238djXdzWnJf94Wo1DcnuGkfijbf3AuBhfs";
 
@@ -53,16 +62,46 @@ pub const CONFIG_URI_OFFSET: usize = STRING_LEN_SIZE + CONFIG_NAME_OFFSET + MAX_
 
 pub const MINT_LAYOUT: u64 = 82;
 
+/// Number of decimals of SOL (1 SOL = 10^9 lamports).
+pub const SOL_DECIMALS: u8 = 9;
+
+/// Compute unit limit requested when a priority fee is set (the fee paid is the
+/// compute unit price times the limit).
+pub const PRIORITY_FEE_COMPUTE_UNITS: u32 = 400_000;
+
+/// Base fee of a transaction signature (in lamports).
+pub const LAMPORTS_PER_SIGNATURE: u64 = 5000;
+
+/// Minimum time (in seconds) between the go live date and the end date: a shorter mint
+/// is most likely a mistake in one of the dates.
+pub const MIN_MINT_DURATION: i64 = 60;
+
+/// Maximum number of times the priority fee is doubled when a transaction is retried.
+pub const MAX_PRIORITY_FEE_BUMPS: u32 = 3;
+
+/// Maximum freeze period (in seconds) accepted by the candy machine program (31 days).
+pub const MAX_FREEZE_TIME: i64 = 60 * 60 * 24 * 31;
+
 /// Maximum number of concurrent tasks (this is important for tasks that handle files
 /// and network connections).
 pub const PARALLEL_LIMIT: usize = 45;
 
+/// Default timeout (in seconds) of the RPC requests, the same as the solana client.
+pub const DEFAULT_RPC_TIMEOUT_SECS: u64 = 30;
+
 /// Default path for assets folder.
 pub const DEFAULT_ASSETS: &str = "assets";
 
 /// Default path for cache file.
 pub const DEFAULT_CACHE: &str = "cache.json";
 
+/// Environment variable with the path of the cache file, so each drop managed from the
+/// same directory can use its own cache without passing `--cache` to every command.
+pub const CACHE_ENV: &str = "SUGAR_CACHE";
+
+/// Default path for the result file of the airdrop.
+pub const DEFAULT_AIRDROP_RESULTS: &str = "airdrop_results.csv";
+
 /// Default path for config file.
 pub const DEFAULT_CONFIG: &str = "config.json";
 
@@ -75,13 +114,38 @@ pub const BUNDLR_DEVNET: &str = "https://devnet.bundlr.network";
 /// Bundlr mainnet endpoint.
 pub const BUNDLR_MAINNET: &str = "https://node1.bundlr.network";
 
+/// Maximum file size (in bytes) accepted by nft.storage in a single upload.
+pub const NFT_STORAGE_MAX_FILE_SIZE: u64 = 100 * 1024 * 1024;
+
+/// Maximum file size (in bytes) of a single AWS S3 upload (larger files require a
+/// multipart upload).
+pub const AWS_MAX_FILE_SIZE: u64 = 5 * 1024 * 1024 * 1024;
+
 /// Default RPC endpoint for devnet.
 pub const DEFAULT_RPC_DEVNET: &str = "https://psytrbhymqlkfrhudd.dev.genesysgo.net:8899/";
 
+/// RPC endpoint of a local validator.
+pub const RPC_LOCALNET: &str = "http://127.0.0.1:8899";
+
+/// Public RPC endpoint for devnet.
+pub const RPC_DEVNET: &str = "https://api.devnet.solana.com";
+
+/// Public RPC endpoint for testnet.
+pub const RPC_TESTNET: &str = "https://api.testnet.solana.com";
+
+/// Public RPC endpoint for mainnet-beta.
+pub const RPC_MAINNET: &str = "https://api.mainnet-beta.solana.com";
+
 pub const CIVIC_NETWORK: &str = "ignREusXmGrscGNUesoU9mxfds9AiYTezUKex2PsZV6";
 
 pub const ENCORE_NETWORK: &str = "tibePmPaoTgrs929rWpu755EXaxC7M3SthVCf6GzjZt";
 
+/// Civic uniqueness gatekeeper network (mainnet).
+pub const CIVIC_UNIQUENESS_NETWORK: &str = "uniqobk8oGh4XBLMqM68K8M2zNu3CdYX7q5go7whQiv";
+
+/// Civic ID verification gatekeeper network (mainnet).
+pub const CIVIC_ID_VERIFICATION_NETWORK: &str = "bni1ewus6aMxTxBi5SAfzEmmXLf8KcVFRmTfproJuKw";
+
 pub const LOOKING_GLASS_EMOJI: Emoji<'_, '_> = Emoji("🔍 ", "");
 
 pub const CANDY_EMOJI: Emoji<'_, '_> = Emoji("🍬 ", "");
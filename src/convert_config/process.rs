@@ -0,0 +1,450 @@
+use console::style;
+use dialoguer::Confirm;
+use serde_json::Map;
+use std::fs::OpenOptions;
+
+use crate::common::*;
+use crate::config::data::{date_as_timestamp, ConfigData};
+use crate::validate::Metadata;
+
+/// Default name of the first metadata file.
+const DEFAULT_METADATA: &str = "0.json";
+
+/// Legacy settings that have the same name (and format) in Sugar.
+const UNCHANGED_KEYS: &[&str] = &[
+    "price",
+    "number",
+    "gatekeeper",
+    "solTreasuryAccount",
+    "splTokenAccount",
+    "splToken",
+    "goLiveDate",
+    "hiddenSettings",
+    "awsS3Bucket",
+    "creators",
+    "symbol",
+    "sellerFeeBasisPoints",
+    "retainAuthority",
+    "isMutable",
+    "uploadMethod",
+];
+
+pub struct ConvertConfigArgs {
+    pub legacy_config: String,
+    pub output: String,
+    pub keypair: Option<String>,
+    pub rpc_url: Option<String>,
+    pub assets_dir: String,
+}
+
+/// A change made to the legacy config during the conversion.
+enum Change {
+    /// Setting converted from the legacy config (name and/or value changed).
+    Converted(String, Value, String, Value),
+    /// Setting missing from the legacy config, set to a default value.
+    Added(String, Value, String),
+    /// Legacy setting that has no equivalent in Sugar.
+    Dropped(String, Value, String),
+}
+
+pub fn process_convert_config(args: ConvertConfigArgs) -> Result<()> {
+    println!(
+        "{} {}Converting legacy config file",
+        style("[1/2]").bold().dim(),
+        LOOKING_GLASS_EMOJI
+    );
+
+    let file = File::open(&args.legacy_config).map_err(|err| {
+        anyhow!(
+            "Failed to open legacy config file '{}': {}",
+            args.legacy_config,
+            err
+        )
+    })?;
+    let legacy: Value = serde_json::from_reader(file).map_err(|err| {
+        anyhow!(
+            "Failed to parse legacy config file '{}': {}",
+            args.legacy_config,
+            err
+        )
+    })?;
+
+    let legacy = match legacy {
+        Value::Object(legacy) => legacy,
+        _ => {
+            return Err(anyhow!(
+                "Invalid legacy config file '{}': expected a JSON object",
+                args.legacy_config
+            ))
+        }
+    };
+
+    let (mut converted, mut changes) = convert_legacy_config(&legacy)?;
+    add_missing_settings(&mut converted, &mut changes, &args)?;
+
+    let config_data: ConfigData = serde_json::from_value(Value::Object(converted))
+        .map_err(|err| anyhow!("Failed to convert the legacy config: {}", err))?;
+
+    print_changes(&changes);
+
+    let dropped: Vec<&String> = changes
+        .iter()
+        .filter_map(|change| match change {
+            Change::Dropped(key, _, _) => Some(key),
+            _ => None,
+        })
+        .collect();
+
+    if !dropped.is_empty() {
+        let message = format!(
+            "{} legacy setting(s) could not be converted: {}",
+            dropped.len(),
+            dropped
+                .iter()
+                .map(|key| key.as_str())
+                .collect::<Vec<&str>>()
+                .join(", ")
+        );
+        warn!("{}", message);
+        println!("\n{} {}", style("WARNING:").yellow().bold(), message);
+    }
+
+    if let Err(errors) = config_data.validate() {
+        let mut message = String::from("The converted config is invalid:");
+        for err in errors {
+            message.push_str(&format!("\n=> {}", err));
+        }
+        return Err(anyhow!(message));
+    }
+
+    println!(
+        "\n{} {}Saving config file",
+        style("[2/2]").bold().dim(),
+        PAPER_EMOJI
+    );
+
+    if Path::new(&args.output).is_file()
+        && !Confirm::new()
+            .with_prompt(format!(
+                "The file \"{}\" already exists. Do you want to overwrite it?",
+                args.output
+            ))
+            .interact()?
+    {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&config_data).expect("Unable to convert config to JSON.")
+        );
+        return Ok(());
+    }
+
+    let file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(&args.output)
+        .map_err(|err| anyhow!("Failed to create config file '{}': {}", args.output, err))?;
+    serde_json::to_writer_pretty(file, &config_data)?;
+
+    println!(
+        "{}",
+        style(format!("Saved config to file: \"{}\"", args.output))
+    );
+
+    Ok(())
+}
+
+/// Map the settings of the legacy (JS CLI) config to the Sugar format.
+fn convert_legacy_config(legacy: &Map<String, Value>) -> Result<(Map<String, Value>, Vec<Change>)> {
+    let mut converted = Map::new();
+    let mut changes = Vec::new();
+
+    for (legacy_key, value) in legacy {
+        // legacy configs might use snake_case keys
+        let key = to_camel_case(legacy_key);
+        let value = camel_case_keys(value.clone());
+
+        if UNCHANGED_KEYS.contains(&key.as_str()) {
+            if key != *legacy_key {
+                changes.push(Change::Converted(
+                    legacy_key.clone(),
+                    value.clone(),
+                    key.clone(),
+                    value.clone(),
+                ));
+            }
+            converted.insert(key, value);
+            continue;
+        }
+
+        let (new_key, new_value) = match key.as_str() {
+            "noRetainAuthority" => match value.as_bool() {
+                Some(no_retain) => ("retainAuthority", Value::Bool(!no_retain)),
+                None => return Err(invalid_value(legacy_key, &value)),
+            },
+            "noMutable" => match value.as_bool() {
+                Some(no_mutable) => ("isMutable", Value::Bool(!no_mutable)),
+                None => return Err(invalid_value(legacy_key, &value)),
+            },
+            "nftStorageKey" => ("nftStorageAuthToken", value.clone()),
+            "storage" => match value.as_str().map(|s| s.to_lowercase()).as_deref() {
+                Some("arweave") | Some("arweave-sol") | Some("arweave-bundle") => {
+                    ("uploadMethod", json!("bundlr"))
+                }
+                Some("aws") => ("uploadMethod", json!("aws")),
+                Some("nft-storage") => ("uploadMethod", json!("nft.storage")),
+                Some("pinata") => ("uploadMethod", json!("pinata")),
+                _ => {
+                    changes.push(Change::Dropped(
+                        legacy_key.clone(),
+                        value,
+                        "storage type not supported by Sugar".to_string(),
+                    ));
+                    continue;
+                }
+            },
+            "endSettings" => ("endSettings", convert_end_settings(legacy_key, &value)?),
+            "whitelistMintSettings" => (
+                "whitelistMintSettings",
+                convert_whitelist_mint_settings(legacy_key, &value)?,
+            ),
+            "pinataJwt" | "pinataGateway" if value.is_null() => continue,
+            "pinataJwt" | "pinataGateway" => {
+                // both settings are merged into the pinata config
+                let pinata_config = converted.entry("pinataConfig").or_insert_with(
+                    || json!({ "jwt": "", "gatewayUrl": "https://gateway.pinata.cloud" }),
+                );
+                let field = if key == "pinataJwt" {
+                    "jwt"
+                } else {
+                    "gatewayUrl"
+                };
+                pinata_config[field] = value.clone();
+
+                changes.push(Change::Converted(
+                    legacy_key.clone(),
+                    value.clone(),
+                    format!("pinataConfig.{}", field),
+                    value,
+                ));
+                continue;
+            }
+            _ => {
+                changes.push(Change::Dropped(
+                    legacy_key.clone(),
+                    value,
+                    "no equivalent setting in Sugar".to_string(),
+                ));
+                continue;
+            }
+        };
+
+        changes.push(Change::Converted(
+            legacy_key.clone(),
+            value,
+            new_key.to_string(),
+            new_value.clone(),
+        ));
+        converted.insert(new_key.to_string(), new_value);
+    }
+
+    Ok((converted, changes))
+}
+
+/// Set the Sugar settings that have no equivalent in the legacy config.
+fn add_missing_settings(
+    converted: &mut Map<String, Value>,
+    changes: &mut Vec<Change>,
+    args: &ConvertConfigArgs,
+) -> Result<()> {
+    match converted.get("goLiveDate") {
+        Some(Value::String(_)) | Some(Value::Null) | None => (),
+        Some(value) => return Err(invalid_value("goLiveDate", value)),
+    }
+
+    // symbol and seller fee basis points were read from the metadata by the JS CLI
+    let metadata_file = PathBuf::from(&args.assets_dir).join(DEFAULT_METADATA);
+    let metadata = match File::open(&metadata_file) {
+        Ok(file) => serde_json::from_reader::<_, Metadata>(file).ok(),
+        Err(_) => None,
+    };
+
+    let mut defaults = vec![
+        (
+            "retainAuthority",
+            json!(true),
+            "default value of the legacy config".to_string(),
+        ),
+        (
+            "isMutable",
+            json!(true),
+            "default value of the legacy config".to_string(),
+        ),
+        (
+            "uploadMethod",
+            json!("bundlr"),
+            "default upload method".to_string(),
+        ),
+        (
+            "goLiveDate",
+            Value::Null,
+            "not live yet, only the authority can mint".to_string(),
+        ),
+    ];
+
+    match metadata {
+        Some(metadata) => {
+            let source = format!("from metadata file '{}'", metadata_file.display());
+            defaults.push(("symbol", json!(metadata.symbol), source.clone()));
+            defaults.push((
+                "sellerFeeBasisPoints",
+                json!(metadata.seller_fee_basis_points),
+                source,
+            ));
+        }
+        None => {
+            let source = format!("metadata file '{}' not found", metadata_file.display());
+            defaults.push(("symbol", json!(""), source.clone()));
+            defaults.push(("sellerFeeBasisPoints", json!(0), source));
+        }
+    }
+
+    if !converted.contains_key("creators") {
+        let sugar_config = sugar_setup(args.keypair.clone(), args.rpc_url.clone())?;
+        defaults.push((
+            "creators",
+            json!([{ "address": sugar_config.keypair.pubkey().to_string(), "share": 100 }]),
+            "keypair as the only creator".to_string(),
+        ));
+    }
+
+    for (key, value, reason) in defaults {
+        if !converted.contains_key(key) {
+            changes.push(Change::Added(key.to_string(), value.clone(), reason));
+            converted.insert(key.to_string(), value);
+        }
+    }
+
+    Ok(())
+}
+
+/// Convert `{ "endSettingType": { "date": true }, "value": ... }` into the Sugar format.
+fn convert_end_settings(key: &str, value: &Value) -> Result<Value> {
+    if value.is_null() {
+        return Ok(Value::Null);
+    }
+
+    let setting_type = &value["endSettingType"];
+    let end_value = &value["value"];
+
+    if setting_type["date"].as_bool() == Some(true) {
+        let timestamp = match end_value {
+            Value::String(date) => date_as_timestamp(date)?,
+            Value::Number(number) if number.is_i64() => number.as_i64().unwrap(),
+            _ => return Err(invalid_value(key, value)),
+        };
+        Ok(json!({ "endSettingType": "Date", "number": timestamp }))
+    } else if setting_type["amount"].as_bool() == Some(true) {
+        match end_value.as_u64() {
+            Some(amount) => Ok(json!({ "endSettingType": "Amount", "number": amount })),
+            None => Err(invalid_value(key, value)),
+        }
+    } else {
+        Err(invalid_value(key, value))
+    }
+}
+
+/// Convert the whitelist mode `{ "burnEveryTime": true }` into the Sugar format.
+fn convert_whitelist_mint_settings(key: &str, value: &Value) -> Result<Value> {
+    if value.is_null() {
+        return Ok(Value::Null);
+    }
+
+    let mode = if value["mode"]["burnEveryTime"].as_bool() == Some(true) {
+        "burnEveryTime"
+    } else if value["mode"]["neverBurn"].as_bool() == Some(true) {
+        "neverBurn"
+    } else {
+        return Err(invalid_value(key, value));
+    };
+
+    let mut settings = value.clone();
+    settings["mode"] = json!(mode);
+
+    if settings.get("discountPrice").is_none() {
+        settings["discountPrice"] = Value::Null;
+    }
+
+    Ok(settings)
+}
+
+fn invalid_value(key: &str, value: &Value) -> anyhow::Error {
+    anyhow!("Invalid value for legacy setting '{}': {}", key, value)
+}
+
+/// Print the list of changes in a diff-like format.
+fn print_changes(changes: &[Change]) {
+    println!("\nChanges from the legacy config:");
+
+    if changes.is_empty() {
+        println!("  (none)");
+    }
+
+    for change in changes {
+        match change {
+            Change::Converted(legacy_key, legacy_value, key, value) => {
+                println!(
+                    "{}",
+                    style(format!("- {}: {}", legacy_key, legacy_value)).red()
+                );
+                println!("{}", style(format!("+ {}: {}", key, value)).green());
+            }
+            Change::Added(key, value, reason) => {
+                println!(
+                    "{} {}",
+                    style(format!("+ {}: {}", key, value)).green(),
+                    style(format!("({})", reason)).dim()
+                );
+            }
+            Change::Dropped(key, value, reason) => {
+                println!(
+                    "{} {}",
+                    style(format!("- {}: {}", key, value)).red(),
+                    style(format!("({})", reason)).dim()
+                );
+            }
+        }
+    }
+}
+
+/// Convert a `snake_case` key into `camelCase` (other keys are returned unchanged).
+fn to_camel_case(key: &str) -> String {
+    let mut camel_case = String::with_capacity(key.len());
+    let mut upper = false;
+
+    for c in key.chars() {
+        if c == '_' {
+            upper = true;
+        } else if upper {
+            camel_case.extend(c.to_uppercase());
+            upper = false;
+        } else {
+            camel_case.push(c);
+        }
+    }
+
+    camel_case
+}
+
+/// Convert all the keys of nested objects into `camelCase`.
+fn camel_case_keys(value: Value) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(
+            map.into_iter()
+                .map(|(key, value)| (to_camel_case(&key), camel_case_keys(value)))
+                .collect(),
+        ),
+        Value::Array(values) => Value::Array(values.into_iter().map(camel_case_keys).collect()),
+        value => value,
+    }
+}
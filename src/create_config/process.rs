@@ -15,8 +15,9 @@ use url::Url;
 
 use crate::candy_machine::ID as CANDY_MACHINE_ID;
 use crate::config::{
-    parse_string_as_date, ConfigData, Creator, EndSettingType, EndSettings, GatekeeperConfig,
-    HiddenSettings, UploadMethod, WhitelistMintMode, WhitelistMintSettings,
+    parse_string_as_date, AwsConfig, ConfigData, Creator, EndSettingType, EndSettings,
+    GatekeeperConfig, HiddenSettings, PinataConfig, ShdwConfig, TreasuryConfig, UploadMethod,
+    WhitelistMintMode, WhitelistMintSettings,
 };
 use crate::constants::*;
 use crate::setup::{setup_client, sugar_setup};
@@ -69,6 +70,16 @@ pub fn process_create_config(args: CreateConfigArgs) -> Result<()> {
             Ok(())
         }
     };
+    let price_validator = |input: &String| -> Result<(), String> {
+        match input.parse::<f64>() {
+            Ok(price) if price > 0.0 => Ok(()),
+            Ok(_) => Err(String::from("Price must be greater than 0.")),
+            Err(_) => Err(format!(
+                "Couldn't parse price input of '{}' to a float.",
+                input
+            )),
+        }
+    };
     let number_validator = |input: &String| -> Result<(), String> {
         if input.parse::<u64>().is_err() {
             Err(format!("Couldn't parse input of '{}' to a number.", input))
@@ -170,7 +181,7 @@ pub fn process_create_config(args: CreateConfigArgs) -> Result<()> {
 
     config_data.price = Input::with_theme(&theme)
         .with_prompt("What is the price of each NFT?")
-        .validate_with(float_validator)
+        .validate_with(price_validator)
         .interact()
         .unwrap()
         .parse::<f64>()
@@ -190,6 +201,13 @@ pub fn process_create_config(args: CreateConfigArgs) -> Result<()> {
         Input::with_theme(&theme)
             .with_prompt("How many NFTs will you have in your candy machine?")
             .validate_with(number_validator)
+            .validate_with(|input: &String| {
+                if input.parse::<u64>().unwrap() > 0 {
+                    Ok(())
+                } else {
+                    Err("Number of NFTs must be greater than 0.")
+                }
+            })
             .interact()
             .unwrap().parse::<u64>().expect("Failed to parse number into u64 that should have already been validated.")
     };
@@ -255,13 +273,13 @@ pub fn process_create_config(args: CreateConfigArgs) -> Result<()> {
     .interact()
     .unwrap();
 
-    config_data.go_live_date = if date.contains("now") {
+    config_data.go_live_date = Some(if date.contains("now") {
         let current_time = chrono::Utc::now();
         current_time.format("%d %b %Y %H:%M:%S %z").to_string()
     } else {
         let date = DateTime::parse_from_str(&date, DATE_MASK)?;
         date.format("%d %b %Y %H:%M:%S %z").to_string()
-    };
+    });
     // creators
 
     let num_creators = Input::with_theme(&theme)
@@ -269,7 +287,7 @@ pub fn process_create_config(args: CreateConfigArgs) -> Result<()> {
         .validate_with(number_validator)
         .validate_with({
             |input: &String| match input.parse::<u8>().unwrap() {
-                1 | 2 | 3 | 4 => Ok(()),
+                1..=4 => Ok(()),
                 _ => Err("Number of creator wallets must be between 1 and 4, inclusive."),
             }
         })
@@ -280,7 +298,7 @@ pub fn process_create_config(args: CreateConfigArgs) -> Result<()> {
 
     let mut total_share = 0;
 
-    (0..num_creators).into_iter().for_each(|i| {
+    (0..num_creators).for_each(|i| {
         let address = Pubkey::from_str(
             &Input::with_theme(&theme)
                 .with_prompt(format!("Enter creator wallet address #{}", i + 1))
@@ -339,41 +357,37 @@ pub fn process_create_config(args: CreateConfigArgs) -> Result<()> {
     // SPL token mint
 
     let sugar_config = sugar_setup(args.keypair, args.rpc_url)?;
-    let client = Arc::new(setup_client(&sugar_config)?);
+    let client = Arc::new(setup_client(&sugar_config, None)?);
     let program = client.program(CANDY_MACHINE_ID);
 
-    if choices.contains(&SPL_INDEX) {
-        config_data.sol_treasury_account = None;
-        config_data.spl_token = Some(
-            Pubkey::from_str(
-                &Input::with_theme(&theme)
-                    .with_prompt("What is your SPL token mint address?")
-                    .validate_with(pubkey_validator)
-                    .validate_with(|input: &String| -> Result<()> {
-                        check_spl_token(&program, input)
-                    })
-                    .interact()
-                    .unwrap(),
-            )
-            .expect("Failed to parse string into pubkey that should have already been validated."),
-        );
-        config_data.spl_token_account = Some(
-                Pubkey::from_str(
-                    &Input::with_theme(&theme)
-                        .with_prompt("What is your SPL token account address (the account that will hold the SPL token mints)?")
-                        .validate_with(pubkey_validator)
-                        .validate_with(|input: &String| -> Result<()> {
-                            check_spl_token_account(&program, input)
-                        })
-                        .interact()
-                        .unwrap(),
-                )
-                    .expect("Failed to parse string into pubkey that should have already been validated."),
-            )
+    config_data.treasury = if choices.contains(&SPL_INDEX) {
+        let token = Pubkey::from_str(
+            &Input::with_theme(&theme)
+                .with_prompt("What is your SPL token mint address?")
+                .validate_with(pubkey_validator)
+                .validate_with(|input: &String| -> Result<()> { check_spl_token(&program, input) })
+                .interact()
+                .unwrap(),
+        )
+        .expect("Failed to parse string into pubkey that should have already been validated.");
+        let account = Pubkey::from_str(
+            &Input::with_theme(&theme)
+                .with_prompt("What is your SPL token account address (the account that will hold the SPL token mints)?")
+                .validate_with(pubkey_validator)
+                .validate_with(|input: &String| -> Result<()> {
+                    check_spl_token_account(&program, input)
+                })
+                .interact()
+                .unwrap(),
+        )
+        .expect("Failed to parse string into pubkey that should have already been validated.");
+
+        TreasuryConfig::Spl {
+            token,
+            account: Some(account),
+        }
     } else {
-        config_data.spl_token = None;
-        config_data.spl_token_account = None;
-        config_data.sol_treasury_account = Some(
+        TreasuryConfig::Sol(
             Pubkey::from_str(
                 &Input::with_theme(&theme)
                     .with_prompt("What is your SOL treasury address?")
@@ -382,7 +396,7 @@ pub fn process_create_config(args: CreateConfigArgs) -> Result<()> {
                     .unwrap(),
             )
             .expect("Failed to parse string into pubkey that should have already been validated."),
-        );
+        )
     };
 
     // gatekeeper
@@ -440,6 +454,13 @@ pub fn process_create_config(args: CreateConfigArgs) -> Result<()> {
                     )
                     .allow_empty(true)
                     .validate_with(float_validator)
+                    .validate_with(|input: &String| {
+                        if input.is_empty() || input.parse::<f64>().unwrap() <= config_data.price {
+                            Ok(())
+                        } else {
+                            Err("The discount price cannot be higher than the price.")
+                        }
+                    })
                     .interact()
                     .unwrap();
             if price.is_empty() {
@@ -484,7 +505,7 @@ pub fn process_create_config(args: CreateConfigArgs) -> Result<()> {
                 .with_prompt("What is the amount to stop the mint?")
                 .validate_with(number_validator)
                 .validate_with(|num: &String| {
-                    if num.parse::<u64>().unwrap() < config_data.number {
+                    if num.parse::<u64>().unwrap() <= config_data.number {
                         Ok(())
                     } else {
                         Err("Your end settings amount cannot be more than the number of items in your candy machine.")
@@ -538,9 +559,10 @@ pub fn process_create_config(args: CreateConfigArgs) -> Result<()> {
             .interact()
             .unwrap();
         let hash = Input::with_theme(&theme)
-            .with_prompt("What is the hash value for your hidden settings?")
+            .with_prompt("What is the hash value for your hidden settings? Hit [ENTER] to compute it from the cache file on deploy.")
+            .allow_empty(true)
             .validate_with(|hash: &String| {
-                if hash.len() != 32 {
+                if !hash.is_empty() && hash.len() != 32 {
                     Err("Your hidden settings hash has to be 32 characters long.")
                 } else {
                     Ok(())
@@ -555,7 +577,7 @@ pub fn process_create_config(args: CreateConfigArgs) -> Result<()> {
 
     // upload method
 
-    let upload_options = vec!["Bundlr", "AWS"];
+    let upload_options = vec!["Bundlr", "AWS", "NFT.Storage", "Pinata", "Shadow Drive"];
     config_data.upload_method = match Select::with_theme(&theme)
         .with_prompt("What upload method do you want to use?")
         .items(&upload_options)
@@ -565,16 +587,88 @@ pub fn process_create_config(args: CreateConfigArgs) -> Result<()> {
     {
         0 => UploadMethod::Bundlr,
         1 => UploadMethod::AWS,
+        2 => UploadMethod::NftStorage,
+        3 => UploadMethod::Pinata,
+        4 => UploadMethod::ShdwDrive,
         _ => UploadMethod::Bundlr,
     };
 
     if config_data.upload_method == UploadMethod::AWS {
-        config_data.aws_s3_bucket = Some(
-            Input::with_theme(&theme)
-                .with_prompt("What is the AWS S3 bucket name?")
-                .interact()
-                .unwrap(),
-        );
+        let bucket = Input::with_theme(&theme)
+            .with_prompt("What is the AWS S3 bucket name?")
+            .interact()
+            .unwrap();
+        let region: String = Input::with_theme(&theme)
+            .with_prompt("What is the AWS S3 bucket region? Hit [ENTER] to use the region of your AWS profile.")
+            .allow_empty(true)
+            .interact()
+            .unwrap();
+        let directory: String = Input::with_theme(&theme)
+            .with_prompt("What directory should the files be uploaded to? Hit [ENTER] to upload them to the root of the bucket.")
+            .allow_empty(true)
+            .interact()
+            .unwrap();
+        config_data.aws_config = Some(AwsConfig::new(
+            bucket,
+            if region.is_empty() {
+                None
+            } else {
+                Some(region)
+            },
+            None,
+            if directory.is_empty() {
+                None
+            } else {
+                Some(directory)
+            },
+            None,
+        ));
+    }
+
+    if config_data.upload_method == UploadMethod::NftStorage {
+        let token: String = Input::with_theme(&theme)
+            .with_prompt("What is your NFT.Storage API token? Hit [ENTER] to read it from the NFT_STORAGE_TOKEN environment variable.")
+            .allow_empty(true)
+            .interact()
+            .unwrap();
+        config_data.nft_storage_auth_token = if token.is_empty() { None } else { Some(token) };
+    }
+
+    if config_data.upload_method == UploadMethod::Pinata {
+        let jwt = Input::with_theme(&theme)
+            .with_prompt("What is your Pinata JWT?")
+            .interact()
+            .unwrap();
+        let gateway_url = Input::with_theme(&theme)
+            .with_prompt("What is the gateway URL for your pinned files?")
+            .default("https://gateway.pinata.cloud".to_string())
+            .validate_with(url_validator)
+            .interact()
+            .unwrap();
+        config_data.pinata_config = Some(PinataConfig::new(jwt, gateway_url, None, None));
+    }
+
+    if config_data.upload_method == UploadMethod::ShdwDrive {
+        let storage_account: String = Input::with_theme(&theme)
+            .with_prompt("What is the address of your Shadow Drive storage account? (leave empty to create one when uploading)")
+            .allow_empty(true)
+            .validate_with(|input: &String| -> Result<(), String> {
+                if input.is_empty() {
+                    Ok(())
+                } else {
+                    pubkey_validator(input)
+                }
+            })
+            .interact()
+            .unwrap();
+        let storage_account = if storage_account.is_empty() {
+            None
+        } else {
+            Some(Pubkey::from_str(&storage_account).expect(
+                "Failed to parse string into pubkey that should have already been validated.",
+            ))
+        };
+        config_data.shdw_config = Some(ShdwConfig::new(storage_account, None, None, None));
     }
 
     // retain authority
@@ -589,6 +683,19 @@ pub fn process_create_config(args: CreateConfigArgs) -> Result<()> {
         .with_prompt("Do you want your NFTs to remain mutable? We HIGHLY recommend you choose yes.")
         .interact()?;
 
+    // makes sure that the generated config can be read back and it is valid
+
+    let json = serde_json::to_string_pretty(&config_data)?;
+    let parsed = ConfigData::parse_reader(json.as_bytes())?;
+
+    if let Err(errors) = parsed.validate() {
+        let mut message = String::from("The generated config is invalid:");
+        for err in errors {
+            message.push_str(&format!("\n=> {}", err));
+        }
+        return Err(anyhow!(message));
+    }
+
     // saving configuration file
 
     println!(
@@ -1,9 +1,205 @@
-use std::sync::{atomic::AtomicBool, Arc};
+use anchor_client::solana_sdk::{pubkey::Pubkey, signature::Signature, transaction::Transaction};
+use anyhow::Result;
+use data_encoding::BASE64;
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt,
+    str::FromStr,
+    sync::{atomic::AtomicBool, Arc},
+};
+
+use crate::cache::DeployCheckpoint;
+use crate::config::ConfigFormat;
+use crate::setup::Network;
 
 pub struct DeployArgs {
     pub config: String,
+    pub config_format: Option<ConfigFormat>,
     pub cache: String,
     pub keypair: Option<String>,
     pub rpc_url: Option<String>,
+    pub network: Option<Network>,
+    pub yes: bool,
+    pub confirm_mainnet: bool,
+    pub allow_partial: bool,
+    pub restart: bool,
+    pub dry_run: bool,
+    pub collection_only: bool,
+    pub unsigned_out: Option<String>,
+    pub authority: Option<String>,
+    pub nonce_accounts: Vec<String>,
+    pub only: Option<ConfigLineRange>,
+    pub output: OutputFormat,
+    pub priority_fee: Option<u64>,
     pub interrupted: Arc<AtomicBool>,
 }
+
+/// Output format of the deploy command.
+///
+/// `json` prints the deploy summary as a JSON object to stdout, writing the progress
+/// of the deploy to stderr.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OutputFormat {
+    Human,
+    Json,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "human" => Ok(OutputFormat::Human),
+            "json" => Ok(OutputFormat::Json),
+            _ => Err(format!(
+                "Invalid output format: {}. Valid formats are: human, json.",
+                s
+            )),
+        }
+    }
+}
+
+/// Range of config line indices to deploy, written as `start..end` (both included).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ConfigLineRange {
+    pub start: u64,
+    pub end: u64,
+}
+
+impl ConfigLineRange {
+    pub fn contains(&self, index: u64) -> bool {
+        index >= self.start && index <= self.end
+    }
+}
+
+impl FromStr for ConfigLineRange {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            format!(
+                "Invalid range: {}. Use 'start..end' with the first and last index to deploy.",
+                s
+            )
+        };
+        let (start, end) = s.split_once("..").ok_or_else(invalid)?;
+        let start = start.trim().parse::<u64>().map_err(|_| invalid())?;
+        let end = end.trim().parse::<u64>().map_err(|_| invalid())?;
+
+        if start > end {
+            return Err(invalid());
+        }
+
+        Ok(ConfigLineRange { start, end })
+    }
+}
+
+impl fmt::Display for ConfigLineRange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}..{}", self.start, self.end)
+    }
+}
+
+/// Summary of a deploy, printed when using the JSON output.
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeploySummary {
+    pub candy_machine: String,
+    pub config_lines_added: usize,
+    /// Lamports spent by the payer, including the rent of the created accounts.
+    pub lamports_spent: u64,
+    pub signatures: Vec<String>,
+}
+
+/// Plan of a dry run deploy: what the deploy would send, printed as JSON with the
+/// `json` output format.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeployPlan {
+    /// Address of the candy machine, when it already exists.
+    pub candy_machine: Option<String>,
+    pub create_candy_machine: bool,
+    pub set_collection: bool,
+    pub config_lines: usize,
+    pub config_line_transactions: usize,
+    /// Number of config lines of each transaction.
+    pub batch_sizes: Vec<usize>,
+    pub rent_lamports: u64,
+    pub estimated_lamports: u64,
+    pub balance_lamports: u64,
+}
+
+/// Name of the file of the candy machine keypair written with the unsigned transactions.
+pub const CANDY_MACHINE_KEYPAIR_FILE: &str = "candy-machine-keypair.json";
+
+/// A transaction of an unsigned deploy, written to a file to be signed elsewhere and
+/// broadcast with `submit`.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeployTransaction {
+    /// What the transaction does, e.g., "Initialize candy machine".
+    pub description: String,
+    pub candy_machine: String,
+    /// Indices of the config lines added by the transaction.
+    #[serde(default)]
+    pub config_lines: Vec<u32>,
+    /// Recent blockhash of the transaction (the nonce value with a durable nonce).
+    pub recent_blockhash: String,
+    /// Durable nonce account advanced by the transaction.
+    #[serde(default)]
+    pub nonce_account: Option<String>,
+    /// Signers that still need to sign the transaction.
+    pub signers: Vec<String>,
+    /// The serialized transaction, base64 encoded.
+    pub transaction: String,
+    /// Checkpoint of the candy machine created by the transaction, recorded in the cache
+    /// once the transaction is confirmed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub checkpoint: Option<DeployCheckpoint>,
+}
+
+impl DeployTransaction {
+    pub fn new(
+        description: &str,
+        candy_machine: &Pubkey,
+        config_lines: Vec<u32>,
+        nonce_account: Option<&Pubkey>,
+        transaction: &Transaction,
+        checkpoint: Option<DeployCheckpoint>,
+    ) -> Result<Self> {
+        Ok(DeployTransaction {
+            description: description.to_string(),
+            candy_machine: candy_machine.to_string(),
+            config_lines,
+            recent_blockhash: transaction.message.recent_blockhash.to_string(),
+            nonce_account: nonce_account.map(|nonce| nonce.to_string()),
+            signers: missing_signers(transaction)
+                .iter()
+                .map(|signer| signer.to_string())
+                .collect(),
+            transaction: BASE64.encode(&bincode::serialize(transaction)?),
+            checkpoint,
+        })
+    }
+
+    /// Decode the (possibly signed) transaction.
+    pub fn decode_transaction(&self) -> Result<Transaction> {
+        let bytes = BASE64.decode(self.transaction.trim().as_bytes())?;
+        Ok(bincode::deserialize(&bytes)?)
+    }
+}
+
+/// Return the signers of the transaction that have not signed it yet.
+pub fn missing_signers(transaction: &Transaction) -> Vec<Pubkey> {
+    let required = transaction.message.header.num_required_signatures as usize;
+
+    transaction
+        .message
+        .account_keys
+        .iter()
+        .take(required)
+        .zip(transaction.signatures.iter())
+        .filter(|(_, signature)| **signature == Signature::default())
+        .map(|(signer, _)| *signer)
+        .collect()
+}
@@ -8,4 +8,34 @@ pub enum DeployError {
     MissingName(String),
     #[error("{0}")]
     AddConfigLineFailed(String),
+    #[error("Number of items ({0}) do not match cache items ({1}), missing indices: {2}. Re-run with '--allow-partial' to deploy anyway")]
+    ItemCountMismatch(u64, usize, String),
+    #[error("'retainAuthority' is false: re-run with '--yes' to confirm that the update authority should not be retained")]
+    RetainAuthorityNotConfirmed,
+    #[error("The config changed since candy machine {0} was created: re-run with '--restart' to deploy a new candy machine")]
+    CheckpointMismatch(String),
+    #[error(
+        "Deploying to mainnet-beta ({0}): re-run with '--confirm-mainnet' (or '--yes') to confirm"
+    )]
+    MainnetNotConfirmed(String),
+    #[error("Insufficient balance to deploy: need ◎ {0}, have ◎ {1} (wallet {2})")]
+    InsufficientBalance(String, String, String),
+    #[error("Missing candy machine in cache file '{0}': run 'deploy' without '--collection-only' to create the candy machine first")]
+    CandyMachineNotDeployed(String),
+    #[error("{0} transaction(s) to build but only {1} nonce account(s) given: use one '--nonce-account' per transaction")]
+    NotEnoughNonceAccounts(usize, usize),
+    #[error("The authority of nonce account {0} is {1}, not the candy machine authority {2}")]
+    NonceAuthorityMismatch(String, String, String),
+    #[error("Range {0} is outside of the {1} item(s) of the config")]
+    RangeOutOfBounds(String, u64),
+    #[error("Range {0} leaves a gap: the config lines are deployed up to index {1}, deploy from index {1} first")]
+    RangeGap(String, u64),
+    #[error(
+        "'--only' cannot be used with 'hiddenSettings': the candy machine has no config lines"
+    )]
+    RangeWithHiddenSettings,
+    #[error("Failed to write the candy machine keypair to '{0}': {1}")]
+    KeypairWriteFailed(String, String),
+    #[error("Deploy aborted by the user")]
+    Aborted,
 }
@@ -1,17 +1,23 @@
+use anchor_client::solana_client::nonce_utils;
 use anchor_client::solana_sdk::{
+    instruction::Instruction,
+    packet::PACKET_DATA_SIZE,
     pubkey::Pubkey,
-    signature::{Keypair, Signature, Signer},
+    signature::{write_keypair_file, Keypair, Signature, Signer},
     system_instruction, system_program, sysvar,
+    transaction::Transaction,
 };
+use anchor_client::RequestBuilder;
 use anchor_lang::prelude::AccountMeta;
 use anyhow::Result;
-use console::style;
+use console::{style, user_attended};
+use dialoguer::Confirm;
 use futures::future::select_all;
 use rand::rngs::OsRng;
-use spl_associated_token_account::get_associated_token_address;
 use std::{
     cmp,
     collections::HashSet,
+    fs,
     str::FromStr,
     sync::{
         atomic::{AtomicBool, Ordering},
@@ -21,41 +27,86 @@ use std::{
 
 use mpl_candy_machine::accounts as nft_accounts;
 use mpl_candy_machine::instruction as nft_instruction;
-use mpl_candy_machine::{CandyMachineData, ConfigLine, Creator as CandyCreator};
+use mpl_candy_machine::{CandyMachineData, ConfigLine};
 pub use mpl_token_metadata::state::{
     MAX_CREATOR_LIMIT, MAX_NAME_LENGTH, MAX_SYMBOL_LENGTH, MAX_URI_LENGTH,
 };
 
 use crate::cache::*;
-use crate::candy_machine::uuid_from_pubkey;
+use crate::candy_machine::get_candy_machine_state;
 use crate::candy_machine::ID as CANDY_MACHINE_ID;
+use crate::candy_machine::{get_candy_machine_account_size, uuid_from_pubkey};
+use crate::collection::{check_collection, get_collection_mint, set_collection, CollectionError};
 use crate::common::*;
-use crate::config::{data::*, parser::get_config_data};
+use crate::config::{data::*, parser::get_config_data, ConfigError};
 use crate::deploy::data::*;
 use crate::deploy::errors::*;
-use crate::setup::{setup_client, sugar_setup};
+use crate::rpc_calls::timed;
+use crate::setup::{get_network_rpc_url, setup_client, sugar_setup, Network};
 use crate::utils::*;
 use crate::validate::parser::{check_name, check_seller_fee_basis_points, check_symbol, check_url};
 
-/// The maximum config line bytes per transaction.
-const MAX_TRANSACTION_BYTES: usize = 1000;
+/// Size (in bytes) of an `add_config_lines` transaction without the config lines:
+/// signature, message header, accounts (candy machine, authority and program), recent
+/// blockhash and the instruction data (discriminator, index and vector length).
+const ADD_CONFIG_LINES_TX_OVERHEAD: usize =
+    1 + 64 + 3 + 1 + (3 * 32) + 32 + 1 + 1 + 1 + 2 + 2 + 8 + 4 + 4;
+
+/// The maximum config line bytes per transaction, so that the transaction fits in a
+/// single packet (a small margin is kept for the compact length encodings).
+const MAX_TRANSACTION_BYTES: usize = PACKET_DATA_SIZE - ADD_CONFIG_LINES_TX_OVERHEAD - 12;
+
+/// Size (in bytes) that a priority fee adds to a transaction: the compute budget program
+/// account and the `SetComputeUnitLimit` and `SetComputeUnitPrice` instructions (program
+/// index, accounts and data lengths, and data).
+const PRIORITY_FEE_TX_OVERHEAD: usize = 32 + (3 + 5) + (3 + 9);
 
 /// The maximum number of config lines per transaction.
 const MAX_TRANSACTION_LINES: usize = 17;
 
+/// The maximum number of missing indices reported.
+const MAX_REPORTED_INDICES: usize = 10;
+
+/// Print a line of the deploy progress: when the summary is printed as JSON, the
+/// progress goes to stderr so that stdout only has the summary.
+macro_rules! progress {
+    ($json:expr, $($arg:tt)*) => {
+        if $json {
+            eprintln!($($arg)*);
+        } else {
+            println!($($arg)*);
+        }
+    };
+}
+
 struct TxInfo {
     candy_pubkey: Pubkey,
     payer: Keypair,
     chunk: Vec<(u32, ConfigLine)>,
+    retry_config: RetryConfig,
+    priority_fee: u64,
+}
+
+/// A transaction of an unsigned deploy before its blockhash is set.
+struct UnsignedTx {
+    description: &'static str,
+    config_lines: Vec<u32>,
+    instructions: Vec<Instruction>,
+    /// Signer of the transaction other than the authority.
+    signer: Option<Keypair>,
 }
 
 pub async fn process_deploy(args: DeployArgs) -> Result<()> {
+    let json = args.output == OutputFormat::Json;
+    let mut summary = DeploySummary::default();
+
     // loads the cache file (this needs to have been created by
     // the upload command)
     let mut cache = load_cache(&args.cache, false)?;
 
     if cache.items.0.is_empty() {
-        println!(
+        progress!(
+            json,
             "{}",
             style("No cache items found - run 'upload' to create the cache file first.")
                 .red()
@@ -70,10 +121,10 @@ pub async fn process_deploy(args: DeployArgs) -> Result<()> {
     // correct length
 
     for (index, item) in &cache.items.0 {
-        if item.name.is_empty() {
+        if item.config_name().is_empty() {
             return Err(DeployError::MissingName(index.to_string()).into());
         } else {
-            check_name(&item.name)?;
+            check_name(item.config_name())?;
         }
 
         if item.metadata_link.is_empty() {
@@ -83,30 +134,273 @@ pub async fn process_deploy(args: DeployArgs) -> Result<()> {
         }
     }
 
-    let sugar_config = sugar_setup(args.keypair, args.rpc_url)?;
-    let client = Arc::new(setup_client(&sugar_config)?);
-    let config_data = get_config_data(&args.config)?;
+    // an explicit RPC url takes precedence over the network
+    let rpc_url = get_network_rpc_url(args.rpc_url, args.network)?;
 
-    let candy_machine_address = &cache.program.candy_machine;
+    let authority = args
+        .authority
+        .as_ref()
+        .map(|authority| parse_pubkey("authority", authority))
+        .transpose()?;
+    let nonce_accounts = args
+        .nonce_accounts
+        .iter()
+        .map(|nonce_account| parse_pubkey("nonce account", nonce_account))
+        .collect::<Result<Vec<Pubkey>, ConfigError>>()?;
+
+    let sugar_config = if authority.is_some() {
+        // the unsigned transactions are signed elsewhere, so the client only reads
+        // from the cluster and builds the instructions: its payer is never used
+        SugarConfig {
+            rpc_url,
+            keypair: Keypair::new(),
+        }
+    } else {
+        sugar_setup(args.keypair, Some(rpc_url))?
+    };
+    let mut config_data = get_config_data(&args.config, args.config_format)?;
+    let client = Arc::new(setup_client(&sugar_config, config_data.rpc_timeout_secs)?);
+
+    // the cluster is identified by its genesis hash, so a mainnet RPC url is
+    // confirmed even when it is not set using the network
+    let rpc_client = setup_rpc_client(&sugar_config.rpc_url, config_data.rpc_timeout_secs)?;
+    let genesis_hash = timed("Get genesis hash", || rpc_client.get_genesis_hash())?.to_string();
+    let network = Network::from_genesis_hash(&genesis_hash);
+    let cluster = match network {
+        Some(network) => network.to_string(),
+        None => format!("unknown cluster (genesis hash {})", genesis_hash),
+    };
+    info!("Deploying to {} ({})", cluster, sugar_config.rpc_url);
+    progress!(
+        json,
+        "{} {} ({})\n",
+        style("Cluster:").bold(),
+        cluster,
+        sugar_config.rpc_url
+    );
+
+    // a dry run or an unsigned deploy sends no transactions, so it does not need to
+    // be confirmed
+    if network == Some(Network::Mainnet) && !args.dry_run && args.unsigned_out.is_none() {
+        confirm_mainnet(&sugar_config.rpc_url, args.yes || args.confirm_mainnet)?;
+    }
+    let content_hash = config_data.content_hash()?;
+
+    if !args.restart
+        && !cache.program.candy_machine.is_empty()
+        && cache.config_changed(&content_hash)
+    {
+        progress!(
+            json,
+            "{} {}\n",
+            style("WARNING:").yellow().bold(),
+            style(format!(
+                "The config changed since the last deploy of candy machine {}: use 'sugar update' to apply the changes to the candy machine.",
+                cache.program.candy_machine
+            ))
+            .yellow()
+        );
+    }
+
+    // only the collection of the deployed candy machine is set, the config lines
+    // are not checked or written
+    if args.collection_only {
+        let priority_fee = args.priority_fee.or(config_data.priority_fee).unwrap_or(0);
+        return deploy_collection(
+            &client,
+            &sugar_config,
+            &config_data,
+            &cache,
+            priority_fee,
+            args.dry_run,
+            json,
+        );
+    }
+
+    // echoes the resolved go live date, so a wrong date or offset is noticed before
+    // the candy machine is created
+    let go_live = match resolve_go_live_date(&config_data.go_live_date)? {
+        Some((timestamp, date)) => format!("{} ({})", date, timestamp),
+        None => "not set".to_string(),
+    };
+    info!("Go live: {}", go_live);
+    progress!(json, "{} {}\n", style("Go live:").bold(), go_live);
 
     // checks the candy machine data
 
     let num_items = config_data.number;
-    let hidden = config_data.hidden_settings.is_some();
+    let config_hash = config_data.deploy_hash()?;
+    let retry_config = config_data.retry_config.unwrap_or_default();
+    let priority_fee = args.priority_fee.or(config_data.priority_fee).unwrap_or(0);
+    // a hidden settings uri template is expanded in placeholder config lines instead
+    let hidden = config_data.onchain_hidden_settings().is_some();
+    let placeholders = config_data.placeholder_settings().cloned();
+
+    if let Some(hidden_settings) = &mut config_data.hidden_settings {
+        if hidden_settings.is_hash_unset() {
+            // the hash is computed from the cache items, so it will be the same
+            // on every deploy as long as the cache items do not change
+            let hash = HiddenSettings::compute_hash(Path::new(&args.cache))?;
+            hidden_settings.set_hash(hash)?;
+            info!(
+                "Hidden settings hash computed from cache file: {}",
+                String::from_utf8(hash.to_vec())?
+            );
+        }
+    }
 
     if num_items != (cache.items.0.len() as u64) {
-        return Err(anyhow!(
-            "Number of items ({}) do not match cache items ({})",
-            num_items,
-            cache.items.0.len()
-        ));
+        let missing = cache.items.missing_indices(num_items);
+        // only the first few indices are reported
+        let mut indices = missing
+            .iter()
+            .take(MAX_REPORTED_INDICES)
+            .map(|i| i.to_string())
+            .collect::<Vec<String>>()
+            .join(", ");
+
+        if missing.is_empty() {
+            indices = String::from("none");
+        } else if missing.len() > MAX_REPORTED_INDICES {
+            indices.push_str(&format!(
+                " (and {} more)",
+                missing.len() - MAX_REPORTED_INDICES
+            ));
+        }
+
+        let error = DeployError::ItemCountMismatch(num_items, cache.items.0.len(), indices);
+
+        if !args.allow_partial {
+            return Err(error.into());
+        }
+
+        warn!("{}", error);
+        progress!(json,
+            "{} {}\n",
+            style("WARNING:").yellow().bold(),
+            style("Deploying a partial candy machine: items without a cache entry will not be mintable.").yellow()
+        );
+    }
+
+    check_symbol(&config_data.symbol)?;
+    check_seller_fee_basis_points(config_data.seller_fee_basis_points)?;
+
+    if args.restart {
+        if !cache.program.candy_machine.is_empty() {
+            progress!(
+                json,
+                "{} {}\n",
+                style("WARNING:").yellow().bold(),
+                style(format!(
+                    "Restarting deploy: candy machine {} will be replaced by a new one.",
+                    cache.program.candy_machine
+                ))
+                .yellow()
+            );
+        }
+        cache.reset_deploy();
+
+        if !args.dry_run {
+            cache.sync_file()?;
+        }
+    } else if let Some(checkpoint) = &cache.checkpoint {
+        // the config lines of the candy machine are only valid for the config used
+        // to create it
+        if !checkpoint.matches(num_items, &config_hash) {
+            return Err(
+                DeployError::CheckpointMismatch(cache.program.candy_machine.clone()).into(),
+            );
+        }
+
+        if let Some(index) = checkpoint.last_config_line {
+            progress!(
+                json,
+                "Resuming deploy of candy machine {} after config line {}.\n",
+                cache.program.candy_machine,
+                index
+            );
+        }
+    }
+
+    if let Some(range) = &args.only {
+        check_config_line_range(range, &config_data, &cache)?;
+        progress!(
+            json,
+            "Deploying config lines {} of {} item(s).\n",
+            range,
+            num_items
+        );
+    }
+
+    let candy_machine_address = &cache.program.candy_machine;
+
+    if candy_machine_address.is_empty() && !config_data.retain_authority {
+        confirm_retain_authority(args.yes || args.dry_run, json)?;
+    }
+
+    // checks that the payer can cover the rent and fees before sending any transaction
+
+    let creating = candy_machine_address.is_empty();
+    let config_line_txs = if hidden {
+        0
     } else {
-        check_symbol(&config_data.symbol)?;
-        check_seller_fee_basis_points(config_data.seller_fee_basis_points)?;
+        // a new candy machine starts from the first config line
+        let checkpoint = if creating {
+            None
+        } else {
+            cache.checkpoint.as_ref()
+        };
+        generate_config_lines(
+            num_items,
+            &cache.items,
+            placeholders.as_ref(),
+            checkpoint,
+            args.only.as_ref(),
+            args.allow_partial,
+            priority_fee,
+        )?
+        .len()
+    };
+    if let (Some(output_dir), Some(authority)) = (&args.unsigned_out, &authority) {
+        return write_unsigned_deploy(
+            &client,
+            &config_data,
+            &cache,
+            output_dir,
+            authority,
+            &nonce_accounts,
+            config_hash,
+            args.only.as_ref(),
+            args.allow_partial,
+            priority_fee,
+            json,
+        );
+    }
+
+    if args.dry_run {
+        return dry_run_deploy(
+            &client,
+            &config_data,
+            &cache,
+            args.only.as_ref(),
+            args.allow_partial,
+            priority_fee,
+            &retry_config,
+            json,
+        );
     }
 
+    let initial_balance = check_balance(
+        &client.program(CANDY_MACHINE_ID),
+        if creating { Some(num_items) } else { None },
+        config_line_txs as u64 + config_data.collection.map_or(0, |_| 1),
+        priority_fee,
+        &retry_config,
+    )?;
+
     let candy_pubkey = if candy_machine_address.is_empty() {
-        println!(
+        progress!(
+            json,
             "{} {}Creating candy machine",
             style(if hidden { "[1/1]" } else { "[1/2]" }).bold().dim(),
             CANDY_EMOJI
@@ -120,39 +414,10 @@ pub async fn process_deploy(args: DeployArgs) -> Result<()> {
         let candy_pubkey = candy_keypair.pubkey();
 
         let uuid = uuid_from_pubkey(&candy_pubkey);
-        let candy_data = create_candy_machine_data(&config_data, uuid)?;
         let program = client.program(CANDY_MACHINE_ID);
 
-        let treasury_wallet = match config_data.spl_token {
-            Some(spl_token) => {
-                let spl_token_account_figured = if config_data.spl_token_account.is_some() {
-                    config_data.spl_token_account
-                } else {
-                    Some(get_associated_token_address(&program.payer(), &spl_token))
-                };
-
-                if config_data.sol_treasury_account.is_some() {
-                    return Err(anyhow!("If spl-token-account or spl-token is set then sol-treasury-account cannot be set"));
-                }
-
-                // validates the mint address of the token accepted as payment
-                check_spl_token(&program, &spl_token.to_string())?;
-
-                if let Some(token_account) = spl_token_account_figured {
-                    // validates the spl token wallet to receive proceedings from SPL token payments
-                    check_spl_token_account(&program, &token_account.to_string())?;
-                    token_account
-                } else {
-                    return Err(anyhow!(
-                        "If spl-token is set, spl-token-account must also be set"
-                    ));
-                }
-            }
-            None => match config_data.sol_treasury_account {
-                Some(sol_treasury_account) => sol_treasury_account,
-                None => sugar_config.keypair.pubkey(),
-            },
-        };
+        let (candy_data, treasury_wallet) =
+            prepare_candy_machine(&config_data, &program, &program.payer(), &uuid, json)?;
 
         // all good, let's create the candy machine
 
@@ -162,27 +427,35 @@ pub async fn process_deploy(args: DeployArgs) -> Result<()> {
             candy_data,
             treasury_wallet,
             program,
+            &retry_config,
+            priority_fee,
         )?;
-        info!("Candy machine initialized with sig: {}", sig);
+        info!(signature = %sig, "Candy machine initialized with sig: {}", sig);
+        summary.signatures.push(sig.to_string());
         info!(
+            candy_machine = %candy_pubkey,
             "Candy machine created with address: {}",
             &candy_pubkey.to_string()
         );
 
         cache.program = CacheProgram::new_from_cm(&candy_pubkey);
+        cache.checkpoint = Some(DeployCheckpoint::new(num_items, config_hash));
+        // the schedule starts from the time the candy machine was created
+        config_data.resolve_schedule_dates(&mut cache.schedule_dates)?;
         cache.sync_file()?;
 
         spinner.finish_and_clear();
 
         candy_pubkey
     } else {
-        println!(
+        progress!(
+            json,
             "{} {}Loading candy machine",
             style(if hidden { "[1/1]" } else { "[1/2]" }).bold().dim(),
             CANDY_EMOJI
         );
 
-        match Pubkey::from_str(candy_machine_address) {
+        let candy_pubkey = match Pubkey::from_str(candy_machine_address) {
             Ok(pubkey) => pubkey,
             Err(_err) => {
                 error!(
@@ -194,33 +467,89 @@ pub async fn process_deploy(args: DeployArgs) -> Result<()> {
                 )
                 .into());
             }
+        };
+
+        // caches created before checkpoints were added start tracking the progress
+        // from the current state
+        if cache.checkpoint.is_none() {
+            cache.checkpoint = Some(DeployCheckpoint::new(num_items, config_hash));
+            cache.update_checkpoint();
+            cache.sync_file()?;
         }
+
+        candy_pubkey
     };
 
-    println!("{} {}", style("Candy machine ID:").bold(), candy_pubkey);
+    progress!(
+        json,
+        "{} {}",
+        style("Candy machine ID:").bold(),
+        candy_pubkey
+    );
+
+    if let Some(collection_mint) = config_data.collection {
+        let program = client.program(CANDY_MACHINE_ID);
+
+        // the collection is only set once, so resumed deploys skip it
+        if get_collection_mint(&program, &candy_pubkey)? != Some(collection_mint) {
+            check_collection(&program, &collection_mint)?;
+            let sig = set_collection(
+                &program,
+                &candy_pubkey,
+                &collection_mint,
+                &retry_config,
+                priority_fee,
+            )?;
+            info!(signature = %sig, "Collection set with sig: {}", sig);
+            summary.signatures.push(sig.to_string());
+        }
+
+        progress!(
+            json,
+            "{} {}",
+            style("Collection mint:").bold(),
+            collection_mint
+        );
+    }
 
     if !hidden {
-        println!(
+        progress!(
+            json,
             "\n{} {}Writing config lines",
             style("[2/2]").bold().dim(),
             PAPER_EMOJI
         );
 
-        let config_lines = generate_config_lines(num_items, &cache.items)?;
+        let config_lines = generate_config_lines(
+            num_items,
+            &cache.items,
+            placeholders.as_ref(),
+            cache.checkpoint.as_ref(),
+            args.only.as_ref(),
+            args.allow_partial,
+            priority_fee,
+        )?;
 
         if config_lines.is_empty() {
-            println!("\nAll config lines deployed.");
+            match &args.only {
+                Some(range) => progress!(json, "\nAll config lines of range {} deployed.", range),
+                None => progress!(json, "\nAll config lines deployed."),
+            }
         } else {
             // clear the interruption handler value ahead of the upload
             args.interrupted.store(false, Ordering::SeqCst);
 
             let errors = upload_config_lines(
-                client,
+                client.clone(),
                 &sugar_config,
                 candy_pubkey,
                 &mut cache,
                 config_lines,
+                retry_config,
+                priority_fee,
                 args.interrupted,
+                &mut summary,
+                json,
             )
             .await?;
 
@@ -246,89 +575,815 @@ pub async fn process_deploy(args: DeployArgs) -> Result<()> {
             }
         }
     } else {
-        println!("\nCandy machine with hidden settings deployed.");
+        progress!(json, "\nCandy machine with hidden settings deployed.");
+    }
+
+    // the config of the deploy is recorded, so later runs can detect changes
+    cache.set_config_hash(&content_hash);
+    cache.sync_file()?;
+
+    if json {
+        let rpc_client = setup_rpc_client(&sugar_config.rpc_url, config_data.rpc_timeout_secs)?;
+        let payer = sugar_config.keypair.pubkey();
+        let balance = with_retry_async(&retry_config, "Get balance", || {
+            rpc_client.get_balance(&payer)
+        })
+        .await?;
+
+        summary.candy_machine = candy_pubkey.to_string();
+        summary.lamports_spent = initial_balance.saturating_sub(balance);
+        println!("{}", serde_json::to_string(&summary)?);
     }
 
     Ok(())
 }
 
-/// Create the candy machine data struct.
-fn create_candy_machine_data(config: &ConfigData, uuid: String) -> Result<CandyMachineData> {
-    let go_live_date = Some(go_live_date_as_timestamp(&config.go_live_date)?);
+/// Set (or update) the collection of the deployed candy machine to the collection of the
+/// config, without adding config lines. Nothing is sent when the collection is already
+/// set to the same mint.
+fn deploy_collection(
+    client: &Client,
+    sugar_config: &SugarConfig,
+    config_data: &ConfigData,
+    cache: &Cache,
+    priority_fee: u64,
+    dry_run: bool,
+    json: bool,
+) -> Result<()> {
+    let collection_mint = config_data
+        .collection
+        .ok_or(CollectionError::MissingCollection)?;
+
+    if cache.program.candy_machine.is_empty() {
+        return Err(DeployError::CandyMachineNotDeployed(cache.file_path.clone()).into());
+    }
+
+    let candy_pubkey = parse_pubkey("candy machine", &cache.program.candy_machine)?;
+    let program = client.program(CANDY_MACHINE_ID);
+    let retry_config = config_data.retry_config.unwrap_or_default();
 
-    let end_settings = config.end_settings.as_ref().map(|s| s.into_candy_format());
+    progress!(
+        json,
+        "{} {}Setting collection",
+        style("[1/1]").bold().dim(),
+        CANDY_EMOJI
+    );
+    progress!(
+        json,
+        "{} {}",
+        style("Candy machine ID:").bold(),
+        candy_pubkey
+    );
 
-    let whitelist_mint_settings = config
-        .whitelist_mint_settings
-        .as_ref()
-        .map(|s| s.into_candy_format());
+    let current = get_collection_mint(&program, &candy_pubkey)?;
+    let mut summary = DeploySummary {
+        candy_machine: candy_pubkey.to_string(),
+        ..Default::default()
+    };
 
-    let hidden_settings = config
-        .hidden_settings
-        .as_ref()
-        .map(|s| s.into_candy_format());
+    progress!(
+        json,
+        "{} {}",
+        style("Collection before:").bold(),
+        current.map_or_else(|| "none".to_string(), |mint| mint.to_string())
+    );
 
-    let gatekeeper = config
-        .gatekeeper
-        .as_ref()
-        .map(|gatekeeper| gatekeeper.into_candy_format());
+    if current == Some(collection_mint) {
+        progress!(
+            json,
+            "The collection is already set, skipping the transaction."
+        );
+    } else {
+        let candy_machine_state = get_candy_machine_state(sugar_config, &candy_pubkey)?;
 
-    let mut creators: Vec<CandyCreator> = Vec::new();
-    let mut share = 0u32;
+        if candy_machine_state.items_redeemed > 0 {
+            return Err(CollectionError::ItemsAlreadyMinted(
+                candy_pubkey.to_string(),
+                candy_machine_state.items_redeemed,
+            )
+            .into());
+        }
 
-    for creator in &config.creators {
-        let c = creator.into_candy_format()?;
-        share += c.share as u32;
+        check_collection(&program, &collection_mint)?;
 
-        creators.push(c);
+        if dry_run {
+            progress!(
+                json,
+                "Dry run: the collection would be set to {}, no transaction sent.",
+                collection_mint
+            );
+        } else {
+            let initial_balance = check_balance(&program, None, 1, priority_fee, &retry_config)?;
+
+            let pb = spinner_with_style();
+            pb.set_message("Sending set collection transaction...");
+
+            let sig = set_collection(
+                &program,
+                &candy_pubkey,
+                &collection_mint,
+                &retry_config,
+                priority_fee,
+            )?;
+
+            pb.finish_and_clear();
+            info!(signature = %sig, "Collection set with sig: {}", sig);
+            summary.signatures.push(sig.to_string());
+
+            let payer = program.payer();
+            let balance = with_retry(&retry_config, "Get balance", || {
+                program.timed_rpc().get_balance(&payer)
+            })?;
+            summary.lamports_spent = initial_balance.saturating_sub(balance);
+        }
     }
 
-    if creators.is_empty() || creators.len() > (MAX_CREATOR_LIMIT - 1) {
-        return Err(anyhow!(
-            "The number of creators must be between 1 and {}.",
-            MAX_CREATOR_LIMIT - 1,
-        ));
+    if !dry_run {
+        progress!(
+            json,
+            "{} {}",
+            style("Collection after:").bold(),
+            collection_mint
+        );
     }
 
-    if share != 100 {
-        return Err(anyhow!(
-            "Creator(s) share must add up to 100, current total {}.",
-            share,
-        ));
+    if json {
+        println!("{}", serde_json::to_string(&summary)?);
     }
 
-    let data = CandyMachineData {
-        uuid,
-        price: price_as_lamports(config.price),
-        symbol: config.symbol.clone(),
-        seller_fee_basis_points: config.seller_fee_basis_points,
-        max_supply: 0,
-        is_mutable: config.is_mutable,
-        retain_authority: config.retain_authority,
-        go_live_date,
-        end_settings,
-        creators,
-        whitelist_mint_settings,
-        hidden_settings,
-        items_available: config.number,
-        gatekeeper,
+    Ok(())
+}
+
+/// Print what the deploy would do without sending any transaction or writing the cache.
+/// The candy machine settings are validated and the config lines are split into
+/// transactions as in a deploy, so the estimate matches the cost of the deploy.
+#[allow(clippy::too_many_arguments)]
+fn dry_run_deploy(
+    client: &Client,
+    config_data: &ConfigData,
+    cache: &Cache,
+    only: Option<&ConfigLineRange>,
+    allow_partial: bool,
+    priority_fee: u64,
+    retry_config: &RetryConfig,
+    json: bool,
+) -> Result<()> {
+    let program = client.program(CANDY_MACHINE_ID);
+    let creating = cache.program.candy_machine.is_empty();
+    let num_items = config_data.number;
+
+    if creating {
+        // the uuid is derived from the address of the new candy machine, so any
+        // address gives a valid candy machine data
+        prepare_candy_machine(
+            config_data,
+            &program,
+            &program.payer(),
+            &uuid_from_pubkey(&Pubkey::new_unique()),
+            json,
+        )?;
+    }
+
+    let set_collection = match config_data.collection {
+        Some(collection_mint) => {
+            let candy_pubkey = Pubkey::from_str(&cache.program.candy_machine).ok();
+            let current = match candy_pubkey {
+                Some(candy_pubkey) => get_collection_mint(&program, &candy_pubkey)?,
+                None => None,
+            };
+
+            if current != Some(collection_mint) {
+                check_collection(&program, &collection_mint)?;
+            }
+            current != Some(collection_mint)
+        }
+        None => false,
     };
 
-    Ok(data)
+    let config_lines = if config_data.onchain_hidden_settings().is_some() {
+        Vec::new()
+    } else {
+        // a new candy machine starts from the first config line
+        let checkpoint = if creating {
+            None
+        } else {
+            cache.checkpoint.as_ref()
+        };
+        generate_config_lines(
+            num_items,
+            &cache.items,
+            config_data.placeholder_settings(),
+            checkpoint,
+            only,
+            allow_partial,
+            priority_fee,
+        )?
+    };
+    let batch_sizes: Vec<usize> = config_lines.iter().map(Vec::len).collect();
+
+    // same transaction count as the balance check of the deploy
+    let transactions = batch_sizes.len() as u64 + config_data.collection.map_or(0, |_| 1);
+    let (rent, required) = estimate_deploy_cost(
+        &program,
+        if creating { Some(num_items) } else { None },
+        transactions,
+        priority_fee,
+        retry_config,
+    )?;
+
+    let payer = program.payer();
+    let balance = with_retry(retry_config, "Get balance", || {
+        program.timed_rpc().get_balance(&payer)
+    })?;
+
+    let plan = DeployPlan {
+        candy_machine: (!creating).then(|| cache.program.candy_machine.clone()),
+        create_candy_machine: creating,
+        set_collection,
+        config_lines: batch_sizes.iter().sum(),
+        config_line_transactions: batch_sizes.len(),
+        batch_sizes,
+        rent_lamports: rent,
+        estimated_lamports: required,
+        balance_lamports: balance,
+    };
+    info!(
+        "Dry run: {} config line(s) in {} transaction(s), estimated cost {} lamports",
+        plan.config_lines, plan.config_line_transactions, plan.estimated_lamports
+    );
+
+    progress!(
+        json,
+        "{} no transactions are sent and the cache file is not changed.\n",
+        style("Dry run:").bold()
+    );
+    progress!(
+        json,
+        "{} {}",
+        style("Candy machine:").bold(),
+        match &plan.candy_machine {
+            Some(candy_machine) => candy_machine.to_string(),
+            None => "new candy machine will be created".to_string(),
+        }
+    );
+    progress!(
+        json,
+        "{} {}",
+        style("Collection:").bold(),
+        match config_data.collection {
+            Some(collection_mint) if set_collection => format!("{} will be set", collection_mint),
+            Some(collection_mint) => format!("{} already set", collection_mint),
+            None => "none".to_string(),
+        }
+    );
+    progress!(
+        json,
+        "{} {} in {} transaction(s){}",
+        style("Config lines:").bold(),
+        plan.config_lines,
+        plan.config_line_transactions,
+        match (plan.batch_sizes.iter().min(), plan.batch_sizes.iter().max()) {
+            (Some(min), Some(max)) => format!(" of {} to {} line(s)", min, max),
+            _ => String::new(),
+        }
+    );
+    progress!(
+        json,
+        "{} {} (rent {}), balance {}",
+        style("Estimated cost:").bold(),
+        format_sol(required),
+        format_sol(rent),
+        format_sol(balance)
+    );
+
+    if json {
+        println!("{}", serde_json::to_string(&plan)?);
+    }
+
+    if balance < required {
+        return Err(DeployError::InsufficientBalance(
+            format_sol(required),
+            format_sol(balance),
+            payer.to_string(),
+        )
+        .into());
+    }
+
+    Ok(())
 }
 
-/// Determine the config lines that need to be uploaded.
+/// Build the transactions of the deploy without the signature of the authority, writing
+/// each one to a file of the output directory to be signed elsewhere and broadcast
+/// with `submit`.
+///
+/// The transactions use the same instructions as a deploy; the transaction that creates
+/// the candy machine is signed by the (new) candy machine account. Without durable nonce
+/// accounts, the transactions use a recent blockhash and must be submitted before it
+/// expires.
+#[allow(clippy::too_many_arguments)]
+fn write_unsigned_deploy(
+    client: &Client,
+    config_data: &ConfigData,
+    cache: &Cache,
+    output_dir: &str,
+    authority: &Pubkey,
+    nonce_accounts: &[Pubkey],
+    config_hash: String,
+    only: Option<&ConfigLineRange>,
+    allow_partial: bool,
+    priority_fee: u64,
+    json: bool,
+) -> Result<()> {
+    progress!(
+        json,
+        "{} {}Building unsigned transactions",
+        style("[1/1]").bold().dim(),
+        PAPER_EMOJI
+    );
+
+    let program = client.program(CANDY_MACHINE_ID);
+    let rpc_client = program.timed_rpc();
+    let num_items = config_data.number;
+    let creating = cache.program.candy_machine.is_empty();
+
+    let mut unsigned = Vec::new();
+
+    let candy_pubkey = if creating {
+        let candy_keypair = Keypair::generate(&mut OsRng);
+        let candy_pubkey = candy_keypair.pubkey();
+
+        let (candy_data, treasury_wallet) = prepare_candy_machine(
+            config_data,
+            &program,
+            authority,
+            &uuid_from_pubkey(&candy_pubkey),
+            json,
+        )?;
+        let rent = timed("Get minimum balance for rent exemption", || {
+            rpc_client.get_minimum_balance_for_rent_exemption(get_candy_machine_account_size(
+                candy_data.items_available,
+            ))
+        })?;
+
+        let instructions = initialize_candy_machine_request(
+            &program,
+            config_data,
+            &candy_pubkey,
+            authority,
+            rent,
+            &candy_data,
+            treasury_wallet,
+            priority_fee,
+        )
+        .instructions()?;
+        unsigned.push(UnsignedTx {
+            description: "Initialize candy machine",
+            config_lines: Vec::new(),
+            instructions,
+            signer: Some(candy_keypair),
+        });
+
+        candy_pubkey
+    } else {
+        Pubkey::from_str(&cache.program.candy_machine).map_err(|_| {
+            CacheError::InvalidCandyMachineAddress(cache.program.candy_machine.clone())
+        })?
+    };
+
+    if config_data.onchain_hidden_settings().is_none() {
+        // a new candy machine starts from the first config line
+        let checkpoint = if creating {
+            None
+        } else {
+            cache.checkpoint.as_ref()
+        };
+
+        for chunk in generate_config_lines(
+            num_items,
+            &cache.items,
+            config_data.placeholder_settings(),
+            checkpoint,
+            only,
+            allow_partial,
+            priority_fee,
+        )? {
+            let instructions =
+                add_config_lines_request(&program, &candy_pubkey, authority, &chunk, priority_fee)
+                    .instructions()?;
+            unsigned.push(UnsignedTx {
+                description: "Add config lines",
+                config_lines: chunk.iter().map(|(index, _)| *index).collect(),
+                instructions,
+                signer: None,
+            });
+        }
+    }
+
+    if unsigned.is_empty() {
+        progress!(
+            json,
+            "\nAll config lines deployed, there are no transactions to build."
+        );
+        return Ok(());
+    }
+
+    if !nonce_accounts.is_empty() && nonce_accounts.len() < unsigned.len() {
+        return Err(
+            DeployError::NotEnoughNonceAccounts(unsigned.len(), nonce_accounts.len()).into(),
+        );
+    }
+
+    let recent_blockhash = if nonce_accounts.is_empty() {
+        Some(timed("Get latest blockhash", || {
+            rpc_client.get_latest_blockhash()
+        })?)
+    } else {
+        None
+    };
+
+    fs::create_dir_all(output_dir)?;
+
+    for (index, mut unsigned_tx) in unsigned.into_iter().enumerate() {
+        let nonce_account = nonce_accounts.get(index);
+
+        // a durable nonce transaction starts by advancing the nonce and uses the
+        // nonce value as its blockhash
+        let blockhash = match nonce_account {
+            Some(nonce_account) => {
+                let account = timed("Get nonce account", || {
+                    nonce_utils::get_account_with_commitment(
+                        &rpc_client,
+                        nonce_account,
+                        rpc_client.commitment(),
+                    )
+                })?;
+                let nonce_data = nonce_utils::data_from_account(&account)?;
+
+                if nonce_data.authority != *authority {
+                    return Err(DeployError::NonceAuthorityMismatch(
+                        nonce_account.to_string(),
+                        nonce_data.authority.to_string(),
+                        authority.to_string(),
+                    )
+                    .into());
+                }
+
+                unsigned_tx.instructions.insert(
+                    0,
+                    system_instruction::advance_nonce_account(nonce_account, authority),
+                );
+                nonce_data.blockhash
+            }
+            None => recent_blockhash.expect("Missing recent blockhash."),
+        };
+
+        let mut transaction =
+            Transaction::new_with_payer(&unsigned_tx.instructions, Some(authority));
+
+        match &unsigned_tx.signer {
+            Some(signer) => {
+                // the keypair is kept, so the transaction can be signed again (e.g., with
+                // a new blockhash) without losing the candy machine address
+                let keypair_path = Path::new(output_dir).join(CANDY_MACHINE_KEYPAIR_FILE);
+                write_keypair_file(signer, &keypair_path).map_err(|err| {
+                    DeployError::KeypairWriteFailed(
+                        keypair_path.display().to_string(),
+                        err.to_string(),
+                    )
+                })?;
+                transaction.partial_sign(&[signer], blockhash);
+            }
+            None => transaction.message.recent_blockhash = blockhash,
+        }
+
+        let kind = if unsigned_tx.config_lines.is_empty() {
+            "initialize"
+        } else {
+            "config-lines"
+        };
+        let path = Path::new(output_dir).join(format!("{:04}-{}.json", index, kind));
+        // the candy machine is only recorded in the cache once 'submit' confirms the
+        // transaction that creates it
+        let checkpoint = if creating && unsigned_tx.config_lines.is_empty() {
+            Some(DeployCheckpoint::new(num_items, config_hash.clone()))
+        } else {
+            None
+        };
+        let deploy_transaction = DeployTransaction::new(
+            unsigned_tx.description,
+            &candy_pubkey,
+            unsigned_tx.config_lines,
+            nonce_account,
+            &transaction,
+            checkpoint,
+        )?;
+        fs::write(&path, serde_json::to_string_pretty(&deploy_transaction)?)?;
+
+        info!(
+            path = %path.display(),
+            blockhash = %blockhash,
+            "Unsigned transaction written"
+        );
+    }
+
+    progress!(
+        json,
+        "{} {}",
+        style("Candy machine ID:").bold(),
+        candy_pubkey
+    );
+    if creating {
+        progress!(
+            json,
+            "Candy machine keypair written to '{}': keep it until the candy machine is created.",
+            Path::new(output_dir)
+                .join(CANDY_MACHINE_KEYPAIR_FILE)
+                .display()
+        );
+    }
+    progress!(
+        json,
+        "\nUnsigned transaction(s) written to '{}', to be signed by {}.",
+        output_dir,
+        authority
+    );
+
+    if config_data.collection.is_some() {
+        progress!(
+            json,
+            "{} {}",
+            style("WARNING:").yellow().bold(),
+            style("The transactions do not set the collection of the candy machine: run 'deploy --collection-only' with the authority keypair once they are submitted.").yellow()
+        );
+    }
+
+    if nonce_accounts.is_empty() {
+        progress!(
+            json,
+            "{} {}",
+            style("WARNING:").yellow().bold(),
+            style("The transactions use a recent blockhash, which expires after about 150 blocks (1-2 minutes): sign and submit them before it expires, or use '--nonce-account' to build transactions that do not expire.").yellow()
+        );
+    }
+
+    progress!(
+        json,
+        "\nReplace the 'transaction' of each file by the signed transaction and run 'sugar submit {}'.",
+        output_dir
+    );
+
+    Ok(())
+}
+
+/// Estimate the lamports required to deploy: the rent of the candy machine account
+/// (zero when it already exists) plus the fees of the initialize transaction (signed
+/// by the payer and the candy machine) and of the single-signer transactions (config
+/// lines and collection). The priority fee is charged for the compute unit limit set
+/// on every transaction.
+pub fn estimate_deploy_lamports(
+    rent: u64,
+    create_candy_machine: bool,
+    transactions: u64,
+    priority_fee: u64,
+) -> u64 {
+    // micro-lamports per compute unit, rounded up to whole lamports
+    let priority_lamports =
+        ((priority_fee as u128 * PRIORITY_FEE_COMPUTE_UNITS as u128 + 999_999) / 1_000_000) as u64;
+
+    let (create_transactions, create_signatures) =
+        if create_candy_machine { (1, 2) } else { (0, 0) };
+
+    rent.saturating_add((create_signatures + transactions).saturating_mul(LAMPORTS_PER_SIGNATURE))
+        .saturating_add((create_transactions + transactions).saturating_mul(priority_lamports))
+}
+
+/// Return the rent of the candy machine account and the estimated lamports required
+/// to deploy. The number of items is only specified when the candy machine needs to
+/// be created.
+fn estimate_deploy_cost(
+    program: &Program,
+    create_items: Option<u64>,
+    transactions: u64,
+    priority_fee: u64,
+    retry_config: &RetryConfig,
+) -> Result<(u64, u64)> {
+    let rent = match create_items {
+        Some(items) => with_retry(
+            retry_config,
+            "Get minimum balance for rent exemption",
+            || {
+                program
+                    .timed_rpc()
+                    .get_minimum_balance_for_rent_exemption(get_candy_machine_account_size(items))
+            },
+        )?,
+        None => 0,
+    };
+    let required =
+        estimate_deploy_lamports(rent, create_items.is_some(), transactions, priority_fee);
+
+    Ok((rent, required))
+}
+
+/// Check that the balance of the payer covers the estimated cost of the deploy,
+/// returning the balance. The number of items is only specified when the candy
+/// machine needs to be created.
+fn check_balance(
+    program: &Program,
+    create_items: Option<u64>,
+    transactions: u64,
+    priority_fee: u64,
+    retry_config: &RetryConfig,
+) -> Result<u64> {
+    let (rent, required) = estimate_deploy_cost(
+        program,
+        create_items,
+        transactions,
+        priority_fee,
+        retry_config,
+    )?;
+
+    let payer = program.payer();
+    let balance = with_retry(retry_config, "Get balance", || {
+        program.timed_rpc().get_balance(&payer)
+    })?;
+
+    info!(
+        "Estimated deploy cost: {} lamports (rent {}, {} transaction(s)), balance: {} lamports",
+        required,
+        rent,
+        transactions + create_items.map_or(0, |_| 1),
+        balance
+    );
+
+    if balance < required {
+        return Err(DeployError::InsufficientBalance(
+            format_sol(required),
+            format_sol(balance),
+            payer.to_string(),
+        )
+        .into());
+    }
+
+    Ok(balance)
+}
+
+/// Ask the user to confirm a deploy to mainnet-beta, unless '--yes' or '--confirm-mainnet'
+/// was used.
+fn confirm_mainnet(rpc_url: &str, confirmed: bool) -> Result<()> {
+    if confirmed {
+        return Ok(());
+    }
+
+    if !user_attended() {
+        return Err(DeployError::MainnetNotConfirmed(rpc_url.to_string()).into());
+    }
+
+    if Confirm::new()
+        .with_prompt("You are deploying to mainnet-beta. Do you want to continue?")
+        .default(false)
+        .interact()?
+    {
+        Ok(())
+    } else {
+        Err(DeployError::Aborted.into())
+    }
+}
+
+/// Ask the user to confirm that the update authority should not be retained. When not
+/// running interactively, the confirmation must be given with `--yes`.
+fn confirm_retain_authority(yes: bool, json: bool) -> Result<()> {
+    progress!(
+        json,
+        "{} {}\n",
+        style("WARNING:").yellow().bold(),
+        style(RETAIN_AUTHORITY_WARNING).yellow()
+    );
+
+    if yes {
+        return Ok(());
+    }
+
+    if !user_attended() {
+        return Err(DeployError::RetainAuthorityNotConfirmed.into());
+    }
+
+    if Confirm::new()
+        .with_prompt("Do you want to continue without retaining the update authority?")
+        .default(false)
+        .interact()?
+    {
+        Ok(())
+    } else {
+        Err(DeployError::Aborted.into())
+    }
+}
+
+/// Check that a range of config lines can be deployed: it must be within the items of
+/// the config and start right after the config lines already deployed, since the
+/// config lines of the candy machine cannot have gaps.
+fn check_config_line_range(
+    range: &ConfigLineRange,
+    config_data: &ConfigData,
+    cache: &Cache,
+) -> Result<()> {
+    if config_data.onchain_hidden_settings().is_some() {
+        return Err(DeployError::RangeWithHiddenSettings.into());
+    }
+
+    if range.end >= config_data.number {
+        return Err(DeployError::RangeOutOfBounds(range.to_string(), config_data.number).into());
+    }
+
+    // a new candy machine has no config lines
+    let next_index = if cache.program.candy_machine.is_empty() {
+        0
+    } else {
+        cache
+            .checkpoint
+            .as_ref()
+            .and_then(|checkpoint| checkpoint.last_config_line)
+            .map_or(0, |index| index as u64 + 1)
+    };
+
+    if range.start > next_index {
+        return Err(DeployError::RangeGap(range.to_string(), next_index).into());
+    }
+
+    Ok(())
+}
+
+/// Return the indices as a list of ranges (e.g., "0-9, 12, 15-20").
+fn format_index_ranges(indices: &[u32]) -> String {
+    let mut ranges: Vec<(u32, u32)> = Vec::new();
+
+    for index in indices {
+        match ranges.last_mut() {
+            Some((_, end)) if *end + 1 == *index => *end = *index,
+            _ => ranges.push((*index, *index)),
+        }
+    }
+
+    ranges
+        .iter()
+        .map(|(start, end)| {
+            if start == end {
+                start.to_string()
+            } else {
+                format!("{}-{}", start, end)
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+/// Determine the config lines that need to be uploaded, split into transactions
+/// by the byte length of the name and uri of each line. Lines already on-chain or up
+/// to the last config line of the checkpoint are skipped, so a new deploy resumes
+/// from the lines that are missing. With a range, only the lines of the range are
+/// returned. With placeholder settings, each line is the placeholder of the item
+/// instead of its name and uri.
 fn generate_config_lines(
     num_items: u64,
     cache_items: &CacheItems,
+    placeholders: Option<&HiddenSettings>,
+    checkpoint: Option<&DeployCheckpoint>,
+    only: Option<&ConfigLineRange>,
+    allow_partial: bool,
+    priority_fee: u64,
 ) -> Result<Vec<Vec<(u32, ConfigLine)>>> {
     let mut config_lines: Vec<Vec<(u32, ConfigLine)>> = Vec::new();
     let mut current: Vec<(u32, ConfigLine)> = Vec::new();
     let mut tx_size = 0;
+    // the compute budget instructions of the priority fee take space from the lines
+    let max_bytes = if priority_fee > 0 {
+        MAX_TRANSACTION_BYTES - PRIORITY_FEE_TX_OVERHEAD
+    } else {
+        MAX_TRANSACTION_BYTES
+    };
+    let first_index = checkpoint
+        .and_then(|checkpoint| checkpoint.last_config_line)
+        .map_or(0, |index| index as u64 + 1);
+    let (first_index, end_index) = match only {
+        Some(range) => (
+            cmp::max(first_index, range.start),
+            cmp::min(num_items, range.end + 1),
+        ),
+        None => (first_index, num_items),
+    };
 
-    for i in 0..num_items {
+    for i in first_index..end_index {
         let item = match cache_items.0.get(&i.to_string()) {
             Some(item) => item,
+            None if allow_partial => {
+                // missing items are skipped, so we cannot continue the current chunk
+                if !current.is_empty() {
+                    config_lines.push(current);
+                    current = Vec::new();
+                    tx_size = 0;
+                }
+                continue;
+            }
             None => {
                 return Err(
                     DeployError::AddConfigLineFailed(format!("Missing cache item {}", i)).into(),
@@ -346,13 +1401,16 @@ fn generate_config_lines(
                 tx_size = 0;
             }
         } else {
-            let config_line = item
-                .into_config_line()
-                .expect("Could not convert item to config line");
+            let config_line = match placeholders {
+                Some(placeholders) => placeholders.placeholder_config_line(i),
+                None => item
+                    .into_config_line()
+                    .expect("Could not convert item to config line"),
+            };
 
             let size = (2 * STRING_LEN_SIZE) + config_line.name.len() + config_line.uri.len();
 
-            if (tx_size + size) > MAX_TRANSACTION_BYTES || current.len() == MAX_TRANSACTION_LINES {
+            if (tx_size + size) > max_bytes || current.len() == MAX_TRANSACTION_LINES {
                 // we need a separate tx to not break the size limit
                 config_lines.push(current);
                 current = Vec::new();
@@ -371,6 +1429,43 @@ fn generate_config_lines(
     Ok(config_lines)
 }
 
+/// Validate the candy machine settings against the cluster, returning the candy machine
+/// data and the treasury wallet used to create it.
+fn prepare_candy_machine(
+    config_data: &ConfigData,
+    program: &Program,
+    authority: &Pubkey,
+    uuid: &str,
+    json: bool,
+) -> Result<(CandyMachineData, Pubkey)> {
+    if let Ok(cluster) = get_cluster(program.timed_rpc()) {
+        for warning in config_data.cluster_warnings(&cluster) {
+            warn!("{}", warning);
+            progress!(json, "{} {}", style("WARNING:").yellow().bold(), warning);
+        }
+    }
+
+    // the price is specified in the spl-token units when a spl-token is used
+    let token_decimals = match config_data.treasury.spl_token() {
+        Some(spl_token) => Some(get_spl_token_decimals(program, &spl_token)?),
+        None => None,
+    };
+    let candy_data = config_data.to_candy_machine_data(uuid, token_decimals)?;
+
+    let treasury_wallet = match config_data.treasury.resolve(authority) {
+        Treasury::Sol(wallet) => wallet,
+        Treasury::Spl { token, account } => {
+            // validates the mint address of the token accepted as payment
+            check_spl_token(program, &token.to_string())?;
+            // validates the spl token wallet to receive proceedings from SPL token payments
+            check_treasury_token_account(&program.timed_rpc(), &account, &token)?;
+            account
+        }
+    };
+
+    Ok((candy_data, treasury_wallet))
+}
+
 /// Send the `initialize_candy_machine` instruction to the candy machine program.
 fn initialize_candy_machine(
     config_data: &ConfigData,
@@ -378,15 +1473,13 @@ fn initialize_candy_machine(
     candy_machine_data: CandyMachineData,
     treasury_wallet: Pubkey,
     program: Program,
+    retry_config: &RetryConfig,
+    priority_fee: u64,
 ) -> Result<Signature> {
     let payer = program.payer();
     let items_available = candy_machine_data.items_available;
 
-    let candy_account_size = CONFIG_ARRAY_START
-        + 4
-        + items_available as usize * CONFIG_LINE_SIZE
-        + 8
-        + 2 * (items_available as usize / 8 + 1);
+    let candy_account_size = get_candy_machine_account_size(items_available);
 
     info!(
         "Initializing candy machine with account size of: {} and address of: {}",
@@ -394,58 +1487,106 @@ fn initialize_candy_machine(
         candy_account.pubkey().to_string()
     );
 
-    let mut tx = program
-        .request()
-        .instruction(system_instruction::create_account(
-            &payer,
-            &candy_account.pubkey(),
+    let rent = with_retry(
+        retry_config,
+        "Get minimum balance for rent exemption",
+        || {
             program
-                .rpc()
-                .get_minimum_balance_for_rent_exemption(candy_account_size)?,
+                .timed_rpc()
+                .get_minimum_balance_for_rent_exemption(candy_account_size)
+        },
+    )?;
+
+    // the transaction is rebuilt on every attempt, so each one uses a recent blockhash
+    let sig = with_priority_fee_retry(
+        retry_config,
+        priority_fee,
+        "Initialize candy machine",
+        |fee| {
+            initialize_candy_machine_request(
+                &program,
+                config_data,
+                &candy_account.pubkey(),
+                &payer,
+                rent,
+                &candy_machine_data,
+                treasury_wallet,
+                fee,
+            )
+            .send_timed(&[candy_account])
+        },
+    )?;
+
+    Ok(sig)
+}
+
+/// Build the request that creates the candy machine account and initializes it, which
+/// is signed by the candy machine account and the authority.
+#[allow(clippy::too_many_arguments)]
+fn initialize_candy_machine_request<'a>(
+    program: &'a Program,
+    config_data: &ConfigData,
+    candy_pubkey: &Pubkey,
+    authority: &Pubkey,
+    rent: u64,
+    candy_machine_data: &CandyMachineData,
+    treasury_wallet: Pubkey,
+    priority_fee: u64,
+) -> RequestBuilder<'a> {
+    let candy_account_size = get_candy_machine_account_size(candy_machine_data.items_available);
+
+    let mut request = with_priority_fee(program.request(), priority_fee)
+        .instruction(system_instruction::create_account(
+            authority,
+            candy_pubkey,
+            rent,
             candy_account_size as u64,
             &program.id(),
         ))
-        .signer(candy_account)
         .accounts(nft_accounts::InitializeCandyMachine {
-            candy_machine: candy_account.pubkey(),
+            candy_machine: *candy_pubkey,
             wallet: treasury_wallet,
-            authority: payer,
-            payer,
+            authority: *authority,
+            payer: *authority,
             system_program: system_program::id(),
             rent: sysvar::rent::ID,
         })
         .args(nft_instruction::InitializeCandyMachine {
-            data: candy_machine_data,
+            data: candy_machine_data.clone(),
         });
 
-    if let Some(token) = config_data.spl_token {
-        tx = tx.accounts(AccountMeta {
+    if let Some(token) = config_data.treasury.spl_token() {
+        request = request.accounts(AccountMeta {
             pubkey: token,
             is_signer: false,
             is_writable: false,
         });
     }
 
-    let sig = tx.send()?;
-
-    Ok(sig)
+    request
 }
 
 /// Send the config lines to the candy machine program.
+#[allow(clippy::too_many_arguments)]
 async fn upload_config_lines(
     client: Arc<Client>,
     sugar_config: &SugarConfig,
     candy_pubkey: Pubkey,
     cache: &mut Cache,
     config_lines: Vec<Vec<(u32, ConfigLine)>>,
+    retry_config: RetryConfig,
+    priority_fee: u64,
     interrupted: Arc<AtomicBool>,
+    summary: &mut DeploySummary,
+    json: bool,
 ) -> Result<Vec<DeployError>> {
-    println!(
+    progress!(
+        json,
         "Sending config line(s) in {} transaction(s): (Ctrl+C to abort)",
         config_lines.len()
     );
 
-    let pb = progress_bar_with_style(config_lines.len() as u64);
+    let pb = task_progress("deploy", config_lines.len() as u64);
 
     debug!("Num of config line chunks: {:?}", config_lines.len());
     info!("Uploading config lines in chunks...");
@@ -460,6 +1601,8 @@ async fn upload_config_lines(
             candy_pubkey,
             payer,
             chunk,
+            retry_config,
+            priority_fee,
         });
     }
 
@@ -473,8 +1616,11 @@ async fn upload_config_lines(
     }
 
     let mut errors = Vec::new();
+    let mut sent = Vec::new();
 
-    while !interrupted.load(Ordering::SeqCst) && !handles.is_empty() {
+    // on interruption no new transactions are sent, but the in-flight ones are awaited
+    // so their config lines are saved in the cache
+    while !handles.is_empty() {
         match select_all(handles).await {
             (Ok(res), _index, remaining) => {
                 // independently if the upload was successful or not
@@ -482,12 +1628,19 @@ async fn upload_config_lines(
                 handles = remaining;
 
                 if res.is_ok() {
-                    let indices = res?;
+                    let (indices, signature) = res?;
+                    summary.config_lines_added += indices.len();
+                    summary.signatures.push(signature.to_string());
 
                     for index in indices {
                         let item = cache.items.0.get_mut(&index.to_string()).unwrap();
                         item.on_chain = true;
+                        sent.push(index);
                     }
+                    cache.update_checkpoint();
+                    // saves the progress so a new deploy resumes from the lines
+                    // that are not on-chain
+                    cache.sync_file()?;
                     // updates the progress bar
                     pb.inc(1);
                 } else {
@@ -509,12 +1662,9 @@ async fn upload_config_lines(
             }
         }
 
-        if !transactions.is_empty() {
+        if !interrupted.load(Ordering::SeqCst) && !transactions.is_empty() {
             // if we are half way through, let spawn more transactions
             if (PARALLEL_LIMIT - handles.len()) > (PARALLEL_LIMIT / 2) {
-                // saves the progress to the cache file
-                cache.sync_file()?;
-
                 for tx in transactions.drain(0..cmp::min(transactions.len(), PARALLEL_LIMIT / 2)) {
                     let tx_client = client.clone();
                     handles.push(tokio::spawn(async move {
@@ -530,13 +1680,23 @@ async fn upload_config_lines(
     } else if !transactions.is_empty() {
         pb.abandon_with_message(format!("{}", style("Upload aborted ").red().bold()));
         return Err(DeployError::AddConfigLineFailed(
-            "Not all config lines were deployed.".to_string(),
+            "Not all config lines were deployed: re-run the deploy to resume.".to_string(),
         )
         .into());
     } else {
         pb.finish_with_message(format!("{}", style("Deploy successful ").green().bold()));
     }
 
+    if !sent.is_empty() {
+        sent.sort_unstable();
+        progress!(
+            json,
+            "{} {}",
+            style("Config lines sent:").bold(),
+            format_index_ranges(&sent)
+        );
+    }
+
     // makes sure the cache file is updated
     cache.sync_file()?;
 
@@ -544,33 +1704,435 @@ async fn upload_config_lines(
 }
 
 /// Send the `add_config_lines` instruction to the candy machine program.
-async fn add_config_lines(client: Arc<Client>, tx_info: TxInfo) -> Result<Vec<u32>> {
-    let program = client.program(CANDY_MACHINE_ID);
-
+async fn add_config_lines(client: Arc<Client>, tx_info: TxInfo) -> Result<(Vec<u32>, Signature)> {
     // this will be used to update the cache
-    let mut indices: Vec<u32> = Vec::new();
-    // configLine does not implement clone, so we have to do this
-    let mut config_lines: Vec<ConfigLine> = Vec::new();
+    let indices: Vec<u32> = tx_info.chunk.iter().map(|(index, _)| *index).collect();
     // start index
     let start_index = tx_info.chunk[0].0;
 
-    for (index, line) in tx_info.chunk {
-        indices.push(index);
-        config_lines.push(line);
-    }
+    let start = Instant::now();
+
+    let sig = with_priority_fee_retry_async(
+        &tx_info.retry_config,
+        tx_info.priority_fee,
+        "Add config lines",
+        |fee| {
+            // the program is not kept across the delay between retries, since it
+            // cannot be sent between the threads of the runtime
+            let program = client.program(CANDY_MACHINE_ID);
+
+            add_config_lines_request(
+                &program,
+                &tx_info.candy_pubkey,
+                &program.payer(),
+                &tx_info.chunk,
+                fee,
+            )
+            .send_timed(&[&tx_info.payer])
+        },
+    )
+    .await?;
+
+    info!(
+        start_index,
+        count = indices.len(),
+        signature = %sig,
+        elapsed_ms = start.elapsed().as_millis() as u64,
+        "Config lines added"
+    );
 
-    let _sig = program
-        .request()
+    Ok((indices, sig))
+}
+
+/// Build the request that adds a chunk of config lines to the candy machine, which is
+/// signed by the authority.
+fn add_config_lines_request<'a>(
+    program: &'a Program,
+    candy_pubkey: &Pubkey,
+    authority: &Pubkey,
+    chunk: &[(u32, ConfigLine)],
+    priority_fee: u64,
+) -> RequestBuilder<'a> {
+    // configLine does not implement clone, so we have to do this
+    let config_lines: Vec<ConfigLine> = chunk
+        .iter()
+        .map(|(_, line)| ConfigLine {
+            name: line.name.clone(),
+            uri: line.uri.clone(),
+        })
+        .collect();
+
+    with_priority_fee(program.request(), priority_fee)
         .accounts(nft_accounts::AddConfigLines {
-            candy_machine: tx_info.candy_pubkey,
-            authority: program.payer(),
+            candy_machine: *candy_pubkey,
+            authority: *authority,
         })
         .args(nft_instruction::AddConfigLines {
-            index: start_index,
+            index: chunk[0].0,
             config_lines,
         })
-        .signer(&tx_info.payer)
-        .send()?;
+}
+
+#[cfg(test)]
+mod tests {
+    use anchor_lang::{InstructionData, ToAccountMetas};
+
+    use super::*;
+
+    fn cache_items(count: u64, name_len: usize, uri_len: usize) -> CacheItems {
+        let mut items = CacheItems::new();
+
+        for index in 0..count {
+            items.0.insert(
+                index.to_string(),
+                CacheItem {
+                    name: "n".repeat(name_len),
+                    media_hash: String::new(),
+                    media_link: String::new(),
+                    metadata_hash: String::new(),
+                    metadata_link: "u".repeat(uri_len),
+                    on_chain: false,
+                    name_override: None,
+                    symbol_override: None,
+                    metadata_encoding: None,
+                },
+            );
+        }
+
+        items
+    }
+
+    fn indices(config_lines: &[Vec<(u32, ConfigLine)>]) -> Vec<Vec<u32>> {
+        config_lines
+            .iter()
+            .map(|chunk| chunk.iter().map(|(index, _)| *index).collect())
+            .collect()
+    }
+
+    /// Return the size of the signed `add_config_lines` transaction of a chunk.
+    fn transaction_size(chunk: &[(u32, ConfigLine)], priority_fee: u64) -> usize {
+        let authority = Pubkey::new_unique();
+        let mut instructions = priority_fee_instructions(priority_fee);
+        instructions.push(Instruction {
+            program_id: CANDY_MACHINE_ID,
+            accounts: nft_accounts::AddConfigLines {
+                candy_machine: Pubkey::new_unique(),
+                authority,
+            }
+            .to_account_metas(None),
+            data: nft_instruction::AddConfigLines {
+                index: chunk[0].0,
+                config_lines: chunk
+                    .iter()
+                    .map(|(_, line)| ConfigLine {
+                        name: line.name.clone(),
+                        uri: line.uri.clone(),
+                    })
+                    .collect(),
+            }
+            .data(),
+        });
+
+        let mut transaction = Transaction::new_with_payer(&instructions, Some(&authority));
+        transaction.signatures = vec![Signature::new_unique()];
+        bincode::serialize(&transaction).unwrap().len()
+    }
+
+    #[test]
+    fn chunks_fit_in_a_transaction() {
+        for priority_fee in [0, 1000] {
+            let max_bytes = if priority_fee > 0 {
+                MAX_TRANSACTION_BYTES - PRIORITY_FEE_TX_OVERHEAD
+            } else {
+                MAX_TRANSACTION_BYTES
+            };
+
+            for (name_len, uri_len) in [(1, 10), (10, 50), (MAX_NAME_LENGTH, MAX_URI_LENGTH)] {
+                let items = cache_items(100, name_len, uri_len);
+                let config_lines =
+                    generate_config_lines(100, &items, None, None, None, false, priority_fee)
+                        .unwrap();
+
+                let line_size = (2 * STRING_LEN_SIZE) + name_len + uri_len;
+                let expected = cmp::min(max_bytes / line_size, MAX_TRANSACTION_LINES);
+
+                // all chunks but the last one are full
+                let (last, full) = config_lines.split_last().unwrap();
+                assert!(full.iter().all(|chunk| chunk.len() == expected));
+                assert!(!last.is_empty() && last.len() <= expected);
+                assert!(config_lines
+                    .iter()
+                    .all(|chunk| transaction_size(chunk, priority_fee) <= PACKET_DATA_SIZE));
+
+                // every index is in the chunks once and in order
+                let all: Vec<u32> = indices(&config_lines).concat();
+                assert_eq!(all, (0..100).collect::<Vec<u32>>());
+            }
+        }
+    }
+
+    #[test]
+    fn longer_lines_need_more_transactions() {
+        let short =
+            generate_config_lines(100, &cache_items(100, 10, 40), None, None, None, false, 0)
+                .unwrap()
+                .len();
+        let long = generate_config_lines(
+            100,
+            &cache_items(100, MAX_NAME_LENGTH, MAX_URI_LENGTH),
+            None,
+            None,
+            None,
+            false,
+            0,
+        )
+        .unwrap()
+        .len();
+
+        assert!(long > short);
+    }
 
-    Ok(indices)
+    #[test]
+    fn chunks_split_at_on_chain_items() {
+        let mut items = cache_items(6, 10, 40);
+        items.0.get_mut("2").unwrap().on_chain = true;
+        items.0.get_mut("3").unwrap().on_chain = true;
+
+        let config_lines = generate_config_lines(6, &items, None, None, None, false, 0).unwrap();
+        assert_eq!(indices(&config_lines), vec![vec![0, 1], vec![4, 5]]);
+    }
+
+    #[test]
+    fn chunks_resume_after_checkpoint() {
+        let items = cache_items(6, 10, 40);
+        let mut checkpoint = DeployCheckpoint::new(6, "hash".to_string());
+        checkpoint.last_config_line = Some(3);
+
+        let config_lines =
+            generate_config_lines(6, &items, None, Some(&checkpoint), None, false, 0).unwrap();
+        assert_eq!(indices(&config_lines), vec![vec![4, 5]]);
+    }
+
+    #[test]
+    fn resume_from_middle_of_deploy() {
+        let mut cache = Cache::new();
+        cache.items = cache_items(40, 10, 40);
+        cache.checkpoint = Some(DeployCheckpoint::new(40, "hash".to_string()));
+
+        let chunks =
+            indices(&generate_config_lines(40, &cache.items, None, None, None, false, 0).unwrap());
+        assert!(chunks.len() >= 3);
+
+        // the deploy is interrupted after the first and third transactions completed
+        for index in chunks[0].iter().chain(chunks[2].iter()) {
+            cache.items.0.get_mut(&index.to_string()).unwrap().on_chain = true;
+        }
+        cache.update_checkpoint();
+
+        let checkpoint = cache.checkpoint.as_ref().unwrap();
+        assert_eq!(
+            checkpoint.last_config_line,
+            chunks[0].last().copied(),
+            "the checkpoint stops before the first missing line"
+        );
+        assert!(checkpoint.matches(40, "hash"));
+
+        // the resumed deploy only sends the lines that are not on-chain
+        let resumed = generate_config_lines(
+            40,
+            &cache.items,
+            None,
+            cache.checkpoint.as_ref(),
+            None,
+            false,
+            0,
+        )
+        .unwrap();
+        let expected: Vec<u32> = chunks[1]
+            .iter()
+            .chain(chunks[3..].concat().iter())
+            .copied()
+            .collect();
+        assert_eq!(indices(&resumed).concat(), expected);
+
+        // a restart sends every line again
+        cache.reset_deploy();
+        assert!(cache.checkpoint.is_none());
+        let restarted =
+            generate_config_lines(40, &cache.items, None, None, None, false, 0).unwrap();
+        assert_eq!(indices(&restarted), chunks);
+    }
+
+    #[test]
+    fn chunks_of_a_range() {
+        let items = cache_items(10, 10, 40);
+        let range = ConfigLineRange { start: 3, end: 5 };
+
+        let config_lines =
+            generate_config_lines(10, &items, None, None, Some(&range), false, 0).unwrap();
+        assert_eq!(indices(&config_lines), vec![vec![3, 4, 5]]);
+    }
+
+    #[test]
+    fn mid_range_deploy() {
+        let config_data = ConfigData {
+            number: 3000,
+            ..ConfigData::default()
+        };
+        let mut cache = Cache::new();
+        cache.items = cache_items(3000, 10, 40);
+        cache.program.candy_machine = Pubkey::new_unique().to_string();
+
+        // the first 1000 config lines were added by a previous deploy
+        for index in 0..1000 {
+            cache.items.0.get_mut(&index.to_string()).unwrap().on_chain = true;
+        }
+        let mut checkpoint = DeployCheckpoint::new(3000, "hash".to_string());
+        checkpoint.last_config_line = Some(999);
+        cache.checkpoint = Some(checkpoint);
+
+        let range = ConfigLineRange {
+            start: 1000,
+            end: 1999,
+        };
+        assert!(check_config_line_range(&range, &config_data, &cache).is_ok());
+
+        let config_lines = generate_config_lines(
+            3000,
+            &cache.items,
+            None,
+            cache.checkpoint.as_ref(),
+            Some(&range),
+            false,
+            0,
+        )
+        .unwrap();
+        let sent = indices(&config_lines).concat();
+        assert_eq!(sent, (1000..2000).collect::<Vec<u32>>());
+
+        // a range after the next index leaves a gap in the config lines
+        let range = ConfigLineRange {
+            start: 1500,
+            end: 1999,
+        };
+        let error = check_config_line_range(&range, &config_data, &cache).unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<DeployError>(),
+            Some(DeployError::RangeGap(_, 1000))
+        ));
+
+        let range = ConfigLineRange {
+            start: 1000,
+            end: 3000,
+        };
+        let error = check_config_line_range(&range, &config_data, &cache).unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<DeployError>(),
+            Some(DeployError::RangeOutOfBounds(_, 3000))
+        ));
+    }
+
+    #[test]
+    fn chunks_with_missing_items() {
+        let mut items = cache_items(5, 10, 40);
+        items.0.shift_remove("2");
+
+        assert!(generate_config_lines(5, &items, None, None, None, false, 0).is_err());
+
+        let config_lines = generate_config_lines(5, &items, None, None, None, true, 0).unwrap();
+        assert_eq!(indices(&config_lines), vec![vec![0, 1], vec![3, 4]]);
+    }
+
+    #[test]
+    fn config_lines_with_name_overrides() {
+        let mut items = cache_items(4, 10, 40);
+        items.0.get_mut("1").unwrap().name_override = Some("Override #2".to_string());
+        items.0.get_mut("3").unwrap().name_override = Some("Override #4".to_string());
+
+        let config_lines = generate_config_lines(4, &items, None, None, None, false, 0).unwrap();
+        let names: Vec<&str> = config_lines
+            .iter()
+            .flatten()
+            .map(|(_, line)| line.name.as_str())
+            .collect();
+        let default_name = "n".repeat(10);
+
+        assert_eq!(
+            names,
+            vec![
+                default_name.as_str(),
+                "Override #2",
+                default_name.as_str(),
+                "Override #4"
+            ]
+        );
+    }
+
+    #[test]
+    fn deploy_cost_estimate() {
+        // rent plus the payer and candy machine signatures of the initialize transaction
+        assert_eq!(estimate_deploy_lamports(1_000_000, true, 0, 0), 1_010_000);
+        assert_eq!(estimate_deploy_lamports(1_000_000, true, 10, 0), 1_060_000);
+        // no rent or initialize transaction when the candy machine exists
+        assert_eq!(estimate_deploy_lamports(0, false, 10, 0), 50_000);
+        assert_eq!(estimate_deploy_lamports(0, false, 0, 0), 0);
+    }
+
+    #[test]
+    fn deploy_cost_estimate_with_priority_fee() {
+        // 1 micro-lamport for 400k compute units rounds up to 1 lamport per transaction
+        assert_eq!(estimate_deploy_lamports(1_000_000, true, 10, 1), 1_060_011);
+        // 1 lamport per compute unit
+        assert_eq!(
+            estimate_deploy_lamports(0, false, 2, 1_000_000),
+            2 * LAMPORTS_PER_SIGNATURE + 2 * PRIORITY_FEE_COMPUTE_UNITS as u64
+        );
+        assert_eq!(
+            estimate_deploy_lamports(u64::MAX, true, 1, u64::MAX),
+            u64::MAX
+        );
+    }
+
+    #[test]
+    fn placeholder_config_lines() {
+        let placeholders = HiddenSettings::new(
+            "Mystery #{index}".to_string(),
+            "https://example.com/{index}.json".to_string(),
+            "0".repeat(32),
+        );
+        let config_lines = generate_config_lines(
+            3,
+            &cache_items(3, 10, 40),
+            Some(&placeholders),
+            None,
+            None,
+            false,
+            0,
+        )
+        .unwrap();
+        let lines: Vec<(&str, &str)> = config_lines
+            .iter()
+            .flatten()
+            .map(|(_, line)| (line.name.as_str(), line.uri.as_str()))
+            .collect();
+
+        assert_eq!(
+            lines,
+            vec![
+                ("Mystery #1", "https://example.com/0.json"),
+                ("Mystery #2", "https://example.com/1.json"),
+                ("Mystery #3", "https://example.com/2.json"),
+            ]
+        );
+    }
+
+    #[test]
+    fn index_ranges() {
+        assert_eq!(format_index_ranges(&[]), "");
+        assert_eq!(format_index_ranges(&[3]), "3");
+        assert_eq!(
+            format_index_ranges(&[0, 1, 2, 5, 7, 8]),
+            "0-2, 5, 7-8".to_string()
+        );
+    }
 }
@@ -1,6 +1,65 @@
+use solana_client::client_error::ClientError;
 use std::path::PathBuf;
 use thiserror::Error;
 
+use crate::config::errors::ConfigError;
+use crate::deploy::errors::DeployError;
+use crate::upload::errors::UploadError;
+
+/// Error of a sugar command, grouping the errors of each module so callers can match
+/// the kind of failure (e.g., `SugarError::Config(ConfigError::InvalidWhitelistMintMode(_))`)
+/// before converting it into an `anyhow::Error`.
+#[derive(Debug, Error)]
+pub enum SugarError {
+    #[error(transparent)]
+    Config(#[from] ConfigError),
+
+    #[error(transparent)]
+    Upload(#[from] UploadError),
+
+    #[error(transparent)]
+    Deploy(#[from] DeployError),
+
+    #[error(transparent)]
+    Rpc(#[from] ClientError),
+}
+
+impl SugarError {
+    /// Return the kind of failure, as recorded in the log file.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            SugarError::Config(_) => "config",
+            SugarError::Upload(_) => "upload",
+            SugarError::Deploy(_) => "deploy",
+            SugarError::Rpc(_) => "rpc",
+        }
+    }
+
+    /// Return the sugar error of an `anyhow::Error`, if it was created from one of the
+    /// module errors.
+    pub fn from_anyhow(error: anyhow::Error) -> Result<SugarError, anyhow::Error> {
+        let error = match error.downcast::<ConfigError>() {
+            Ok(error) => return Ok(error.into()),
+            Err(error) => error,
+        };
+        let error = match error.downcast::<UploadError>() {
+            Ok(error) => return Ok(error.into()),
+            Err(error) => error,
+        };
+        let error = match error.downcast::<DeployError>() {
+            Ok(error) => return Ok(error.into()),
+            Err(error) => error,
+        };
+
+        let error = match error.downcast::<ClientError>() {
+            Ok(error) => return Ok(error.into()),
+            Err(error) => error,
+        };
+
+        error.downcast::<SugarError>()
+    }
+}
+
 #[derive(Debug, Error)]
 pub enum SetupError {
     #[error("Error setting up sugar: {0}")]
@@ -20,6 +79,58 @@ pub enum CacheError {
 
     #[error("Failed to parse cache file with error: {0}")]
     CacheFileWrongFormat(String),
+
+    #[error("Cache file version {0} is not supported (latest supported version is {1}), upgrade Sugar to use this cache file.")]
+    UnsupportedCacheVersion(u64, u32),
+}
+
+#[derive(Debug, Error)]
+pub enum KeypairError {
+    #[error("Keypair file '{0}' not found: use the --keypair option or set the keypair of the solana config.")]
+    KeypairFileNotFound(String),
+
+    #[error("Failed to read keypair file '{0}': {1}")]
+    FailedToReadKeypair(String, String),
+
+    #[error("Invalid keypair file '{0}': {1}. The file must contain the JSON array of the secret key bytes or the base58 encoded secret key.")]
+    InvalidKeypair(String, String),
+
+    #[error(
+        "The --keypair and --keypair-seed options cannot be used together: use only one of them."
+    )]
+    ConflictingKeypair,
+
+    #[error("Invalid seed phrase: {0}")]
+    InvalidSeedPhrase(String),
+
+    #[error("Invalid derivation path '{0}': {1}")]
+    InvalidDerivationPath(String, String),
+
+    #[error("Failed to derive keypair from seed phrase: {0}")]
+    FailedToDeriveKeypair(String),
+}
+
+#[derive(Debug, Error)]
+pub enum SplTokenError {
+    #[error("The spl-token mint account {0} was not found: check the 'splToken' address and that you are connected to the right cluster.")]
+    MintNotFound(String),
+
+    #[error("The account {0} exists but it is not a spl-token mint (owner is {1}).")]
+    NotAMint(String, String),
+
+    #[error("The spl-token mint {0} is not initialized.")]
+    MintNotInitialized(String),
+
+    #[error("The spl-token account {0} was not found: check the 'splTokenAccount' address and that you are connected to the right cluster.")]
+    TokenAccountNotFound(String),
+
+    #[error(
+        "The account {0} exists but it is not an initialized spl-token account (owner is {1})."
+    )]
+    NotATokenAccount(String, String),
+
+    #[error("The spl-token account {0} holds tokens of mint {1}, but 'splToken' is {2}.")]
+    TokenAccountMintMismatch(String, String, String),
 }
 
 #[derive(Debug, Error)]
@@ -48,3 +159,26 @@ pub struct FileOpenError<'a> {
     pub path: &'a PathBuf,
     pub error: std::io::Error,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn module_errors_from_anyhow() {
+        let error: anyhow::Error = ConfigError::InvalidCreatorShares(90).into();
+        let message = error.to_string();
+
+        let error = SugarError::from_anyhow(error).unwrap();
+        assert!(matches!(
+            error,
+            SugarError::Config(ConfigError::InvalidCreatorShares(90))
+        ));
+        assert_eq!(error.kind(), "config");
+        // the message of the module error is kept
+        assert_eq!(error.to_string(), message);
+
+        let error = anyhow::anyhow!("not a module error");
+        assert!(SugarError::from_anyhow(error).is_err());
+    }
+}
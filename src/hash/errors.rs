@@ -0,0 +1,13 @@
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum HashError {
+    #[error("Missing 'hiddenSettings' in the config file: the hash is only used by candy machines with hidden settings.")]
+    MissingHiddenSettings,
+
+    #[error("The config can only be updated when it is read from a file.")]
+    ConfigNotAFile,
+
+    #[error("Failed to update the config file '{0}': {1}")]
+    UpdateConfigFailed(String, String),
+}
@@ -0,0 +1,91 @@
+use console::style;
+use data_encoding::HEXLOWER;
+use std::{fs, path::Path};
+
+use crate::common::*;
+use crate::config::{
+    data::HiddenSettings, get_config_data, ConfigError, ConfigFormat, STDIN_CONFIG,
+};
+use crate::hash::HashError;
+
+pub struct HashArgs {
+    pub config: String,
+    pub config_format: Option<ConfigFormat>,
+    pub cache: String,
+    pub update_config: bool,
+}
+
+pub fn process_hash(args: HashArgs) -> Result<()> {
+    let config_data = get_config_data(&args.config, args.config_format)?;
+
+    if config_data.hidden_settings.is_none() {
+        return Err(HashError::MissingHiddenSettings.into());
+    }
+
+    // the hash is the hex encoding of the digest
+    let digest = HiddenSettings::compute_digest(Path::new(&args.cache))?;
+    let hash_string = HEXLOWER.encode(&digest);
+
+    println!("{} {}", style("Hash:").bold(), hash_string);
+    println!(
+        "{} {}",
+        style("Hash (base58):").bold(),
+        bs58::encode(digest).into_string()
+    );
+
+    if args.update_config {
+        if args.config == STDIN_CONFIG {
+            return Err(HashError::ConfigNotAFile.into());
+        }
+
+        write_hash_to_config(&args.config, args.config_format, &hash_string)?;
+        println!(
+            "\nUpdated 'hiddenSettings.hash' of config file '{}'.",
+            args.config
+        );
+    }
+
+    Ok(())
+}
+
+/// Replace the hidden settings hash of the config file, keeping the rest of the file
+/// values as they are (environment variable references are not expanded). The file is
+/// written back in its own format.
+fn write_hash_to_config(config_path: &str, format: Option<ConfigFormat>, hash: &str) -> Result<()> {
+    let path = Path::new(config_path);
+    let format = format.unwrap_or_else(|| ConfigFormat::from_path(path));
+    let update_error = |err: String| HashError::UpdateConfigFailed(config_path.to_string(), err);
+
+    let contents = fs::read_to_string(path).map_err(|err| update_error(err.to_string()))?;
+
+    let mut value: Value = match format {
+        ConfigFormat::Json => serde_json::from_str(&contents).map_err(|err| err.to_string()),
+        ConfigFormat::Toml => toml::from_str(&contents).map_err(|err| err.to_string()),
+        ConfigFormat::Yaml => serde_yaml::from_str(&contents).map_err(|err| err.to_string()),
+    }
+    .map_err(ConfigError::ParseError)?;
+
+    match value
+        .get_mut("hiddenSettings")
+        .and_then(Value::as_object_mut)
+    {
+        Some(hidden_settings) => {
+            hidden_settings.insert("hash".to_string(), Value::String(hash.to_string()))
+        }
+        None => return Err(HashError::MissingHiddenSettings.into()),
+    };
+
+    let contents = match format {
+        ConfigFormat::Json => serde_json::to_string_pretty(&value).map_err(|err| err.to_string()),
+        // converts to a toml value first, so that tables are written after the values
+        ConfigFormat::Toml => toml::Value::try_from(&value)
+            .and_then(|value| toml::to_string_pretty(&value))
+            .map_err(|err| err.to_string()),
+        ConfigFormat::Yaml => serde_yaml::to_string(&value).map_err(|err| err.to_string()),
+    }
+    .map_err(update_error)?;
+
+    fs::write(path, contents).map_err(|err| update_error(err.to_string()))?;
+
+    Ok(())
+}
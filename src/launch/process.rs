@@ -5,9 +5,9 @@ use dialoguer::Confirm;
 use std::sync::{atomic::AtomicBool, Arc};
 
 use crate::common::LAUNCH_EMOJI;
-use crate::config::parser::get_config_data;
+use crate::config::{parser::get_config_data, ConfigFormat};
 use crate::create_config::{process_create_config, CreateConfigArgs};
-use crate::deploy::{process_deploy, DeployArgs};
+use crate::deploy::{process_deploy, DeployArgs, OutputFormat};
 use crate::upload::{process_upload, UploadArgs};
 use crate::validate::{process_validate, ValidateArgs};
 use crate::verify::{process_verify, VerifyArgs};
@@ -15,10 +15,14 @@ use crate::verify::{process_verify, VerifyArgs};
 pub struct LaunchArgs {
     pub assets_dir: String,
     pub config: String,
+    pub config_format: Option<ConfigFormat>,
     pub keypair: Option<String>,
     pub rpc_url: Option<String>,
     pub cache: String,
     pub strict: bool,
+    pub yes: bool,
+    pub confirm_mainnet: bool,
+    pub priority_fee: Option<u64>,
     pub interrupted: Arc<AtomicBool>,
 }
 
@@ -30,7 +34,7 @@ pub async fn process_launch(args: LaunchArgs) -> Result<()> {
         ..Default::default()
     };
 
-    if let Err(err) = get_config_data(&args.config) {
+    if let Err(err) = get_config_data(&args.config, args.config_format) {
         // padding
         println!();
         if Confirm::with_theme(&theme)
@@ -66,9 +70,15 @@ pub async fn process_launch(args: LaunchArgs) -> Result<()> {
     let upload_args = UploadArgs {
         assets_dir: args.assets_dir.clone(),
         config: args.config.clone(),
+        config_format: args.config_format,
         keypair: args.keypair.clone(),
         rpc_url: args.rpc_url.clone(),
         cache: args.cache.clone(),
+        skip_validation: false,
+        no_dedup: false,
+        force: false,
+        verify_remote: false,
+        yes: args.yes,
         interrupted: args.interrupted.clone(),
     };
 
@@ -78,9 +88,23 @@ pub async fn process_launch(args: LaunchArgs) -> Result<()> {
 
     let deploy_args = DeployArgs {
         config: args.config.clone(),
+        config_format: args.config_format,
         keypair: args.keypair.clone(),
         rpc_url: args.rpc_url.clone(),
+        network: None,
         cache: args.cache.clone(),
+        yes: args.yes,
+        confirm_mainnet: args.confirm_mainnet,
+        allow_partial: false,
+        restart: false,
+        dry_run: false,
+        collection_only: false,
+        unsigned_out: None,
+        authority: None,
+        nonce_accounts: Vec::new(),
+        only: None,
+        output: OutputFormat::Human,
+        priority_fee: args.priority_fee,
         interrupted: args.interrupted.clone(),
     };
 
@@ -89,12 +113,17 @@ pub async fn process_launch(args: LaunchArgs) -> Result<()> {
     println!("\n{} sugar verify\n", style(">>>").magenta());
 
     let verify_args = VerifyArgs {
+        config: args.config.clone(),
+        config_format: args.config_format,
         keypair: args.keypair.clone(),
         rpc_url: args.rpc_url.clone(),
         cache: args.cache.clone(),
+        // newly uploaded files might take a while to be available on the gateways
+        check_uris: false,
+        concurrency: 1,
     };
 
-    process_verify(verify_args)?;
+    process_verify(verify_args).await?;
 
     Ok(())
 }
@@ -1,20 +1,32 @@
+pub mod airdrop;
 pub mod cache;
 pub mod candy_machine;
 pub mod cli;
+pub mod collection;
 pub mod common;
 pub mod config;
+pub mod config_schema;
 pub mod constants;
+pub mod convert_config;
 pub mod create_config;
 pub mod deploy;
 pub mod errors;
+pub mod hash;
 pub mod launch;
 pub mod mint;
 pub mod parse;
+pub mod progress;
+pub mod reveal;
+pub mod rpc_calls;
 pub mod setup;
 pub mod show;
+pub mod sign;
+pub mod submit;
+pub mod sync_cache;
 pub mod update;
 pub mod upload;
 pub mod utils;
 pub mod validate;
+pub mod validate_config;
 pub mod verify;
 pub mod withdraw;
@@ -14,20 +14,34 @@ use tracing::subscriber::set_global_default;
 use tracing_bunyan_formatter::{BunyanFormattingLayer, JsonStorageLayer};
 use tracing_subscriber::{self, filter::LevelFilter, prelude::*, EnvFilter};
 
-use sugar_cli::cli::{Cli, Commands};
+use sugar_cli::airdrop::{process_airdrop, AirdropArgs};
+use sugar_cli::cli::{CacheCommands, Cli, CollectionCommands, Commands, ConfigCommands, LogFormat};
+use sugar_cli::collection::{process_set_collection, SetCollectionArgs};
+use sugar_cli::config_schema::{process_config_schema, ConfigSchemaArgs};
 use sugar_cli::constants::{COMPLETE_EMOJI, ERROR_EMOJI};
+use sugar_cli::convert_config::{process_convert_config, ConvertConfigArgs};
 use sugar_cli::create_config::{process_create_config, CreateConfigArgs};
-use sugar_cli::deploy::{process_deploy, DeployArgs};
+use sugar_cli::deploy::{process_deploy, DeployArgs, OutputFormat};
+use sugar_cli::errors::SugarError;
+use sugar_cli::hash::{process_hash, HashArgs};
 use sugar_cli::launch::{process_launch, LaunchArgs};
 use sugar_cli::mint::{process_mint, MintArgs};
+use sugar_cli::progress::set_json_logs;
+use sugar_cli::reveal::{process_reveal, RevealArgs};
+use sugar_cli::rpc_calls::{print_rpc_calls, set_rpc_calls};
+use sugar_cli::setup::{set_commitment, set_keypair_seed, set_rpc_timeout, set_skip_rpc_check};
 use sugar_cli::show::{process_show, ShowArgs};
+use sugar_cli::sign::{process_sign, SignArgs};
+use sugar_cli::submit::{process_submit, SubmitArgs};
+use sugar_cli::sync_cache::{process_sync_cache, SyncCacheArgs};
 use sugar_cli::update::{process_update, UpdateArgs};
 use sugar_cli::upload::{process_upload, UploadArgs};
 use sugar_cli::validate::{process_validate, ValidateArgs};
+use sugar_cli::validate_config::{process_validate_config, ValidateConfigArgs};
 use sugar_cli::verify::{process_verify, VerifyArgs};
 use sugar_cli::withdraw::{process_withdraw, WithdrawArgs};
 
-fn setup_logging(level: Option<EnvFilter>) -> Result<()> {
+fn setup_logging(level: Option<EnvFilter>, format: LogFormat) -> Result<()> {
     // Log path; change this to be dynamic for multiple OSes.
     // Log in current directory for now.
     let log_path = PathBuf::from("sugar.log");
@@ -35,7 +49,7 @@ fn setup_logging(level: Option<EnvFilter>) -> Result<()> {
     let file = OpenOptions::new()
         .write(true)
         .create(true)
-        .open(&log_path)
+        .open(log_path)
         .unwrap();
 
     // Prioritize user-provided level, otherwise read from RUST_LOG env var for log level, fall back to "tracing" if not set.
@@ -48,8 +62,21 @@ fn setup_logging(level: Option<EnvFilter>) -> Result<()> {
     let formatting_layer = BunyanFormattingLayer::new("sugar".into(), file);
     let level_filter = LevelFilter::from_str(&env_filter.to_string())?;
 
+    // progress bars would be mixed with the JSON log records on stderr
+    set_json_logs(format == LogFormat::Json);
+
+    // in json mode, log records are also written to stderr so they do not get
+    // mixed with the progress output on stdout
+    let json_layer = match format {
+        LogFormat::Json => Some(
+            BunyanFormattingLayer::new("sugar".into(), std::io::stderr).with_filter(level_filter),
+        ),
+        LogFormat::Human => None,
+    };
+
     let subscriber = tracing_subscriber::registry()
         .with(formatting_layer.with_filter(level_filter))
+        .with(json_layer)
         .with(JsonStorageLayer);
 
     set_global_default(subscriber).expect("Failed to set global default subscriber");
@@ -57,23 +84,47 @@ fn setup_logging(level: Option<EnvFilter>) -> Result<()> {
     Ok(())
 }
 
+/// Set when stdout is reserved for the JSON output of the command.
+static JSON_OUTPUT: AtomicBool = AtomicBool::new(false);
+
+/// Print the outcome of the command, to stderr when stdout has JSON output.
+fn print_outcome(message: String) {
+    if JSON_OUTPUT.load(Ordering::SeqCst) {
+        eprintln!("{}", message);
+    } else {
+        println!("{}", message);
+    }
+}
+
 #[tokio::main(worker_threads = 4)]
 async fn main() {
-    match run().await {
+    let result = run().await;
+    // no-op unless --rpc-calls is set
+    print_rpc_calls();
+
+    match result {
         Ok(()) => {
-            println!(
+            print_outcome(format!(
                 "\n{}{}",
                 COMPLETE_EMOJI,
                 style("Command successful.").green().bold().dim()
-            );
+            ));
         }
         Err(err) => {
-            println!(
+            let message = err.to_string();
+
+            // the errors of the modules are logged with their kind of failure
+            match SugarError::from_anyhow(err) {
+                Ok(error) => tracing::error!(kind = error.kind(), "{}", error),
+                Err(error) => tracing::error!("{:?}", error),
+            }
+
+            print_outcome(format!(
                 "\n{}{} {}",
                 ERROR_EMOJI,
                 style("Error running command (re-run needed):").red(),
-                err,
-            );
+                message,
+            ));
             // finished the program with an error code to the OS
             std::process::exit(1);
         }
@@ -93,33 +144,142 @@ async fn run() -> Result<()> {
             Ok(filter) => filter,
             Err(_) => return log_level_error,
         };
-        setup_logging(Some(filter))?;
+        setup_logging(Some(filter), cli.log_format)?;
     } else {
-        setup_logging(None)?;
+        setup_logging(None, cli.log_format)?;
     }
 
     tracing::info!("Lend me some sugar, I am your neighbor.");
 
+    set_rpc_calls(cli.rpc_calls);
+    set_commitment(cli.commitment);
+    set_rpc_timeout(cli.rpc_timeout);
+    set_skip_rpc_check(cli.skip_rpc_check);
+
+    if cli.keypair_seed {
+        set_keypair_seed(cli.derivation_path.clone());
+    }
+
     let interrupted = Arc::new(AtomicBool::new(true));
     let ctrl_handler = interrupted.clone();
 
     ctrlc::set_handler(move || {
         if ctrl_handler.load(Ordering::SeqCst) {
             // we really need to exit
-            println!(
+            print_outcome(format!(
                 "\n\n{}{} Operation aborted.",
                 ERROR_EMOJI,
                 style("Error running command (re-run needed):").red(),
-            );
+            ));
             // finished the program with an error code to the OS
             std::process::exit(1);
         }
-        // signal that we want to exit
+        // signal that we want to exit: the in-flight tasks are completed and the
+        // cache is saved before exiting
+        eprintln!(
+            "\n\n{} waiting for the in-flight tasks to finish and saving the cache (press Ctrl-C again to exit immediately)...",
+            style("Interrupted:").yellow().bold(),
+        );
         ctrl_handler.store(true, Ordering::SeqCst);
     })
     .expect("Error setting Ctrl-C handler");
 
     match cli.command {
+        Commands::Airdrop {
+            recipients,
+            keypair,
+            rpc_url,
+            cache,
+            candy_machine,
+            output,
+            skip_bad_rows,
+        } => process_airdrop(AirdropArgs {
+            keypair,
+            rpc_url,
+            cache,
+            candy_machine,
+            recipients,
+            output,
+            skip_bad_rows,
+            priority_fee: cli.priority_fee,
+        })?,
+        Commands::Cache { command } => match command {
+            CacheCommands::Sync {
+                candy_machine,
+                keypair,
+                rpc_url,
+                cache,
+                force,
+            } => process_sync_cache(SyncCacheArgs {
+                keypair,
+                rpc_url,
+                cache,
+                candy_machine,
+                force,
+            })?,
+        },
+        Commands::Collection { command } => match command {
+            CollectionCommands::Set {
+                collection_mint,
+                config,
+                candy_machine,
+                keypair,
+                rpc_url,
+                cache,
+            } => process_set_collection(SetCollectionArgs {
+                keypair,
+                rpc_url,
+                cache,
+                config,
+                config_format: cli.config_format,
+                candy_machine,
+                collection_mint,
+                priority_fee: cli.priority_fee,
+            })?,
+        },
+        Commands::Config { command } => match command {
+            ConfigCommands::Create {
+                config,
+                keypair,
+                rpc_url,
+                assets_dir,
+            } => process_create_config(CreateConfigArgs {
+                config,
+                keypair,
+                rpc_url,
+                assets_dir,
+            })?,
+            ConfigCommands::Convert {
+                legacy_config,
+                output,
+                keypair,
+                rpc_url,
+                assets_dir,
+            } => process_convert_config(ConvertConfigArgs {
+                legacy_config,
+                output,
+                keypair,
+                rpc_url,
+                assets_dir,
+            })?,
+            ConfigCommands::Validate {
+                config,
+                keypair,
+                rpc_url,
+                json,
+            } => process_validate_config(ValidateConfigArgs {
+                config,
+                config_format: cli.config_format,
+                keypair,
+                rpc_url,
+                json,
+            })?,
+            ConfigCommands::Schema { output } => {
+                // stdout only has the schema when it is not written to a file
+                JSON_OUTPUT.store(output.is_none(), Ordering::SeqCst);
+                process_config_schema(ConfigSchemaArgs { output })?
+            }
+        },
         Commands::CreateConfig {
             config,
             keypair,
@@ -138,14 +298,20 @@ async fn run() -> Result<()> {
             rpc_url,
             cache,
             strict,
+            yes,
+            confirm_mainnet,
         } => {
             process_launch(LaunchArgs {
                 assets_dir,
                 config,
+                config_format: cli.config_format,
                 keypair,
                 rpc_url,
                 cache,
                 strict,
+                yes,
+                confirm_mainnet,
+                priority_fee: cli.priority_fee,
                 interrupted: interrupted.clone(),
             })
             .await?
@@ -156,12 +322,19 @@ async fn run() -> Result<()> {
             cache,
             number,
             candy_machine,
+            receiver,
+            mints_out,
+            resume,
         } => process_mint(MintArgs {
             keypair,
             rpc_url,
             cache,
             number,
             candy_machine,
+            receiver,
+            mints_out,
+            resume,
+            priority_fee: cli.priority_fee,
         })?,
         Commands::Update {
             config,
@@ -170,42 +343,96 @@ async fn run() -> Result<()> {
             cache,
             new_authority,
             candy_machine,
+            apply_schedule,
         } => process_update(UpdateArgs {
             config,
+            config_format: cli.config_format,
             keypair,
             rpc_url,
             cache,
             new_authority,
             candy_machine,
+            apply_schedule,
+            priority_fee: cli.priority_fee,
         })?,
         Commands::Deploy {
             config,
             keypair,
             rpc_url,
+            network,
             cache,
+            yes,
+            confirm_mainnet,
+            allow_partial,
+            restart,
+            dry_run,
+            collection_only,
+            unsigned_out,
+            authority,
+            nonce_account,
+            only,
+            output,
         } => {
+            // stdout only has the deploy summary when using the JSON output
+            JSON_OUTPUT.store(output == OutputFormat::Json, Ordering::SeqCst);
+
             process_deploy(DeployArgs {
                 config,
+                config_format: cli.config_format,
                 keypair,
                 rpc_url,
+                network,
                 cache,
+                yes,
+                confirm_mainnet,
+                allow_partial,
+                restart,
+                dry_run,
+                collection_only,
+                unsigned_out,
+                authority,
+                nonce_accounts: nonce_account,
+                only,
+                output,
+                priority_fee: cli.priority_fee,
                 interrupted: interrupted.clone(),
             })
             .await?
         }
+        Commands::Hash {
+            config,
+            cache,
+            update_config,
+        } => process_hash(HashArgs {
+            config,
+            config_format: cli.config_format,
+            cache,
+            update_config,
+        })?,
         Commands::Upload {
             assets_dir,
             config,
             keypair,
             rpc_url,
             cache,
+            skip_validation,
+            no_dedup,
+            force,
+            verify_remote,
+            yes,
         } => {
             process_upload(UploadArgs {
                 assets_dir,
                 config,
+                config_format: cli.config_format,
                 keypair,
                 rpc_url,
                 cache,
+                skip_validation,
+                no_dedup,
+                force,
+                verify_remote,
+                yes,
                 interrupted: interrupted.clone(),
             })
             .await?
@@ -213,37 +440,97 @@ async fn run() -> Result<()> {
         Commands::Validate { assets_dir, strict } => {
             process_validate(ValidateArgs { assets_dir, strict })?
         }
-        Commands::Withdraw {
+        Commands::Reveal {
             candy_machine,
+            config,
             keypair,
             rpc_url,
-            list,
-        } => process_withdraw(WithdrawArgs {
-            candy_machine,
+            cache,
+        } => process_reveal(RevealArgs {
+            config,
+            config_format: cli.config_format,
             keypair,
             rpc_url,
-            list,
+            cache,
+            candy_machine,
+            priority_fee: cli.priority_fee,
         })?,
-        Commands::Verify {
+        Commands::Sign {
+            candy_machine,
             keypair,
             rpc_url,
             cache,
-        } => process_verify(VerifyArgs {
+        } => process_sign(SignArgs {
             keypair,
             rpc_url,
             cache,
+            candy_machine,
         })?,
-        Commands::Show {
-            keypair,
+        Commands::Submit {
+            files,
+            rpc_url,
+            cache,
+        } => process_submit(SubmitArgs {
+            files,
             rpc_url,
             cache,
+        })?,
+        Commands::Withdraw {
             candy_machine,
-        } => process_show(ShowArgs {
             keypair,
             rpc_url,
+            list,
+            all,
             cache,
+            dry_run,
+        } => process_withdraw(WithdrawArgs {
             candy_machine,
+            keypair,
+            rpc_url,
+            list,
+            all,
+            cache,
+            dry_run,
         })?,
+        Commands::Verify {
+            config,
+            keypair,
+            rpc_url,
+            cache,
+            skip_uris,
+            concurrency,
+        } => {
+            process_verify(VerifyArgs {
+                config,
+                config_format: cli.config_format,
+                keypair,
+                rpc_url,
+                cache,
+                check_uris: !skip_uris,
+                concurrency,
+            })
+            .await?
+        }
+        Commands::Show {
+            keypair,
+            rpc_url,
+            cache,
+            candy_machine,
+            as_config,
+            output,
+        } => {
+            // stdout only has the candy machine details when using the JSON output
+            JSON_OUTPUT.store(output == OutputFormat::Json, Ordering::SeqCst);
+
+            process_show(ShowArgs {
+                keypair,
+                rpc_url,
+                cache,
+                candy_machine,
+                as_config,
+                output,
+            })?
+        }
     }
 
     Ok(())
@@ -0,0 +1,13 @@
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum MintError {
+    #[error("The mint log '{0}' has {1} minted item(s): re-run with '--resume' to continue the mint or use a different '--mints-out' file.")]
+    MintLogExists(String, usize),
+
+    #[error("Invalid row at line {1} of the mint log '{0}': {2}")]
+    InvalidMintLog(String, usize, String),
+
+    #[error("Failed to write the mint log '{0}': {1}")]
+    FailedToWriteMintLog(String, String),
+}
@@ -1,5 +1,7 @@
+pub mod errors;
 pub mod pdas;
 pub mod process;
 
+pub use errors::*;
 pub use pdas::*;
 pub use process::*;
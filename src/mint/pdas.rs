@@ -47,3 +47,25 @@ pub fn get_candy_machine_creator_pda(candy_machine_id: &Pubkey) -> (Pubkey, u8)
 
     Pubkey::find_program_address(creator_seeds, &candy_machine::ID)
 }
+
+pub fn get_collection_pda(candy_machine_id: &Pubkey) -> Pubkey {
+    // Derive collection PDA (stores the collection mint of the candy machine)
+    let collection_seeds = &["collection".as_bytes(), candy_machine_id.as_ref()];
+    let (pda, _bump) = Pubkey::find_program_address(collection_seeds, &candy_machine::ID);
+
+    pda
+}
+
+pub fn get_collection_authority_record_pda(mint: &Pubkey, authority: &Pubkey) -> Pubkey {
+    // Derive collection authority record account
+    let record_seeds = &[
+        "metadata".as_bytes(),
+        &TOKEN_METADATA_ID.to_bytes(),
+        &mint.to_bytes(),
+        "collection_authority".as_bytes(),
+        &authority.to_bytes(),
+    ];
+    let (pda, _bump) = Pubkey::find_program_address(record_seeds, &TOKEN_METADATA_ID);
+
+    pda
+}
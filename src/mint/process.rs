@@ -6,7 +6,7 @@ use anchor_client::{
         signature::{Keypair, Signature, Signer},
         system_instruction, system_program, sysvar,
     },
-    Client,
+    Client, ClientError, Program,
 };
 use anchor_lang::prelude::AccountMeta;
 use anyhow::Result;
@@ -19,7 +19,13 @@ use spl_token::{
     state::Account,
     ID as TOKEN_PROGRAM_ID,
 };
-use std::{str::FromStr, sync::Arc};
+use std::{
+    collections::BTreeMap,
+    fs::{self, OpenOptions},
+    io::Write,
+    str::FromStr,
+    sync::Arc,
+};
 
 use mpl_candy_machine::accounts as nft_accounts;
 use mpl_candy_machine::instruction as nft_instruction;
@@ -29,7 +35,10 @@ use crate::cache::load_cache;
 use crate::candy_machine::ID as CANDY_MACHINE_ID;
 use crate::candy_machine::*;
 use crate::common::*;
+use crate::config::RetryConfig;
 use crate::mint::pdas::*;
+use crate::mint::MintError;
+use crate::rpc_calls::timed;
 use crate::utils::*;
 
 pub struct MintArgs {
@@ -38,11 +47,102 @@ pub struct MintArgs {
     pub cache: String,
     pub number: Option<u64>,
     pub candy_machine: Option<String>,
+    pub receiver: Option<String>,
+    pub mints_out: Option<String>,
+    pub resume: bool,
+    pub priority_fee: Option<u64>,
+}
+
+/// Log of the minted NFTs, with an `index,mint,signature` row written after each mint,
+/// so a mint that fails partway can be resumed without minting the same items again.
+struct MintLog {
+    path: String,
+    minted: BTreeMap<u64, (Pubkey, Signature)>,
+}
+
+impl MintLog {
+    /// Load the mint log, creating it when it does not exist. The items of an existing
+    /// log are only reused when resuming a mint.
+    fn load(path: &str, resume: bool) -> Result<MintLog> {
+        let mut mint_log = MintLog {
+            path: path.to_string(),
+            minted: BTreeMap::new(),
+        };
+
+        if !Path::new(path).exists() {
+            fs::write(path, "index,mint,signature\n").map_err(|err| {
+                MintError::FailedToWriteMintLog(path.to_string(), err.to_string())
+            })?;
+            return Ok(mint_log);
+        }
+
+        let content = fs::read_to_string(path)?;
+
+        for (index, row) in content.lines().enumerate() {
+            let line = index + 1;
+            let row = row.trim();
+
+            if row.is_empty() || (index == 0 && row.starts_with("index")) {
+                continue;
+            }
+
+            let invalid = |reason: &str| {
+                MintError::InvalidMintLog(path.to_string(), line, reason.to_string())
+            };
+            let columns: Vec<&str> = row.split(',').map(str::trim).collect();
+
+            if columns.len() != 3 {
+                return Err(invalid("expected 3 columns (index,mint,signature)").into());
+            }
+
+            let item = columns[0]
+                .parse::<u64>()
+                .map_err(|_| invalid("invalid index"))?;
+            let nft_mint = Pubkey::from_str(columns[1]).map_err(|_| invalid("invalid mint"))?;
+            let signature =
+                Signature::from_str(columns[2]).map_err(|_| invalid("invalid signature"))?;
+
+            if mint_log
+                .minted
+                .insert(item, (nft_mint, signature))
+                .is_some()
+            {
+                return Err(invalid("duplicated index").into());
+            }
+        }
+
+        if !resume && !mint_log.minted.is_empty() {
+            return Err(MintError::MintLogExists(path.to_string(), mint_log.minted.len()).into());
+        }
+
+        Ok(mint_log)
+    }
+
+    fn contains(&self, index: u64) -> bool {
+        self.minted.contains_key(&index)
+    }
+
+    /// Append the row of a minted item to the log.
+    fn record(&mut self, index: u64, nft: (Pubkey, Signature)) -> Result<()> {
+        let write_error = |err: std::io::Error| {
+            MintError::FailedToWriteMintLog(self.path.clone(), err.to_string())
+        };
+
+        let mut file = OpenOptions::new()
+            .append(true)
+            .open(&self.path)
+            .map_err(write_error)?;
+        writeln!(file, "{},{},{}", index, nft.0, nft.1).map_err(write_error)?;
+
+        self.minted.insert(index, nft);
+
+        Ok(())
+    }
 }
 
 pub fn process_mint(args: MintArgs) -> Result<()> {
     let sugar_config = sugar_setup(args.keypair, args.rpc_url)?;
-    let client = Arc::new(setup_client(&sugar_config)?);
+    let client = Arc::new(setup_client(&sugar_config, None)?);
 
     // the candy machine id specified takes precedence over the one from the cache
 
@@ -72,10 +172,49 @@ pub fn process_mint(args: MintArgs) -> Result<()> {
 
     let candy_machine_state = Arc::new(get_candy_machine_state(&sugar_config, &candy_pubkey)?);
     let number = args.number.unwrap_or(1);
+    let priority_fee = args.priority_fee.unwrap_or(0);
+
+    let receiver = match &args.receiver {
+        Some(receiver) => match Pubkey::from_str(receiver) {
+            Ok(receiver) => receiver,
+            Err(_) => {
+                let error = anyhow!("Failed to parse receiver address: {}", receiver);
+                error!("{:?}", error);
+                return Err(error);
+            }
+        },
+        None => sugar_config.keypair.pubkey(),
+    };
+    println!("Receiver: {}", receiver);
+
+    let mut mint_log = match &args.mints_out {
+        Some(path) => Some(MintLog::load(path, args.resume)?),
+        None => None,
+    };
+    // only the items of the log within the number requested count as minted
+    let already_minted = mint_log.as_ref().map_or(0, |mint_log| {
+        mint_log.minted.keys().filter(|i| **i < number).count()
+    }) as u64;
+
+    if number > 0 && already_minted == number {
+        println!(
+            "All {} item(s) already minted, see '{}'.",
+            number,
+            args.mints_out.unwrap_or_default()
+        );
+        return Ok(());
+    } else if already_minted > 0 {
+        println!(
+            "Resuming mint: {} of {} item(s) already minted",
+            already_minted, number
+        );
+    }
+
+    let pending = number - already_minted;
     let available = candy_machine_state.data.items_available - candy_machine_state.items_redeemed;
 
-    if number > available || number == 0 {
-        let error = anyhow!("{} item(s) available, requested {}", available, number);
+    if pending > available || number == 0 {
+        let error = anyhow!("{} item(s) available, requested {}", available, pending);
         error!("{:?}", error);
         return Err(error);
     }
@@ -94,8 +233,22 @@ pub fn process_mint(args: MintArgs) -> Result<()> {
             Arc::clone(&client),
             candy_pubkey,
             Arc::clone(&candy_machine_state),
+            receiver,
+            priority_fee,
         ) {
-            Ok(signature) => format!("{} {}", style("Signature:").bold(), signature),
+            Ok((nft_mint, signature)) => {
+                if let Some(mint_log) = &mut mint_log {
+                    mint_log.record(0, (nft_mint, signature))?;
+                }
+
+                format!(
+                    "{} {}\n{} {}",
+                    style("Mint:").bold(),
+                    nft_mint,
+                    style("Signature:").bold(),
+                    signature
+                )
+            }
             Err(err) => {
                 pb.abandon_with_message(format!("{}", style("Mint failed ").red().bold()));
                 error!("{:?}", err);
@@ -106,32 +259,115 @@ pub fn process_mint(args: MintArgs) -> Result<()> {
         pb.finish_with_message(result);
     } else {
         let pb = progress_bar_with_style(number);
+        pb.inc(already_minted);
 
-        for _i in 0..number {
-            if let Err(err) = mint(
+        let minted = mint_pending(number, &mut mint_log, &pb, || {
+            mint(
                 Arc::clone(&client),
                 candy_pubkey,
                 Arc::clone(&candy_machine_state),
-            ) {
+                receiver,
+                priority_fee,
+            )
+        });
+
+        match minted {
+            Ok(minted) => {
+                pb.finish();
+                print_minted(&minted);
+            }
+            Err((minted, err)) => {
+                // stops at the first error, since the following mints would most
+                // likely fail for the same reason (e.g., sold out)
                 pb.abandon_with_message(format!("{}", style("Mint failed ").red().bold()));
                 error!("{:?}", err);
-                return Err(err);
+                print_minted(&minted);
+
+                let resume = match &args.mints_out {
+                    Some(_) => " (re-run with '--resume' to mint the remaining items)",
+                    None => "",
+                };
+                return Err(anyhow!(
+                    "Minted {} of {} item(s){}: {}",
+                    already_minted + minted.len() as u64,
+                    number,
+                    resume,
+                    err
+                ));
             }
+        }
+    }
 
-            pb.inc(1);
+    Ok(())
+}
+
+/// Mint address and signature of a minted NFT.
+type MintedNft = (Pubkey, Signature);
+
+/// Mint the first `number` items that are not in the mint log, recording each mint
+/// in the log. The mint stops at the first error, which is returned with the NFTs
+/// minted before it.
+fn mint_pending<F>(
+    number: u64,
+    mint_log: &mut Option<MintLog>,
+    pb: &ProgressBar,
+    mut mint_item: F,
+) -> Result<Vec<MintedNft>, (Vec<MintedNft>, anyhow::Error)>
+where
+    F: FnMut() -> Result<MintedNft>,
+{
+    let mut minted = Vec::new();
+
+    for index in 0..number {
+        // the log is checked before each mint, so a resumed mint never mints
+        // an item of the log again
+        if mint_log
+            .as_ref()
+            .map_or(false, |mint_log| mint_log.contains(index))
+        {
+            continue;
         }
 
-        pb.finish();
+        match mint_item() {
+            Ok(nft) => {
+                if let Some(mint_log) = mint_log {
+                    if let Err(err) = mint_log.record(index, nft) {
+                        minted.push(nft);
+                        return Err((minted, err));
+                    }
+                }
+                minted.push(nft);
+            }
+            Err(err) => return Err((minted, err)),
+        }
+
+        pb.inc(1);
     }
 
-    Ok(())
+    Ok(minted)
+}
+
+/// Print the mint address and signature of the NFTs minted.
+fn print_minted(minted: &[(Pubkey, Signature)]) {
+    if minted.is_empty() {
+        return;
+    }
+
+    println!("\n{:44} Signature", "Mint");
+    println!("{:-<133}", "-");
+
+    for (nft_mint, signature) in minted {
+        println!("{:44} {}", nft_mint.to_string(), signature);
+    }
 }
 
 pub fn mint(
     client: Arc<Client>,
     candy_machine_id: Pubkey,
     candy_machine_state: Arc<CandyMachine>,
-) -> Result<Signature> {
+    receiver: Pubkey,
+    priority_fee: u64,
+) -> Result<(Pubkey, Signature)> {
     let program = client.program(CANDY_MACHINE_ID);
     let payer = program.payer();
     let wallet = candy_machine_state.wallet;
@@ -140,7 +376,7 @@ pub fn mint(
 
     if let Some(_gatekeeper) = &candy_machine_data.gatekeeper {
         return Err(anyhow!(
-            "Command-line mint disabled (gatekeeper settings in use)"
+            "Command-line mint disabled (gatekeeper settings in use): a gateway token cannot be obtained from the command line"
         ));
     } else if candy_machine_state.items_redeemed >= candy_machine_data.items_available {
         return Err(anyhow!(ErrorCode::CandyMachineEmpty));
@@ -199,9 +435,11 @@ pub fn mint(
     let metaplex_program_id = Pubkey::from_str(METAPLEX_PROGRAM_ID)?;
 
     // Allocate memory for the account
-    let min_rent = program
-        .rpc()
-        .get_minimum_balance_for_rent_exemption(MINT_LAYOUT as usize)?;
+    let min_rent = timed("Get minimum balance for rent exemption", || {
+        program
+            .timed_rpc()
+            .get_minimum_balance_for_rent_exemption(MINT_LAYOUT as usize)
+    })?;
 
     // Create mint account
     let create_mint_account_ix = system_instruction::create_account(
@@ -221,12 +459,12 @@ pub fn mint(
         0,
     )?;
 
-    // Derive associated token account
-    let assoc = get_associated_token_address(&payer, &nft_mint.pubkey());
+    // Derive associated token account of the receiver
+    let assoc = get_associated_token_address(&receiver, &nft_mint.pubkey());
 
     // Create associated account instruction
     let create_assoc_account_ix =
-        create_associated_token_account(&payer, &payer, &nft_mint.pubkey());
+        create_associated_token_account(&payer, &receiver, &nft_mint.pubkey());
 
     // Mint to instruction
     let mint_to_ix = mint_to(
@@ -269,7 +507,9 @@ pub fn mint(
 
             let mut token_found = false;
 
-            match program.rpc().get_account_data(&whitelist_token) {
+            match timed("Get whitelist token account", || {
+                program.timed_rpc().get_account_data(&whitelist_token)
+            }) {
                 Ok(ata_data) => {
                     if !ata_data.is_empty() {
                         let account = Account::unpack_unchecked(&ata_data)?;
@@ -325,7 +565,10 @@ pub fn mint(
             is_writable: false,
         });
 
-        let ata_exists = !program.rpc().get_account_data(&token_mint)?.is_empty();
+        let ata_exists = !timed("Get token account", || {
+            program.timed_rpc().get_account_data(&token_mint)
+        })?
+        .is_empty();
 
         if ata_exists {
             let approve_ix = spl_token::instruction::approve(
@@ -355,67 +598,163 @@ pub fn mint(
     let (candy_machine_creator_pda, creator_bump) =
         get_candy_machine_creator_pda(&candy_machine_id);
 
-    let mut builder = program
-        .request()
-        .instruction(create_mint_account_ix)
-        .instruction(init_mint_ix)
-        .instruction(create_assoc_account_ix)
-        .instruction(mint_to_ix)
-        .signer(&nft_mint)
-        .accounts(nft_accounts::MintNFT {
-            candy_machine: candy_machine_id,
-            candy_machine_creator: candy_machine_creator_pda,
-            payer,
-            wallet,
-            metadata: metadata_pda,
-            mint: nft_mint.pubkey(),
-            mint_authority: payer,
-            update_authority: payer,
-            master_edition: master_edition_pda,
-            token_metadata_program: metaplex_program_id,
-            token_program: TOKEN_PROGRAM_ID,
-            system_program: system_program::id(),
-            rent: sysvar::rent::ID,
-            clock: sysvar::clock::ID,
-            recent_blockhashes: sysvar::recent_blockhashes::ID,
-            instruction_sysvar_account: sysvar::instructions::ID,
-        })
-        .args(nft_instruction::MintNft { creator_bump });
+    let retry_config = RetryConfig::default();
+
+    let mut sent = false;
 
-    // Add additional instructions based on candy machine settings.
-    if !additional_instructions.is_empty() {
-        for instruction in additional_instructions {
-            builder = builder.instruction(instruction);
+    // the transaction is rebuilt on every attempt, so each one uses a recent blockhash
+    let sig = with_priority_fee_retry(&retry_config, priority_fee, "Mint", |fee| {
+        // an attempt can land without being confirmed in time: the mint account then
+        // exists, and sending the transaction again would fail with "already in use"
+        if sent {
+            if let Some(signature) = landed_mint_signature(&program, &nft_mint.pubkey())? {
+                return Ok(signature);
+            }
+        }
+        sent = true;
+
+        let mut builder = with_priority_fee(program.request(), fee)
+            .instruction(create_mint_account_ix.clone())
+            .instruction(init_mint_ix.clone())
+            .instruction(create_assoc_account_ix.clone())
+            .instruction(mint_to_ix.clone())
+            .accounts(nft_accounts::MintNFT {
+                candy_machine: candy_machine_id,
+                candy_machine_creator: candy_machine_creator_pda,
+                payer,
+                wallet,
+                metadata: metadata_pda,
+                mint: nft_mint.pubkey(),
+                mint_authority: payer,
+                update_authority: payer,
+                master_edition: master_edition_pda,
+                token_metadata_program: metaplex_program_id,
+                token_program: TOKEN_PROGRAM_ID,
+                system_program: system_program::id(),
+                rent: sysvar::rent::ID,
+                clock: sysvar::clock::ID,
+                recent_blockhashes: sysvar::recent_blockhashes::ID,
+                instruction_sysvar_account: sysvar::instructions::ID,
+            })
+            .args(nft_instruction::MintNft { creator_bump });
+
+        // Add additional instructions based on candy machine settings.
+        for instruction in &additional_instructions {
+            builder = builder.instruction(instruction.clone());
         }
-    }
 
-    if !additional_accounts.is_empty() {
-        for account in additional_accounts {
-            builder = builder.accounts(account);
+        for account in &additional_accounts {
+            builder = builder.accounts(account.clone());
         }
+
+        let mut signers: Vec<&dyn Signer> = vec![&nft_mint];
+        signers.extend(
+            additional_signers
+                .iter()
+                .map(|signer| signer as &dyn Signer),
+        );
+
+        builder.send_timed(&signers)
+    })?;
+
+    info!(mint = %nft_mint.pubkey(), signature = %sig, "Minted");
+
+    // Cleanup instructions, such as revoke token burn authority, require a separate transaction.
+    if !cleanup_instructions.is_empty() {
+        let sig2 = with_retry(&retry_config, "Mint cleanup", || {
+            let mut builder = program.request();
+
+            for instruction in &cleanup_instructions {
+                builder = builder.instruction(instruction.clone());
+            }
+
+            builder.send_timed(&[])
+        })?;
+
+        info!("Cleanup TxId: {}", sig2);
     }
 
-    if !additional_signers.is_empty() {
-        for signer in &additional_signers {
-            builder = builder.signer(signer);
-        }
+    Ok((nft_mint.pubkey(), sig))
+}
+
+/// Return the signature of the transaction that created the NFT mint account, if the
+/// account exists.
+fn landed_mint_signature(
+    program: &Program,
+    nft_mint: &Pubkey,
+) -> Result<Option<Signature>, ClientError> {
+    let rpc_client = program.timed_rpc();
+    let account = timed("Get mint", || {
+        rpc_client.get_account_with_commitment(nft_mint, rpc_client.commitment())
+    })?;
+
+    if account.value.is_none() {
+        return Ok(None);
     }
 
-    let sig = builder.send()?;
+    // the signatures are sorted from the newest, so the mint transaction is the last
+    let signatures = timed("Get signatures", || {
+        rpc_client.get_signatures_for_address(nft_mint)
+    })?;
 
-    // Cleanup instructions, such as revoke token burn authority, require a separate transaction.
-    let mut builder = program.request();
+    Ok(signatures
+        .last()
+        .and_then(|status| Signature::from_str(&status.signature).ok()))
+}
 
-    if !cleanup_instructions.is_empty() {
-        for instruction in cleanup_instructions {
-            builder = builder.instruction(instruction);
-        }
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+
+    fn nft() -> (Pubkey, Signature) {
+        (Pubkey::new_unique(), Signature::new_unique())
     }
 
-    let sig2 = builder.send()?;
+    #[test]
+    fn resume_skips_minted_items() {
+        let path = env::temp_dir().join(format!("sugar-mints-{}.csv", std::process::id()));
+        let path = path.to_string_lossy().to_string();
+        let pb = ProgressBar::hidden();
+
+        // the second mint fails
+        let mut mint_log = Some(MintLog::load(&path, false).unwrap());
+        let mut calls = 0;
+        let first = nft();
+        let (minted, _) = mint_pending(3, &mut mint_log, &pb, || {
+            calls += 1;
+            match calls {
+                1 => Ok(first),
+                _ => Err(anyhow!("mint failed")),
+            }
+        })
+        .unwrap_err();
+        assert_eq!(minted, vec![first]);
+
+        // the log is kept unless resuming
+        assert!(MintLog::load(&path, false).is_err());
 
-    info!("Minted! TxId: {}", sig);
-    info!("Cleanup TxId: {}", sig2);
+        let mut mint_log = Some(MintLog::load(&path, true).unwrap());
+        assert_eq!(mint_log.as_ref().unwrap().minted[&0], first);
 
-    Ok(sig)
+        let mut calls = 0;
+        let minted = mint_pending(3, &mut mint_log, &pb, || {
+            calls += 1;
+            Ok(nft())
+        })
+        .unwrap();
+        assert_eq!(calls, 2);
+        assert_eq!(minted.len(), 2);
+
+        // every item is in the log once
+        let mint_log = MintLog::load(&path, true).unwrap();
+        assert_eq!(
+            mint_log.minted.keys().copied().collect::<Vec<u64>>(),
+            vec![0, 1, 2]
+        );
+        assert_eq!(mint_log.minted[&0], first);
+
+        fs::remove_file(path).unwrap();
+    }
 }
@@ -1,32 +1,42 @@
 use anyhow::{anyhow, Result};
-use std::{env, fs::File, path::Path};
+use std::{
+    env,
+    path::{Path, PathBuf},
+};
 
-use crate::config::data::*;
+use crate::config::{data::*, errors::ConfigError};
 
-pub fn parse_solana_config() -> Option<SolanaConfig> {
+/// Environment variable with the path of the solana config file.
+pub const SOLANA_CONFIG_ENV: &str = "SOLANA_CONFIG";
+
+/// Return the path of the solana CLI config file ("~/.config/solana/cli/config.yml").
+pub fn default_solana_config_path() -> PathBuf {
     let home = if cfg!(unix) {
         env::var_os("HOME").expect("Couldn't find UNIX home key.")
     } else if cfg!(windows) {
         let drive = env::var_os("HOMEDRIVE").expect("Couldn't find Windows home drive key.");
         let path = env::var_os("HOMEPATH").expect("Couldn't find Windows home path key.");
-        Path::new(&drive).join(&path).as_os_str().to_owned()
+        Path::new(&drive).join(path).as_os_str().to_owned()
     } else if cfg!(target_os = "macos") {
         env::var_os("HOME").expect("Couldn't find MacOS home key.")
     } else {
         panic!("Unsupported OS!");
     };
 
-    let config_path = Path::new(&home)
+    Path::new(&home)
         .join(".config")
         .join("solana")
         .join("cli")
-        .join("config.yml");
+        .join("config.yml")
+}
 
-    let conf_file = match File::open(config_path) {
-        Ok(f) => f,
-        Err(_) => return None,
-    };
-    serde_yaml::from_reader(&conf_file).ok()
+/// Return the solana config, if there is one.
+pub fn parse_solana_config() -> Result<Option<SolanaConfig>> {
+    match SolanaConfig::load_default() {
+        Ok(sol_config) => Ok(Some(sol_config)),
+        Err(ConfigError::SolanaConfigNotFound(_)) => Ok(None),
+        Err(err) => Err(err.into()),
+    }
 }
 
 pub fn path_to_string(path: &Path) -> Result<String> {
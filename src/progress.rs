@@ -0,0 +1,174 @@
+use console::user_attended_stderr;
+use indicatif::{ProgressBar, ProgressStyle};
+use std::{
+    sync::atomic::{AtomicBool, AtomicU64, Ordering},
+    time::Instant,
+};
+use tracing::info;
+
+/// Percentage step between the progress lines when the progress bar is suppressed.
+const PROGRESS_LOG_STEP: u64 = 10;
+
+/// Set when the log records are written as JSON to stderr.
+static JSON_LOGS: AtomicBool = AtomicBool::new(false);
+
+/// Report the progress of the JSON log records instead of drawing progress bars,
+/// since the bars would be mixed with the records on stderr.
+pub fn set_json_logs(enabled: bool) {
+    JSON_LOGS.store(enabled, Ordering::SeqCst);
+}
+
+/// Progress of a task with a known number of steps (e.g., files to upload or config
+/// line transactions to send).
+pub trait Progress: Send + Sync {
+    /// Advance the progress by `delta` steps.
+    fn inc(&self, delta: u64);
+
+    /// Finish the task successfully.
+    fn finish_with_message(&self, message: String);
+
+    /// Stop the task before all steps were completed.
+    fn abandon_with_message(&self, message: String);
+}
+
+/// Return the progress reporter of a task: a progress bar with the rate and ETA when
+/// running in a terminal, otherwise periodic percentage lines.
+pub fn task_progress(task: &'static str, len: u64) -> Box<dyn Progress> {
+    if user_attended_stderr() && !JSON_LOGS.load(Ordering::SeqCst) {
+        Box::new(BarProgress::new(task, len))
+    } else {
+        Box::new(LogProgress::new(task, len))
+    }
+}
+
+/// Progress bar showing the completed steps, the rate and the ETA.
+pub struct BarProgress {
+    task: &'static str,
+    bar: ProgressBar,
+    start: Instant,
+}
+
+impl BarProgress {
+    pub fn new(task: &'static str, len: u64) -> Self {
+        let bar = ProgressBar::new(len);
+        // forces the progress bar to show immediately
+        bar.tick();
+        bar.enable_steady_tick(1000);
+        bar.set_style(
+            ProgressStyle::default_bar()
+                .template("[{elapsed_precise}] {msg}{wide_bar} {pos}/{len} ({per_sec}, ETA {eta})"),
+        );
+
+        BarProgress {
+            task,
+            bar,
+            start: Instant::now(),
+        }
+    }
+
+    fn log_end(&self, finished: bool) {
+        info!(
+            task = self.task,
+            completed = self.bar.position(),
+            total = self.bar.length(),
+            elapsed_ms = self.start.elapsed().as_millis() as u64,
+            finished,
+            "{} {}",
+            self.task,
+            if finished { "finished" } else { "stopped" }
+        );
+    }
+}
+
+impl Progress for BarProgress {
+    fn inc(&self, delta: u64) {
+        self.bar.inc(delta);
+    }
+
+    fn finish_with_message(&self, message: String) {
+        self.bar.finish_with_message(message);
+        self.log_end(true);
+    }
+
+    fn abandon_with_message(&self, message: String) {
+        self.bar.abandon_with_message(message);
+        self.log_end(false);
+    }
+}
+
+/// Progress reported as a line (and log record) every `PROGRESS_LOG_STEP` percent, for
+/// non-interactive runs.
+pub struct LogProgress {
+    task: &'static str,
+    len: u64,
+    position: AtomicU64,
+    reported: AtomicU64,
+    start: Instant,
+}
+
+impl LogProgress {
+    pub fn new(task: &'static str, len: u64) -> Self {
+        LogProgress {
+            task,
+            len,
+            position: AtomicU64::new(0),
+            reported: AtomicU64::new(0),
+            start: Instant::now(),
+        }
+    }
+
+    fn report(&self, position: u64, message: &str) {
+        let percent = if self.len == 0 {
+            100
+        } else {
+            position * 100 / self.len
+        };
+
+        info!(
+            task = self.task,
+            completed = position,
+            total = self.len,
+            percent,
+            elapsed_ms = self.start.elapsed().as_millis() as u64,
+            "{} {}% ({}/{}) {}",
+            self.task,
+            percent,
+            position,
+            self.len,
+            message
+        );
+
+        // the JSON log records are already written to stderr
+        if !JSON_LOGS.load(Ordering::SeqCst) {
+            eprintln!(
+                "{} {}% ({}/{}) {}",
+                self.task, percent, position, self.len, message
+            );
+        }
+    }
+}
+
+impl Progress for LogProgress {
+    fn inc(&self, delta: u64) {
+        let position = self.position.fetch_add(delta, Ordering::SeqCst) + delta;
+
+        if self.len == 0 {
+            return;
+        }
+
+        let step = position * 100 / self.len / PROGRESS_LOG_STEP;
+
+        // only the first increment reaching a step reports it
+        if step > self.reported.fetch_max(step, Ordering::SeqCst) && position < self.len {
+            self.report(position, "");
+        }
+    }
+
+    fn finish_with_message(&self, message: String) {
+        self.report(self.position.load(Ordering::SeqCst), &message);
+    }
+
+    fn abandon_with_message(&self, message: String) {
+        self.report(self.position.load(Ordering::SeqCst), &message);
+    }
+}
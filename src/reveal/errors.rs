@@ -0,0 +1,21 @@
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum RevealError {
+    #[error("Candy machine {0} does not use hidden settings: its NFTs are minted with their metadata, there is nothing to reveal.")]
+    NotHidden(String),
+
+    #[error("Candy machine {0} is immutable ('isMutable' is false): the metadata of its NFTs cannot be updated.")]
+    ImmutableCandyMachine(String),
+
+    #[error("The hash of the cache file ({0}) does not match the hidden settings hash of the candy machine ({1}): check that you are using the cache file of the hidden drop.")]
+    HashMismatch(String, String),
+
+    #[error(
+        "The keypair {0} is not the update authority of the NFTs minted by candy machine {1}."
+    )]
+    NotUpdateAuthority(String, String),
+
+    #[error("Failed to reveal {0} NFT(s), run the command again to retry.")]
+    RevealFailed(usize),
+}
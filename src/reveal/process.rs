@@ -0,0 +1,235 @@
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use console::style;
+use mpl_token_metadata::{
+    instruction::update_metadata_accounts_v2,
+    state::{DataV2, Metadata},
+    ID as TOKEN_METADATA_ID,
+};
+use std::{collections::HashSet, path::Path, str::FromStr};
+
+use crate::cache::{load_cache, CacheItem};
+use crate::candy_machine::{get_candy_machine_state, get_minted_metadata, ID as CANDY_MACHINE_ID};
+use crate::common::*;
+use crate::config::{parser::get_placeholder_settings, ConfigFormat, HiddenSettings, RetryConfig};
+use crate::reveal::RevealError;
+use crate::utils::*;
+
+/// The maximum number of update instructions per transaction: each instruction carries
+/// the full metadata (name, symbol, uri and creators), so only a couple of them fit in
+/// a transaction.
+const REVEAL_BATCH_SIZE: usize = 2;
+
+pub struct RevealArgs {
+    pub config: String,
+    pub config_format: Option<ConfigFormat>,
+    pub keypair: Option<String>,
+    pub rpc_url: Option<String>,
+    pub cache: String,
+    pub candy_machine: Option<String>,
+    pub priority_fee: Option<u64>,
+}
+
+pub fn process_reveal(args: RevealArgs) -> Result<()> {
+    let sugar_config = sugar_setup(args.keypair, args.rpc_url)?;
+    let client = setup_client(&sugar_config, None)?;
+    let program = client.program(CANDY_MACHINE_ID);
+    let update_authority = program.payer();
+    let priority_fee = args.priority_fee.unwrap_or(0);
+
+    let cache = load_cache(&args.cache, false)?;
+
+    // the candy machine id specified takes precedence over the one from the cache
+
+    let candy_machine_id = match args.candy_machine {
+        Some(candy_machine_id) => candy_machine_id,
+        None => cache.program.candy_machine.clone(),
+    };
+
+    let candy_pubkey = match Pubkey::from_str(&candy_machine_id) {
+        Ok(candy_pubkey) => candy_pubkey,
+        Err(_) => {
+            let error = anyhow!("Failed to parse candy machine id: {}", candy_machine_id);
+            error!("{:?}", error);
+            return Err(error);
+        }
+    };
+
+    println!(
+        "{} {}Loading candy machine",
+        style("[1/3]").bold().dim(),
+        LOOKING_GLASS_EMOJI
+    );
+    println!("{} {}", style("Candy machine ID:").bold(), candy_machine_id);
+
+    let pb = spinner_with_style();
+    pb.set_message("Connecting...");
+
+    let candy_machine_state = get_candy_machine_state(&sugar_config, &candy_pubkey)?;
+
+    if !candy_machine_state.data.is_mutable {
+        return Err(RevealError::ImmutableCandyMachine(candy_machine_id).into());
+    }
+
+    let hidden_name = match candy_machine_state.data.hidden_settings {
+        Some(hidden_settings) => {
+            // the hash committed at deploy prevents revealing the NFTs with a different cache
+            let cache_hash = HiddenSettings::compute_hash(Path::new(&args.cache))?;
+
+            if !HiddenSettings::matches_cache(&hidden_settings.hash, Path::new(&args.cache))? {
+                return Err(RevealError::HashMismatch(
+                    String::from_utf8_lossy(&cache_hash).to_string(),
+                    String::from_utf8_lossy(&hidden_settings.hash).to_string(),
+                )
+                .into());
+            }
+
+            pb.finish_with_message("Cache file matches the hidden settings hash");
+            hidden_settings.name
+        }
+        // the NFTs of a hidden settings uri template are minted from placeholder config
+        // lines, which have no hash
+        None => match get_placeholder_settings(&args.config, args.config_format)? {
+            Some(placeholders) => {
+                pb.finish_with_message("Candy machine uses placeholder config lines");
+                placeholders.onchain_name().to_string()
+            }
+            None => return Err(RevealError::NotHidden(candy_machine_id).into()),
+        },
+    };
+
+    println!(
+        "\n{} {}Looking up minted NFTs",
+        style("[2/3]").bold().dim(),
+        LOOKING_GLASS_EMOJI
+    );
+
+    let pb = spinner_with_style();
+    pb.set_message("Connecting...");
+
+    let minted = get_minted_metadata(&program, &candy_pubkey)?;
+
+    // NFTs already showing the uri of a cache item were revealed by a previous run,
+    // so the command can be resumed after an interruption
+    let revealed_links: HashSet<&str> = cache
+        .items
+        .0
+        .values()
+        .map(|item| item.metadata_link.as_str())
+        .collect();
+
+    let mut pending = Vec::new();
+    let mut revealed = 0;
+
+    for (pubkey, metadata) in minted.iter() {
+        if revealed_links.contains(metadata.data.uri.trim_matches(char::from(0))) {
+            revealed += 1;
+            continue;
+        }
+
+        // the name of a hidden NFT is the hidden settings name followed by the mint
+        // number, which is the index of the cache item plus one
+        let name = metadata.data.name.trim_matches(char::from(0));
+        let item = name
+            .strip_prefix(&hidden_name)
+            .and_then(|number| number.strip_prefix('#'))
+            .and_then(|number| number.parse::<u64>().ok())
+            .and_then(|number| number.checked_sub(1))
+            .and_then(|index| cache.items.0.get(&index.to_string()));
+
+        match item {
+            Some(item) => pending.push((*pubkey, metadata, item)),
+            None => warn!("No cache item found for NFT '{}' ({})", name, pubkey),
+        }
+    }
+
+    pb.finish_with_message(format!("Found {} minted NFT(s)", minted.len()));
+
+    if let Some((_, metadata, _)) = pending.first() {
+        if metadata.update_authority != update_authority {
+            return Err(RevealError::NotUpdateAuthority(
+                update_authority.to_string(),
+                candy_machine_id,
+            )
+            .into());
+        }
+    }
+
+    println!(
+        "\n{} {}Revealing NFTs {}",
+        style("[3/3]").bold().dim(),
+        PAPER_EMOJI,
+        if pending.is_empty() { "(skipping)" } else { "" }
+    );
+
+    let retry_config = RetryConfig::default();
+    let batches: Vec<&[(Pubkey, &Metadata, &CacheItem)]> =
+        pending.chunks(REVEAL_BATCH_SIZE).collect();
+    let pb = progress_bar_with_style(batches.len() as u64);
+
+    let mut updated = 0;
+    let mut failed = 0;
+
+    for batch in batches {
+        let result = with_priority_fee_retry(&retry_config, priority_fee, "Reveal NFTs", |fee| {
+            let mut builder = with_priority_fee(program.request(), fee);
+
+            for (pubkey, metadata, item) in batch {
+                builder = builder.instruction(update_metadata_accounts_v2(
+                    TOKEN_METADATA_ID,
+                    *pubkey,
+                    update_authority,
+                    None,
+                    Some(revealed_data(metadata, item)),
+                    None,
+                    None,
+                ));
+            }
+
+            builder.send_timed(&[])
+        });
+
+        match result {
+            Ok(signature) => {
+                info!(count = batch.len(), signature = %signature, "NFTs revealed");
+                updated += batch.len();
+            }
+            Err(err) => {
+                error!("Failed to reveal metadata batch: {:?}", err);
+                failed += batch.len();
+            }
+        }
+
+        pb.inc(1);
+    }
+
+    pb.finish_and_clear();
+
+    println!(
+        "\n{}",
+        style(format!(
+            "{} NFT(s) revealed, {} already revealed.",
+            updated, revealed
+        ))
+        .bold()
+    );
+
+    if failed > 0 {
+        return Err(RevealError::RevealFailed(failed).into());
+    }
+
+    Ok(())
+}
+
+/// Return the metadata of a minted NFT with the name and uri of its cache item; the
+/// remaining fields are kept unchanged.
+fn revealed_data(metadata: &Metadata, item: &CacheItem) -> DataV2 {
+    DataV2 {
+        name: item.config_name().to_string(),
+        symbol: metadata.data.symbol.trim_matches(char::from(0)).to_string(),
+        uri: item.metadata_link.clone(),
+        seller_fee_basis_points: metadata.data.seller_fee_basis_points,
+        creators: metadata.data.creators.clone(),
+        collection: metadata.collection.clone(),
+        uses: metadata.uses.clone(),
+    }
+}
@@ -0,0 +1,137 @@
+use console::style;
+use lazy_static::lazy_static;
+use std::{
+    collections::BTreeMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+/// Set when the timing of the RPC calls is recorded.
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+lazy_static! {
+    /// Timing of the recorded RPC calls, by call description.
+    static ref CALLS: Mutex<BTreeMap<String, Vec<RpcCall>>> = Mutex::new(BTreeMap::new());
+}
+
+/// Timing of a single RPC call.
+struct RpcCall {
+    duration: Duration,
+    retries: u32,
+}
+
+/// Record the timing of the RPC calls, printing a summary when the command finishes.
+pub fn set_rpc_calls(enabled: bool) {
+    ENABLED.store(enabled, Ordering::SeqCst);
+}
+
+/// Return `true` when the timing of the RPC calls is recorded.
+pub fn rpc_calls_enabled() -> bool {
+    ENABLED.load(Ordering::SeqCst)
+}
+
+/// Record an RPC call that took `duration` (not including the delay between retries)
+/// and was retried `retries` times.
+pub fn record_rpc_call(call: &str, duration: Duration, retries: u32) {
+    if !rpc_calls_enabled() {
+        return;
+    }
+
+    if let Ok(mut calls) = CALLS.lock() {
+        calls
+            .entry(call.to_string())
+            .or_insert_with(Vec::new)
+            .push(RpcCall { duration, retries });
+    }
+}
+
+/// Run an RPC call, recording its timing when enabled.
+pub fn timed<T, F: FnOnce() -> T>(call: &str, operation: F) -> T {
+    if !rpc_calls_enabled() {
+        return operation();
+    }
+
+    let start = Instant::now();
+    let result = operation();
+    record_rpc_call(call, start.elapsed(), 0);
+
+    result
+}
+
+/// Print the number of calls, retries and the p50/p95 durations of each recorded RPC
+/// call to stderr.
+pub fn print_rpc_calls() {
+    if !rpc_calls_enabled() {
+        return;
+    }
+
+    let calls = match CALLS.lock() {
+        Ok(calls) => calls,
+        Err(_) => return,
+    };
+
+    eprintln!("\n{}", style("RPC calls").bold());
+
+    if calls.is_empty() {
+        eprintln!("no RPC calls recorded");
+        return;
+    }
+
+    let width = calls
+        .keys()
+        .map(|call| call.len())
+        .max()
+        .unwrap_or(0)
+        .max(4);
+    let separator = format!(
+        "+-{}-+--------+---------+----------+----------+",
+        "-".repeat(width)
+    );
+
+    eprintln!("{}", separator);
+    eprintln!(
+        "| {:<width$} | {:>6} | {:>7} | {:>8} | {:>8} |",
+        "call",
+        "count",
+        "retries",
+        "p50",
+        "p95",
+        width = width
+    );
+    eprintln!("{}", separator);
+
+    for (call, records) in calls.iter() {
+        let mut durations: Vec<Duration> = records.iter().map(|r| r.duration).collect();
+        durations.sort_unstable();
+        let retries: u32 = records.iter().map(|r| r.retries).sum();
+
+        eprintln!(
+            "| {:<width$} | {:>6} | {:>7} | {:>8} | {:>8} |",
+            call,
+            records.len(),
+            retries,
+            format_duration(percentile(&durations, 50)),
+            format_duration(percentile(&durations, 95)),
+            width = width
+        );
+    }
+
+    eprintln!("{}", separator);
+}
+
+/// Return the nearest-rank percentile of the sorted durations.
+fn percentile(sorted: &[Duration], percent: usize) -> Duration {
+    if sorted.is_empty() {
+        return Duration::ZERO;
+    }
+
+    let rank = (percent * sorted.len() + 99) / 100;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+fn format_duration(duration: Duration) -> String {
+    format!("{}ms", duration.as_millis())
+}
@@ -1,80 +1,715 @@
+use anchor_client::solana_client::{
+    client_error::ClientErrorKind,
+    rpc_client::RpcClient,
+    rpc_request::{RpcError, RpcResponseErrorData},
+};
 use anchor_client::{
     solana_sdk::{
         commitment_config::CommitmentConfig,
-        signature::{keypair::Keypair, read_keypair_file},
+        derivation_path::DerivationPath,
+        signature::{keypair::Keypair, keypair_from_seed_and_derivation_path, Signature, Signer},
+        transaction::Transaction,
+    },
+    Client, ClientError, Cluster, Program, RequestBuilder,
+};
+use anyhow::Result;
+use bip39::{Language, Mnemonic, Seed};
+use console::style;
+use dialoguer::Password;
+use lazy_static::lazy_static;
+use std::{
+    env, fmt, fs,
+    io::ErrorKind,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
     },
-    Client, Cluster,
+    time::Duration,
 };
-use anyhow::{anyhow, Result};
-use tracing::error;
+use tracing::{error, info, warn};
 
-use crate::config::data::SugarConfig;
-use crate::constants::{DEFAULT_KEYPATH, DEFAULT_RPC_DEVNET};
+use crate::config::data::{Commitment, SugarConfig};
+use crate::constants::*;
+use crate::errors::KeypairError;
 use crate::parse::*;
+use crate::rpc_calls::timed;
+use crate::utils::{DEVNET_HASH, MAINNET_HASH, TESTNET_HASH};
+
+lazy_static! {
+    /// Derivation options of the seed phrase keypair, set when `--keypair-seed` is used.
+    static ref KEYPAIR_SEED: Mutex<Option<KeypairSeed>> = Mutex::new(None);
+
+    /// Keypair derived from the seed phrase, so commands that run other commands (e.g.,
+    /// `launch`) only prompt for the seed phrase once.
+    static ref SEED_KEYPAIR: Mutex<Option<Keypair>> = Mutex::new(None);
+
+    /// Commitment of the RPC requests, set when `--commitment` is used.
+    static ref COMMITMENT: Mutex<Option<Commitment>> = Mutex::new(None);
+
+    /// Timeout (in seconds) of the RPC requests, set when `--rpc-timeout` is used.
+    static ref RPC_TIMEOUT: Mutex<Option<u64>> = Mutex::new(None);
+
+    /// Cluster and payer of the anchor client, set by `setup_client`, so the requests of
+    /// the program can be sent with the timeout of `--rpc-timeout`.
+    static ref ANCHOR_CLIENT: Mutex<Option<AnchorClient>> = Mutex::new(None);
+}
+
+/// Set when `--skip-rpc-check` is used.
+static SKIP_RPC_CHECK: AtomicBool = AtomicBool::new(false);
+
+/// Set once the RPC was checked, so commands that run other commands (e.g., `launch`)
+/// only check it once.
+static RPC_CHECKED: AtomicBool = AtomicBool::new(false);
+
+/// Name of the environment variable with the seed phrase, read instead of prompting.
+pub const SEED_PHRASE_ENV: &str = "SUGAR_SEED_PHRASE";
+
+/// Name of the environment variable with the seed phrase passphrase, read instead of
+/// prompting.
+pub const SEED_PASSPHRASE_ENV: &str = "SUGAR_SEED_PASSPHRASE";
+
+#[derive(Clone, Debug)]
+pub struct KeypairSeed {
+    pub derivation_path: Option<String>,
+}
+
+/// Use a keypair derived from a seed phrase instead of a keypair file for the
+/// commands that need a signer.
+pub fn set_keypair_seed(derivation_path: Option<String>) {
+    *KEYPAIR_SEED.lock().unwrap() = Some(KeypairSeed { derivation_path });
+}
+
+/// Use the specified commitment for the RPC requests and transaction confirmations
+/// instead of the one from the solana config.
+pub fn set_commitment(commitment: Option<Commitment>) {
+    *COMMITMENT.lock().unwrap() = commitment;
+}
+
+/// Return the commitment to use: the user-provided one, otherwise the one from the
+/// solana config or confirmed.
+pub fn get_commitment() -> Result<Commitment> {
+    if let Some(commitment) = *COMMITMENT.lock().unwrap() {
+        return Ok(commitment);
+    }
+
+    Ok(match parse_solana_config()? {
+        Some(sol_config) => sol_config.commitment,
+        None => Commitment::default(),
+    })
+}
+
+/// Use the specified timeout (in seconds) for the RPC requests instead of the one from
+/// the config file.
+pub fn set_rpc_timeout(timeout_secs: Option<u64>) {
+    *RPC_TIMEOUT.lock().unwrap() = timeout_secs;
+}
+
+/// Return the timeout of the RPC requests: the user-provided one, otherwise the one
+/// from the config file (`rpc_timeout_secs`) or 30 seconds.
+pub fn get_rpc_timeout(rpc_timeout_secs: Option<u64>) -> Duration {
+    let timeout_secs = RPC_TIMEOUT
+        .lock()
+        .unwrap()
+        .or(rpc_timeout_secs)
+        .unwrap_or(DEFAULT_RPC_TIMEOUT_SECS);
+    Duration::from_secs(timeout_secs)
+}
+
+/// Skip the health check of the RPC when setting up a command.
+pub fn set_skip_rpc_check(skip: bool) {
+    SKIP_RPC_CHECK.store(skip, Ordering::SeqCst);
+}
+
+/// Health of an RPC endpoint, reported by [`check_rpc_health`].
+pub struct RpcHealth {
+    /// Version of the node (solana-core).
+    pub version: String,
+    /// Current slot of the node, if it could be fetched.
+    pub slot: Option<u64>,
+    /// Reason the node reports to be unhealthy (e.g., it is behind the cluster).
+    pub unhealthy: Option<String>,
+}
+
+/// Check the health of an RPC endpoint with `getVersion`, `getSlot` and `getHealth`.
+///
+/// An error is only returned when the endpoint cannot be reached (the version request
+/// fails); a node that is behind the cluster is reported as unhealthy.
+pub fn check_rpc_health(rpc_url: &str) -> Result<RpcHealth> {
+    let rpc_client = setup_rpc_client(rpc_url, None)?;
+
+    let version = timed("Get version", || rpc_client.get_version())?.solana_core;
+    let slot = timed("Get slot", || rpc_client.get_slot()).ok();
+
+    let unhealthy = match timed("Get health", || rpc_client.get_health()) {
+        Ok(()) => None,
+        Err(err) => Some(match err.kind() {
+            ClientErrorKind::RpcError(RpcError::RpcResponseError {
+                data:
+                    RpcResponseErrorData::NodeUnhealthy {
+                        num_slots_behind: Some(num_slots_behind),
+                    },
+                ..
+            }) => format!("node is {} slot(s) behind the cluster", num_slots_behind),
+            _ => err.to_string(),
+        }),
+    };
+
+    Ok(RpcHealth {
+        version,
+        slot,
+        unhealthy,
+    })
+}
 
-pub fn setup_client(sugar_config: &SugarConfig) -> Result<Client> {
+/// Check the RPC once per run (unless `--skip-rpc-check` was used), logging the node
+/// version and slot and warning when the node is unhealthy or cannot be reached. The
+/// command still runs, since the warning is only meant to explain later failures.
+fn startup_rpc_check(rpc_url: &str) {
+    if SKIP_RPC_CHECK.load(Ordering::SeqCst) || RPC_CHECKED.swap(true, Ordering::SeqCst) {
+        return;
+    }
+
+    // the warnings go to stderr, so the JSON output of the commands is not affected
+    match check_rpc_health(rpc_url) {
+        Ok(health) => {
+            info!(
+                rpc_url = %rpc_url,
+                version = %health.version,
+                slot = ?health.slot,
+                "RPC node version {}",
+                health.version
+            );
+
+            if let Some(reason) = health.unhealthy {
+                warn!("RPC {} is unhealthy: {}", rpc_url, reason);
+                eprintln!(
+                    "{} RPC '{}' reports it is unhealthy ({}): requests may fail or return stale data (use --skip-rpc-check to skip this check).\n",
+                    style("WARNING:").yellow().bold(),
+                    rpc_url,
+                    reason
+                );
+            }
+        }
+        Err(err) => {
+            warn!("RPC {} check failed: {}", rpc_url, err);
+            eprintln!(
+                "{} RPC '{}' could not be checked: {}\n",
+                style("WARNING:").yellow().bold(),
+                rpc_url,
+                err
+            );
+        }
+    }
+}
+
+/// Solana cluster shorthand, mapped to a known RPC url.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Network {
+    Localnet,
+    Devnet,
+    Testnet,
+    Mainnet,
+}
+
+impl Network {
+    pub fn rpc_url(&self) -> &'static str {
+        match self {
+            Network::Localnet => RPC_LOCALNET,
+            Network::Devnet => RPC_DEVNET,
+            Network::Testnet => RPC_TESTNET,
+            Network::Mainnet => RPC_MAINNET,
+        }
+    }
+
+    /// Return the network of a genesis hash, if it is one of the public clusters.
+    pub fn from_genesis_hash(genesis_hash: &str) -> Option<Network> {
+        match genesis_hash {
+            DEVNET_HASH => Some(Network::Devnet),
+            TESTNET_HASH => Some(Network::Testnet),
+            MAINNET_HASH => Some(Network::Mainnet),
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for Network {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "localnet" | "localhost" => Ok(Network::Localnet),
+            "devnet" => Ok(Network::Devnet),
+            "testnet" => Ok(Network::Testnet),
+            "mainnet" | "mainnet-beta" => Ok(Network::Mainnet),
+            _ => Err(format!(
+                "Invalid network: {}. Valid networks are: localnet, devnet, testnet, mainnet.",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Network {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Network::Localnet => "localnet",
+            Network::Devnet => "devnet",
+            Network::Testnet => "testnet",
+            Network::Mainnet => "mainnet-beta",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Clone)]
+struct AnchorClient {
+    rpc_url: String,
+    payer: Arc<Keypair>,
+    commitment: CommitmentConfig,
+    timeout: Duration,
+}
+
+impl AnchorClient {
+    fn rpc(&self) -> RpcClient {
+        RpcClient::new_with_timeout_and_commitment(
+            self.rpc_url.clone(),
+            self.timeout,
+            self.commitment,
+        )
+    }
+}
+
+/// Return the anchor client of the command.
+///
+/// anchor-client 0.19 creates a new RPC client with the default timeout for each
+/// `Program::rpc()` and `RequestBuilder::send()`, so the requests of the program go
+/// through [`TimedRpc::timed_rpc`] and [`TimedSend::send_timed`] instead, which use the
+/// timeout of `--rpc-timeout` or, when it is not used, `rpc_timeout_secs` (the timeout
+/// of the config file, if the command has one).
+pub fn setup_client(sugar_config: &SugarConfig, rpc_timeout_secs: Option<u64>) -> Result<Client> {
     let rpc_url = sugar_config.rpc_url.clone();
     let ws_url = rpc_url.replace("http", "ws");
-    let cluster = Cluster::Custom(rpc_url, ws_url);
+    let cluster = Cluster::Custom(rpc_url.clone(), ws_url);
 
     let key_bytes = sugar_config.keypair.to_bytes();
     let payer = Keypair::from_bytes(&key_bytes)?;
 
-    let opts = CommitmentConfig::confirmed();
+    let opts = CommitmentConfig::from(get_commitment()?);
+    let timeout = get_rpc_timeout(rpc_timeout_secs);
+    info!("Using RPC timeout of {}s", timeout.as_secs());
+
+    *ANCHOR_CLIENT.lock().unwrap() = Some(AnchorClient {
+        rpc_url,
+        payer: Arc::new(Keypair::from_bytes(&key_bytes)?),
+        commitment: opts,
+        timeout,
+    });
+
     Ok(Client::new_with_options(cluster, payer, opts))
 }
 
-pub fn sugar_setup(
-    keypair_opt: Option<String>,
+/// Return an RPC client that uses the timeout of `--rpc-timeout` or, when it is not used,
+/// `rpc_timeout_secs` (the timeout of the config file, if the command has one). A timed
+/// out request fails with a retryable error.
+pub fn setup_rpc_client(rpc_url: &str, rpc_timeout_secs: Option<u64>) -> Result<RpcClient> {
+    Ok(RpcClient::new_with_timeout_and_commitment(
+        rpc_url.to_string(),
+        get_rpc_timeout(rpc_timeout_secs),
+        CommitmentConfig::from(get_commitment()?),
+    ))
+}
+
+/// RPC client of a program that uses the timeout of `--rpc-timeout`.
+pub trait TimedRpc {
+    /// Return an RPC client for the cluster of the program, to use instead of
+    /// `Program::rpc()`.
+    fn timed_rpc(&self) -> RpcClient;
+}
+
+impl TimedRpc for Program {
+    fn timed_rpc(&self) -> RpcClient {
+        match ANCHOR_CLIENT.lock().unwrap().as_ref() {
+            Some(client) => client.rpc(),
+            None => self.rpc(),
+        }
+    }
+}
+
+/// Transaction of a request that is sent with the timeout of `--rpc-timeout`.
+pub trait TimedSend<'a> {
+    /// Sign the transaction of the request with the payer of the client and the
+    /// `signers`, then send it, to use instead of `RequestBuilder::send()`.
+    fn send_timed(self, signers: &[&'a dyn Signer]) -> Result<Signature, ClientError>;
+}
+
+impl<'a> TimedSend<'a> for RequestBuilder<'a> {
+    fn send_timed(mut self, signers: &[&'a dyn Signer]) -> Result<Signature, ClientError> {
+        // the lock is not held while the transaction is sent, since the config lines
+        // of a deploy are sent concurrently
+        let client = ANCHOR_CLIENT.lock().unwrap().clone();
+
+        let client = match client {
+            Some(client) => client,
+            None => {
+                for signer in signers {
+                    self = self.signer(*signer);
+                }
+                return self.send();
+            }
+        };
+
+        let instructions = self.instructions()?;
+
+        let mut all_signers: Vec<&dyn Signer> = vec![client.payer.as_ref()];
+        all_signers.extend_from_slice(signers);
+
+        let rpc_client = client.rpc();
+        let recent_blockhash = rpc_client.get_latest_blockhash()?;
+        let tx = Transaction::new_signed_with_payer(
+            &instructions,
+            Some(&client.payer.pubkey()),
+            &all_signers,
+            recent_blockhash,
+        );
+
+        rpc_client
+            .send_and_confirm_transaction(&tx)
+            .map_err(Into::into)
+    }
+}
+
+/// Return the RPC url to use: the user-provided one, then the one of the network,
+/// otherwise the one from the solana config or devnet.
+pub fn get_network_rpc_url(
     rpc_url_opt: Option<String>,
-) -> Result<SugarConfig> {
-    let sol_config_option = parse_solana_config();
+    network_opt: Option<Network>,
+) -> Result<String> {
+    match (rpc_url_opt, network_opt) {
+        (Some(rpc_url), _) => Ok(rpc_url),
+        (None, Some(network)) => Ok(network.rpc_url().to_string()),
+        (None, None) => get_rpc_url(None),
+    }
+}
 
+/// Return the RPC url to use: the user-provided one, otherwise the one from the solana
+/// config or devnet.
+pub fn get_rpc_url(rpc_url_opt: Option<String>) -> Result<String> {
     let rpc_url = match rpc_url_opt {
         Some(rpc_url) => rpc_url,
-        None => match sol_config_option {
-            Some(ref sol_config) => sol_config.json_rpc_url.clone(),
+        None => match parse_solana_config()? {
+            Some(sol_config) => sol_config.json_rpc_url,
             None => String::from(DEFAULT_RPC_DEVNET),
         },
     };
 
-    let keypair = match keypair_opt {
-        Some(keypair_path) => match read_keypair_file(&keypair_path) {
-            Ok(keypair) => keypair,
-            Err(e) => {
-                error!("Failed to read keypair file: {}", e);
-                return Err(anyhow!("Failed to read keypair file: {}", e));
-            }
-        },
+    Ok(rpc_url)
+}
 
-        None => match sol_config_option {
-            Some(ref sol_config) => match read_keypair_file(&sol_config.keypair_path) {
-                Ok(keypair) => keypair,
-                Err(e) => {
-                    error!(
-                        "Failed to read keypair file: {}, {}",
-                        &sol_config.keypair_path, e
-                    );
-                    return Err(anyhow!(
-                        "Failed to read keypair file: {}, {}",
-                        &sol_config.keypair_path,
-                        e
-                    ));
-                }
-            },
-            None => match read_keypair_file(&*shellexpand::tilde(DEFAULT_KEYPATH)) {
-                Ok(keypair) => keypair,
-                Err(e) => {
-                    error!("Failed to read keypair file: {}, {}", DEFAULT_KEYPATH, e);
-                    return Err(anyhow!(
-                        "Failed to read keypair file: {}, {}",
-                        DEFAULT_KEYPATH,
-                        e
-                    ));
-                }
-            },
+/// Load a keypair file, either in the JSON format of the solana CLI (an array with the
+/// secret key bytes) or as a base58 encoded secret key.
+pub fn load_keypair(path: &str) -> Result<Keypair, KeypairError> {
+    let contents = fs::read_to_string(path).map_err(|err| match err.kind() {
+        ErrorKind::NotFound => KeypairError::KeypairFileNotFound(path.to_string()),
+        _ => KeypairError::FailedToReadKeypair(path.to_string(), err.to_string()),
+    })?;
+    let contents = contents.trim();
+
+    let bytes = if contents.starts_with('[') {
+        serde_json::from_str::<Vec<u8>>(contents)
+            .map_err(|err| KeypairError::InvalidKeypair(path.to_string(), err.to_string()))?
+    } else {
+        bs58::decode(contents)
+            .into_vec()
+            .map_err(|err| KeypairError::InvalidKeypair(path.to_string(), err.to_string()))?
+    };
+
+    Keypair::from_bytes(&bytes)
+        .map_err(|err| KeypairError::InvalidKeypair(path.to_string(), err.to_string()))
+}
+
+/// Return the path of the keypair to use: the user-provided one, otherwise the one from
+/// the solana config or the default path.
+pub fn get_keypair_path(keypair_opt: Option<String>) -> Result<String> {
+    let keypair_path = match keypair_opt {
+        Some(keypair_path) => keypair_path,
+        None => match parse_solana_config()? {
+            Some(sol_config) => sol_config.keypair_path,
+            None => shellexpand::tilde(DEFAULT_KEYPATH).to_string(),
         },
     };
 
+    Ok(keypair_path)
+}
+
+/// Derive a keypair from a BIP39 seed phrase and passphrase the same way the solana CLI
+/// does: the derivation path uses the solana CLI `key` format (e.g., "0/0" for
+/// m/44'/501'/0'/0'), and defaults to m/44'/501'.
+pub fn keypair_from_seed_phrase(
+    seed_phrase: &str,
+    passphrase: &str,
+    derivation_path: Option<&str>,
+) -> Result<Keypair, KeypairError> {
+    let derivation_path = derivation_path
+        .map(|path| {
+            DerivationPath::from_key_str(path).map_err(|err| {
+                KeypairError::InvalidDerivationPath(path.to_string(), err.to_string())
+            })
+        })
+        .transpose()?;
+
+    let sanitized = seed_phrase
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ");
+    let mnemonic = Mnemonic::from_phrase(&sanitized, Language::English)
+        .map_err(|err| KeypairError::InvalidSeedPhrase(err.to_string()))?;
+    let seed = Seed::new(&mnemonic, passphrase);
+
+    keypair_from_seed_and_derivation_path(seed.as_bytes(), derivation_path)
+        .map_err(|err| KeypairError::FailedToDeriveKeypair(err.to_string()))
+}
+
+/// Read the seed phrase and its passphrase from the environment or, if not set, prompt
+/// for them without echoing; they are never logged.
+fn read_seed_phrase() -> Result<(String, String)> {
+    let seed_phrase = match env::var(SEED_PHRASE_ENV) {
+        Ok(seed_phrase) => seed_phrase,
+        Err(_) => Password::new().with_prompt("Seed phrase").interact()?,
+    };
+    let passphrase = match env::var(SEED_PASSPHRASE_ENV) {
+        Ok(passphrase) => passphrase,
+        Err(_) => Password::new()
+            .with_prompt("Seed phrase passphrase (press ENTER if there is none)")
+            .allow_empty_password(true)
+            .interact()?,
+    };
+
+    Ok((seed_phrase, passphrase))
+}
+
+/// Return the keypair derived on the first call, deriving it on later calls only if
+/// the first one failed.
+fn derive_once(
+    derived: &Mutex<Option<Keypair>>,
+    derive: impl FnOnce() -> Result<Keypair>,
+) -> Result<Keypair> {
+    let mut derived = derived.lock().unwrap();
+
+    if derived.is_none() {
+        *derived = Some(derive()?);
+    }
+
+    let keypair = derived.as_ref().unwrap();
+    Ok(Keypair::from_bytes(&keypair.to_bytes())?)
+}
+
+/// Return the keypair to use and a description of where it came from: the seed phrase
+/// when `--keypair-seed` is used, otherwise the keypair file.
+pub fn get_keypair(keypair_opt: Option<String>) -> Result<(Keypair, String)> {
+    let keypair_seed = KEYPAIR_SEED.lock().unwrap().clone();
+
+    if let Some(keypair_seed) = keypair_seed {
+        if keypair_opt.is_some() {
+            return Err(KeypairError::ConflictingKeypair.into());
+        }
+
+        let keypair = derive_once(&SEED_KEYPAIR, || {
+            let (seed_phrase, passphrase) = read_seed_phrase()?;
+            Ok(keypair_from_seed_phrase(
+                &seed_phrase,
+                &passphrase,
+                keypair_seed.derivation_path.as_deref(),
+            )?)
+        })?;
+
+        let source = match keypair_seed.derivation_path {
+            Some(path) => format!("seed phrase, key {}", path),
+            None => "seed phrase".to_string(),
+        };
+        return Ok((keypair, source));
+    }
+
+    let keypair_path = get_keypair_path(keypair_opt)?;
+    let keypair = load_keypair(&keypair_path)?;
+
+    Ok((keypair, keypair_path))
+}
+
+pub fn sugar_setup(
+    keypair_opt: Option<String>,
+    rpc_url_opt: Option<String>,
+) -> Result<SugarConfig> {
+    let rpc_url = get_rpc_url(rpc_url_opt)?;
+
+    let (keypair, source) = match get_keypair(keypair_opt) {
+        Ok(keypair) => keypair,
+        Err(err) => {
+            error!("{}", err);
+            return Err(err);
+        }
+    };
+    info!("Using keypair {} ({})", keypair.pubkey(), source);
+    info!("Using commitment {}", get_commitment()?.to_string());
+
+    startup_rpc_check(&rpc_url);
+
     Ok(SugarConfig { rpc_url, keypair })
 }
+
+#[cfg(test)]
+mod tests {
+    use anchor_client::solana_sdk::{instruction::Instruction, pubkey::Pubkey};
+    use anyhow::anyhow;
+    use std::{net::TcpListener, sync::atomic::AtomicUsize, thread, time::Instant};
+
+    use super::*;
+    use crate::config::RetryConfig;
+    use crate::utils::with_retry;
+
+    const TEST_PHRASE: &str = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+
+    fn derived_pubkey(passphrase: &str, derivation_path: Option<&str>) -> String {
+        keypair_from_seed_phrase(TEST_PHRASE, passphrase, derivation_path)
+            .unwrap()
+            .pubkey()
+            .to_string()
+    }
+
+    #[test]
+    fn seed_phrase_keypairs() {
+        // pubkeys of `solana-keygen pubkey prompt://?key=<path>` for the test phrase
+        assert_eq!(
+            derived_pubkey("", None),
+            "D2PPQSYFe83nDzk96FqGumVU8JA7J8vj2Rhjc2oXzEi5"
+        );
+        assert_eq!(
+            derived_pubkey("", Some("0/0")),
+            "HAgk14JpMQLgt6rVgv7cBQFJWFto5Dqxi472uT3DKpqk"
+        );
+        assert_eq!(
+            derived_pubkey("", Some("1/0")),
+            "Hh8QwFUA6MtVu1qAoq12ucvFHNwCcVTV7hpWjeY1Hztb"
+        );
+    }
+
+    #[test]
+    fn seed_phrase_keypairs_with_passphrase() {
+        assert_eq!(
+            derived_pubkey("TREZOR", None),
+            "BwwLFb2UdANxF9FUtE56vVEnyScBYrbijgyJBrBRjMkW"
+        );
+        assert_eq!(
+            derived_pubkey("TREZOR", Some("0/0")),
+            "7zSmbu6gKkb6HB7UDPtHYjwCWuBHU1D4TpNZFm4sndQe"
+        );
+    }
+
+    #[test]
+    fn seed_phrase_whitespace_is_ignored() {
+        let phrase = format!("  {}\n", TEST_PHRASE.replace(' ', "   "));
+        let keypair = keypair_from_seed_phrase(&phrase, "", Some("0/0")).unwrap();
+
+        assert_eq!(
+            keypair.pubkey().to_string(),
+            "HAgk14JpMQLgt6rVgv7cBQFJWFto5Dqxi472uT3DKpqk"
+        );
+    }
+
+    #[test]
+    fn invalid_seed_phrases() {
+        assert!(matches!(
+            keypair_from_seed_phrase("abandon abandon", "", None),
+            Err(KeypairError::InvalidSeedPhrase(_))
+        ));
+        assert!(matches!(
+            keypair_from_seed_phrase(TEST_PHRASE, "", Some("x/y")),
+            Err(KeypairError::InvalidDerivationPath(..))
+        ));
+    }
+
+    #[test]
+    fn seed_keypair_is_derived_once() {
+        let derived = Mutex::new(None);
+        let first = derive_once(&derived, || {
+            Ok(keypair_from_seed_phrase(TEST_PHRASE, "", None)?)
+        })
+        .unwrap();
+
+        let second = derive_once(&derived, || panic!("the keypair was derived twice")).unwrap();
+        assert_eq!(first.pubkey(), second.pubkey());
+    }
+
+    #[test]
+    fn failed_seed_keypair_is_derived_again() {
+        let derived = Mutex::new(None);
+
+        assert!(derive_once(&derived, || Err(anyhow!("no seed phrase"))).is_err());
+        assert!(derive_once(&derived, || {
+            Ok(keypair_from_seed_phrase(TEST_PHRASE, "", None)?)
+        })
+        .is_ok());
+    }
+
+    /// Start a server that accepts connections but never responds, returning its url and
+    /// the number of connections it accepted.
+    fn unresponsive_server() -> (String, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let connections = Arc::new(AtomicUsize::new(0));
+
+        let accepted = connections.clone();
+        thread::spawn(move || {
+            // the streams are kept open, so the requests time out instead of failing
+            let mut streams = Vec::new();
+            for stream in listener.incoming().flatten() {
+                accepted.fetch_add(1, Ordering::SeqCst);
+                streams.push(stream);
+            }
+        });
+
+        (url, connections)
+    }
+
+    #[test]
+    fn timed_out_requests_are_retried() {
+        set_rpc_timeout(Some(1));
+        let (url, connections) = unresponsive_server();
+        let retry_config = RetryConfig {
+            max_retries: 2,
+            base_delay_ms: 1,
+            max_delay_ms: 2,
+        };
+
+        let start = Instant::now();
+        let client = setup_rpc_client(&url, None).unwrap();
+        let result = with_retry(&retry_config, "Get version", || client.get_version());
+
+        assert!(result.is_err());
+        assert_eq!(connections.load(Ordering::SeqCst), 3);
+        // much less than the default timeout of the solana client
+        assert!(start.elapsed() < Duration::from_secs(15));
+    }
+
+    #[test]
+    fn anchor_requests_use_rpc_timeout() {
+        set_rpc_timeout(Some(1));
+        let (url, connections) = unresponsive_server();
+        let sugar_config = SugarConfig {
+            keypair: Keypair::new(),
+            rpc_url: url,
+        };
+        let client = setup_client(&sugar_config, None).unwrap();
+        let program = client.program(Pubkey::new_unique());
+
+        let start = Instant::now();
+        assert!(program.timed_rpc().get_version().is_err());
+
+        let instruction = Instruction::new_with_bytes(program.id(), &[], Vec::new());
+        let result = program.request().instruction(instruction).send_timed(&[]);
+
+        assert!(result.is_err());
+        assert_eq!(connections.load(Ordering::SeqCst), 2);
+        assert!(start.elapsed() < Duration::from_secs(15));
+    }
+}
@@ -1,13 +1,18 @@
-use anchor_client::solana_sdk::{native_token::LAMPORTS_PER_SOL, pubkey::Pubkey};
+use anchor_client::solana_sdk::pubkey::Pubkey;
 use anyhow::Result;
 use chrono::NaiveDateTime;
 use console::style;
 use mpl_candy_machine::{EndSettingType, WhitelistMintMode};
+use serde::Serialize;
 use std::str::FromStr;
 
 use crate::cache::load_cache;
+use crate::candy_machine::ID as CANDY_MACHINE_ID;
 use crate::candy_machine::*;
 use crate::common::*;
+use crate::config::data::{format_sol, timestamp_as_date, ConfigData, SugarConfig, TreasuryConfig};
+use crate::deploy::OutputFormat;
+use crate::setup::setup_client;
 use crate::utils::*;
 
 pub struct ShowArgs {
@@ -15,16 +20,54 @@ pub struct ShowArgs {
     pub rpc_url: Option<String>,
     pub cache: String,
     pub candy_machine: Option<String>,
+    pub as_config: bool,
+    pub output: OutputFormat,
+}
+
+/// Candy machine details, printed when using the JSON output.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CandyMachineSummary {
+    pub candy_machine: String,
+    pub authority: String,
+    pub wallet: String,
+    pub spl_token: Option<String>,
+    pub items_available: u64,
+    pub items_redeemed: u64,
+    /// Price in lamports (or in the base units of the spl token).
+    pub price: u64,
+    pub symbol: String,
+    pub seller_fee_basis_points: u16,
+    pub go_live_date: Option<String>,
+    pub creators: Vec<CreatorSummary>,
+}
+
+/// Creator of the candy machine, with its on-chain verification status.
+#[derive(Debug, Serialize)]
+pub struct CreatorSummary {
+    pub address: String,
+    pub share: u8,
+    pub verified: bool,
 }
 
 pub fn process_show(args: ShowArgs) -> Result<()> {
-    println!(
-        "{} {}Looking up candy machine",
-        style("[1/1]").bold().dim(),
-        LOOKING_GLASS_EMOJI
-    );
+    // the config and JSON outputs are kept free of progress messages so they can be
+    // redirected to a file
+    let quiet = args.as_config || args.output == OutputFormat::Json;
 
-    let pb = spinner_with_style();
+    if !quiet {
+        println!(
+            "{} {}Looking up candy machine",
+            style("[1/1]").bold().dim(),
+            LOOKING_GLASS_EMOJI
+        );
+    }
+
+    let pb = if quiet {
+        ProgressBar::hidden()
+    } else {
+        spinner_with_style()
+    };
     pb.set_message("Connecting...");
 
     // the candy machine id specified takes precedence over the one from the cache
@@ -48,6 +91,42 @@ pub fn process_show(args: ShowArgs) -> Result<()> {
     };
 
     let cndy_state = get_candy_machine_state(&sugar_config, &candy_machine_id)?;
+
+    if args.as_config {
+        let config_data = on_chain_config(&sugar_config, &cndy_state)?;
+        pb.finish_and_clear();
+        println!("{}", serde_json::to_string_pretty(&config_data)?);
+        return Ok(());
+    }
+
+    if args.output == OutputFormat::Json {
+        let summary = CandyMachineSummary {
+            candy_machine: candy_machine_id.to_string(),
+            authority: cndy_state.authority.to_string(),
+            wallet: cndy_state.wallet.to_string(),
+            spl_token: cndy_state.token_mint.map(|mint| mint.to_string()),
+            items_available: cndy_state.data.items_available,
+            items_redeemed: cndy_state.items_redeemed,
+            price: cndy_state.data.price,
+            symbol: cndy_state.data.symbol.clone(),
+            seller_fee_basis_points: cndy_state.data.seller_fee_basis_points,
+            go_live_date: cndy_state.data.go_live_date.map(timestamp_as_date),
+            creators: cndy_state
+                .data
+                .creators
+                .iter()
+                .map(|creator| CreatorSummary {
+                    address: creator.address.to_string(),
+                    share: creator.share,
+                    verified: creator.verified,
+                })
+                .collect(),
+        };
+        pb.finish_and_clear();
+        println!("{}", serde_json::to_string_pretty(&summary)?);
+        return Ok(());
+    }
+
     let cndy_data = cndy_state.data;
 
     pb.finish_and_clear();
@@ -79,11 +158,7 @@ pub fn process_show(args: ShowArgs) -> Result<()> {
     print_with_style(
         "",
         "price",
-        format!(
-            "◎ {} ({})",
-            cndy_data.price as f64 / LAMPORTS_PER_SOL as f64,
-            cndy_data.price
-        ),
+        format!("◎ {} ({})", format_sol(cndy_data.price), cndy_data.price),
     );
     print_with_style("", "symbol", cndy_data.symbol.to_string());
     print_with_style(
@@ -112,15 +187,24 @@ pub fn process_show(args: ShowArgs) -> Result<()> {
         print_with_style("", "go live date", "none".to_string());
     }
     print_with_style("", "creators", "".to_string());
+    println!(
+        " {}",
+        style(format!(
+            ":   {:<3} {:44} {:>5}  verified",
+            "#", "address", "share"
+        ))
+        .dim()
+    );
 
-    for (index, creator) in cndy_data.creators.into_iter().enumerate() {
-        let info = format!(
-            "{} ({}%{})",
-            creator.address,
+    for (index, creator) in cndy_data.creators.iter().enumerate() {
+        println!(
+            " {}   {:<3} {:44} {:>4}%  {}",
+            style(":").dim(),
+            index + 1,
+            creator.address.to_string(),
             creator.share,
-            if creator.verified { ", verified" } else { "" },
+            if creator.verified { "yes" } else { "no" }
         );
-        print_with_style(":   ", &(index + 1).to_string(), info);
     }
 
     // end settings
@@ -177,7 +261,7 @@ pub fn process_show(args: ShowArgs) -> Result<()> {
             ":   ",
             "discount price",
             if let Some(value) = whitelist_settings.discount_price {
-                format!("◎ {} ({})", value as f64 / LAMPORTS_PER_SOL as f64, value)
+                format!("◎ {} ({})", format_sol(value), value)
             } else {
                 "none".to_string()
             },
@@ -206,6 +290,30 @@ pub fn process_show(args: ShowArgs) -> Result<()> {
     Ok(())
 }
 
+/// Create the config of the candy machine, including the treasury settings stored
+/// in the candy machine state.
+fn on_chain_config(sugar_config: &SugarConfig, cndy_state: &CandyMachine) -> Result<ConfigData> {
+    let config_data = if let Some(token_mint) = cndy_state.token_mint {
+        let client = setup_client(sugar_config, None)?;
+        let program = client.program(CANDY_MACHINE_ID);
+        let decimals = get_spl_token_decimals(&program, &token_mint)?;
+
+        let mut config_data =
+            ConfigData::from_candy_machine_data_with_decimals(&cndy_state.data, decimals);
+        config_data.treasury = TreasuryConfig::Spl {
+            token: token_mint,
+            account: Some(cndy_state.wallet),
+        };
+        config_data
+    } else {
+        let mut config_data = ConfigData::from_candy_machine_data(&cndy_state.data);
+        config_data.treasury = TreasuryConfig::Sol(cndy_state.wallet);
+        config_data
+    };
+
+    Ok(config_data)
+}
+
 fn print_with_style(indent: &str, key: &str, value: String) {
     println!(
         " {} {}",
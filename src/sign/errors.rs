@@ -0,0 +1,10 @@
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum SignError {
+    #[error("The keypair {0} is not a creator of the NFTs minted by candy machine {1}.")]
+    NotACreator(String, String),
+
+    #[error("Failed to sign {0} NFT(s), run the command again to retry.")]
+    SignFailed(usize),
+}
@@ -0,0 +1,138 @@
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use console::style;
+use mpl_token_metadata::{instruction::sign_metadata, ID as TOKEN_METADATA_ID};
+use std::str::FromStr;
+
+use crate::cache::load_cache;
+use crate::candy_machine::{get_minted_metadata, ID as CANDY_MACHINE_ID};
+use crate::common::*;
+use crate::config::RetryConfig;
+use crate::sign::SignError;
+use crate::utils::*;
+
+/// The maximum number of sign instructions per transaction.
+const SIGN_BATCH_SIZE: usize = 10;
+
+pub struct SignArgs {
+    pub keypair: Option<String>,
+    pub rpc_url: Option<String>,
+    pub cache: String,
+    pub candy_machine: Option<String>,
+}
+
+pub fn process_sign(args: SignArgs) -> Result<()> {
+    let sugar_config = sugar_setup(args.keypair, args.rpc_url)?;
+    let client = setup_client(&sugar_config, None)?;
+    let program = client.program(CANDY_MACHINE_ID);
+    let creator = program.payer();
+
+    // the candy machine id specified takes precedence over the one from the cache
+
+    let candy_machine_id = match args.candy_machine {
+        Some(candy_machine_id) => candy_machine_id,
+        None => {
+            let cache = load_cache(&args.cache, false)?;
+            cache.program.candy_machine
+        }
+    };
+
+    let candy_pubkey = match Pubkey::from_str(&candy_machine_id) {
+        Ok(candy_pubkey) => candy_pubkey,
+        Err(_) => {
+            let error = anyhow!("Failed to parse candy machine id: {}", candy_machine_id);
+            error!("{:?}", error);
+            return Err(error);
+        }
+    };
+
+    println!(
+        "{} {}Looking up minted NFTs",
+        style("[1/2]").bold().dim(),
+        LOOKING_GLASS_EMOJI
+    );
+    println!("{} {}", style("Candy machine ID:").bold(), candy_machine_id);
+
+    let pb = spinner_with_style();
+    pb.set_message("Connecting...");
+
+    let accounts = get_minted_metadata(&program, &candy_pubkey)?;
+
+    let mut unsigned = Vec::new();
+    let mut verified = 0;
+
+    for (pubkey, metadata) in &accounts {
+        let creators = metadata.data.creators.as_deref().unwrap_or_default();
+
+        match creators.iter().find(|c| c.address == creator) {
+            Some(c) if c.verified => verified += 1,
+            Some(_) => unsigned.push(*pubkey),
+            None => (),
+        }
+    }
+
+    pb.finish_with_message(format!("Found {} minted NFT(s)", accounts.len()));
+
+    if !accounts.is_empty() && unsigned.is_empty() && verified == 0 {
+        return Err(SignError::NotACreator(creator.to_string(), candy_pubkey.to_string()).into());
+    }
+
+    println!(
+        "\n{} {}Signing NFTs {}",
+        style("[2/2]").bold().dim(),
+        PAPER_EMOJI,
+        if unsigned.is_empty() {
+            "(skipping)"
+        } else {
+            ""
+        }
+    );
+
+    let retry_config = RetryConfig::default();
+    let batches: Vec<&[Pubkey]> = unsigned.chunks(SIGN_BATCH_SIZE).collect();
+    let pb = progress_bar_with_style(batches.len() as u64);
+
+    let mut signed = 0;
+    let mut failed = 0;
+
+    for batch in batches {
+        let result = with_retry(&retry_config, "Sign metadata", || {
+            let mut builder = program.request();
+
+            for metadata in batch {
+                builder = builder.instruction(sign_metadata(TOKEN_METADATA_ID, *metadata, creator));
+            }
+
+            builder.send_timed(&[])
+        });
+
+        match result {
+            Ok(signature) => {
+                info!(count = batch.len(), signature = %signature, "NFTs signed");
+                signed += batch.len();
+            }
+            Err(err) => {
+                error!("Failed to sign metadata batch: {:?}", err);
+                failed += batch.len();
+            }
+        }
+
+        pb.inc(1);
+    }
+
+    pb.finish_and_clear();
+
+    println!(
+        "\n{}",
+        style(format!(
+            "{} NFT(s) newly verified, {} already verified.",
+            signed, verified
+        ))
+        .bold()
+    );
+
+    if failed > 0 {
+        return Err(SignError::SignFailed(failed).into());
+    }
+
+    Ok(())
+}
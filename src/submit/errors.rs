@@ -0,0 +1,21 @@
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum SubmitError {
+    #[error("No transaction files found in '{0}'.")]
+    NoTransactionFiles(String),
+
+    #[error("Invalid transaction file '{0}': {1}")]
+    InvalidTransactionFile(String, String),
+
+    #[error(
+        "Transaction of '{0}' is not signed by {1}: sign it and replace its 'transaction' value."
+    )]
+    MissingSignatures(String, String),
+
+    #[error("Transaction of '{0}' has an invalid signature: it was modified after being signed.")]
+    InvalidSignatures(String),
+
+    #[error("Failed to submit the transaction of '{0}': {1}")]
+    SendFailed(String, String),
+}
@@ -0,0 +1,204 @@
+use anchor_client::solana_sdk::transaction::Transaction;
+use console::style;
+use std::fs;
+
+use crate::cache::{load_cache, CacheProgram};
+use crate::common::*;
+use crate::config::RetryConfig;
+use crate::deploy::{missing_signers, DeployTransaction, CANDY_MACHINE_KEYPAIR_FILE};
+use crate::setup::get_rpc_url;
+use crate::submit::SubmitError;
+use crate::utils::with_retry;
+
+pub struct SubmitArgs {
+    pub files: Vec<String>,
+    pub rpc_url: Option<String>,
+    pub cache: String,
+}
+
+/// Broadcast the signed transactions of an unsigned deploy, in the order of the files.
+///
+/// All transactions are checked to be fully signed before sending any of them. The
+/// candy machine is recorded in the cache once the transaction creating it is confirmed,
+/// and the config lines of each confirmed transaction are marked as on-chain, so a new
+/// deploy (or submit) skips them.
+pub fn process_submit(args: SubmitArgs) -> Result<()> {
+    println!(
+        "{} {}Loading transactions",
+        style("[1/2]").bold().dim(),
+        LOOKING_GLASS_EMOJI
+    );
+
+    let mut transactions = Vec::new();
+
+    for path in transaction_files(&args.files)? {
+        let (deploy_transaction, transaction) = load_transaction(&path)?;
+        transactions.push((path, deploy_transaction, transaction));
+    }
+
+    println!("Found {} signed transaction(s)", transactions.len());
+
+    println!(
+        "\n{} {}Sending transactions",
+        style("[2/2]").bold().dim(),
+        COMPUTER_EMOJI
+    );
+
+    let rpc_client = setup_rpc_client(&get_rpc_url(args.rpc_url)?, None)?;
+    // the cache is only updated when it is the cache of the candy machine
+    let mut cache = if Path::new(&args.cache).exists() {
+        Some(load_cache(&args.cache, false)?)
+    } else {
+        None
+    };
+
+    let retry_config = RetryConfig::default();
+    let pb = task_progress("submit", transactions.len() as u64);
+    let mut skipped = 0;
+
+    for (path, deploy_transaction, transaction) in transactions {
+        let on_chain = |cache: &Cache, index: &u32| {
+            cache.program.candy_machine == deploy_transaction.candy_machine
+                && cache
+                    .items
+                    .0
+                    .get(&index.to_string())
+                    .map_or(false, |item| item.on_chain)
+        };
+
+        // a submit can be re-run after a failure without resending the config lines
+        // that are already on-chain
+        if let Some(cache) = &cache {
+            if !deploy_transaction.config_lines.is_empty()
+                && deploy_transaction
+                    .config_lines
+                    .iter()
+                    .all(|index| on_chain(cache, index))
+            {
+                skipped += 1;
+                pb.inc(1);
+                continue;
+            }
+        }
+
+        let signature = match with_retry(&retry_config, "Submit transaction", || {
+            rpc_client.send_and_confirm_transaction(&transaction)
+        }) {
+            Ok(signature) => signature,
+            Err(err) => {
+                pb.abandon_with_message(format!("{}", style("Submit failed ").red().bold()));
+                return Err(SubmitError::SendFailed(path, err.to_string()).into());
+            }
+        };
+
+        info!(
+            path = %path,
+            signature = %signature,
+            "{} transaction submitted",
+            deploy_transaction.description
+        );
+
+        if let Some(cache) = &mut cache {
+            // the candy machine of an unsigned deploy is recorded once it is created
+            if let Some(checkpoint) = &deploy_transaction.checkpoint {
+                if cache.program.candy_machine.is_empty() {
+                    let candy_pubkey = Pubkey::from_str(&deploy_transaction.candy_machine)
+                        .map_err(|_| {
+                            CacheError::InvalidCandyMachineAddress(
+                                deploy_transaction.candy_machine.clone(),
+                            )
+                        })?;
+                    cache.program = CacheProgram::new_from_cm(&candy_pubkey);
+                    cache.checkpoint = Some(checkpoint.clone());
+                    cache.sync_file()?;
+                }
+            }
+
+            if cache.program.candy_machine == deploy_transaction.candy_machine
+                && !deploy_transaction.config_lines.is_empty()
+            {
+                for index in &deploy_transaction.config_lines {
+                    if let Some(item) = cache.items.0.get_mut(&index.to_string()) {
+                        item.on_chain = true;
+                    }
+                }
+                cache.update_checkpoint();
+                cache.sync_file()?;
+            }
+        }
+
+        pb.inc(1);
+    }
+
+    pb.finish_with_message(format!("{}", style("Submit successful ").green().bold()));
+
+    if skipped > 0 {
+        println!(
+            "{} transaction(s) skipped (config lines already on-chain)",
+            skipped
+        );
+    }
+
+    Ok(())
+}
+
+/// Return the transaction files to submit: the files specified and the JSON files of
+/// the directories specified (except the candy machine keypair), sorted by name.
+fn transaction_files(paths: &[String]) -> Result<Vec<String>> {
+    let mut files = Vec::new();
+
+    for path in paths {
+        if Path::new(path).is_dir() {
+            let mut entries = fs::read_dir(path)?
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|entry| entry.extension().map_or(false, |ext| ext == "json"))
+                .filter(|entry| {
+                    entry
+                        .file_name()
+                        .map_or(true, |name| name != CANDY_MACHINE_KEYPAIR_FILE)
+                })
+                .map(|entry| path_to_string(&entry))
+                .collect::<Result<Vec<String>>>()?;
+
+            if entries.is_empty() {
+                return Err(SubmitError::NoTransactionFiles(path.to_string()).into());
+            }
+
+            entries.sort();
+            files.extend(entries);
+        } else {
+            files.push(path.to_string());
+        }
+    }
+
+    Ok(files)
+}
+
+/// Load a transaction file, checking that its transaction is signed by all signers.
+fn load_transaction(path: &str) -> Result<(DeployTransaction, Transaction)> {
+    let invalid = |err: String| SubmitError::InvalidTransactionFile(path.to_string(), err);
+
+    let content = fs::read_to_string(path).map_err(|err| invalid(err.to_string()))?;
+    let deploy_transaction: DeployTransaction =
+        serde_json::from_str(&content).map_err(|err| invalid(err.to_string()))?;
+    let transaction = deploy_transaction
+        .decode_transaction()
+        .map_err(|err| invalid(err.to_string()))?;
+
+    let missing = missing_signers(&transaction);
+
+    if !missing.is_empty() {
+        let signers = missing
+            .iter()
+            .map(|signer| signer.to_string())
+            .collect::<Vec<String>>()
+            .join(", ");
+        return Err(SubmitError::MissingSignatures(path.to_string(), signers).into());
+    }
+
+    if transaction.verify().is_err() {
+        return Err(SubmitError::InvalidSignatures(path.to_string()).into());
+    }
+
+    Ok((deploy_transaction, transaction))
+}
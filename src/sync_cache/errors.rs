@@ -0,0 +1,10 @@
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum SyncCacheError {
+    #[error("Cache file '{0}' already exists: use --force to replace it with the cache rebuilt from the candy machine.")]
+    CacheExists(String),
+
+    #[error("Failed to get candy machine account data from Solana for address: {0}.")]
+    FailedToGetAccountData(String),
+}
@@ -0,0 +1,257 @@
+use anchor_lang::AccountDeserialize;
+use console::style;
+
+use mpl_candy_machine::CandyMachine;
+
+use crate::cache::*;
+use crate::candy_machine::get_config_line;
+use crate::common::*;
+use crate::config::data::parse_pubkey;
+use crate::rpc_calls::timed;
+use crate::sync_cache::SyncCacheError;
+use crate::utils::*;
+
+pub struct SyncCacheArgs {
+    pub keypair: Option<String>,
+    pub rpc_url: Option<String>,
+    pub cache: String,
+    pub candy_machine: String,
+    pub force: bool,
+}
+
+pub fn process_sync_cache(args: SyncCacheArgs) -> Result<()> {
+    // an existing cache might have information that cannot be recovered from the
+    // candy machine (e.g., the media links), so it is only replaced on request
+    if Path::new(&args.cache).exists() && !args.force {
+        return Err(SyncCacheError::CacheExists(args.cache).into());
+    }
+
+    let candy_machine_id = parse_pubkey("candy machine", &args.candy_machine)?;
+    let sugar_config = sugar_setup(args.keypair, args.rpc_url)?;
+
+    println!(
+        "{} {}Loading candy machine",
+        style("[1/2]").bold().dim(),
+        CANDY_EMOJI
+    );
+
+    let pb = spinner_with_style();
+    pb.set_message("Connecting...");
+
+    let rpc_client = setup_rpc_client(&sugar_config.rpc_url, None)?;
+
+    let data = match timed("Get candy machine account", || {
+        rpc_client.get_account_data(&candy_machine_id)
+    }) {
+        Ok(data) => data,
+        Err(err) => {
+            pb.finish_and_clear();
+            return Err(SyncCacheError::FailedToGetAccountData(err.to_string()).into());
+        }
+    };
+    let candy_machine: CandyMachine = CandyMachine::try_deserialize(&mut data.as_slice())?;
+
+    pb.finish_with_message("Completed");
+
+    println!(
+        "\n{} {}Rebuilding cache",
+        style("[2/2]").bold().dim(),
+        PAPER_EMOJI
+    );
+
+    let mut cache = Cache::new();
+    cache.program = CacheProgram::new_from_cm(&candy_machine_id);
+    cache.file_path = args.cache.clone();
+
+    let items_available = candy_machine.data.items_available;
+    let missing = if candy_machine.data.hidden_settings.is_some() {
+        println!("Candy machine uses hidden settings: there are no config lines to recover.");
+        Vec::new()
+    } else {
+        let (items, missing) = recover_items(&data, items_available);
+        cache.items = items;
+        missing
+    };
+
+    cache.sync_file()?;
+
+    info!(
+        candy_machine = %candy_machine_id,
+        items = cache.items.0.len(),
+        missing = missing.len(),
+        "Cache rebuilt from candy machine"
+    );
+
+    println!(
+        "Recovered {} of {} config line(s) from candy machine {}",
+        cache.items.0.len(),
+        items_available,
+        candy_machine_id
+    );
+
+    if !missing.is_empty() {
+        println!(
+            "{} {} config line(s) were not added to the candy machine and are missing from the cache.",
+            style("WARNING:").yellow().bold(),
+            missing.len()
+        );
+    }
+
+    println!(
+        "{} the media links and the hashes of the asset files are not stored on-chain and were left blank: \
+        'upload' treats the items as changed and uploads the assets again.",
+        style("NOTE:").bold()
+    );
+    println!("\nCache saved to '{}'.", args.cache);
+
+    Ok(())
+}
+
+/// Recover the cache items from the config lines of the candy machine account data,
+/// returning the items and the indices of the config lines that were not added.
+fn recover_items(data: &[u8], items_available: u64) -> (CacheItems, Vec<usize>) {
+    let mut items = CacheItems::new();
+    let mut missing = Vec::new();
+
+    for index in 0..items_available as usize {
+        match get_config_line(data, items_available, index) {
+            Some(config_line) => {
+                // the media and local file information is not stored in the
+                // candy machine, only the name and uri of the config line
+                items.0.insert(
+                    index.to_string(),
+                    CacheItem {
+                        name: config_line.name,
+                        media_hash: String::new(),
+                        media_link: String::new(),
+                        metadata_hash: String::new(),
+                        metadata_link: config_line.uri,
+                        on_chain: true,
+                        name_override: None,
+                        symbol_override: None,
+                        metadata_encoding: None,
+                    },
+                );
+            }
+            None => missing.push(index),
+        }
+    }
+
+    (items, missing)
+}
+
+#[cfg(test)]
+mod tests {
+    use anchor_lang::AccountSerialize;
+    use mpl_candy_machine::{CandyMachineData, ConfigLine};
+
+    use super::*;
+    use crate::candy_machine::get_candy_machine_account_size;
+    use crate::constants::{
+        CONFIG_ARRAY_START, CONFIG_LINE_SIZE, CONFIG_NAME_OFFSET, CONFIG_URI_OFFSET,
+        STRING_LEN_SIZE,
+    };
+
+    /// Serialize a candy machine account with the config lines of the specified
+    /// indices added, laid out as the program stores them.
+    fn account_fixture(items_available: u64, lines: &[(usize, ConfigLine)]) -> Vec<u8> {
+        let candy_machine = CandyMachine {
+            authority: Pubkey::new_unique(),
+            wallet: Pubkey::new_unique(),
+            token_mint: None,
+            items_redeemed: 0,
+            data: CandyMachineData {
+                uuid: "ABCDEF".to_string(),
+                price: 1_000_000_000,
+                symbol: "TEST".to_string(),
+                seller_fee_basis_points: 500,
+                max_supply: 0,
+                is_mutable: true,
+                retain_authority: true,
+                go_live_date: None,
+                end_settings: None,
+                creators: Vec::new(),
+                hidden_settings: None,
+                whitelist_mint_settings: None,
+                items_available,
+                gatekeeper: None,
+            },
+        };
+
+        let mut data = vec![0u8; get_candy_machine_account_size(items_available)];
+        let mut header = Vec::new();
+        candy_machine.try_serialize(&mut header).unwrap();
+        data[..header.len()].copy_from_slice(&header);
+
+        let write_string = |data: &mut Vec<u8>, start: usize, value: &str| {
+            data[start..start + STRING_LEN_SIZE]
+                .copy_from_slice(&(value.len() as u32).to_le_bytes());
+            data[start + STRING_LEN_SIZE..start + STRING_LEN_SIZE + value.len()]
+                .copy_from_slice(value.as_bytes());
+        };
+
+        data[CONFIG_ARRAY_START..CONFIG_ARRAY_START + STRING_LEN_SIZE]
+            .copy_from_slice(&(lines.len() as u32).to_le_bytes());
+        let bit_mask_start =
+            CONFIG_ARRAY_START + STRING_LEN_SIZE + items_available as usize * CONFIG_LINE_SIZE + 4;
+
+        for (index, line) in lines {
+            let line_start = CONFIG_ARRAY_START + STRING_LEN_SIZE + CONFIG_LINE_SIZE * index;
+            write_string(
+                &mut data,
+                line_start + CONFIG_NAME_OFFSET - STRING_LEN_SIZE,
+                &line.name,
+            );
+            write_string(
+                &mut data,
+                line_start + CONFIG_URI_OFFSET - STRING_LEN_SIZE,
+                &line.uri,
+            );
+            data[bit_mask_start + index / 8] |= 1 << (7 - index % 8);
+        }
+
+        data
+    }
+
+    fn config_line(index: usize) -> ConfigLine {
+        ConfigLine {
+            name: format!("Item #{}", index),
+            uri: format!("https://example.com/{}.json", index),
+        }
+    }
+
+    #[test]
+    fn recover_items_from_account() {
+        let lines: Vec<(usize, ConfigLine)> = [0, 1, 2, 4, 9]
+            .iter()
+            .map(|index| (*index, config_line(*index)))
+            .collect();
+        let data = account_fixture(10, &lines);
+
+        let candy_machine = CandyMachine::try_deserialize(&mut data.as_slice()).unwrap();
+        assert_eq!(candy_machine.data.items_available, 10);
+
+        let (items, missing) = recover_items(&data, candy_machine.data.items_available);
+        assert_eq!(missing, vec![3, 5, 6, 7, 8]);
+        assert_eq!(items.0.len(), 5);
+
+        for (index, line) in &lines {
+            let item = &items.0[&index.to_string()];
+            assert_eq!(item.name, line.name);
+            assert_eq!(item.metadata_link, line.uri);
+            assert!(item.on_chain);
+            // config lines do not store the media information
+            assert!(item.media_link.is_empty());
+            assert!(item.media_hash.is_empty());
+        }
+    }
+
+    #[test]
+    fn recover_items_of_empty_account() {
+        let data = account_fixture(3, &[]);
+        let (items, missing) = recover_items(&data, 3);
+
+        assert!(items.0.is_empty());
+        assert_eq!(missing, vec![0, 1, 2]);
+    }
+}
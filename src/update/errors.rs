@@ -0,0 +1,10 @@
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum UpdateError {
+    #[error("Candy machine {0} is immutable ('isMutable' is false): its settings cannot be changed, a new candy machine must be deployed instead.")]
+    ImmutableCandyMachine(String),
+
+    #[error("Missing 'schedule' in the config file: it is required to apply the scheduled price.")]
+    MissingSchedule,
+}
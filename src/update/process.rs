@@ -1,9 +1,10 @@
 use anchor_client::solana_sdk::pubkey::Pubkey;
 use anchor_lang::prelude::AccountMeta;
 use anyhow::Result;
+use chrono::Utc;
 use console::style;
-use spl_associated_token_account::get_associated_token_address;
-use std::str::FromStr;
+use serde_json::Value;
+use std::{collections::BTreeMap, str::FromStr};
 
 use mpl_candy_machine::instruction as nft_instruction;
 use mpl_candy_machine::{accounts as nft_accounts, CandyMachineData};
@@ -12,7 +13,11 @@ use crate::candy_machine::get_candy_machine_state;
 use crate::candy_machine::ID as CANDY_MACHINE_ID;
 use crate::common::*;
 use crate::config::{data::*, parser::get_config_data};
-use crate::utils::{check_spl_token, check_spl_token_account, spinner_with_style};
+use crate::update::UpdateError;
+use crate::utils::{
+    check_spl_token, check_treasury_token_account, get_spl_token_decimals, spinner_with_style,
+    with_priority_fee, with_priority_fee_retry,
+};
 use crate::{cache::load_cache, config::data::ConfigData};
 
 pub struct UpdateArgs {
@@ -21,13 +26,16 @@ pub struct UpdateArgs {
     pub cache: String,
     pub new_authority: Option<String>,
     pub config: String,
+    pub config_format: Option<ConfigFormat>,
     pub candy_machine: Option<String>,
+    pub apply_schedule: bool,
+    pub priority_fee: Option<u64>,
 }
 
 pub fn process_update(args: UpdateArgs) -> Result<()> {
     let sugar_config = sugar_setup(args.keypair, args.rpc_url)?;
-    let client = setup_client(&sugar_config)?;
-    let config_data = get_config_data(&args.config)?;
+    let config_data = get_config_data(&args.config, args.config_format)?;
+    let client = setup_client(&sugar_config, config_data.rpc_timeout_secs)?;
 
     // the candy machine id specified takes precedence over the one from the cache
 
@@ -58,8 +66,38 @@ pub fn process_update(args: UpdateArgs) -> Result<()> {
     let pb = spinner_with_style();
     pb.set_message("Connecting...");
 
+    let program = client.program(CANDY_MACHINE_ID);
     let candy_machine_state = get_candy_machine_state(&sugar_config, &candy_pubkey)?;
-    let candy_machine_data = create_candy_machine_data(&config_data, candy_machine_state.data)?;
+
+    if !candy_machine_state.data.is_mutable {
+        pb.finish_and_clear();
+        return Err(UpdateError::ImmutableCandyMachine(candy_pubkey.to_string()).into());
+    }
+
+    // the price is specified in the spl-token units when a spl-token is used
+    let decimals = match config_data.treasury.spl_token() {
+        Some(spl_token) => get_spl_token_decimals(&program, &spl_token)?,
+        None => SOL_DECIMALS,
+    };
+
+    // the price of the active schedule entry replaces the price of the config
+    let candy_config = if args.apply_schedule {
+        let schedule_dates = get_schedule_dates(&args.cache, &config_data)?;
+        let price = config_data
+            .scheduled_price(Utc::now().timestamp(), &schedule_dates)?
+            .ok_or(UpdateError::MissingSchedule)?;
+        info!("Applying scheduled price: {}", price);
+
+        ConfigData {
+            price,
+            ..config_data.clone()
+        }
+    } else {
+        config_data.clone()
+    };
+
+    let candy_machine_data =
+        create_candy_machine_data(&candy_config, candy_machine_state.data.clone(), decimals)?;
 
     pb.finish_with_message("Done");
 
@@ -71,94 +109,90 @@ pub fn process_update(args: UpdateArgs) -> Result<()> {
 
     let mut remaining_accounts: Vec<AccountMeta> = Vec::new();
 
-    if config_data.spl_token.is_some() {
-        if let Some(token) = config_data.spl_token {
-            remaining_accounts.push(AccountMeta {
-                pubkey: token,
-                is_signer: false,
-                is_writable: false,
-            })
-        }
+    if let Some(token) = config_data.treasury.spl_token() {
+        remaining_accounts.push(AccountMeta {
+            pubkey: token,
+            is_signer: false,
+            is_writable: false,
+        })
     }
 
-    let program = client.program(CANDY_MACHINE_ID);
-
-    let treasury_account = match config_data.spl_token {
-        Some(spl_token) => {
-            let spl_token_account_figured = if config_data.spl_token_account.is_some() {
-                config_data.spl_token_account
-            } else {
-                Some(get_associated_token_address(&program.payer(), &spl_token))
-            };
-
-            if config_data.sol_treasury_account.is_some() {
-                return Err(anyhow!("If spl-token-account or spl-token is set then sol-treasury-account cannot be set"));
-            }
-
+    let treasury_account = match config_data.treasury.resolve(&program.payer()) {
+        Treasury::Sol(wallet) => wallet,
+        Treasury::Spl { token, account } => {
             // validates the mint address of the token accepted as payment
-            check_spl_token(&program, &spl_token.to_string())?;
-
-            if let Some(token_account) = spl_token_account_figured {
-                // validates the spl token wallet to receive proceedings from SPL token payments
-                check_spl_token_account(&program, &token_account.to_string())?;
-                token_account
-            } else {
-                return Err(anyhow!(
-                    "If spl-token is set, spl-token-account must also be set"
-                ));
-            }
+            check_spl_token(&program, &token.to_string())?;
+            // validates the spl token wallet to receive proceedings from SPL token payments
+            check_treasury_token_account(
+                &setup_rpc_client(&sugar_config.rpc_url, config_data.rpc_timeout_secs)?,
+                &account,
+                &token,
+            )?;
+            account
         }
-        None => match config_data.sol_treasury_account {
-            Some(sol_treasury_account) => sol_treasury_account,
-            None => sugar_config.keypair.pubkey(),
-        },
     };
 
-    let mut builder = program
-        .request()
-        .accounts(nft_accounts::UpdateCandyMachine {
-            candy_machine: candy_pubkey,
-            authority: program.payer(),
-            wallet: treasury_account,
-        })
-        .args(nft_instruction::UpdateCandyMachine {
-            data: candy_machine_data,
-        });
+    let mut changes = data_changes(&candy_machine_state.data, &candy_machine_data, decimals)?;
 
-    if !remaining_accounts.is_empty() {
-        for account in remaining_accounts {
-            builder = builder.accounts(account);
-        }
+    if candy_machine_state.wallet != treasury_account {
+        changes.push((
+            "wallet".to_string(),
+            Value::String(candy_machine_state.wallet.to_string()),
+            Value::String(treasury_account.to_string()),
+        ));
     }
 
-    let pb = spinner_with_style();
-    pb.set_message("Sending update transaction...");
+    if candy_machine_state.token_mint != config_data.treasury.spl_token() {
+        let to_value = |mint: Option<Pubkey>| {
+            mint.map(|m| Value::String(m.to_string()))
+                .unwrap_or(Value::Null)
+        };
+        changes.push((
+            "splToken".to_string(),
+            to_value(candy_machine_state.token_mint),
+            to_value(config_data.treasury.spl_token()),
+        ));
+    }
 
-    let update_signature = builder.send()?;
+    print_changes(&changes);
+
+    let retry_config = config_data.retry_config.unwrap_or_default();
+    let priority_fee = args.priority_fee.or(config_data.priority_fee).unwrap_or(0);
+
+    if changes.is_empty() {
+        println!("The candy machine is up to date, skipping the update transaction.");
+    } else {
+        send_update(
+            &program,
+            &retry_config,
+            priority_fee,
+            &candy_pubkey,
+            &treasury_account,
+            &candy_machine_data,
+            &remaining_accounts,
+        )?;
+    }
 
-    pb.finish_with_message(format!(
-        "{} {}",
-        style("Update signature:").bold(),
-        update_signature
-    ));
+    refresh_checkpoint(&args.cache, &candy_pubkey, &config_data)?;
 
     if let Some(new_authority) = args.new_authority {
         let pb = spinner_with_style();
         pb.set_message("Sending update authority transaction...");
 
         let new_authority_pubkey = Pubkey::from_str(&new_authority)?;
-        let builder = program
-            .request()
-            .accounts(nft_accounts::UpdateCandyMachine {
-                candy_machine: candy_pubkey,
-                authority: program.payer(),
-                wallet: treasury_account,
-            })
-            .args(nft_instruction::UpdateAuthority {
-                new_authority: Some(new_authority_pubkey),
-            });
-
-        let authority_signature = builder.send()?;
+        let authority_signature =
+            with_priority_fee_retry(&retry_config, priority_fee, "Update authority", |fee| {
+                with_priority_fee(program.request(), fee)
+                    .accounts(nft_accounts::UpdateCandyMachine {
+                        candy_machine: candy_pubkey,
+                        authority: program.payer(),
+                        wallet: treasury_account,
+                    })
+                    .args(nft_instruction::UpdateAuthority {
+                        new_authority: Some(new_authority_pubkey),
+                    })
+                    .send_timed(&[])
+            })?;
         pb.finish_with_message(format!(
             "{} {}",
             style("Authority signature:").bold(),
@@ -169,33 +203,165 @@ pub fn process_update(args: UpdateArgs) -> Result<()> {
     Ok(())
 }
 
+/// Return the timestamps of the relative start dates of the schedule: the dates are
+/// resolved once and saved in the cache, so the schedule does not move between runs.
+fn get_schedule_dates(cache_path: &str, config: &ConfigData) -> Result<BTreeMap<String, i64>> {
+    if !Path::new(cache_path).exists() {
+        let mut schedule_dates = BTreeMap::new();
+        config.resolve_schedule_dates(&mut schedule_dates)?;
+        return Ok(schedule_dates);
+    }
+
+    let mut cache = load_cache(cache_path, false)?;
+
+    if config.resolve_schedule_dates(&mut cache.schedule_dates)? {
+        cache.sync_file()?;
+    }
+
+    Ok(cache.schedule_dates)
+}
+
+/// Update the config hash of the deploy checkpoint, so a deploy can still resume
+/// after the candy machine was updated with the current config, and the content hash
+/// of the config, so the update is not reported as a config change.
+fn refresh_checkpoint(cache_path: &str, candy_pubkey: &Pubkey, config: &ConfigData) -> Result<()> {
+    if !Path::new(cache_path).exists() {
+        return Ok(());
+    }
+
+    let mut cache = load_cache(cache_path, false)?;
+
+    if cache.program.candy_machine != candy_pubkey.to_string() {
+        return Ok(());
+    }
+
+    if let Some(checkpoint) = &mut cache.checkpoint {
+        checkpoint.config_hash = config.deploy_hash()?;
+    }
+
+    if cache.config_hash.is_some() {
+        cache.set_config_hash(&config.content_hash()?);
+    }
+
+    cache.sync_file()?;
+
+    Ok(())
+}
+
+fn send_update(
+    program: &Program,
+    retry_config: &RetryConfig,
+    priority_fee: u64,
+    candy_pubkey: &Pubkey,
+    treasury_account: &Pubkey,
+    candy_machine_data: &CandyMachineData,
+    remaining_accounts: &[AccountMeta],
+) -> Result<()> {
+    let pb = spinner_with_style();
+    pb.set_message("Sending update transaction...");
+
+    let update_signature =
+        with_priority_fee_retry(retry_config, priority_fee, "Update candy machine", |fee| {
+            let mut builder = with_priority_fee(program.request(), fee)
+                .accounts(nft_accounts::UpdateCandyMachine {
+                    candy_machine: *candy_pubkey,
+                    authority: program.payer(),
+                    wallet: *treasury_account,
+                })
+                .args(nft_instruction::UpdateCandyMachine {
+                    data: candy_machine_data.clone(),
+                });
+
+            for account in remaining_accounts {
+                builder = builder.accounts(account.clone());
+            }
+
+            builder.send_timed(&[])
+        })?;
+
+    pb.finish_with_message(format!(
+        "{} {}",
+        style("Update signature:").bold(),
+        update_signature
+    ));
+
+    Ok(())
+}
+
+/// Return the settings that differ between the on-chain and the new candy machine
+/// data as `(key, current value, new value)`, using the config file keys and values.
+fn data_changes(
+    current: &CandyMachineData,
+    new: &CandyMachineData,
+    decimals: u8,
+) -> Result<Vec<(String, Value, Value)>> {
+    let current = serde_json::to_value(ConfigData::from_candy_machine_data_with_decimals(
+        current, decimals,
+    ))?;
+    let new = serde_json::to_value(ConfigData::from_candy_machine_data_with_decimals(
+        new, decimals,
+    ))?;
+
+    let mut changes = Vec::new();
+
+    if let (Value::Object(current), Value::Object(new)) = (current, new) {
+        for (key, value) in new {
+            let current_value = current.get(&key).cloned().unwrap_or(Value::Null);
+
+            if current_value != value {
+                changes.push((key, current_value, value));
+            }
+        }
+    }
+
+    Ok(changes)
+}
+
+/// Print the list of changes in a diff-like format.
+fn print_changes(changes: &[(String, Value, Value)]) {
+    println!("\nChanges from the on-chain configuration:");
+
+    if changes.is_empty() {
+        println!("  (none)");
+    }
+
+    for (key, current, new) in changes {
+        println!("{}", style(format!("- {}: {}", key, current)).red());
+        println!("{}", style(format!("+ {}: {}", key, new)).green());
+    }
+
+    println!();
+}
+
 fn create_candy_machine_data(
     config: &ConfigData,
     candy_machine: CandyMachineData,
+    decimals: u8,
 ) -> Result<CandyMachineData> {
+    config.check_price_precision(decimals)?;
+    let price = price_as_token_amount(config.price, decimals);
     info!("{:?}", config.go_live_date);
-    let go_live_date = Some(go_live_date_as_timestamp(&config.go_live_date)?);
+    let go_live_date = go_live_date_as_timestamp(&config.go_live_date)?;
 
     let end_settings = &config.end_settings.as_ref().map(|s| s.into_candy_format());
 
     let whitelist_mint_settings = &config
         .whitelist_mint_settings
         .as_ref()
-        .map(|s| s.into_candy_format());
+        .map(|s| s.into_candy_format_with_decimals(decimals));
 
     let hidden_settings = &config
-        .hidden_settings
-        .as_ref()
+        .onchain_hidden_settings()
         .map(|s| s.into_candy_format());
 
     let gatekeeper = config.gatekeeper.as_ref().map(|g| g.into_candy_format());
 
     let data = CandyMachineData {
         uuid: candy_machine.uuid,
-        price: price_as_lamports(config.price),
+        price,
         symbol: candy_machine.symbol,
         seller_fee_basis_points: candy_machine.seller_fee_basis_points,
-        max_supply: 0,
+        max_supply: config.max_supply.unwrap_or(0),
         is_mutable: config.is_mutable,
         retain_authority: config.retain_authority,
         go_live_date,
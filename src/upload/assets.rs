@@ -1,16 +1,18 @@
 use bundlr_sdk::{tags::Tag, Bundlr, SolanaSigner};
 use data_encoding::HEXLOWER;
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
 use glob::glob;
 use regex::Regex;
 use ring::digest::{Context, SHA256};
 use serde_json;
 use std::{
     fs::{self, File, OpenOptions},
-    io::{BufReader, Read},
+    io::{BufReader, Read, Write},
     sync::Arc,
 };
 
 use crate::common::*;
+use crate::config::ContentEncoding;
 use crate::upload::errors::*;
 use crate::validate::format::Metadata;
 
@@ -46,6 +48,9 @@ impl AssetPair {
             metadata_hash: self.metadata_hash,
             metadata_link: String::new(),
             on_chain: false,
+            name_override: None,
+            symbol_override: None,
+            metadata_encoding: None,
         }
     }
 }
@@ -170,7 +175,38 @@ fn encode(file: &str) -> Result<String> {
     Ok(HEXLOWER.encode(context.finish().as_ref()))
 }
 
-pub fn get_updated_metadata(metadata_file: &str, media_link: &str) -> Result<String> {
+/// Compress the content of a file with the specified encoding.
+pub fn encode_content(data: &[u8], encoding: ContentEncoding) -> Result<Vec<u8>> {
+    match encoding {
+        ContentEncoding::Gzip => {
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+            encoder.write_all(data)?;
+            Ok(encoder.finish()?)
+        }
+    }
+}
+
+/// Decompress the content of a file compressed with the specified encoding.
+pub fn decode_content(data: &[u8], encoding: ContentEncoding) -> Result<Vec<u8>> {
+    match encoding {
+        ContentEncoding::Gzip => {
+            let mut content = Vec::new();
+            GzDecoder::new(data).read_to_end(&mut content)?;
+            Ok(content)
+        }
+    }
+}
+
+/// Return the content of the metadata file with the link of its media file, the symbol
+/// override of the item (if any), and the default `animation_url` and `external_url` when
+/// the file does not have them.
+pub fn get_updated_metadata(
+    metadata_file: &str,
+    media_link: &str,
+    symbol: &Option<String>,
+    animation_url: &Option<String>,
+    external_url: &Option<String>,
+) -> Result<String> {
     let mut metadata: Metadata = {
         let m = OpenOptions::new()
             .read(true)
@@ -189,5 +225,17 @@ pub fn get_updated_metadata(metadata_file: &str, media_link: &str) -> Result<Str
 
     metadata.image = media_link.to_string();
 
+    if let Some(symbol) = symbol {
+        metadata.symbol = symbol.clone();
+    }
+
+    if metadata.animation_url.is_none() {
+        metadata.animation_url = animation_url.clone();
+    }
+
+    if metadata.external_url.is_none() {
+        metadata.external_url = external_url.clone();
+    }
+
     Ok(serde_json::to_string(&metadata).unwrap())
 }
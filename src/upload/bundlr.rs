@@ -3,22 +3,13 @@ use async_trait::async_trait;
 use bundlr_sdk::{tags::Tag, Bundlr, SolanaSigner};
 use clap::crate_version;
 use console::style;
-use futures::future::select_all;
-use std::{
-    cmp,
-    collections::HashSet,
-    ffi::OsStr,
-    fs,
-    path::Path,
-    sync::{
-        atomic::{AtomicBool, Ordering},
-        Arc,
-    },
-};
+use std::{cmp, sync::Arc};
 use tokio::time::{sleep, Duration};
 
 use crate::candy_machine::ID as CANDY_MACHINE_ID;
-use crate::{common::*, config::*, constants::PARALLEL_LIMIT, upload::*, utils::*};
+use crate::{
+    common::*, config::*, constants::PARALLEL_LIMIT, rpc_calls::timed, upload::*, utils::*,
+};
 
 /// The number os retries to fetch the Bundlr balance (MAX_RETRY * DELAY_UNTIL_RETRY ms limit)
 const MAX_RETRY: u64 = 120;
@@ -32,21 +23,13 @@ const HEADER_SIZE: u64 = 2000;
 /// Minimum file size for cost calculation
 const MINIMUM_SIZE: u64 = 10000;
 
-/// Size of the mock media URI for cost calculation
-const MOCK_URI_SIZE: usize = 100;
-
-struct TxInfo {
-    asset_id: String,
-    file_path: String,
-    media_link: String,
-    data_type: DataType,
-    tag: Vec<Tag>,
-}
-
 pub struct BundlrHandler {
     client: Arc<Bundlr<SolanaSigner>>,
     pubkey: Pubkey,
     node: String,
+    parallel_limit: usize,
+    /// Timeout of the RPC requests from the config file.
+    rpc_timeout_secs: Option<u64>,
 }
 
 impl BundlrHandler {
@@ -55,9 +38,10 @@ impl BundlrHandler {
         config_data: &ConfigData,
         sugar_config: &SugarConfig,
     ) -> Result<BundlrHandler> {
-        let client = setup_client(sugar_config)?;
-        let program = client.program(CANDY_MACHINE_ID);
-        let solana_cluster: Cluster = get_cluster(program.rpc())?;
+        let solana_cluster: Cluster = get_cluster(setup_rpc_client(
+            &sugar_config.rpc_url,
+            config_data.rpc_timeout_secs,
+        )?)?;
 
         let bundlr_node = match config_data.upload_method {
             UploadMethod::Bundlr => match solana_cluster {
@@ -92,6 +76,8 @@ impl BundlrHandler {
             client: Arc::new(bundlr_client),
             pubkey: bundlr_pubkey,
             node: bundlr_node.to_string(),
+            parallel_limit: config_data.upload_parallel_limit(PARALLEL_LIMIT),
+            rpc_timeout_secs: config_data.rpc_timeout_secs,
         })
     }
 
@@ -122,7 +108,9 @@ impl BundlrHandler {
         amount: u64,
     ) -> Result<Response> {
         let ix = system_instruction::transfer(&payer.pubkey(), bundlr_address, amount);
-        let recent_blockhash = program.rpc().get_latest_blockhash()?;
+        let recent_blockhash = timed("Get latest blockhash", || {
+            program.timed_rpc().get_latest_blockhash()
+        })?;
         let payer_pubkey = payer.pubkey();
 
         let tx = Transaction::new_signed_with_payer(
@@ -134,18 +122,15 @@ impl BundlrHandler {
 
         println!("Funding address:");
         println!("  -> pubkey: {}", payer_pubkey);
-        println!(
-            "  -> lamports: {} (◎ {})",
-            amount,
-            amount as f64 / LAMPORTS_PER_SOL as f64
-        );
+        println!("  -> lamports: {} (◎ {})", amount, format_sol(amount));
 
-        let sig = program
-            .rpc()
-            .send_and_confirm_transaction_with_spinner_and_commitment(
+        let sig = timed("Fund bundlr", || {
+            let rpc_client = program.timed_rpc();
+            rpc_client.send_and_confirm_transaction_with_spinner_and_commitment(
                 &tx,
-                CommitmentConfig::confirmed(),
-            )?;
+                rpc_client.commitment(),
+            )
+        })?;
 
         println!("{} {sig}", style("Signature:").bold());
 
@@ -177,6 +162,12 @@ impl BundlrHandler {
             .expect("Failed to parse bundlr balance."))
     }
 
+    /// Return the size charged by Bundlr for the upload of a file: each file pays for
+    /// the transaction header and at least the minimum size.
+    pub fn upload_size(file_size: u64) -> u64 {
+        HEADER_SIZE + cmp::max(MINIMUM_SIZE, file_size)
+    }
+
     /// Return the Bundlr fee for upload based on the data size.
     pub async fn get_bundlr_fee(
         http_client: &HttpClient,
@@ -192,85 +183,74 @@ impl BundlrHandler {
             .parse::<u64>()?;
         Ok(required_amount)
     }
+}
 
-    /// Send a transaction to Bundlr and wait for a response.
-    async fn send_bundlr_tx(
-        bundlr_client: Arc<Bundlr<SolanaSigner>>,
-        tx_info: TxInfo,
-    ) -> Result<(String, String)> {
-        let data = match tx_info.data_type {
-            DataType::Media => fs::read(&tx_info.file_path)?,
-            DataType::Metadata => {
-                // replaces the media link without modifying the original file to avoid
-                // changing the hash of the metadata file
-                get_updated_metadata(&tx_info.file_path, &tx_info.media_link)?.into_bytes()
-            }
-        };
-
-        let tx = bundlr_client.create_transaction_with_tags(data, tx_info.tag);
-        let response = bundlr_client.send_transaction(tx).await?;
-        let id = response
-            .get("id")
-            .expect("Failed to convert transaction id to string.")
-            .as_str()
-            .expect("Failed to get an id from bundlr transaction.");
+#[async_trait]
+impl Uploader for BundlrHandler {
+    fn name(&self) -> &str {
+        "Bundlr"
+    }
 
-        Ok((tx_info.asset_id, id.to_string()))
+    fn parallel_limit(&self) -> usize {
+        self.parallel_limit
     }
-}
 
-#[async_trait]
-impl UploadHandler for BundlrHandler {
-    /// Funds Bundlr account for the upload.
-    async fn prepare(
+    /// Estimates the Bundlr fee (in lamports) of the files to upload.
+    async fn estimate_cost(
         &self,
         sugar_config: &SugarConfig,
         assets: &HashMap<usize, AssetPair>,
         media_indices: &[usize],
         metadata_indices: &[usize],
-    ) -> Result<()> {
-        // calculates the size of the files to upload
-        let mut total_size = 0;
-
-        for index in media_indices {
-            let item = assets.get(index).unwrap();
-            let path = Path::new(&item.media);
-            total_size += HEADER_SIZE + cmp::max(MINIMUM_SIZE, std::fs::metadata(path)?.len());
-        }
-
-        let mock_uri = "x".repeat(MOCK_URI_SIZE);
-
-        for index in metadata_indices {
-            let item = assets.get(index).unwrap();
-
-            total_size += HEADER_SIZE
-                + cmp::max(
-                    MINIMUM_SIZE,
-                    get_updated_metadata(&item.metadata, &mock_uri)
-                        .expect("Failed to get updated metadata.")
-                        .into_bytes()
-                        .len() as u64,
-                );
-        }
+    ) -> Result<CostEstimate> {
+        let size = upload_sizes(assets, media_indices, metadata_indices)?
+            .into_iter()
+            .map(BundlrHandler::upload_size)
+            .sum();
 
-        info!("Total upload size: {}", total_size);
+        info!("Total upload size: {}", size);
 
         let http_client = reqwest::Client::new();
-
-        let lamports_fee =
-            BundlrHandler::get_bundlr_fee(&http_client, &self.node, total_size).await?;
+        let lamports = BundlrHandler::get_bundlr_fee(&http_client, &self.node, size).await?;
         let address = sugar_config.keypair.pubkey().to_string();
-        let mut balance =
-            BundlrHandler::get_bundlr_balance(&http_client, &address, &self.node).await?;
+        let balance = BundlrHandler::get_bundlr_balance(&http_client, &address, &self.node).await?;
+
+        Ok(CostEstimate::Lamports {
+            size,
+            lamports,
+            balance,
+        })
+    }
+
+    /// Funds Bundlr account for the upload.
+    async fn prepare(
+        &self,
+        sugar_config: &SugarConfig,
+        assets: &HashMap<usize, AssetPair>,
+        media_indices: &[usize],
+        metadata_indices: &[usize],
+    ) -> Result<()> {
+        let (lamports_fee, mut balance) = match self
+            .estimate_cost(sugar_config, assets, media_indices, metadata_indices)
+            .await?
+        {
+            CostEstimate::Lamports {
+                lamports, balance, ..
+            } => (lamports, balance),
+            _ => unreachable!("Bundlr cost is estimated in lamports"),
+        };
 
         info!(
             "Bundlr balance {} lamports, require {} lamports",
             balance, lamports_fee
         );
 
+        let http_client = reqwest::Client::new();
+        let address = sugar_config.keypair.pubkey().to_string();
+
         // funds the bundlr wallet for media upload
 
-        let client = setup_client(sugar_config)?;
+        let client = setup_client(sugar_config, self.rpc_timeout_secs)?;
         let program = client.program(CANDY_MACHINE_ID);
 
         if lamports_fee > balance {
@@ -319,166 +299,105 @@ impl UploadHandler for BundlrHandler {
         Ok(())
     }
 
-    /// Upload the data to Bundlr.
-    async fn upload_data(
-        &self,
-        _sugar_config: &SugarConfig,
-        assets: &HashMap<usize, AssetPair>,
-        cache: &mut Cache,
-        indices: &[usize],
-        data_type: DataType,
-        interrupted: Arc<AtomicBool>,
-    ) -> Result<Vec<UploadError>> {
-        let mut extension = HashSet::with_capacity(1);
-        let mut paths = Vec::new();
-
-        for index in indices {
-            let item = match assets.get(index) {
-                Some(asset_index) => asset_index,
-                None => return Err(anyhow::anyhow!("Failed to get asset at index {}", index)),
-            };
-            // chooses the file path based on the data type
-            let file_path = match data_type {
-                DataType::Media => item.media.clone(),
-                DataType::Metadata => item.metadata.clone(),
-            };
-
-            let path = Path::new(&file_path);
-            let ext = path
-                .extension()
-                .and_then(OsStr::to_str)
-                .expect("Failed to convert extension from unicode");
-            extension.insert(String::from(ext));
-
-            paths.push(file_path);
-        }
-
-        // validates that all files have the same extension
-        let extension = if extension.len() == 1 {
-            extension.iter().next().unwrap()
-        } else {
-            return Err(anyhow!("Invalid file extension: {:?}", extension));
-        };
-
-        let sugar_tag = Tag::new("App-Name".into(), format!("Sugar {}", crate_version!()));
-
-        let media_tag = match data_type {
-            DataType::Media => Tag::new("Content-Type".into(), format!("image/{extension}")),
-            DataType::Metadata => Tag::new("Content-Type".into(), "application/json".to_string()),
-        };
-
-        // upload data to bundlr
-
-        println!("\nSending data: (Ctrl+C to abort)");
-
-        let pb = progress_bar_with_style(paths.len() as u64);
-        let mut transactions = Vec::new();
-
-        for file_path in paths {
-            // path to the media/metadata file
-            let path = Path::new(&file_path);
-
-            // id of the asset (to be used to update the cache link)
-            let asset_id = String::from(
-                path.file_stem()
-                    .and_then(OsStr::to_str)
-                    .expect("Failed to convert path to unicode."),
-            );
+    /// Send a transaction to Bundlr and wait for a response.
+    async fn upload_asset(&self, asset: AssetInfo) -> Result<UploadedAsset> {
+        let data = asset.content()?;
 
-            let cache_item = match cache.items.0.get(&asset_id) {
-                Some(item) => item,
-                None => return Err(anyhow!("Failed to get config item at index {}", asset_id)),
-            };
+        let mut tags = vec![
+            Tag::new("App-Name".into(), format!("Sugar {}", crate_version!())),
+            Tag::new("Content-Type".into(), asset.content_type),
+        ];
 
-            transactions.push(TxInfo {
-                asset_id: asset_id.to_string(),
-                file_path: String::from(path.to_str().expect("Failed to parse path from unicode.")),
-                media_link: cache_item.media_link.clone(),
-                data_type: data_type.clone(),
-                tag: vec![sugar_tag.clone(), media_tag.clone()],
-            });
+        if let Some(encoding) = asset.content_encoding {
+            tags.push(Tag::new("Content-Encoding".into(), encoding.to_string()));
         }
 
-        let mut handles = Vec::new();
-
-        for tx in transactions.drain(0..cmp::min(transactions.len(), PARALLEL_LIMIT)) {
-            let bundlr_client = self.client.clone();
-            handles.push(tokio::spawn(async move {
-                BundlrHandler::send_bundlr_tx(bundlr_client, tx).await
-            }));
-        }
+        let tx = self.client.create_transaction_with_tags(data, tags);
+        let response = self.client.send_transaction(tx).await?;
+        let id = response
+            .get("id")
+            .expect("Failed to convert transaction id to string.")
+            .as_str()
+            .expect("Failed to get an id from bundlr transaction.");
 
-        let mut errors = Vec::new();
-
-        while !interrupted.load(Ordering::SeqCst) && !handles.is_empty() {
-            match select_all(handles).await {
-                (Ok(res), _index, remaining) => {
-                    // independently if the upload was successful or not
-                    // we continue to try the remaining ones
-                    handles = remaining;
-
-                    if res.is_ok() {
-                        let val = res?;
-                        let link = format!("https://arweave.net/{}", val.clone().1);
-                        // cache item to update
-                        let item = cache.items.0.get_mut(&val.0).unwrap();
-
-                        match data_type {
-                            DataType::Media => item.media_link = link,
-                            DataType::Metadata => item.metadata_link = link,
-                        }
-                        // updates the progress bar
-                        pb.inc(1);
-                    } else {
-                        // user will need to retry the upload
-                        errors.push(UploadError::SendDataFailed(format!(
-                            "Bundlr upload error: {:?}",
-                            res.err().unwrap()
-                        )));
-                    }
-                }
-                (Err(err), _index, remaining) => {
-                    errors.push(UploadError::SendDataFailed(format!(
-                        "Bundlr upload error: {:?}",
-                        err
-                    )));
-                    // ignoring all errors
-                    handles = remaining;
-                }
-            }
+        Ok(UploadedAsset::new(format!("https://arweave.net/{}", id)))
+    }
+}
 
-            if !transactions.is_empty() {
-                // if we are half way through, let spawn more transactions
-                if (PARALLEL_LIMIT - handles.len()) > (PARALLEL_LIMIT / 2) {
-                    // syncs cache (checkpoint)
-                    cache.sync_file()?;
-
-                    for tx in
-                        transactions.drain(0..cmp::min(transactions.len(), PARALLEL_LIMIT / 2))
-                    {
-                        let bundlr_client = self.client.clone();
-                        handles.push(tokio::spawn(async move {
-                            BundlrHandler::send_bundlr_tx(bundlr_client, tx).await
-                        }));
-                    }
-                }
-            }
-        }
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    use super::*;
+
+    /// Price per byte of the mock Bundlr node.
+    const LAMPORTS_PER_BYTE: u64 = 3;
+
+    /// Start a Bundlr node that prices one upload at `LAMPORTS_PER_BYTE`, returning its
+    /// url.
+    fn mock_node() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request_line = String::new();
+            BufReader::new(&stream)
+                .read_line(&mut request_line)
+                .unwrap();
+
+            // GET /price/solana/<size> HTTP/1.1
+            let path = request_line.split_whitespace().nth(1).unwrap();
+            let size = path
+                .strip_prefix("/price/solana/")
+                .unwrap()
+                .parse::<u64>()
+                .unwrap();
+            let price = (size * LAMPORTS_PER_BYTE).to_string();
+
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                price.len(),
+                price
+            )
+            .unwrap();
+        });
 
-        if !errors.is_empty() {
-            pb.abandon_with_message(format!("{}", style("Upload failed ").red().bold()));
-        } else if !transactions.is_empty() {
-            pb.abandon_with_message(format!("{}", style("Upload aborted ").red().bold()));
-            return Err(
-                UploadError::SendDataFailed("Not all files were uploaded.".to_string()).into(),
-            );
-        } else {
-            pb.finish_with_message(format!("{}", style("Upload successful ").green().bold()));
-        }
+        url
+    }
 
-        // makes sure the cache file is updated
-        cache.sync_file()?;
+    #[test]
+    fn upload_size_of_file() {
+        // small files are charged the minimum size
+        assert_eq!(BundlrHandler::upload_size(0), HEADER_SIZE + MINIMUM_SIZE);
+        assert_eq!(BundlrHandler::upload_size(512), HEADER_SIZE + MINIMUM_SIZE);
+        assert_eq!(
+            BundlrHandler::upload_size(MINIMUM_SIZE),
+            HEADER_SIZE + MINIMUM_SIZE
+        );
+        // larger files are charged per byte, plus the header
+        assert_eq!(
+            BundlrHandler::upload_size(MINIMUM_SIZE + 1),
+            HEADER_SIZE + MINIMUM_SIZE + 1
+        );
+        assert_eq!(BundlrHandler::upload_size(1_000_000), 1_002_000);
+    }
 
-        Ok(errors)
+    #[tokio::test]
+    async fn upload_fee_of_files() {
+        let node = mock_node();
+        let size: u64 = [100, 20_000, 1_000_000]
+            .into_iter()
+            .map(BundlrHandler::upload_size)
+            .sum();
+        assert_eq!(size, 12_000 + 22_000 + 1_002_000);
+
+        let fee = BundlrHandler::get_bundlr_fee(&reqwest::Client::new(), &node, size)
+            .await
+            .unwrap();
+        assert_eq!(fee, size * LAMPORTS_PER_BYTE);
     }
 }
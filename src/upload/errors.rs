@@ -30,4 +30,23 @@ pub enum UploadError {
         "Mismatch value for \"{0}\" property in file \"{1}\": expected \"{2}\", found \"{3}\""
     )]
     MismatchValue(String, String, String, String),
+
+    #[error("Symbol override '{1}' of cache item {0} is longer than {2} characters")]
+    SymbolOverrideTooLong(String, String, usize),
+
+    #[error("Not enough space in the Shadow Drive storage account {0}: {1} bytes required, {2} bytes available")]
+    NotEnoughStorage(String, u64, u64),
+
+    #[error(
+        "Found {0} problem(s) in the assets, fix them before uploading or use --skip-validation"
+    )]
+    InvalidAssets(usize),
+
+    #[error(
+        "The upload requires funding {0}: use --yes to confirm when not running interactively"
+    )]
+    FundingNotConfirmed(String),
+
+    #[error("Upload aborted")]
+    Aborted,
 }
@@ -0,0 +1,95 @@
+use async_trait::async_trait;
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Mutex,
+};
+use tokio::time::{sleep, Duration};
+
+use crate::{common::*, upload::*};
+
+/// Uploader used in tests: it records the uploads without sending the files anywhere,
+/// returning `mock://<asset id>` links.
+pub struct MockUploader {
+    parallel_limit: usize,
+    /// Time each upload takes, by asset id.
+    delays: HashMap<String, Duration>,
+    /// Asset ids whose upload fails.
+    failures: HashSet<String>,
+    /// Asset ids of the uploads, in the order they were started.
+    uploads: Mutex<Vec<String>>,
+    /// Content of the uploaded metadata files (as it would be sent to the storage), by asset id.
+    contents: Mutex<HashMap<String, Vec<u8>>>,
+}
+
+impl MockUploader {
+    pub fn new(parallel_limit: usize) -> Self {
+        MockUploader {
+            parallel_limit,
+            delays: HashMap::new(),
+            failures: HashSet::new(),
+            uploads: Mutex::new(Vec::new()),
+            contents: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Make the upload of an asset take the specified time.
+    pub fn with_delay(mut self, asset_id: &str, delay: Duration) -> Self {
+        self.delays.insert(asset_id.to_string(), delay);
+        self
+    }
+
+    /// Make the upload of an asset fail.
+    pub fn with_failure(mut self, asset_id: &str) -> Self {
+        self.failures.insert(asset_id.to_string());
+        self
+    }
+
+    /// Return the link of an uploaded asset.
+    pub fn link(asset_id: &str) -> String {
+        format!("mock://{}", asset_id)
+    }
+
+    /// Return the asset ids of the uploads, in the order they were started.
+    pub fn uploads(&self) -> Vec<String> {
+        self.uploads.lock().unwrap().clone()
+    }
+
+    /// Return the content of an uploaded metadata file.
+    pub fn content(&self, asset_id: &str) -> Option<Vec<u8>> {
+        self.contents.lock().unwrap().get(asset_id).cloned()
+    }
+}
+
+#[async_trait]
+impl Uploader for MockUploader {
+    fn name(&self) -> &str {
+        "Mock"
+    }
+
+    fn parallel_limit(&self) -> usize {
+        self.parallel_limit
+    }
+
+    async fn upload_asset(&self, asset: AssetInfo) -> Result<UploadedAsset> {
+        self.uploads.lock().unwrap().push(asset.asset_id.clone());
+
+        if let Some(delay) = self.delays.get(&asset.asset_id) {
+            sleep(*delay).await;
+        }
+
+        if self.failures.contains(&asset.asset_id) {
+            return Err(anyhow!("Mock upload of asset {} failed", asset.asset_id));
+        }
+
+        // only the metadata files are read, since their content is generated on upload
+        if let DataType::Metadata = asset.data_type {
+            let data = asset.content()?;
+            self.contents
+                .lock()
+                .unwrap()
+                .insert(asset.asset_id.clone(), data);
+        }
+
+        Ok(UploadedAsset::new(MockUploader::link(&asset.asset_id)))
+    }
+}
@@ -2,10 +2,22 @@ pub mod assets;
 pub mod aws;
 pub mod bundlr;
 pub mod errors;
+pub mod mock;
+pub mod nft_storage;
+pub mod pinata;
 pub mod process;
+pub mod rate_limiter;
+pub mod shdw;
+pub mod uploader;
 
 pub use assets::*;
 pub use aws::*;
 pub use bundlr::*;
 pub use errors::*;
+pub use mock::*;
+pub use nft_storage::*;
+pub use pinata::*;
 pub use process::*;
+pub use rate_limiter::*;
+pub use shdw::*;
+pub use uploader::*;
@@ -0,0 +1,129 @@
+use async_trait::async_trait;
+use reqwest::{header, StatusCode};
+use std::{env, sync::Arc};
+use tokio::time::{sleep, Duration};
+
+use crate::{common::*, config::*, upload::*};
+
+/// Environment variable holding the nft.storage API token.
+pub const NFT_STORAGE_TOKEN_ENV: &str = "NFT_STORAGE_TOKEN";
+
+/// nft.storage upload endpoint.
+const NFT_STORAGE_API_URL: &str = "https://api.nft.storage/upload";
+
+/// Maximum number of concurrent uploads (nft.storage rate limits requests).
+const NFT_STORAGE_PARALLEL_LIMIT: usize = 10;
+
+/// The number of retries when the upload is rate limited or fails with a server error.
+const MAX_RETRY: u32 = 5;
+
+/// Time (ms) to wait until the first retry (doubled on each subsequent retry).
+const DELAY_UNTIL_RETRY: u64 = 1000;
+
+pub struct NftStorageHandler {
+    client: Arc<HttpClient>,
+    rate_limiter: Arc<RateLimiter>,
+    parallel_limit: usize,
+}
+
+impl NftStorageHandler {
+    /// Initialize a new NftStorageHandler.
+    pub async fn initialize(config_data: &ConfigData) -> Result<NftStorageHandler> {
+        let token = match &config_data.nft_storage_auth_token {
+            Some(token) => token.to_string(),
+            None => env::var(NFT_STORAGE_TOKEN_ENV).map_err(|_| {
+                anyhow!(
+                    "Missing 'nftStorageAuthToken' value in config file or '{}' environment variable.",
+                    NFT_STORAGE_TOKEN_ENV
+                )
+            })?,
+        };
+
+        let mut headers = header::HeaderMap::new();
+        let mut auth_value = header::HeaderValue::from_str(&format!("Bearer {}", token))?;
+        auth_value.set_sensitive(true);
+        headers.insert(header::AUTHORIZATION, auth_value);
+
+        let parallel_limit = config_data.upload_parallel_limit(NFT_STORAGE_PARALLEL_LIMIT);
+        let client = http_client_builder(config_data, parallel_limit)
+            .default_headers(headers)
+            .build()?;
+
+        Ok(NftStorageHandler {
+            client: Arc::new(client),
+            rate_limiter: Arc::new(RateLimiter::new(config_data.upload_requests_per_second())),
+            parallel_limit,
+        })
+    }
+}
+
+#[async_trait]
+impl Uploader for NftStorageHandler {
+    fn name(&self) -> &str {
+        "nft.storage"
+    }
+
+    fn parallel_limit(&self) -> usize {
+        self.parallel_limit
+    }
+
+    /// Send an object to nft.storage and wait for a response.
+    async fn upload_asset(&self, asset: AssetInfo) -> Result<UploadedAsset> {
+        let data = asset.content()?;
+
+        if data.len() as u64 > NFT_STORAGE_MAX_FILE_SIZE {
+            return Err(anyhow!(
+                "File '{}' exceeds the nft.storage upload limit of {} bytes",
+                asset.file_path,
+                NFT_STORAGE_MAX_FILE_SIZE
+            ));
+        }
+
+        let mut delay = DELAY_UNTIL_RETRY;
+        let mut retry = 0;
+
+        let response = loop {
+            self.rate_limiter.acquire().await;
+
+            let response = self
+                .client
+                .post(NFT_STORAGE_API_URL)
+                .header(header::CONTENT_TYPE, &asset.content_type)
+                .body(data.clone())
+                .send()
+                .await?;
+
+            let status = response.status();
+
+            if (status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error())
+                && retry < MAX_RETRY
+            {
+                // rate limited (or temporary failure), waits before trying again
+                debug!(
+                    "nft.storage upload of '{}' failed with status {}, retrying in {} ms",
+                    asset.file_path, status, delay
+                );
+                sleep(Duration::from_millis(delay)).await;
+                delay *= 2;
+                retry += 1;
+            } else if status.is_success() {
+                break response.json::<Value>().await?;
+            } else {
+                return Err(anyhow!(
+                    "nft.storage upload of '{}' failed with status {}: {}",
+                    asset.file_path,
+                    status,
+                    response.text().await?
+                ));
+            }
+        };
+
+        let cid = response
+            .get("value")
+            .and_then(|value| value.get("cid"))
+            .and_then(Value::as_str)
+            .ok_or_else(|| anyhow!("Failed to get a cid from nft.storage response."))?;
+
+        Ok(UploadedAsset::new(format!("ipfs://{}", cid)))
+    }
+}
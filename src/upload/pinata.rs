@@ -0,0 +1,156 @@
+use async_trait::async_trait;
+use data_encoding::HEXLOWER;
+use reqwest::{
+    header,
+    multipart::{Form, Part},
+};
+use ring::digest::{digest, SHA256};
+use std::{ffi::OsStr, sync::Arc};
+
+use crate::{common::*, config::*, upload::*};
+
+/// Pinata API endpoint.
+const PINATA_API_URL: &str = "https://api.pinata.cloud";
+
+/// Default maximum number of concurrent uploads.
+const PINATA_PARALLEL_LIMIT: usize = 10;
+
+pub struct PinataHandler {
+    client: Arc<HttpClient>,
+    gateway_url: String,
+    rate_limiter: Arc<RateLimiter>,
+    parallel_limit: usize,
+}
+
+impl PinataHandler {
+    /// Initialize a new PinataHandler.
+    pub async fn initialize(config_data: &ConfigData) -> Result<PinataHandler> {
+        let pinata_config = match &config_data.pinata_config {
+            Some(pinata_config) => pinata_config,
+            None => return Err(anyhow!("Missing 'pinataConfig' value in config file.")),
+        };
+
+        let mut headers = header::HeaderMap::new();
+        let mut auth_value =
+            header::HeaderValue::from_str(&format!("Bearer {}", pinata_config.jwt))?;
+        auth_value.set_sensitive(true);
+        headers.insert(header::AUTHORIZATION, auth_value);
+
+        let parallel_limit = config_data.upload_parallel_limit(PINATA_PARALLEL_LIMIT);
+        let client = http_client_builder(config_data, parallel_limit)
+            .default_headers(headers)
+            .build()?;
+
+        Ok(PinataHandler {
+            client: Arc::new(client),
+            gateway_url: pinata_config.gateway_url.trim_end_matches('/').to_string(),
+            rate_limiter: Arc::new(RateLimiter::new(config_data.upload_requests_per_second())),
+            parallel_limit,
+        })
+    }
+
+    /// Return the link of a file in the gateway.
+    fn gateway_link(&self, cid: &str) -> String {
+        format!("{}/ipfs/{}", self.gateway_url, cid)
+    }
+
+    /// Return the CID of a pinned file with the specified content hash, if there is one.
+    async fn get_pinned_cid(
+        http_client: &HttpClient,
+        content_hash: &str,
+    ) -> Result<Option<String>> {
+        let url = format!(
+            "{}/data/pinList?status=pinned&pageLimit=1&metadata[name]={}",
+            PINATA_API_URL, content_hash
+        );
+        let response = http_client
+            .get(&url)
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<Value>()
+            .await?;
+
+        let cid = response
+            .get("rows")
+            .and_then(Value::as_array)
+            .and_then(|rows| rows.first())
+            .and_then(|row| row.get("ipfs_pin_hash"))
+            .and_then(Value::as_str)
+            .map(String::from);
+
+        Ok(cid)
+    }
+}
+
+#[async_trait]
+impl Uploader for PinataHandler {
+    fn name(&self) -> &str {
+        "Pinata"
+    }
+
+    fn parallel_limit(&self) -> usize {
+        self.parallel_limit
+    }
+
+    /// Send an object to Pinata (unless it is already pinned) and wait for a response.
+    async fn upload_asset(&self, asset: AssetInfo) -> Result<UploadedAsset> {
+        let data = asset.content()?;
+
+        // files are pinned using their content hash as name, so we can find out
+        // whether the same content has been pinned already
+        let content_hash = HEXLOWER.encode(digest(&SHA256, &data).as_ref());
+
+        self.rate_limiter.acquire().await;
+
+        if let Some(cid) = PinataHandler::get_pinned_cid(&self.client, &content_hash).await? {
+            return Ok(UploadedAsset::existing(self.gateway_link(&cid)));
+        }
+
+        let file_name = Path::new(&asset.file_path)
+            .file_name()
+            .and_then(OsStr::to_str)
+            .expect("Failed to convert path file name to valid unicode.")
+            .to_string();
+
+        let form = Form::new()
+            .part(
+                "file",
+                Part::bytes(data)
+                    .file_name(file_name)
+                    .mime_str(&asset.content_type)?,
+            )
+            .text(
+                "pinataMetadata",
+                json!({ "name": content_hash }).to_string(),
+            );
+
+        self.rate_limiter.acquire().await;
+
+        let response = self
+            .client
+            .post(format!("{}/pinning/pinFileToIPFS", PINATA_API_URL))
+            .multipart(form)
+            .send()
+            .await?;
+
+        let status = response.status();
+
+        if !status.is_success() {
+            return Err(anyhow!(
+                "Pinata upload of '{}' failed with status {}: {}",
+                asset.file_path,
+                status,
+                response.text().await?
+            ));
+        }
+
+        let response = response.json::<Value>().await?;
+        let cid = response
+            .get("IpfsHash")
+            .and_then(Value::as_str)
+            .ok_or_else(|| anyhow!("Failed to get a CID from Pinata response."))?;
+
+        Ok(UploadedAsset::new(self.gateway_link(cid)))
+    }
+}
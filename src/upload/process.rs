@@ -1,5 +1,7 @@
-use async_trait::async_trait;
-use console::style;
+use console::{style, user_attended};
+use dialoguer::Confirm;
+use futures::{stream, StreamExt};
+use reqwest::StatusCode;
 use std::{
     collections::HashSet,
     sync::{
@@ -8,50 +10,37 @@ use std::{
     },
 };
 
-use crate::cache::{load_cache, Cache};
+use crate::cache::{load_cache, Cache, CacheItem};
 use crate::common::*;
-use crate::config::{data::SugarConfig, get_config_data, UploadMethod};
-use crate::upload::bundlr::BundlrHandler;
+use crate::config::{format_sol, get_config_data, ConfigData, ConfigFormat};
 use crate::upload::*;
 use crate::utils::*;
-use crate::validate::format::Metadata;
-
-/// A trait for storage upload handlers.
-#[async_trait]
-pub trait UploadHandler {
-    /// Prepares the upload of the specified media/metadata files.
-    async fn prepare(
-        &self,
-        sugar_config: &SugarConfig,
-        assets: &HashMap<usize, AssetPair>,
-        media_indices: &[usize],
-        metadata_indices: &[usize],
-    ) -> Result<()>;
-
-    /// Upload the data to a (permanent) storage.
-    async fn upload_data(
-        &self,
-        sugar_config: &SugarConfig,
-        assets: &HashMap<usize, AssetPair>,
-        cache: &mut Cache,
-        indices: &[usize],
-        data_type: DataType,
-        interrupted: Arc<AtomicBool>,
-    ) -> Result<Vec<UploadError>>;
-}
+use crate::validate::{format::Metadata, validate_assets, AssetError};
+
+/// The maximum number of pending indices reported.
+const MAX_REPORTED_INDICES: usize = 10;
+
+/// The maximum number of concurrent requests checking the uploaded files.
+const REMOTE_CHECK_LIMIT: usize = 10;
 
 pub struct UploadArgs {
     pub assets_dir: String,
     pub config: String,
+    pub config_format: Option<ConfigFormat>,
     pub keypair: Option<String>,
     pub rpc_url: Option<String>,
     pub cache: String,
+    pub skip_validation: bool,
+    pub no_dedup: bool,
+    pub force: bool,
+    pub verify_remote: bool,
+    pub yes: bool,
     pub interrupted: Arc<AtomicBool>,
 }
 
 pub async fn process_upload(args: UploadArgs) -> Result<()> {
     let sugar_config = sugar_setup(args.keypair, args.rpc_url)?;
-    let config_data = get_config_data(&args.config)?;
+    let config_data = get_config_data(&args.config, args.config_format)?;
 
     // loading assets
     println!(
@@ -64,30 +53,72 @@ pub async fn process_upload(args: UploadArgs) -> Result<()> {
     pb.enable_steady_tick(120);
     pb.set_message("Reading files...");
 
+    // checks the assets before uploading anything, reporting all problems found
+    if !args.skip_validation {
+        let (warnings, errors): (Vec<_>, Vec<_>) =
+            validate_assets(Path::new(&args.assets_dir), &config_data)?
+                .into_iter()
+                .partition(AssetError::is_warning);
+
+        for warning in &warnings {
+            warn!("{}", warning);
+            pb.println(format!("{}{}", style("=> ").dim(), style(warning).yellow()));
+        }
+
+        if !errors.is_empty() {
+            pb.finish_and_clear();
+
+            for err in &errors {
+                error!("{}", err);
+                println!("{}{}", style("=> ").dim(), err);
+            }
+
+            return Err(UploadError::InvalidAssets(errors.len()).into());
+        }
+    }
+
     let asset_pairs = get_asset_pairs(&args.assets_dir)?;
     // creates/loads the cache
     let mut cache = load_cache(&args.cache, true)?;
 
+    if args.force {
+        // the links of previous uploads are not reused either
+        cache.media_links.clear();
+    }
+
+    // the links of the cache are only trusted after checking that the files exist
+    let remote_check = if args.verify_remote && !args.force {
+        pb.set_message("Checking uploaded files...");
+        Some(verify_remote_links(&mut cache, &asset_pairs).await)
+    } else {
+        None
+    };
+
     // list of indices to upload
     // 0: media
     // 1: metadata
     let mut indices = (Vec::new(), Vec::new());
 
     for (index, pair) in &asset_pairs {
+        // the symbol and seller fee basis points are checked even when the
+        // validation is skipped, since they must be the same on all items
+        check_metadata_values(pair, &config_data)?;
+
         match cache.items.0.get_mut(&index.to_string()) {
             Some(item) => {
                 // has the media file changed?
-                if !item.media_hash.eq(&pair.media_hash) || item.media_link.is_empty() {
+                if args.force || !item.media_hash.eq(&pair.media_hash) || item.media_link.is_empty()
+                {
                     // we replace the entire item to trigger the media and metadata upload
-                    cache
-                        .items
-                        .0
-                        .insert(index.to_string(), pair.clone().into_cache_item());
+                    // (the overrides are kept since they are not part of the assets)
+                    let new_item = pair.clone().into_cache_item().with_overrides_of(item);
+                    *item = new_item;
                     // we need to upload both media/metadata
                     indices.0.push(*index);
                     indices.1.push(*index);
                 } else if !item.metadata_hash.eq(&pair.metadata_hash)
                     || item.metadata_link.is_empty()
+                    || item.metadata_encoding != config_data.metadata_encoding
                 {
                     // triggers the metadata upload
                     item.metadata_hash = pair.metadata_hash.clone();
@@ -107,51 +138,64 @@ pub async fn process_upload(args: UploadArgs) -> Result<()> {
                 indices.1.push(*index);
             }
         }
-        // sanity check: verifies that both symbol and seller-fee-basis-points are the
-        // same as the ones in the config file
-        let f = File::open(Path::new(&pair.metadata))?;
-        match serde_json::from_reader(f) {
-            Ok(metadata) => {
-                let metadata: Metadata = metadata;
-                // symbol check
-                if config_data.symbol.ne(&metadata.symbol) {
-                    return Err(UploadError::MismatchValue(
-                        "symbol".to_string(),
-                        pair.metadata.clone(),
-                        config_data.symbol,
-                        metadata.symbol,
-                    )
-                    .into());
-                }
-                // seller-fee-basis-points check
-                if config_data.seller_fee_basis_points != metadata.seller_fee_basis_points {
-                    return Err(UploadError::MismatchValue(
-                        "seller_fee_basis_points".to_string(),
-                        pair.metadata.clone(),
-                        config_data.seller_fee_basis_points.to_string(),
-                        metadata.seller_fee_basis_points.to_string(),
-                    )
-                    .into());
-                }
-            }
-            Err(err) => {
-                let error = anyhow!("Error parsing metadata ({}): {}", pair.metadata, err);
-                error!("{:?}", error);
-                return Err(error);
-            }
-        }
+
+        // the symbol override replaces the symbol of the uploaded metadata
+        check_symbol_override(&index.to_string(), &cache.items.0[&index.to_string()])?;
     }
 
+    // media files with the same content are uploaded once, the other items reuse
+    // the link of the uploaded file
+    let duplicates = if args.no_dedup {
+        HashMap::new()
+    } else {
+        dedup_media(&mut cache, &asset_pairs, &mut indices.0)
+    };
+
     pb.finish_and_clear();
 
+    if let Some(remote_check) = &remote_check {
+        println!(
+            "Checked {} uploaded file(s): {} missing from the storage",
+            remote_check.checked, remote_check.missing_files
+        );
+
+        if remote_check.failed > 0 {
+            println!(
+                "{} {} file(s) could not be checked and are assumed to be uploaded",
+                style("WARNING:").yellow().bold(),
+                remote_check.failed
+            );
+        }
+    }
+
+    // every item to upload has its metadata uploaded
+    let mut pending = indices.1.clone();
+    pending.sort_unstable();
+
     println!(
-        "Found {} media/metadata pair(s), uploading files:",
+        "Found {} media/metadata pair(s): {} already uploaded, {} pending{}",
+        asset_pairs.len(),
+        asset_pairs.len() - pending.len(),
+        pending.len(),
+        format_pending_indices(&pending)
+    );
+    info!(
+        total = asset_pairs.len(),
+        pending = pending.len(),
+        "Upload resume: {} of {} item(s) pending",
+        pending.len(),
         asset_pairs.len()
     );
-    println!("+--------------------+");
-    println!("| media     | {:>6} |", indices.0.len());
-    println!("| metadata  | {:>6} |", indices.1.len());
-    println!("+--------------------+");
+
+    if pending.is_empty() {
+        println!("All items are already uploaded (use --force to upload them again).");
+    } else {
+        println!("\nUploading files:");
+        println!("+--------------------+");
+        println!("| media     | {:>6} |", indices.0.len());
+        println!("| metadata  | {:>6} |", indices.1.len());
+        println!("+--------------------+");
+    }
 
     // this should never happen, since every time we update the media file we
     // need to update the metadata
@@ -179,19 +223,34 @@ pub async fn process_upload(args: UploadArgs) -> Result<()> {
         let pb = spinner_with_style();
         pb.set_message("Connecting...");
 
-        let handler = match config_data.upload_method {
-            UploadMethod::Bundlr => Box::new(
-                BundlrHandler::initialize(&get_config_data(&args.config)?, &sugar_config).await?,
-            ) as Box<dyn UploadHandler>,
-            UploadMethod::AWS => {
-                Box::new(AWSHandler::initialize(&get_config_data(&args.config)?).await?)
-                    as Box<dyn UploadHandler>
-            }
-        };
+        let uploader =
+            <dyn Uploader>::from_method(&config_data.upload_method, &config_data, &sugar_config)
+                .await?;
 
         pb.finish_with_message("Connected");
 
-        handler
+        let estimate = uploader
+            .estimate_cost(&sugar_config, &asset_pairs, &indices.0, &indices.1)
+            .await?;
+
+        info!(
+            upload_method = %config_data.upload_method.to_string(),
+            "Upload cost estimate: {}",
+            estimate
+        );
+        println!("{} {}", style("Estimated cost:").bold(), estimate);
+
+        // paid uploads fund the storage from the wallet when the balance is not enough
+        if let CostEstimate::Lamports {
+            lamports, balance, ..
+        } = estimate
+        {
+            if lamports > balance {
+                confirm_funding(uploader.name(), lamports - balance, args.yes)?;
+            }
+        }
+
+        uploader
             .prepare(&sugar_config, &asset_pairs, &indices.0, &indices.1)
             .await?;
 
@@ -211,27 +270,31 @@ pub async fn process_upload(args: UploadArgs) -> Result<()> {
 
         if !indices.0.is_empty() {
             errors.extend(
-                handler
-                    .upload_data(
-                        &sugar_config,
-                        &asset_pairs,
-                        &mut cache,
-                        &indices.0,
-                        DataType::Media,
-                        args.interrupted.clone(),
-                    )
-                    .await?,
+                upload_data(
+                    uploader.clone(),
+                    &config_data,
+                    &asset_pairs,
+                    &mut cache,
+                    &indices.0,
+                    DataType::Media,
+                    args.interrupted.clone(),
+                )
+                .await?,
             );
 
+            // the duplicated files get the link of the file uploaded in their place
+            link_duplicate_media(&mut cache, &asset_pairs, &duplicates);
+            cache.sync_file()?;
+
             // updates the list of metadata indices since the media upload
             // might fail - removes any index that the media upload failed
             if !indices.1.is_empty() {
-                for index in indices.0 {
+                for index in indices.0.iter().chain(duplicates.keys()) {
                     let item = cache.items.0.get(&index.to_string()).unwrap();
 
                     if item.media_link.is_empty() {
                         // no media link, not ready for metadata upload
-                        indices.1.retain(|&x| x != index);
+                        indices.1.retain(|x| x != index);
                     }
                 }
             }
@@ -250,16 +313,16 @@ pub async fn process_upload(args: UploadArgs) -> Result<()> {
 
         if !indices.1.is_empty() {
             errors.extend(
-                handler
-                    .upload_data(
-                        &sugar_config,
-                        &asset_pairs,
-                        &mut cache,
-                        &indices.1,
-                        DataType::Metadata,
-                        args.interrupted.clone(),
-                    )
-                    .await?,
+                upload_data(
+                    uploader.clone(),
+                    &config_data,
+                    &asset_pairs,
+                    &mut cache,
+                    &indices.1,
+                    DataType::Metadata,
+                    args.interrupted.clone(),
+                )
+                .await?,
             );
         }
     } else {
@@ -271,6 +334,23 @@ pub async fn process_upload(args: UploadArgs) -> Result<()> {
     cache.items.0.sort_keys();
     cache.sync_file()?;
 
+    if let Some(remote_check) = &remote_check {
+        if !remote_check.missing.is_empty() {
+            let uploaded = remote_check
+                .missing
+                .iter()
+                .filter_map(|index| cache.items.0.get(&index.to_string()))
+                .filter(|item| !(item.media_link.is_empty() || item.metadata_link.is_empty()))
+                .count();
+
+            println!(
+                "{}/{} item(s) missing from the storage uploaded again",
+                uploaded,
+                remote_check.missing.len()
+            );
+        }
+    }
+
     let mut count = 0;
 
     for (_index, item) in cache.items.0 {
@@ -318,3 +398,420 @@ pub async fn process_upload(args: UploadArgs) -> Result<()> {
 
     Ok(())
 }
+
+/// Result of the check of the uploaded files.
+struct RemoteCheck {
+    /// Number of links checked.
+    checked: usize,
+    /// Number of links not found in the storage.
+    missing_files: usize,
+    /// Number of links that could not be checked (e.g., network errors).
+    failed: usize,
+    /// Indices of the items with missing files.
+    missing: HashSet<usize>,
+}
+
+/// Sanity check: verifies that both symbol and seller-fee-basis-points of the metadata
+/// file are the same as the ones in the config file.
+fn check_metadata_values(pair: &AssetPair, config_data: &ConfigData) -> Result<()> {
+    let f = File::open(Path::new(&pair.metadata))?;
+    let metadata: Metadata = match serde_json::from_reader(f) {
+        Ok(metadata) => metadata,
+        Err(err) => {
+            let error = anyhow!("Error parsing metadata ({}): {}", pair.metadata, err);
+            error!("{:?}", error);
+            return Err(error);
+        }
+    };
+
+    // symbol check
+    if config_data.symbol.ne(&metadata.symbol) {
+        return Err(UploadError::MismatchValue(
+            "symbol".to_string(),
+            pair.metadata.clone(),
+            config_data.symbol.clone(),
+            metadata.symbol,
+        )
+        .into());
+    }
+    // seller-fee-basis-points check
+    if config_data.seller_fee_basis_points != metadata.seller_fee_basis_points {
+        return Err(UploadError::MismatchValue(
+            "seller_fee_basis_points".to_string(),
+            pair.metadata.clone(),
+            config_data.seller_fee_basis_points.to_string(),
+            metadata.seller_fee_basis_points.to_string(),
+        )
+        .into());
+    }
+
+    Ok(())
+}
+
+/// Check that the symbol override of a cache item (if any) fits the symbol limit.
+fn check_symbol_override(index: &str, item: &CacheItem) -> Result<()> {
+    match &item.symbol_override {
+        Some(symbol) if symbol.len() > MAX_SYMBOL_LENGTH => {
+            Err(UploadError::SymbolOverrideTooLong(
+                index.to_string(),
+                symbol.clone(),
+                MAX_SYMBOL_LENGTH,
+            )
+            .into())
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Check that the media/metadata links of the cache exist in the storage, clearing the
+/// links not found so the files are uploaded again.
+async fn verify_remote_links(cache: &mut Cache, assets: &HashMap<usize, AssetPair>) -> RemoteCheck {
+    let mut links = Vec::new();
+
+    for index in assets.keys() {
+        if let Some(item) = cache.items.0.get(&index.to_string()) {
+            if !item.media_link.is_empty() {
+                links.push((*index, DataType::Media, item.media_link.clone()));
+            }
+            if !item.metadata_link.is_empty() {
+                links.push((*index, DataType::Metadata, item.metadata_link.clone()));
+            }
+        }
+    }
+
+    let http_client = HttpClient::new();
+
+    let results: Vec<(usize, DataType, String, reqwest::Result<StatusCode>)> = stream::iter(links)
+        .map(|(index, data_type, link)| {
+            let http_client = http_client.clone();
+            async move {
+                let status = http_client
+                    .head(&link)
+                    .send()
+                    .await
+                    .map(|response| response.status());
+                (index, data_type, link, status)
+            }
+        })
+        .buffer_unordered(REMOTE_CHECK_LIMIT)
+        .collect()
+        .await;
+
+    let mut remote_check = RemoteCheck {
+        checked: results.len(),
+        missing_files: 0,
+        failed: 0,
+        missing: HashSet::new(),
+    };
+
+    for (index, data_type, link, status) in results {
+        match status {
+            Ok(StatusCode::NOT_FOUND) | Ok(StatusCode::GONE) => {
+                warn!(asset_index = index, data_type = ?data_type, link = %link, "Uploaded file not found");
+
+                let item = cache.items.0.get_mut(&index.to_string()).unwrap();
+
+                match data_type {
+                    // the media link is also removed from the links reused by content
+                    // and an empty media link triggers the upload of the metadata too
+                    DataType::Media => {
+                        item.media_link = String::new();
+                        cache
+                            .media_links
+                            .retain(|_hash, media_link| *media_link != link);
+                    }
+                    DataType::Metadata => item.metadata_link = String::new(),
+                }
+
+                remote_check.missing_files += 1;
+                remote_check.missing.insert(index);
+            }
+            Ok(_) => (),
+            Err(err) => {
+                warn!("Failed to check uploaded file {}: {}", link, err);
+                remote_check.failed += 1;
+            }
+        }
+    }
+
+    remote_check
+}
+
+/// Ask the user to confirm the funding of the storage from the wallet, unless '--yes'
+/// was used.
+fn confirm_funding(storage: &str, amount: u64, yes: bool) -> Result<()> {
+    if yes {
+        return Ok(());
+    }
+
+    if !user_attended() {
+        return Err(UploadError::FundingNotConfirmed(storage.to_string()).into());
+    }
+
+    if Confirm::new()
+        .with_prompt(format!(
+            "The upload requires funding {} with ◎ {} from your wallet. Do you want to continue?",
+            storage,
+            format_sol(amount)
+        ))
+        .default(false)
+        .interact()?
+    {
+        Ok(())
+    } else {
+        Err(UploadError::Aborted.into())
+    }
+}
+
+/// Return the list of pending indices to print, with only the first few indices.
+fn format_pending_indices(pending: &[usize]) -> String {
+    if pending.is_empty() {
+        return String::new();
+    }
+
+    let mut indices = pending
+        .iter()
+        .take(MAX_REPORTED_INDICES)
+        .map(|i| i.to_string())
+        .collect::<Vec<String>>()
+        .join(", ");
+
+    if pending.len() > MAX_REPORTED_INDICES {
+        indices.push_str(&format!(
+            " (and {} more)",
+            pending.len() - MAX_REPORTED_INDICES
+        ));
+    }
+
+    format!(" ({})", indices)
+}
+
+/// Remove from the media indices the files with the same content as another file being
+/// uploaded or already uploaded, returning the duplicates to link after the upload
+/// (index to index of the uploaded file).
+fn dedup_media(
+    cache: &mut Cache,
+    assets: &HashMap<usize, AssetPair>,
+    media_indices: &mut Vec<usize>,
+) -> HashMap<usize, usize> {
+    // makes sure the links of previous uploads are known, also for caches created
+    // before the links were stored by content
+    for item in cache.items.0.values() {
+        if !item.media_link.is_empty() {
+            cache
+                .media_links
+                .entry(item.media_hash.clone())
+                .or_insert_with(|| item.media_link.clone());
+        }
+    }
+
+    media_indices.sort_unstable();
+
+    let mut uploading: HashMap<&str, usize> = HashMap::new();
+    let mut duplicates = HashMap::new();
+
+    media_indices.retain(|index| {
+        let media_hash = assets[index].media_hash.as_str();
+
+        if let Some(link) = cache.media_links.get(media_hash) {
+            let item = cache.items.0.get_mut(&index.to_string()).unwrap();
+            item.media_link = link.clone();
+            false
+        } else if let Some(uploaded) = uploading.get(media_hash) {
+            duplicates.insert(*index, *uploaded);
+            false
+        } else {
+            uploading.insert(media_hash, *index);
+            true
+        }
+    });
+
+    if !duplicates.is_empty() {
+        info!(
+            "{} media file(s) have the same content as another file and will not be uploaded",
+            duplicates.len()
+        );
+    }
+
+    duplicates
+}
+
+/// Set the media link of the duplicated files to the link of the uploaded file with the
+/// same content, and store the links of the uploaded files by content.
+fn link_duplicate_media(
+    cache: &mut Cache,
+    assets: &HashMap<usize, AssetPair>,
+    duplicates: &HashMap<usize, usize>,
+) {
+    for (index, uploaded) in duplicates {
+        let link = cache
+            .items
+            .0
+            .get(&uploaded.to_string())
+            .map(|item| item.media_link.clone())
+            .unwrap_or_default();
+
+        if let Some(item) = cache.items.0.get_mut(&index.to_string()) {
+            item.media_link = link;
+        }
+    }
+
+    for (index, pair) in assets {
+        if let Some(item) = cache.items.0.get(&index.to_string()) {
+            if !item.media_link.is_empty() {
+                cache
+                    .media_links
+                    .insert(pair.media_hash.clone(), item.media_link.clone());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use super::*;
+
+    /// Return the assets of the items, where items with the same content letter have
+    /// identical media files.
+    fn media_assets(contents: &str, cache: &mut Cache) -> HashMap<usize, AssetPair> {
+        let mut assets = HashMap::new();
+
+        for (index, content) in contents.chars().enumerate() {
+            let pair = AssetPair {
+                name: format!("Item #{}", index),
+                metadata: format!("assets/{}.json", index),
+                metadata_hash: format!("metadata-{}", index),
+                media: format!("assets/{}.png", index),
+                media_hash: format!("media-{}", content),
+            };
+            cache
+                .items
+                .0
+                .insert(index.to_string(), pair.clone().into_cache_item());
+            assets.insert(index, pair);
+        }
+
+        assets
+    }
+
+    fn metadata_pair(name: &str, symbol: &str, seller_fee_basis_points: u16) -> AssetPair {
+        let path = env::temp_dir().join(format!("sugar-{}-{}.json", name, std::process::id()));
+        let metadata = json!({
+            "name": "Item #1",
+            "symbol": symbol,
+            "description": "",
+            "seller_fee_basis_points": seller_fee_basis_points,
+            "image": "0.png",
+            "attributes": [],
+            "properties": { "files": [], "category": "image" }
+        });
+        fs::write(&path, metadata.to_string()).unwrap();
+
+        AssetPair {
+            name: "Item #1".to_string(),
+            metadata: path.to_string_lossy().to_string(),
+            metadata_hash: String::new(),
+            media: String::new(),
+            media_hash: String::new(),
+        }
+    }
+
+    #[test]
+    fn metadata_values_match_config() {
+        let config_data = ConfigData {
+            symbol: "TEST".to_string(),
+            seller_fee_basis_points: 500,
+            ..ConfigData::default()
+        };
+
+        let pair = metadata_pair("values-match", "TEST", 500);
+        assert!(check_metadata_values(&pair, &config_data).is_ok());
+        fs::remove_file(&pair.metadata).unwrap();
+
+        let pair = metadata_pair("values-symbol", "OTHER", 500);
+        let error = check_metadata_values(&pair, &config_data).unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<UploadError>(),
+            Some(UploadError::MismatchValue(property, ..)) if property == "symbol"
+        ));
+        fs::remove_file(&pair.metadata).unwrap();
+
+        let pair = metadata_pair("values-fee", "TEST", 1000);
+        let error = check_metadata_values(&pair, &config_data).unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<UploadError>(),
+            Some(UploadError::MismatchValue(property, ..)) if property == "seller_fee_basis_points"
+        ));
+        fs::remove_file(&pair.metadata).unwrap();
+    }
+
+    #[test]
+    fn symbol_override_length() {
+        let mut cache = Cache::new();
+        media_assets("a", &mut cache);
+        let mut item = cache.items.0["0"].clone();
+        assert!(check_symbol_override("0", &item).is_ok());
+
+        item.symbol_override = Some("S".repeat(MAX_SYMBOL_LENGTH));
+        assert!(check_symbol_override("0", &item).is_ok());
+
+        item.symbol_override = Some("S".repeat(MAX_SYMBOL_LENGTH + 1));
+        let error = check_symbol_override("3", &item).unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<UploadError>(),
+            Some(UploadError::SymbolOverrideTooLong(index, ..)) if index == "3"
+        ));
+    }
+
+    #[tokio::test]
+    async fn duplicate_media_uploaded_once() {
+        let path = env::temp_dir().join(format!("sugar-dedup-{}.json", std::process::id()));
+        let mut cache = Cache::new();
+        cache.file_path = path.to_string_lossy().to_string();
+        // items 0, 2 and 3 have the same media file
+        let assets = media_assets("abaa", &mut cache);
+        let mut indices: Vec<usize> = (0..4).collect();
+
+        let duplicates = dedup_media(&mut cache, &assets, &mut indices);
+        assert_eq!(indices, vec![0, 1]);
+
+        let uploader = Arc::new(MockUploader::new(4));
+        let errors = upload_data(
+            uploader.clone(),
+            &ConfigData::default(),
+            &assets,
+            &mut cache,
+            &indices,
+            DataType::Media,
+            Arc::new(AtomicBool::new(false)),
+        )
+        .await
+        .unwrap();
+        assert!(errors.is_empty());
+        link_duplicate_media(&mut cache, &assets, &duplicates);
+
+        assert_eq!(uploader.uploads().len(), 2);
+        let media_links: Vec<&str> = (0..4)
+            .map(|index| cache.items.0[&index.to_string()].media_link.as_str())
+            .collect();
+        assert_eq!(
+            media_links,
+            vec!["mock://0", "mock://1", "mock://0", "mock://0"]
+        );
+        assert_eq!(cache.media_links["media-a"], "mock://0");
+
+        // a new item with the same content reuses the link stored in the cache
+        let mut rerun = Cache::new();
+        rerun.media_links = cache.media_links.clone();
+        let assets = media_assets("ab", &mut rerun);
+        let mut indices: Vec<usize> = (0..2).collect();
+
+        assert!(dedup_media(&mut rerun, &assets, &mut indices).is_empty());
+        assert!(indices.is_empty());
+        assert_eq!(rerun.items.0["1"].media_link, "mock://1");
+
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,100 @@
+use std::time::Instant;
+use tokio::{
+    sync::Mutex,
+    time::{sleep, Duration},
+};
+
+/// A token bucket limiting the rate of requests shared by the parallel uploads.
+///
+/// The bucket holds a single token, so requests are evenly spaced and never exceed the
+/// rate over any window. Waiting tasks acquire the lock in FIFO order, so the permits
+/// are handed out fairly across workers.
+pub struct RateLimiter {
+    /// Time between two tokens, or `None` when the rate is unlimited.
+    interval: Option<Duration>,
+    /// Time of the last token handed out.
+    last: Mutex<Option<Instant>>,
+}
+
+impl RateLimiter {
+    /// Create a new rate limiter; a `None` rate does not limit the requests.
+    pub fn new(requests_per_second: Option<f64>) -> RateLimiter {
+        RateLimiter {
+            interval: requests_per_second
+                .filter(|rate| *rate > 0.0)
+                .map(|rate| Duration::from_secs_f64(1.0 / rate)),
+            last: Mutex::new(None),
+        }
+    }
+
+    /// Wait until a request can be sent, returning the time the token was handed out.
+    pub async fn acquire(&self) -> Instant {
+        let interval = match self.interval {
+            Some(interval) => interval,
+            None => return Instant::now(),
+        };
+
+        // the lock is held while waiting, so the next task only starts waiting for
+        // its token once this one got its own
+        let mut last = self.last.lock().await;
+
+        if let Some(previous) = *last {
+            let elapsed = previous.elapsed();
+
+            if elapsed < interval {
+                sleep(interval - elapsed).await;
+            }
+        }
+
+        let now = Instant::now();
+        *last = Some(now);
+        now
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::future::join_all;
+    use std::sync::Arc;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn requests_do_not_exceed_rate() {
+        let rate = 50.0;
+        let requests = 20;
+        let limiter = Arc::new(RateLimiter::new(Some(rate)));
+        let start = Instant::now();
+
+        // several workers share the limiter
+        let times = join_all((0..requests).map(|_| {
+            let limiter = limiter.clone();
+            // the time of the token, since the task may resume later than it was given
+            tokio::spawn(async move { limiter.acquire().await })
+        }))
+        .await;
+
+        let mut times: Vec<Instant> = times.into_iter().map(Result::unwrap).collect();
+        times.sort();
+
+        // consecutive permits are at least one interval apart, so no window has
+        // more requests than the rate allows
+        let interval = Duration::from_secs_f64(1.0 / rate);
+        for pair in times.windows(2) {
+            assert!(pair[1] - pair[0] >= interval);
+        }
+        assert!(start.elapsed() >= interval * (requests - 1));
+    }
+
+    #[tokio::test]
+    async fn unlimited_rate() {
+        let limiter = RateLimiter::new(None);
+        let start = Instant::now();
+
+        for _ in 0..100 {
+            limiter.acquire().await;
+        }
+
+        assert!(start.elapsed() < Duration::from_millis(100));
+    }
+}
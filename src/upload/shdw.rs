@@ -0,0 +1,486 @@
+use anchor_client::solana_client::rpc_client::RpcClient;
+use anchor_client::solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    signature::Keypair,
+    system_program, sysvar,
+    transaction::Transaction,
+};
+use async_trait::async_trait;
+use data_encoding::{BASE64, HEXLOWER};
+use reqwest::multipart::{Form, Part};
+use ring::digest::{digest, SHA256};
+use spl_associated_token_account::get_associated_token_address;
+use std::{
+    cmp,
+    ffi::OsStr,
+    sync::{Arc, RwLock},
+};
+
+use crate::{common::*, config::*, rpc_calls::timed, upload::*};
+
+/// Shadow Drive API endpoint.
+const SHDW_API_URL: &str = "https://shadow-storage.genesysgo.net";
+
+/// Shadow Drive endpoint serving the uploaded files.
+const SHDW_DRIVE_URL: &str = "https://shdw-drive.genesysgo.net";
+
+/// Default maximum number of concurrent uploads.
+const SHDW_PARALLEL_LIMIT: usize = 10;
+
+/// Identifier of the storage accounts created by Sugar, used to find the account of
+/// a previous (interrupted) upload.
+const SHDW_STORAGE_IDENTIFIER: &str = "sugar";
+
+/// Offset of the account counter in the user info account (after the discriminator).
+const USER_INFO_COUNTER_OFFSET: usize = 8;
+
+/// Information of a storage account reported by Shadow Drive.
+struct StorageInfo {
+    identifier: String,
+    reserved: u64,
+    usage: u64,
+    writable: bool,
+}
+
+impl StorageInfo {
+    fn available(&self) -> u64 {
+        self.reserved.saturating_sub(self.usage)
+    }
+}
+
+pub struct ShdwHandler {
+    client: Arc<HttpClient>,
+    keypair: Arc<Keypair>,
+    /// Storage account of the upload; when the config does not set one, it is found or
+    /// created when the upload is prepared.
+    storage_account: RwLock<Option<Pubkey>>,
+    size: Option<u64>,
+    rate_limiter: Arc<RateLimiter>,
+    parallel_limit: usize,
+    /// Timeout of the RPC requests from the config file.
+    rpc_timeout_secs: Option<u64>,
+}
+
+impl ShdwHandler {
+    /// Initialize a new ShdwHandler.
+    pub async fn initialize(
+        config_data: &ConfigData,
+        sugar_config: &SugarConfig,
+    ) -> Result<ShdwHandler> {
+        let shdw_config = match &config_data.shdw_config {
+            Some(shdw_config) => shdw_config,
+            None => return Err(anyhow!("Missing 'shdwConfig' value in config file.")),
+        };
+
+        let parallel_limit = config_data.upload_parallel_limit(SHDW_PARALLEL_LIMIT);
+        let client = http_client_builder(config_data, parallel_limit).build()?;
+
+        Ok(ShdwHandler {
+            client: Arc::new(client),
+            keypair: Arc::new(Keypair::from_bytes(&sugar_config.keypair.to_bytes())?),
+            storage_account: RwLock::new(shdw_config.storage_account),
+            size: shdw_config.size,
+            rate_limiter: Arc::new(RateLimiter::new(config_data.upload_requests_per_second())),
+            parallel_limit,
+            rpc_timeout_secs: config_data.rpc_timeout_secs,
+        })
+    }
+
+    fn storage_account(&self) -> Option<Pubkey> {
+        *self.storage_account.read().unwrap()
+    }
+
+    /// Return the information of a storage account.
+    async fn get_storage_info(&self, storage_account: &Pubkey) -> Result<StorageInfo> {
+        let response = self
+            .client
+            .post(format!("{}/storage-account-info", SHDW_API_URL))
+            .json(&json!({ "storage_account": storage_account.to_string() }))
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<Value>()
+            .await?;
+
+        let value = |key: &str| {
+            response
+                .get(key)
+                .and_then(|v| v.as_u64().or_else(|| v.as_str()?.parse().ok()))
+                .ok_or_else(|| {
+                    anyhow!(
+                        "Failed to get '{}' of storage account {}.",
+                        key,
+                        storage_account
+                    )
+                })
+        };
+        let flag = |key: &str| response.get(key).and_then(Value::as_bool).unwrap_or(false);
+
+        Ok(StorageInfo {
+            identifier: response
+                .get("identifier")
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string(),
+            reserved: value("reserved_bytes")?,
+            usage: value("current_usage")?,
+            writable: !flag("immutable") && !flag("to_be_deleted"),
+        })
+    }
+
+    /// Return a storage account created by Sugar with at least `size` bytes available
+    /// (e.g., the account of an interrupted upload), or create a new one. Returns the
+    /// account and its available space.
+    async fn find_or_create_storage_account(
+        &self,
+        sugar_config: &SugarConfig,
+        size: u64,
+    ) -> Result<(Pubkey, u64)> {
+        let program_id = Pubkey::from_str(SHDW_DRIVE_PROGRAM_ID)?;
+        let owner = self.keypair.pubkey();
+        let rpc_client = setup_rpc_client(&sugar_config.rpc_url, self.rpc_timeout_secs)?;
+
+        let (user_info, _) =
+            Pubkey::find_program_address(&[b"user-info", owner.as_ref()], &program_id);
+        // the user info only exists after the first storage account is created
+        let account_counter = match timed("Get user info", || {
+            rpc_client.get_account_data(&user_info)
+        }) {
+            Ok(data) => data
+                .get(USER_INFO_COUNTER_OFFSET..USER_INFO_COUNTER_OFFSET + 4)
+                .map(|counter| u32::from_le_bytes(counter.try_into().unwrap()))
+                .ok_or_else(|| anyhow!("Invalid Shadow Drive user info account {}.", user_info))?,
+            Err(_) => 0,
+        };
+
+        for seed in (0..account_counter).rev() {
+            let storage_account = storage_account_pda(&program_id, &owner, seed);
+
+            // deleted accounts are not found
+            if let Ok(info) = self.get_storage_info(&storage_account).await {
+                if info.identifier == SHDW_STORAGE_IDENTIFIER
+                    && info.writable
+                    && info.available() >= size
+                {
+                    return Ok((storage_account, info.available()));
+                }
+            }
+        }
+
+        let storage_account = self
+            .create_storage_account(&rpc_client, &program_id, account_counter, size)
+            .await?;
+
+        println!(
+            "Created Shadow Drive storage account {} ({} bytes): set it as \
+             'shdwConfig.storageAccount' to use it for other uploads.",
+            storage_account, size
+        );
+
+        Ok((storage_account, size))
+    }
+
+    /// Create a storage account of `size` bytes, paid in SHDW from the token account of
+    /// the keypair. The transaction is signed by the keypair and sent to Shadow Drive,
+    /// which co-signs it as the uploader.
+    async fn create_storage_account(
+        &self,
+        rpc_client: &RpcClient,
+        program_id: &Pubkey,
+        seed: u32,
+        size: u64,
+    ) -> Result<Pubkey> {
+        let owner = self.keypair.pubkey();
+        let token_mint = Pubkey::from_str(SHDW_TOKEN_MINT)?;
+        let storage_account = storage_account_pda(program_id, &owner, seed);
+
+        let (storage_config, _) = Pubkey::find_program_address(&[b"storage-config"], program_id);
+        let (user_info, _) =
+            Pubkey::find_program_address(&[b"user-info", owner.as_ref()], program_id);
+        let (stake_account, _) =
+            Pubkey::find_program_address(&[b"stake-account", storage_account.as_ref()], program_id);
+
+        let instruction = Instruction {
+            program_id: *program_id,
+            accounts: vec![
+                AccountMeta::new(storage_config, false),
+                AccountMeta::new(user_info, false),
+                AccountMeta::new(storage_account, false),
+                AccountMeta::new(stake_account, false),
+                AccountMeta::new_readonly(token_mint, false),
+                AccountMeta::new(owner, true),
+                AccountMeta::new_readonly(Pubkey::from_str(SHDW_UPLOADER)?, true),
+                AccountMeta::new(get_associated_token_address(&owner, &token_mint), false),
+                AccountMeta::new_readonly(system_program::ID, false),
+                AccountMeta::new_readonly(spl_token::ID, false),
+                AccountMeta::new_readonly(sysvar::rent::ID, false),
+            ],
+            data: initialize_account_data(SHDW_STORAGE_IDENTIFIER, size),
+        };
+
+        let blockhash = timed("Get latest blockhash", || rpc_client.get_latest_blockhash())?;
+        let mut transaction = Transaction::new_with_payer(&[instruction], Some(&owner));
+        transaction.partial_sign(&[&*self.keypair], blockhash);
+
+        self.rate_limiter.acquire().await;
+
+        let response = self
+            .client
+            .post(format!("{}/storage-account", SHDW_API_URL))
+            .json(&json!({ "transaction": BASE64.encode(&bincode::serialize(&transaction)?) }))
+            .send()
+            .await?;
+
+        let status = response.status();
+
+        if !status.is_success() {
+            return Err(anyhow!(
+                "Failed to create Shadow Drive storage account ({} bytes) with status {}: {}",
+                size,
+                status,
+                response.text().await?
+            ));
+        }
+
+        Ok(storage_account)
+    }
+}
+
+/// Return the storage account of the owner with the specified seed (the value of the
+/// account counter of the owner when the account was created).
+fn storage_account_pda(program_id: &Pubkey, owner: &Pubkey, seed: u32) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"storage-account", owner.as_ref(), &seed.to_le_bytes()],
+        program_id,
+    )
+    .0
+}
+
+/// Return the data of the `initialize_account2` instruction: the anchor discriminator
+/// followed by the identifier and the size of the storage account.
+fn initialize_account_data(identifier: &str, size: u64) -> Vec<u8> {
+    let mut data = digest(&SHA256, b"global:initialize_account2").as_ref()[..8].to_vec();
+    data.extend_from_slice(&(identifier.len() as u32).to_le_bytes());
+    data.extend_from_slice(identifier.as_bytes());
+    data.extend_from_slice(&size.to_le_bytes());
+    data
+}
+
+#[async_trait]
+impl Uploader for ShdwHandler {
+    fn name(&self) -> &str {
+        "Shadow Drive"
+    }
+
+    fn parallel_limit(&self) -> usize {
+        self.parallel_limit
+    }
+
+    /// Compares the size of the files to upload with the space available in the
+    /// storage account.
+    async fn estimate_cost(
+        &self,
+        _sugar_config: &SugarConfig,
+        assets: &HashMap<usize, AssetPair>,
+        media_indices: &[usize],
+        metadata_indices: &[usize],
+    ) -> Result<CostEstimate> {
+        let size = upload_sizes(assets, media_indices, metadata_indices)?
+            .iter()
+            .sum();
+
+        let available = match self.storage_account() {
+            Some(storage_account) => self.get_storage_info(&storage_account).await?.available(),
+            // the storage account created for the upload has the space needed
+            None => new_account_size(self.size, size),
+        };
+
+        Ok(CostEstimate::Storage { size, available })
+    }
+
+    /// Check that the storage account has enough space for the files to upload, finding
+    /// or creating the storage account when the config does not set one.
+    async fn prepare(
+        &self,
+        sugar_config: &SugarConfig,
+        assets: &HashMap<usize, AssetPair>,
+        media_indices: &[usize],
+        metadata_indices: &[usize],
+    ) -> Result<()> {
+        let (total_size, available) = match self
+            .estimate_cost(sugar_config, assets, media_indices, metadata_indices)
+            .await?
+        {
+            CostEstimate::Storage { size, available } => (size, available),
+            _ => unreachable!("Shadow Drive cost is estimated in storage space"),
+        };
+
+        let (storage_account, available) = match self.storage_account() {
+            Some(storage_account) => (storage_account, available),
+            None => {
+                let (storage_account, available) = self
+                    .find_or_create_storage_account(sugar_config, available)
+                    .await?;
+                *self.storage_account.write().unwrap() = Some(storage_account);
+                (storage_account, available)
+            }
+        };
+
+        info!(
+            "Shadow Drive storage available {} bytes, require {} bytes",
+            available, total_size
+        );
+
+        if total_size > available {
+            return Err(UploadError::NotEnoughStorage(
+                storage_account.to_string(),
+                total_size,
+                available,
+            )
+            .into());
+        }
+
+        Ok(())
+    }
+
+    /// Send an object to Shadow Drive (unless it was already uploaded) and wait for a
+    /// response.
+    async fn upload_asset(&self, asset: AssetInfo) -> Result<UploadedAsset> {
+        let storage_account = self
+            .storage_account()
+            .ok_or_else(|| anyhow!("Missing Shadow Drive storage account."))?;
+
+        let data = asset.content()?;
+
+        // files are named after their content hash, so an upload of the same content
+        // resolves to the same link
+        let extension = Path::new(&asset.file_path)
+            .extension()
+            .and_then(OsStr::to_str)
+            .expect("Failed to convert path extension to valid unicode.");
+        let file_name = format!(
+            "{}.{}",
+            HEXLOWER.encode(digest(&SHA256, &data).as_ref()),
+            extension
+        );
+        let link = format!("{}/{}/{}", SHDW_DRIVE_URL, storage_account, file_name);
+
+        self.rate_limiter.acquire().await;
+
+        if self.client.head(&link).send().await?.status().is_success() {
+            return Ok(UploadedAsset::existing(link));
+        }
+
+        // the upload is authorized by signing the hash of the file names
+        let message = format!(
+            "Shadow Drive Signed Message:\nStorage Account: {}\nUpload files with hash: {}",
+            storage_account,
+            HEXLOWER.encode(digest(&SHA256, file_name.as_bytes()).as_ref())
+        );
+        let signature = self.keypair.sign_message(message.as_bytes());
+
+        let form = Form::new()
+            .part(
+                "file",
+                Part::bytes(data)
+                    .file_name(file_name.clone())
+                    .mime_str(&asset.content_type)?,
+            )
+            .text("message", bs58::encode(signature.as_ref()).into_string())
+            .text("signer", self.keypair.pubkey().to_string())
+            .text("storage_account", storage_account.to_string())
+            .text("fileNames", file_name);
+
+        self.rate_limiter.acquire().await;
+
+        let response = self
+            .client
+            .post(format!("{}/upload", SHDW_API_URL))
+            .multipart(form)
+            .send()
+            .await?;
+
+        let status = response.status();
+
+        if !status.is_success() {
+            return Err(anyhow!(
+                "Shadow Drive upload of '{}' failed with status {}: {}",
+                asset.file_path,
+                status,
+                response.text().await?
+            ));
+        }
+
+        let response = response.json::<Value>().await?;
+        let location = response
+            .get("finalized_locations")
+            .and_then(Value::as_array)
+            .and_then(|locations| locations.first())
+            .and_then(Value::as_str)
+            .ok_or_else(|| {
+                anyhow!("Failed to get the file location from Shadow Drive response.")
+            })?;
+
+        Ok(UploadedAsset::new(location.to_string()))
+    }
+}
+
+/// Return the size of the storage account created for an upload: the size from the
+/// config, but never less than the size of the files to upload.
+fn new_account_size(config_size: Option<u64>, required: u64) -> u64 {
+    cmp::max(config_size.unwrap_or(0), required)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn new_account_fits_the_upload() {
+        assert_eq!(new_account_size(None, 1000), 1000);
+        assert_eq!(new_account_size(Some(5000), 1000), 5000);
+        // a config size smaller than the upload does not shrink the account
+        assert_eq!(new_account_size(Some(10), 1000), 1000);
+    }
+
+    #[test]
+    fn available_storage() {
+        let info = |reserved, usage| StorageInfo {
+            identifier: SHDW_STORAGE_IDENTIFIER.to_string(),
+            reserved,
+            usage,
+            writable: true,
+        };
+
+        assert_eq!(info(1000, 400).available(), 600);
+        assert_eq!(info(1000, 1200).available(), 0);
+    }
+
+    #[test]
+    fn initialize_account_instruction_data() {
+        let data = initialize_account_data("sugar", 1024);
+
+        assert_eq!(
+            &data[..8],
+            &digest(&SHA256, b"global:initialize_account2").as_ref()[..8]
+        );
+        assert_eq!(&data[8..12], &5u32.to_le_bytes());
+        assert_eq!(&data[12..17], b"sugar");
+        assert_eq!(&data[17..], &1024u64.to_le_bytes());
+    }
+
+    #[test]
+    fn storage_accounts_by_seed() {
+        let program_id = Pubkey::from_str(SHDW_DRIVE_PROGRAM_ID).unwrap();
+        let owner = Pubkey::new_unique();
+
+        assert_eq!(
+            storage_account_pda(&program_id, &owner, 0),
+            storage_account_pda(&program_id, &owner, 0)
+        );
+        assert_ne!(
+            storage_account_pda(&program_id, &owner, 0),
+            storage_account_pda(&program_id, &owner, 1)
+        );
+    }
+}
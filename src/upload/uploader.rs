@@ -0,0 +1,836 @@
+use async_trait::async_trait;
+use console::style;
+use futures::future::select_all;
+use reqwest::ClientBuilder;
+use std::{
+    cmp,
+    collections::{HashSet, VecDeque},
+    ffi::OsStr,
+    fmt, fs,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use crate::{common::*, config::*, upload::*};
+
+/// Size of the mock media uri used to estimate the size of the metadata files.
+const MOCK_URI_SIZE: usize = 100;
+
+/// Number of completed uploads between writes of the cache file.
+const CACHE_SYNC_BATCH: usize = 50;
+
+/// Maximum time between writes of the cache file while uploads complete.
+const CACHE_SYNC_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Information of a media/metadata file to upload.
+pub struct AssetInfo {
+    /// Id of the asset (to be used to update the cache link).
+    pub asset_id: String,
+    /// Path to the media/metadata file.
+    pub file_path: String,
+    /// Link of the media file, replaced in the metadata file when uploading metadata.
+    pub media_link: String,
+    /// Symbol override of the item, replaced in the metadata file when uploading metadata.
+    pub symbol: Option<String>,
+    /// Default `animation_url` of the metadata file, from the template of the config.
+    pub animation_url: Option<String>,
+    /// Default `external_url` of the metadata file, from the template of the config.
+    pub external_url: Option<String>,
+    /// Encoding used to compress the file, if any.
+    pub content_encoding: Option<ContentEncoding>,
+    pub data_type: DataType,
+    pub content_type: String,
+}
+
+impl AssetInfo {
+    /// Return the content of the file as it is sent to the storage: the metadata files have
+    /// the media link and the default urls set, and are compressed with the content
+    /// encoding (if any).
+    pub fn content(&self) -> Result<Vec<u8>> {
+        match self.data_type {
+            DataType::Media => Ok(fs::read(&self.file_path)?),
+            DataType::Metadata => {
+                // replaces the media link without modifying the original file to avoid
+                // changing the hash of the metadata file
+                let metadata = get_updated_metadata(
+                    &self.file_path,
+                    &self.media_link,
+                    &self.symbol,
+                    &self.animation_url,
+                    &self.external_url,
+                )?;
+
+                match self.content_encoding {
+                    Some(encoding) => encode_content(metadata.as_bytes(), encoding),
+                    None => Ok(metadata.into_bytes()),
+                }
+            }
+        }
+    }
+}
+
+/// Result of the upload of a single file.
+pub struct UploadedAsset {
+    pub link: String,
+    /// Set when the storage already had a file with the same content, which was
+    /// not uploaded again.
+    pub existing: bool,
+}
+
+impl UploadedAsset {
+    pub fn new(link: String) -> Self {
+        UploadedAsset {
+            link,
+            existing: false,
+        }
+    }
+
+    pub fn existing(link: String) -> Self {
+        UploadedAsset {
+            link,
+            existing: true,
+        }
+    }
+}
+
+/// Estimated cost of an upload, in the units charged by the storage.
+pub enum CostEstimate {
+    /// Paid in lamports from the balance funded at the storage (e.g., Bundlr).
+    Lamports {
+        size: u64,
+        lamports: u64,
+        balance: u64,
+    },
+    /// Uses the prepaid space (in bytes) of a storage account (e.g., Shadow Drive).
+    Storage { size: u64, available: u64 },
+    /// Billed by the storage provider outside of Sugar (e.g., AWS).
+    Billed { size: u64 },
+    /// No cost to upload the files.
+    Free { size: u64 },
+}
+
+impl CostEstimate {
+    /// Return `true` when the cost is over what is already funded at the storage.
+    pub fn exceeds_balance(&self) -> bool {
+        match self {
+            CostEstimate::Lamports {
+                lamports, balance, ..
+            } => lamports > balance,
+            CostEstimate::Storage { size, available } => size > available,
+            CostEstimate::Billed { .. } | CostEstimate::Free { .. } => false,
+        }
+    }
+}
+
+impl fmt::Display for CostEstimate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CostEstimate::Lamports {
+                size,
+                lamports,
+                balance,
+            } => write!(
+                f,
+                "{} lamports (◎ {}) for {} byte(s), funded balance {} lamports (◎ {})",
+                lamports,
+                format_sol(*lamports),
+                size,
+                balance,
+                format_sol(*balance)
+            ),
+            CostEstimate::Storage { size, available } => write!(
+                f,
+                "{} byte(s) of storage, {} byte(s) available",
+                size, available
+            ),
+            CostEstimate::Billed { size } => {
+                write!(f, "{} byte(s), billed by the storage provider", size)
+            }
+            CostEstimate::Free { size } => write!(f, "free ({} byte(s))", size),
+        }
+    }
+}
+
+/// A storage backend for the upload of media/metadata files.
+///
+/// Uploaders only send a single file at a time; the parallel upload, progress and
+/// cache updates are handled by [`upload_data`].
+#[async_trait]
+pub trait Uploader: Send + Sync {
+    /// Name of the storage, used in the progress and error messages.
+    fn name(&self) -> &str;
+
+    /// Maximum number of concurrent uploads.
+    fn parallel_limit(&self) -> usize;
+
+    /// Prepares the upload of the specified media/metadata files (e.g., funding the
+    /// storage account).
+    async fn prepare(
+        &self,
+        _sugar_config: &SugarConfig,
+        _assets: &HashMap<usize, AssetPair>,
+        _media_indices: &[usize],
+        _metadata_indices: &[usize],
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    /// Estimates the cost of the upload of the specified media/metadata files.
+    async fn estimate_cost(
+        &self,
+        _sugar_config: &SugarConfig,
+        assets: &HashMap<usize, AssetPair>,
+        media_indices: &[usize],
+        metadata_indices: &[usize],
+    ) -> Result<CostEstimate> {
+        let size = upload_sizes(assets, media_indices, metadata_indices)?
+            .iter()
+            .sum();
+        Ok(CostEstimate::Free { size })
+    }
+
+    /// Upload a file to the storage, returning its link.
+    async fn upload_asset(&self, asset: AssetInfo) -> Result<UploadedAsset>;
+}
+
+impl dyn Uploader {
+    /// Return the uploader of an upload method, configured from the config file.
+    pub async fn from_method(
+        method: &UploadMethod,
+        config_data: &ConfigData,
+        sugar_config: &SugarConfig,
+    ) -> Result<Arc<dyn Uploader>> {
+        Ok(match method {
+            UploadMethod::Bundlr => {
+                Arc::new(BundlrHandler::initialize(config_data, sugar_config).await?)
+            }
+            UploadMethod::AWS => Arc::new(AWSHandler::initialize(config_data).await?),
+            UploadMethod::NftStorage => Arc::new(NftStorageHandler::initialize(config_data).await?),
+            UploadMethod::Pinata => Arc::new(PinataHandler::initialize(config_data).await?),
+            UploadMethod::ShdwDrive => {
+                Arc::new(ShdwHandler::initialize(config_data, sugar_config).await?)
+            }
+        })
+    }
+}
+
+/// Return the size (in bytes) of each media/metadata file to upload. The metadata
+/// files are measured with a mock media link, since the media is not uploaded yet.
+pub fn upload_sizes(
+    assets: &HashMap<usize, AssetPair>,
+    media_indices: &[usize],
+    metadata_indices: &[usize],
+) -> Result<Vec<u64>> {
+    let asset = |index: &usize| {
+        assets
+            .get(index)
+            .ok_or_else(|| anyhow!("Failed to get asset at index {}", index))
+    };
+    let mut sizes = Vec::with_capacity(media_indices.len() + metadata_indices.len());
+
+    for index in media_indices {
+        sizes.push(std::fs::metadata(Path::new(&asset(index)?.media))?.len());
+    }
+
+    let mock_uri = "x".repeat(MOCK_URI_SIZE);
+
+    for index in metadata_indices {
+        sizes.push(
+            get_updated_metadata(&asset(index)?.metadata, &mock_uri, &None, &None, &None)?.len()
+                as u64,
+        );
+    }
+
+    Ok(sizes)
+}
+
+/// Return the builder of the HTTP client of an uploader, which keeps up to
+/// `uploadPoolSize` idle connections open to the storage.
+pub fn http_client_builder(config_data: &ConfigData, parallel_limit: usize) -> ClientBuilder {
+    HttpClient::builder().pool_max_idle_per_host(config_data.upload_pool_size(parallel_limit))
+}
+
+/// Writes the cache file every [`CACHE_SYNC_BATCH`] completed uploads or every
+/// [`CACHE_SYNC_INTERVAL`], whichever comes first, instead of after each upload.
+struct CacheSync {
+    pending: usize,
+    last_sync: Instant,
+}
+
+impl CacheSync {
+    fn new() -> Self {
+        CacheSync {
+            pending: 0,
+            last_sync: Instant::now(),
+        }
+    }
+
+    /// Record a completed upload, writing the cache file when a batch is due.
+    fn record(&mut self, cache: &mut Cache) -> Result<()> {
+        self.pending += 1;
+
+        if self.pending >= CACHE_SYNC_BATCH || self.last_sync.elapsed() >= CACHE_SYNC_INTERVAL {
+            self.flush(cache)?;
+        }
+
+        Ok(())
+    }
+
+    /// Write the cache file if there are uploads not saved yet.
+    fn flush(&mut self, cache: &mut Cache) -> Result<()> {
+        if self.pending > 0 {
+            cache.sync_file()?;
+            self.pending = 0;
+        }
+        self.last_sync = Instant::now();
+
+        Ok(())
+    }
+}
+
+/// Upload the data to the storage of the uploader, saving the links in the cache.
+pub async fn upload_data(
+    uploader: Arc<dyn Uploader>,
+    config_data: &ConfigData,
+    assets: &HashMap<usize, AssetPair>,
+    cache: &mut Cache,
+    indices: &[usize],
+    data_type: DataType,
+    interrupted: Arc<AtomicBool>,
+) -> Result<Vec<UploadError>> {
+    let mut extension = HashSet::with_capacity(1);
+    let mut paths = Vec::new();
+
+    for index in indices {
+        let item = match assets.get(index) {
+            Some(asset_index) => asset_index,
+            None => return Err(anyhow!("Failed to get asset at index {}", index)),
+        };
+        // chooses the file path based on the data type
+        let file_path = match data_type {
+            DataType::Media => item.media.clone(),
+            DataType::Metadata => item.metadata.clone(),
+        };
+
+        let path = Path::new(&file_path);
+        let ext = path
+            .extension()
+            .and_then(OsStr::to_str)
+            .expect("Failed to convert path extension to valid unicode.");
+        extension.insert(String::from(ext));
+
+        paths.push(file_path);
+    }
+
+    // validates that all files have the same extension
+    let extension = if extension.len() == 1 {
+        extension.iter().next().unwrap()
+    } else {
+        return Err(anyhow!("Invalid file extension: {:?}", extension));
+    };
+
+    let content_type = match data_type {
+        DataType::Media => format!("image/{}", extension),
+        DataType::Metadata => "application/json".to_string(),
+    };
+
+    println!("\nSending data: (Ctrl+C to abort)");
+
+    let pb = task_progress("upload", paths.len() as u64);
+    let mut objects = VecDeque::new();
+
+    for file_path in paths {
+        // path to the media/metadata file
+        let path = Path::new(&file_path);
+        // id of the asset (to be used to update the cache link)
+        let asset_id = String::from(
+            path.file_stem()
+                .and_then(OsStr::to_str)
+                .expect("Failed to get convert path file ext to valid unicode."),
+        );
+        let cache_item = match cache.items.0.get(&asset_id) {
+            Some(item) => item,
+            None => return Err(anyhow!("Failed to get config item at index {}", asset_id)),
+        };
+
+        // the url templates are only used by the metadata files
+        let url_template = |template: &Option<String>| match data_type {
+            DataType::Media => None,
+            DataType::Metadata => template
+                .as_ref()
+                .map(|template| expand_index_template(template, &asset_id)),
+        };
+
+        objects.push_back(AssetInfo {
+            asset_id: asset_id.to_string(),
+            file_path: String::from(path.to_str().expect("Failed to convert path from unicode.")),
+            media_link: cache_item.media_link.clone(),
+            symbol: match data_type {
+                DataType::Media => None,
+                DataType::Metadata => cache_item.symbol_override.clone(),
+            },
+            animation_url: url_template(&config_data.animation_url_template),
+            external_url: url_template(&config_data.external_url_template),
+            // only the metadata files are compressed
+            content_encoding: match data_type {
+                DataType::Media => None,
+                DataType::Metadata => config_data.metadata_encoding,
+            },
+            data_type: data_type.clone(),
+            content_type: content_type.clone(),
+        });
+    }
+
+    let spawn_upload = |object: AssetInfo| {
+        let uploader = uploader.clone();
+        tokio::spawn(async move {
+            let asset_id = object.asset_id.clone();
+            uploader
+                .upload_asset(object)
+                .await
+                .map(|uploaded| (asset_id, uploaded))
+        })
+    };
+
+    let parallel_limit = uploader.parallel_limit();
+    let mut handles = Vec::new();
+
+    for object in objects.drain(0..cmp::min(objects.len(), parallel_limit)) {
+        handles.push(spawn_upload(object));
+    }
+
+    let start = Instant::now();
+    let mut cache_sync = CacheSync::new();
+    let mut errors = Vec::new();
+    let mut uploaded = 0;
+    let mut skipped = 0;
+
+    // on interruption no new uploads are started, but the in-flight ones are
+    // awaited so their links are saved in the cache
+    while !handles.is_empty() {
+        match select_all(handles).await {
+            (Ok(res), _index, remaining) => {
+                // independently if the upload was successful or not
+                // we continue to try the remaining ones
+                handles = remaining;
+
+                match res {
+                    Ok((asset_id, asset)) => {
+                        // cache item to update
+                        let item = cache.items.0.get_mut(&asset_id).unwrap();
+
+                        info!(
+                            asset_index = %asset_id,
+                            data_type = ?data_type,
+                            link = %asset.link,
+                            elapsed_ms = start.elapsed().as_millis() as u64,
+                            "{} upload completed",
+                            uploader.name()
+                        );
+
+                        match data_type {
+                            DataType::Media => item.media_link = asset.link,
+                            DataType::Metadata => {
+                                item.metadata_link = asset.link;
+                                item.metadata_encoding = config_data.metadata_encoding;
+                            }
+                        }
+
+                        if asset.existing {
+                            skipped += 1;
+                        } else {
+                            uploaded += 1;
+                        }
+                        // saves the progress in batches, so an interruption loses
+                        // at most one batch of links
+                        cache_sync.record(cache)?;
+                        // updates the progress bar
+                        pb.inc(1);
+                    }
+                    Err(err) => {
+                        // user will need to retry the upload
+                        errors.push(UploadError::SendDataFailed(format!(
+                            "{} upload error: {:?}",
+                            uploader.name(),
+                            err
+                        )));
+                    }
+                }
+            }
+            (Err(err), _index, remaining) => {
+                errors.push(UploadError::SendDataFailed(format!(
+                    "{} upload error: {:?}",
+                    uploader.name(),
+                    err
+                )));
+                // ignoring all errors
+                handles = remaining;
+            }
+        }
+
+        // keeps the pool full, starting a new upload for each one completed
+        while !interrupted.load(Ordering::SeqCst) && handles.len() < parallel_limit {
+            match objects.pop_front() {
+                Some(object) => handles.push(spawn_upload(object)),
+                None => break,
+            }
+        }
+    }
+
+    // makes sure the cache file is updated, also when the upload failed or
+    // was interrupted
+    cache_sync.flush(cache)?;
+
+    if !errors.is_empty() {
+        pb.abandon_with_message(format!("{}", style("Upload failed ").red().bold()));
+    } else if !objects.is_empty() {
+        pb.abandon_with_message(format!("{}", style("Upload aborted ").red().bold()));
+        return Err(UploadError::SendDataFailed(
+            "Not all files were uploaded: re-run the upload to resume.".to_string(),
+        )
+        .into());
+    } else {
+        pb.finish_with_message(format!("{}", style("Upload successful ").green().bold()));
+    }
+
+    if skipped > 0 {
+        println!(
+            "{} file(s) uploaded, {} file(s) skipped (already in {})",
+            uploaded,
+            skipped,
+            uploader.name()
+        );
+    }
+
+    Ok(errors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, fs};
+
+    use crate::cache::CacheItem;
+
+    fn cache_path(name: &str) -> PathBuf {
+        env::temp_dir().join(format!("sugar-{}-{}.json", name, std::process::id()))
+    }
+
+    /// Content of the metadata file of an asset.
+    fn metadata_json(index: usize) -> String {
+        serde_json::json!({
+            "name": format!("Item #{}", index),
+            "symbol": "TEST",
+            "description": "Test item",
+            "seller_fee_basis_points": 500,
+            "image": format!("{}.png", index),
+            "attributes": [],
+            "properties": {
+                "files": [{ "uri": format!("{}.png", index), "type": "image/png" }],
+                "category": "image"
+            }
+        })
+        .to_string()
+    }
+
+    /// Write the files of the assets in a temporary directory and add them to the cache.
+    fn test_assets(name: &str, count: usize, cache: &mut Cache) -> HashMap<usize, AssetPair> {
+        let assets_dir = assets_path(name);
+        fs::create_dir_all(&assets_dir).unwrap();
+        let mut assets = HashMap::new();
+
+        for index in 0..count {
+            let metadata = assets_dir.join(format!("{}.json", index));
+            let media = assets_dir.join(format!("{}.png", index));
+            fs::write(&metadata, metadata_json(index)).unwrap();
+            fs::write(&media, [index as u8; 8]).unwrap();
+
+            let pair = AssetPair {
+                name: format!("Item #{}", index),
+                metadata: metadata.to_string_lossy().to_string(),
+                metadata_hash: format!("metadata-{}", index),
+                media: media.to_string_lossy().to_string(),
+                media_hash: format!("media-{}", index),
+            };
+            cache
+                .items
+                .0
+                .insert(index.to_string(), pair.clone().into_cache_item());
+            assets.insert(index, pair);
+        }
+
+        assets
+    }
+
+    fn assets_path(name: &str) -> PathBuf {
+        env::temp_dir().join(format!("sugar-{}-{}-assets", name, std::process::id()))
+    }
+
+    #[tokio::test]
+    async fn cache_links_match_out_of_order_uploads() {
+        let path = cache_path("out-of-order");
+        let mut cache = Cache::new();
+        cache.file_path = path.to_string_lossy().to_string();
+        let assets = test_assets("out-of-order", 5, &mut cache);
+        let indices: Vec<usize> = (0..5).collect();
+        // the uploads complete in reverse order of their index
+        let reverse_order_uploader =
+            indices
+                .iter()
+                .fold(MockUploader::new(5), |uploader, index| {
+                    uploader.with_delay(
+                        &index.to_string(),
+                        Duration::from_millis((5 - *index as u64) * 20),
+                    )
+                });
+
+        let errors = upload_data(
+            Arc::new(reverse_order_uploader),
+            &ConfigData::default(),
+            &assets,
+            &mut cache,
+            &indices,
+            DataType::Media,
+            Arc::new(AtomicBool::new(false)),
+        )
+        .await
+        .unwrap();
+        assert!(errors.is_empty());
+
+        // every link is saved on the item of its own asset
+        let saved = Cache::load(&cache.file_path, false).unwrap();
+        for index in indices {
+            let item: &CacheItem = saved.items.0.get(&index.to_string()).unwrap();
+            assert_eq!(item.media_link, MockUploader::link(&index.to_string()));
+            assert_eq!(item.media_hash, format!("media-{}", index));
+            assert!(item.metadata_link.is_empty());
+        }
+
+        fs::remove_file(path).unwrap();
+        fs::remove_dir_all(assets_path("out-of-order")).unwrap();
+    }
+
+    #[tokio::test]
+    async fn failed_upload_keeps_other_links() {
+        let path = cache_path("failed-upload");
+        let mut cache = Cache::new();
+        cache.file_path = path.to_string_lossy().to_string();
+        let assets = test_assets("failed-upload", 4, &mut cache);
+        let indices: Vec<usize> = (0..4).collect();
+        let uploader = Arc::new(MockUploader::new(2).with_failure("2"));
+
+        let errors = upload_data(
+            uploader.clone(),
+            &ConfigData::default(),
+            &assets,
+            &mut cache,
+            &indices,
+            DataType::Metadata,
+            Arc::new(AtomicBool::new(false)),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0]
+            .to_string()
+            .contains("Mock upload of asset 2 failed"));
+
+        let mut uploads = uploader.uploads();
+        uploads.sort();
+        assert_eq!(uploads, vec!["0", "1", "2", "3"]);
+
+        // the failed asset is uploaded again by the next run
+        let saved = Cache::load(&cache.file_path, false).unwrap();
+        for index in ["0", "1", "3"] {
+            assert_eq!(
+                saved.items.0[index].metadata_link,
+                MockUploader::link(index)
+            );
+        }
+        assert!(saved.items.0["2"].metadata_link.is_empty());
+
+        fs::remove_file(path).unwrap();
+        fs::remove_dir_all(assets_path("failed-upload")).unwrap();
+    }
+
+    #[tokio::test]
+    async fn compressed_metadata_round_trip() {
+        let path = cache_path("compressed-metadata");
+        let mut cache = Cache::new();
+        cache.file_path = path.to_string_lossy().to_string();
+        let assets = test_assets("compressed-metadata", 2, &mut cache);
+        for (index, item) in cache.items.0.iter_mut() {
+            item.media_link = MockUploader::link(index);
+        }
+        let indices: Vec<usize> = (0..2).collect();
+        let uploader = Arc::new(MockUploader::new(2));
+        let config_data = ConfigData {
+            metadata_encoding: Some(ContentEncoding::Gzip),
+            ..ConfigData::default()
+        };
+
+        let errors = upload_data(
+            uploader.clone(),
+            &config_data,
+            &assets,
+            &mut cache,
+            &indices,
+            DataType::Metadata,
+            Arc::new(AtomicBool::new(false)),
+        )
+        .await
+        .unwrap();
+        assert!(errors.is_empty());
+
+        let saved = Cache::load(&cache.file_path, false).unwrap();
+        for index in indices {
+            let asset_id = index.to_string();
+            let content = uploader.content(&asset_id).unwrap();
+            // the uploaded file is compressed
+            assert_ne!(content, metadata_json(index).into_bytes());
+
+            // and decompresses to the original metadata with the media link
+            let decoded: serde_json::Value =
+                serde_json::from_slice(&decode_content(&content, ContentEncoding::Gzip).unwrap())
+                    .unwrap();
+            let mut expected: serde_json::Value =
+                serde_json::from_str(&metadata_json(index)).unwrap();
+            expected["image"] = MockUploader::link(&asset_id).into();
+            expected["properties"]["files"][0]["uri"] = MockUploader::link(&asset_id).into();
+            for key in ["animation_url", "external_url", "collection"] {
+                expected[key] = serde_json::Value::Null;
+            }
+            assert_eq!(decoded, expected);
+
+            // the cache records the encoding of the uploaded file
+            let item = &saved.items.0[&asset_id];
+            assert_eq!(item.metadata_link, MockUploader::link(&asset_id));
+            assert_eq!(item.metadata_encoding, Some(ContentEncoding::Gzip));
+        }
+
+        fs::remove_file(path).unwrap();
+        fs::remove_dir_all(assets_path("compressed-metadata")).unwrap();
+    }
+
+    #[tokio::test]
+    async fn metadata_url_templates() {
+        let path = cache_path("url-templates");
+        let mut cache = Cache::new();
+        cache.file_path = path.to_string_lossy().to_string();
+        let assets = test_assets("url-templates", 2, &mut cache);
+        // the metadata of item 1 has its own urls
+        let mut metadata: serde_json::Value = serde_json::from_str(&metadata_json(1)).unwrap();
+        metadata["animation_url"] = "https://example.com/own.mp4".into();
+        metadata["external_url"] = "https://example.com/own".into();
+        fs::write(&assets[&1].metadata, metadata.to_string()).unwrap();
+
+        let indices: Vec<usize> = (0..2).collect();
+        let uploader = Arc::new(MockUploader::new(2));
+        let config_data = ConfigData {
+            animation_url_template: Some("https://example.com/{index}.mp4".to_string()),
+            external_url_template: Some("https://example.com/items/{index}".to_string()),
+            ..ConfigData::default()
+        };
+
+        let errors = upload_data(
+            uploader.clone(),
+            &config_data,
+            &assets,
+            &mut cache,
+            &indices,
+            DataType::Metadata,
+            Arc::new(AtomicBool::new(false)),
+        )
+        .await
+        .unwrap();
+        assert!(errors.is_empty());
+
+        let uploaded = |asset_id: &str| -> serde_json::Value {
+            serde_json::from_slice(&uploader.content(asset_id).unwrap()).unwrap()
+        };
+
+        // the templates are expanded with the index of the item
+        let metadata = uploaded("0");
+        assert_eq!(metadata["animation_url"], "https://example.com/0.mp4");
+        assert_eq!(metadata["external_url"], "https://example.com/items/0");
+
+        // the urls of the metadata file take precedence over the templates
+        let metadata = uploaded("1");
+        assert_eq!(metadata["animation_url"], "https://example.com/own.mp4");
+        assert_eq!(metadata["external_url"], "https://example.com/own");
+
+        fs::remove_file(path).unwrap();
+        fs::remove_dir_all(assets_path("url-templates")).unwrap();
+    }
+
+    #[tokio::test]
+    async fn metadata_with_symbol_overrides() {
+        let path = cache_path("symbol-overrides");
+        let mut cache = Cache::new();
+        cache.file_path = path.to_string_lossy().to_string();
+        let assets = test_assets("symbol-overrides", 3, &mut cache);
+        cache.items.0.get_mut("1").unwrap().symbol_override = Some("OVR".to_string());
+
+        let indices: Vec<usize> = (0..3).collect();
+        let uploader = Arc::new(MockUploader::new(3));
+
+        let errors = upload_data(
+            uploader.clone(),
+            &ConfigData::default(),
+            &assets,
+            &mut cache,
+            &indices,
+            DataType::Metadata,
+            Arc::new(AtomicBool::new(false)),
+        )
+        .await
+        .unwrap();
+        assert!(errors.is_empty());
+
+        // the override replaces the symbol of its item only
+        let symbols: Vec<String> = ["0", "1", "2"]
+            .iter()
+            .map(|asset_id| {
+                let metadata: serde_json::Value =
+                    serde_json::from_slice(&uploader.content(asset_id).unwrap()).unwrap();
+                metadata["symbol"].as_str().unwrap().to_string()
+            })
+            .collect();
+        assert_eq!(symbols, vec!["TEST", "OVR", "TEST"]);
+
+        fs::remove_file(path).unwrap();
+        fs::remove_dir_all(assets_path("symbol-overrides")).unwrap();
+    }
+
+    #[test]
+    fn cache_sync_writes_in_batches() {
+        let path = cache_path("sync-batch");
+        let mut cache = Cache::new();
+        cache.file_path = path.to_string_lossy().to_string();
+        let mut cache_sync = CacheSync::new();
+
+        for _ in 1..CACHE_SYNC_BATCH {
+            cache_sync.record(&mut cache).unwrap();
+        }
+        assert!(!path.exists());
+
+        cache_sync.record(&mut cache).unwrap();
+        assert!(path.exists());
+        assert_eq!(cache_sync.pending, 0);
+
+        // nothing pending, so a flush does not write the file again
+        fs::remove_file(&path).unwrap();
+        cache_sync.flush(&mut cache).unwrap();
+        assert!(!path.exists());
+
+        cache_sync.record(&mut cache).unwrap();
+        cache_sync.flush(&mut cache).unwrap();
+        assert!(path.exists());
+
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -1,18 +1,44 @@
 pub use anchor_client::solana_sdk::hash::Hash;
 use anchor_client::{
     solana_sdk::{
+        account::Account as SolanaAccount,
+        compute_budget,
+        instruction::Instruction,
         program_pack::{IsInitialized, Pack},
         pubkey::Pubkey,
+        transaction::TransactionError,
     },
-    Program,
+    ClientError, Program, RequestBuilder,
 };
 pub use anyhow::{anyhow, Result};
 pub use indicatif::{ProgressBar, ProgressStyle};
-use solana_client::rpc_client::RpcClient;
+use lazy_static::lazy_static;
+use solana_client::{
+    client_error::{ClientError as RpcClientError, ClientErrorKind},
+    rpc_client::RpcClient,
+    rpc_custom_error::JSON_RPC_SERVER_ERROR_NODE_UNHEALTHY,
+    rpc_request::RpcError,
+};
 use spl_token::state::{Account, Mint};
-use std::str::FromStr;
+use std::{
+    collections::HashMap,
+    str::FromStr,
+    sync::Mutex,
+    thread,
+    time::{Duration, Instant},
+};
+use tracing::debug;
 
-use crate::config::data::Cluster;
+use crate::config::data::{Cluster, RetryConfig};
+use crate::constants::{MAX_PRIORITY_FEE_BUMPS, PRIORITY_FEE_COMPUTE_UNITS};
+use crate::errors::SplTokenError;
+use crate::rpc_calls::{record_rpc_call, timed};
+use crate::setup::TimedRpc;
+
+lazy_static! {
+    /// Mints already fetched, so commands in the same run do not query them again.
+    static ref MINT_CACHE: Mutex<HashMap<Pubkey, MintInfo>> = Mutex::new(HashMap::new());
+}
 
 /// Hash for devnet cluster
 pub const DEVNET_HASH: &str = "EtWTRABZaYq6iMfeYKouRu166VU2xqa1wcaWoxPkrZBG";
@@ -20,11 +46,14 @@ pub const DEVNET_HASH: &str = "EtWTRABZaYq6iMfeYKouRu166VU2xqa1wcaWoxPkrZBG";
 /// Hash for mainnet-beta cluster
 pub const MAINNET_HASH: &str = "5eykt4UsFv8P8NJdTREpY1vzqKqZKvdpKuc147dw2N9d";
 
+/// Hash for testnet cluster
+pub const TESTNET_HASH: &str = "4uhcVJyU9pJkvQyS88uRDiswHXSCkY3zQawwpjk2NsNY";
+
 /// Return the environment of the current connected RPC.
 pub fn get_cluster(rpc_client: RpcClient) -> Result<Cluster> {
     let devnet_hash = Hash::from_str(DEVNET_HASH).unwrap();
     let mainnet_hash = Hash::from_str(MAINNET_HASH).unwrap();
-    let genesis_hash = rpc_client.get_genesis_hash()?;
+    let genesis_hash = timed("Get genesis hash", || rpc_client.get_genesis_hash())?;
 
     if genesis_hash == devnet_hash {
         Ok(Cluster::Devnet)
@@ -38,29 +67,72 @@ pub fn get_cluster(rpc_client: RpcClient) -> Result<Cluster> {
     }
 }
 
+/// Information of a spl-token mint.
+#[derive(Debug, Clone, Copy)]
+pub struct MintInfo {
+    pub decimals: u8,
+    pub supply: u64,
+}
+
+/// Return the information of a (initialized) spl-token mint. The information is
+/// cached, so the mint account is only fetched once per run.
+pub fn get_mint_info(program: &Program, mint: &Pubkey) -> Result<MintInfo> {
+    if let Some(info) = MINT_CACHE.lock().unwrap().get(mint) {
+        return Ok(*info);
+    }
+
+    let rpc_client = program.timed_rpc();
+    let account = timed("Get mint", || {
+        rpc_client.get_account_with_commitment(mint, rpc_client.commitment())
+    })?
+    .value;
+
+    let info = parse_mint_info(mint, account.as_ref())?;
+    MINT_CACHE.lock().unwrap().insert(*mint, info);
+
+    Ok(info)
+}
+
+/// Return the information of a mint from its account, checking that the account is an
+/// initialized spl-token mint.
+fn parse_mint_info(mint: &Pubkey, account: Option<&SolanaAccount>) -> Result<MintInfo> {
+    let account = account.ok_or_else(|| SplTokenError::MintNotFound(mint.to_string()))?;
+
+    if account.owner != spl_token::ID || account.data.len() != Mint::LEN {
+        return Err(SplTokenError::NotAMint(mint.to_string(), account.owner.to_string()).into());
+    }
+
+    let token_mint = Mint::unpack_from_slice(&account.data)?;
+
+    if !token_mint.is_initialized {
+        return Err(SplTokenError::MintNotInitialized(mint.to_string()).into());
+    }
+
+    Ok(MintInfo {
+        decimals: token_mint.decimals,
+        supply: token_mint.supply,
+    })
+}
+
 /// Check that the mint token is a valid address.
 pub fn check_spl_token(program: &Program, input: &str) -> Result<()> {
     let pubkey = Pubkey::from_str(input)?;
-    let token_data = program.rpc().get_account_data(&pubkey)?;
-    if token_data.len() != 82 {
-        return Err(anyhow!("Invalid spl-token passed in."));
-    }
-    let token_mint = Mint::unpack_from_slice(&token_data)?;
+    get_mint_info(program, &pubkey)?;
 
-    if token_mint.is_initialized {
-        Ok(())
-    } else {
-        Err(anyhow!(format!(
-            "The specified spl-token is not initialized: {}",
-            input
-        )))
-    }
+    Ok(())
+}
+
+/// Return the number of decimals of the mint token.
+pub fn get_spl_token_decimals(program: &Program, mint: &Pubkey) -> Result<u8> {
+    Ok(get_mint_info(program, mint)?.decimals)
 }
 
 /// Check that the mint token account is a valid account.
 pub fn check_spl_token_account(program: &Program, input: &str) -> Result<()> {
     let pubkey = Pubkey::from_str(input)?;
-    let ata_data = program.rpc().get_account_data(&pubkey)?;
+    let ata_data = timed("Get token account", || {
+        program.timed_rpc().get_account_data(&pubkey)
+    })?;
     let ata_account = Account::unpack_unchecked(&ata_data)?;
 
     if IsInitialized::is_initialized(&ata_account) {
@@ -73,6 +145,45 @@ pub fn check_spl_token_account(program: &Program, input: &str) -> Result<()> {
     }
 }
 
+/// Check that the treasury spl-token account is an initialized token account of the
+/// mint. The owner is not checked, since the treasury can belong to another wallet than
+/// the authority.
+pub fn check_treasury_token_account(
+    rpc_client: &RpcClient,
+    token_account: &Pubkey,
+    mint: &Pubkey,
+) -> Result<(), SplTokenError> {
+    let account = timed("Get token account", || {
+        rpc_client.get_account_with_commitment(token_account, rpc_client.commitment())
+    })
+    .ok()
+    .and_then(|response| response.value)
+    .ok_or_else(|| SplTokenError::TokenAccountNotFound(token_account.to_string()))?;
+
+    let not_a_token_account =
+        || SplTokenError::NotATokenAccount(token_account.to_string(), account.owner.to_string());
+
+    if account.owner != spl_token::ID || account.data.len() != Account::LEN {
+        return Err(not_a_token_account());
+    }
+
+    let token = Account::unpack_from_slice(&account.data).map_err(|_| not_a_token_account())?;
+
+    if !token.is_initialized() {
+        return Err(not_a_token_account());
+    }
+
+    if token.mint != *mint {
+        return Err(SplTokenError::TokenAccountMintMismatch(
+            token_account.to_string(),
+            token.mint.to_string(),
+            mint.to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
 pub fn spinner_with_style() -> ProgressBar {
     let pb = ProgressBar::new_spinner();
     pb.enable_steady_tick(120);
@@ -102,3 +213,375 @@ pub fn progress_bar_with_style(len: u64) -> ProgressBar {
     );
     pb
 }
+
+/// Run an RPC operation, retrying it with exponential backoff when it fails with a
+/// transient error (e.g., rate limit or connection error). Any other error (e.g.,
+/// insufficient funds) is returned straight away.
+pub fn with_retry<T, E, F>(
+    retry_config: &RetryConfig,
+    description: &str,
+    mut operation: F,
+) -> Result<T>
+where
+    E: Into<anyhow::Error>,
+    F: FnMut() -> std::result::Result<T, E>,
+{
+    retry_loop(retry_config, description, |_attempt| operation())
+}
+
+/// Run a transaction with the specified priority fee (in micro-lamports per compute
+/// unit), retrying it as `with_retry` does. The fee passed to the operation is doubled
+/// on each retry (up to `MAX_PRIORITY_FEE_BUMPS` times), so a transaction that is not
+/// landing on a congested network is retried with a higher fee.
+pub fn with_priority_fee_retry<T, E, F>(
+    retry_config: &RetryConfig,
+    priority_fee: u64,
+    description: &str,
+    mut operation: F,
+) -> Result<T>
+where
+    E: Into<anyhow::Error>,
+    F: FnMut(u64) -> std::result::Result<T, E>,
+{
+    retry_loop(retry_config, description, |attempt| {
+        operation(priority_fee_for_attempt(priority_fee, attempt, description))
+    })
+}
+
+/// Return the priority fee of an attempt: the fee is doubled on each retry, up to
+/// `MAX_PRIORITY_FEE_BUMPS` times.
+fn priority_fee_for_attempt(priority_fee: u64, attempt: u32, description: &str) -> u64 {
+    let fee = priority_fee.saturating_mul(1 << attempt.min(MAX_PRIORITY_FEE_BUMPS));
+
+    if attempt > 0 && fee > 0 {
+        debug!(
+            priority_fee = fee,
+            "{} using a higher priority fee", description
+        );
+    }
+
+    fee
+}
+
+fn retry_loop<T, E, F>(retry_config: &RetryConfig, description: &str, mut operation: F) -> Result<T>
+where
+    E: Into<anyhow::Error>,
+    F: FnMut(u32) -> std::result::Result<T, E>,
+{
+    let mut retry = Retry::new(retry_config, description);
+
+    loop {
+        let start = Instant::now();
+        let result = operation(retry.attempt);
+
+        match retry.next_delay(result.map_err(Into::into), start.elapsed()) {
+            RetryResult::Done(result) => return result,
+            RetryResult::Retry(delay) => thread::sleep(delay),
+        }
+    }
+}
+
+/// Run an RPC operation as `with_retry` does, from async code: the delay between
+/// retries does not block the runtime.
+pub async fn with_retry_async<T, E, F>(
+    retry_config: &RetryConfig,
+    description: &str,
+    mut operation: F,
+) -> Result<T>
+where
+    E: Into<anyhow::Error>,
+    F: FnMut() -> std::result::Result<T, E>,
+{
+    with_priority_fee_retry_async(retry_config, 0, description, |_fee| operation()).await
+}
+
+/// Run a transaction as `with_priority_fee_retry` does, from async code: the delay
+/// between retries does not block the runtime, so other tasks (e.g., the parallel
+/// transactions of a deploy) keep running.
+pub async fn with_priority_fee_retry_async<T, E, F>(
+    retry_config: &RetryConfig,
+    priority_fee: u64,
+    description: &str,
+    mut operation: F,
+) -> Result<T>
+where
+    E: Into<anyhow::Error>,
+    F: FnMut(u64) -> std::result::Result<T, E>,
+{
+    let mut retry = Retry::new(retry_config, description);
+
+    loop {
+        let fee = priority_fee_for_attempt(priority_fee, retry.attempt, description);
+        let start = Instant::now();
+        let result = operation(fee);
+
+        match retry.next_delay(result.map_err(Into::into), start.elapsed()) {
+            RetryResult::Done(result) => return result,
+            RetryResult::Retry(delay) => tokio::time::sleep(delay).await,
+        }
+    }
+}
+
+/// Attempts of a retried operation, shared by the blocking and the async retry loops.
+struct Retry<'a> {
+    retry_config: &'a RetryConfig,
+    description: &'a str,
+    attempt: u32,
+    // time spent on the attempts, not including the delay between retries
+    duration: Duration,
+}
+
+enum RetryResult<T> {
+    Done(Result<T>),
+    Retry(Duration),
+}
+
+impl<'a> Retry<'a> {
+    fn new(retry_config: &'a RetryConfig, description: &'a str) -> Self {
+        Retry {
+            retry_config,
+            description,
+            attempt: 0,
+            duration: Duration::ZERO,
+        }
+    }
+
+    /// Record the result of an attempt, returning the delay before the next attempt
+    /// when the operation failed with an error that can be retried.
+    fn next_delay<T>(&mut self, result: Result<T>, elapsed: Duration) -> RetryResult<T> {
+        self.duration += elapsed;
+
+        match result {
+            Ok(value) => {
+                record_rpc_call(self.description, self.duration, self.attempt);
+                RetryResult::Done(Ok(value))
+            }
+            Err(err) => {
+                if self.attempt >= self.retry_config.max_retries || !is_retryable(&err) {
+                    record_rpc_call(self.description, self.duration, self.attempt);
+                    return RetryResult::Done(Err(err));
+                }
+
+                self.attempt += 1;
+                let delay = self.retry_config.delay_ms(self.attempt);
+
+                debug!(
+                    attempt = self.attempt,
+                    max_retries = self.retry_config.max_retries,
+                    delay_ms = delay,
+                    "{} failed, retrying: {}",
+                    self.description,
+                    err
+                );
+
+                RetryResult::Retry(Duration::from_millis(delay))
+            }
+        }
+    }
+}
+
+/// Return the compute budget instructions that set the priority fee (in micro-lamports
+/// per compute unit) of a transaction. No instructions are returned when the fee is zero.
+///
+/// The instructions are built by hand since the SDK version used does not include the
+/// `SetComputeUnitLimit` and `SetComputeUnitPrice` instructions.
+pub fn priority_fee_instructions(priority_fee: u64) -> Vec<Instruction> {
+    if priority_fee == 0 {
+        return Vec::new();
+    }
+
+    // SetComputeUnitLimit (2) and SetComputeUnitPrice (3)
+    let mut limit_data = vec![2];
+    limit_data.extend_from_slice(&PRIORITY_FEE_COMPUTE_UNITS.to_le_bytes());
+    let mut price_data = vec![3];
+    price_data.extend_from_slice(&priority_fee.to_le_bytes());
+
+    vec![
+        Instruction::new_with_bytes(compute_budget::id(), &limit_data, Vec::new()),
+        Instruction::new_with_bytes(compute_budget::id(), &price_data, Vec::new()),
+    ]
+}
+
+/// Add the instructions that set the priority fee to the start of the request.
+pub fn with_priority_fee(mut builder: RequestBuilder, priority_fee: u64) -> RequestBuilder {
+    for instruction in priority_fee_instructions(priority_fee) {
+        builder = builder.instruction(instruction);
+    }
+
+    builder
+}
+
+/// Return `true` if the error is a transient RPC error that is worth retrying.
+fn is_retryable(err: &anyhow::Error) -> bool {
+    let client_error = match err.downcast_ref::<ClientError>() {
+        Some(ClientError::SolanaClientError(client_error)) => Some(client_error),
+        Some(_) => None,
+        None => err.downcast_ref::<RpcClientError>(),
+    };
+
+    let client_error = match client_error {
+        Some(client_error) => client_error,
+        None => return false,
+    };
+
+    // an expired blockhash is fine to retry since the transaction is rebuilt
+    if let Some(TransactionError::BlockhashNotFound) = client_error.get_transaction_error() {
+        return true;
+    }
+
+    match client_error.kind() {
+        ClientErrorKind::Io(_) => true,
+        ClientErrorKind::Reqwest(err) => {
+            err.is_timeout()
+                || err.is_connect()
+                || err.status().map_or(false, |status| {
+                    status.as_u16() == 429 || status.is_server_error()
+                })
+        }
+        ClientErrorKind::RpcError(RpcError::RpcResponseError { code, .. }) => {
+            *code == JSON_RPC_SERVER_ERROR_NODE_UNHEALTHY
+        }
+        // the transaction was not confirmed before its blockhash expired (e.g., it was
+        // dropped on a congested network)
+        ClientErrorKind::RpcError(RpcError::ForUser(message)) => {
+            message.starts_with("unable to confirm transaction")
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{ConfigData, ConfigError};
+
+    /// Return the account of an initialized mint with the specified decimals.
+    fn mint_account(decimals: u8) -> SolanaAccount {
+        let mint = Mint {
+            mint_authority: None.into(),
+            supply: 1_000_000,
+            decimals,
+            is_initialized: true,
+            freeze_authority: None.into(),
+        };
+        let mut data = vec![0; Mint::LEN];
+        Mint::pack(mint, &mut data).unwrap();
+
+        SolanaAccount {
+            lamports: 1_461_600,
+            data,
+            owner: spl_token::ID,
+            executable: false,
+            rent_epoch: 0,
+        }
+    }
+
+    #[test]
+    fn price_precision_of_mint() {
+        let mint = Pubkey::new_unique();
+        let decimals = parse_mint_info(&mint, Some(&mint_account(2)))
+            .unwrap()
+            .decimals;
+        assert_eq!(decimals, 2);
+
+        let config = ConfigData {
+            price: 0.01,
+            ..ConfigData::default()
+        };
+        assert!(config.check_price_precision(decimals).is_ok());
+
+        // the price rounds to a zero amount of the token
+        let config = ConfigData {
+            price: 0.001,
+            ..ConfigData::default()
+        };
+        let error = config.check_price_precision(decimals).unwrap_err();
+        assert!(matches!(
+            &error,
+            ConfigError::PriceBelowTokenPrecision(field, _, 2, min_price)
+                if field == "price" && min_price == "0.01"
+        ));
+        // a price in SOL is precise enough
+        assert!(config.check_price_precision(9).is_ok());
+    }
+
+    #[test]
+    fn invalid_mint_accounts() {
+        let mint = Pubkey::new_unique();
+        assert!(parse_mint_info(&mint, None).is_err());
+
+        let mut account = mint_account(2);
+        account.owner = Pubkey::new_unique();
+        assert!(parse_mint_info(&mint, Some(&account)).is_err());
+
+        let mut account = mint_account(2);
+        account.data = vec![0; Mint::LEN];
+        assert!(parse_mint_info(&mint, Some(&account)).is_err());
+    }
+
+    const RETRY_CONFIG: RetryConfig = RetryConfig {
+        max_retries: 3,
+        base_delay_ms: 1,
+        max_delay_ms: 2,
+    };
+
+    fn connection_error() -> RpcClientError {
+        std::io::Error::new(std::io::ErrorKind::ConnectionReset, "connection reset").into()
+    }
+
+    #[test]
+    fn retry_transient_errors() {
+        let mut attempts = 0;
+        let result = with_retry(&RETRY_CONFIG, "Test", || {
+            attempts += 1;
+            if attempts < 3 {
+                Err(connection_error())
+            } else {
+                Ok(attempts)
+            }
+        });
+
+        assert_eq!(result.unwrap(), 3);
+    }
+
+    #[test]
+    fn retry_gives_up_after_max_retries() {
+        let mut attempts = 0;
+        let result: Result<()> = with_retry(&RETRY_CONFIG, "Test", || {
+            attempts += 1;
+            Err(connection_error())
+        });
+
+        assert!(result.is_err());
+        assert_eq!(attempts, RETRY_CONFIG.max_retries + 1);
+    }
+
+    #[test]
+    fn no_retry_of_other_errors() {
+        let mut attempts = 0;
+        let result: Result<()> = with_retry(&RETRY_CONFIG, "Test", || {
+            attempts += 1;
+            Err(anyhow::anyhow!("insufficient funds"))
+        });
+
+        assert!(result.is_err());
+        assert_eq!(attempts, 1);
+    }
+
+    #[tokio::test]
+    async fn async_retry_bumps_priority_fee() {
+        let mut fees = Vec::new();
+        let result = with_priority_fee_retry_async(&RETRY_CONFIG, 10, "Test", |fee| {
+            fees.push(fee);
+            if fees.len() < 3 {
+                Err(connection_error())
+            } else {
+                Ok(fee)
+            }
+        })
+        .await;
+
+        assert_eq!(result.unwrap(), 40);
+        assert_eq!(fees, vec![10, 20, 40]);
+    }
+}
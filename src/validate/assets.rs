@@ -0,0 +1,436 @@
+use anyhow::Result;
+use ring::digest::{digest, SHA256};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::{self, File},
+    io::Read,
+    path::Path,
+};
+
+use crate::config::data::ConfigData;
+use crate::upload::get_media_extension;
+use crate::validate::{
+    errors::{AssetError, ValidateError},
+    format::Metadata,
+};
+
+/// Number of bytes read from the start of a media file to detect its type.
+const MEDIA_HEADER_SIZE: usize = 64;
+
+/// Media types detected from the content of the media files and their extensions.
+const MEDIA_TYPES: &[(&str, &[&str])] = &[
+    ("image/png", &["png"]),
+    ("image/jpeg", &["jpg", "jpeg"]),
+    ("image/gif", &["gif"]),
+    ("image/webp", &["webp"]),
+    ("image/svg+xml", &["svg"]),
+    ("video/mp4", &["mp4", "mov"]),
+    ("video/webm", &["webm"]),
+    ("audio/mpeg", &["mp3"]),
+    ("audio/wav", &["wav"]),
+    ("audio/flac", &["flac"]),
+    ("model/gltf-binary", &["glb"]),
+];
+
+/// Check the media/metadata pairs of the assets directory against the config: the
+/// indices of the files must be the sequence 0..number without gaps or duplicates, every
+/// pair must exist, the metadata must parse and have an image, the symbol and seller
+/// fee basis points must match the config and the creators shares must add up to 100.
+/// The media files must fit the file size limit of the upload method and their content
+/// must match their extension.
+///
+/// Every problem found is returned (not only the first one), including the warnings
+/// (see [`AssetError::is_warning`]); an error is only returned when the assets directory
+/// cannot be read.
+pub fn validate_assets(dir: &Path, config: &ConfigData) -> Result<Vec<AssetError>> {
+    let assets_dir = dir.to_str().ok_or(ValidateError::InvalidAssetsDirectory)?;
+
+    let extension = get_media_extension(assets_dir)?;
+    let indices = scan_indices(dir)?;
+    let mut errors = validate_indices(&indices, config.number);
+    // metadata files by content, to find the files copied under another index
+    let mut metadata_hashes: HashMap<Vec<u8>, usize> = HashMap::new();
+
+    for index in 0..config.number as usize {
+        if !indices.contains_key(&index) {
+            // already reported as a gap
+            continue;
+        }
+
+        let metadata_file = dir.join(format!("{index}.json"));
+        let media_file = dir.join(format!("{index}.{extension}"));
+
+        if !media_file.is_file() {
+            errors.push(AssetError::MissingFile(
+                index,
+                media_file.display().to_string(),
+            ));
+        } else {
+            errors.extend(validate_media(index, &media_file, &extension, config));
+        }
+
+        if !metadata_file.is_file() {
+            errors.push(AssetError::MissingFile(
+                index,
+                metadata_file.display().to_string(),
+            ));
+            continue;
+        }
+
+        let content = match fs::read(&metadata_file) {
+            Ok(content) => content,
+            Err(err) => {
+                errors.push(AssetError::InvalidMetadata(index, err.to_string()));
+                continue;
+            }
+        };
+
+        let hash = digest(&SHA256, &content).as_ref().to_vec();
+
+        if let Some(other) = metadata_hashes.insert(hash, index) {
+            errors.push(AssetError::DuplicateMetadata(index, other));
+        }
+
+        let metadata: Metadata = match serde_json::from_slice(&content) {
+            Ok(metadata) => metadata,
+            Err(err) => {
+                errors.push(AssetError::InvalidMetadata(index, err.to_string()));
+                continue;
+            }
+        };
+
+        if metadata.image.is_empty() {
+            errors.push(AssetError::MissingImage(index));
+        }
+
+        if config.symbol != metadata.symbol {
+            errors.push(AssetError::MismatchValue(
+                index,
+                "symbol".to_string(),
+                config.symbol.clone(),
+                metadata.symbol.clone(),
+            ));
+        }
+
+        if config.seller_fee_basis_points != metadata.seller_fee_basis_points {
+            errors.push(AssetError::MismatchValue(
+                index,
+                "seller_fee_basis_points".to_string(),
+                config.seller_fee_basis_points.to_string(),
+                metadata.seller_fee_basis_points.to_string(),
+            ));
+        }
+
+        if let Some(creators) = &metadata.properties.creators {
+            let share: u32 = creators.iter().map(|c| c.share as u32).sum();
+
+            if share != 100 {
+                errors.push(AssetError::InvalidCreatorShare(index, share));
+            }
+        }
+    }
+
+    Ok(errors)
+}
+
+/// Return the asset files of the directory by index (the number of the file name),
+/// ignoring the hidden files and the files without a numeric name (e.g., the collection
+/// files).
+fn scan_indices(dir: &Path) -> Result<BTreeMap<usize, Vec<String>>> {
+    let mut indices: BTreeMap<usize, Vec<String>> = BTreeMap::new();
+
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let file_name = match path.file_name().and_then(|name| name.to_str()) {
+            Some(file_name) if !file_name.starts_with('.') && path.is_file() => file_name,
+            _ => continue,
+        };
+        let stem = file_name.split('.').next().unwrap_or_default();
+
+        if let Ok(index) = stem.parse::<usize>() {
+            indices
+                .entry(index)
+                .or_default()
+                .push(file_name.to_string());
+        }
+    }
+
+    for files in indices.values_mut() {
+        files.sort();
+    }
+
+    Ok(indices)
+}
+
+/// Check that the indices of the asset files are the sequence 0..number: every index
+/// has files, no index has more than one media or metadata file (e.g., "2.png" and
+/// "02.png") and there are no files after the last index.
+fn validate_indices(indices: &BTreeMap<usize, Vec<String>>, number: u64) -> Vec<AssetError> {
+    let mut errors = Vec::new();
+
+    for index in 0..number as usize {
+        if !indices.contains_key(&index) {
+            errors.push(AssetError::MissingIndex(index));
+        }
+    }
+
+    for (index, files) in indices {
+        if *index as u64 >= number {
+            errors.push(AssetError::UnexpectedIndex(
+                *index,
+                number,
+                files.join(", "),
+            ));
+            continue;
+        }
+
+        let (metadata, media): (Vec<&String>, Vec<&String>) = files
+            .iter()
+            .partition(|file| file.to_lowercase().ends_with(".json"));
+
+        if metadata.len() > 1 || media.len() > 1 {
+            errors.push(AssetError::DuplicateIndex(*index, files.join(", ")));
+        }
+    }
+
+    errors
+}
+
+/// Check the size of a media file against the limit of the upload method and that its
+/// content matches the extension.
+fn validate_media(
+    index: usize,
+    media_file: &Path,
+    extension: &str,
+    config: &ConfigData,
+) -> Vec<AssetError> {
+    let mut errors = Vec::new();
+    let path = media_file.display().to_string();
+
+    if let Some(max_file_size) = config.upload_method.max_file_size() {
+        let size = fs::metadata(media_file).map(|m| m.len()).unwrap_or(0);
+
+        if size > max_file_size {
+            errors.push(AssetError::FileTooLarge(
+                index,
+                path.clone(),
+                size,
+                config.upload_method.to_string(),
+                max_file_size,
+            ));
+        }
+    }
+
+    // the type is detected from the content, since the extension of a file does not
+    // guarantee its type
+    let mut header = Vec::with_capacity(MEDIA_HEADER_SIZE);
+
+    if let Ok(file) = File::open(media_file) {
+        let _ = file.take(MEDIA_HEADER_SIZE as u64).read_to_end(&mut header);
+    }
+
+    match detect_media_type(&header) {
+        Some(media_type) => {
+            let extension = extension.to_lowercase();
+            let matches = MEDIA_TYPES.iter().any(|(mime, extensions)| {
+                *mime == media_type && extensions.contains(&extension.as_str())
+            });
+
+            if !matches {
+                errors.push(AssetError::MediaTypeMismatch(
+                    index,
+                    path,
+                    media_type.to_string(),
+                ));
+            }
+        }
+        // there are valid media types that are not detected (e.g., unusual formats), so
+        // only a mismatch with the extension is an error
+        None => errors.push(AssetError::UnknownMediaType(index, path)),
+    }
+
+    errors
+}
+
+/// Return the media type of a file from the first bytes of its content.
+pub fn detect_media_type(header: &[u8]) -> Option<&'static str> {
+    let starts_at = |offset: usize, signature: &[u8]| {
+        header.len() >= offset + signature.len()
+            && &header[offset..offset + signature.len()] == signature
+    };
+
+    if starts_at(0, b"\x89PNG\r\n\x1a\n") {
+        Some("image/png")
+    } else if starts_at(0, b"\xff\xd8\xff") {
+        Some("image/jpeg")
+    } else if starts_at(0, b"GIF87a") || starts_at(0, b"GIF89a") {
+        Some("image/gif")
+    } else if starts_at(0, b"RIFF") && starts_at(8, b"WEBP") {
+        Some("image/webp")
+    } else if starts_at(0, b"RIFF") && starts_at(8, b"WAVE") {
+        Some("audio/wav")
+    } else if starts_at(4, b"ftyp") {
+        Some("video/mp4")
+    } else if starts_at(0, b"\x1a\x45\xdf\xa3") {
+        Some("video/webm")
+    } else if starts_at(0, b"ID3") || starts_at(0, b"\xff\xfb") || starts_at(0, b"\xff\xf3") {
+        Some("audio/mpeg")
+    } else if starts_at(0, b"fLaC") {
+        Some("audio/flac")
+    } else if starts_at(0, b"glTF") {
+        Some("model/gltf-binary")
+    } else {
+        let text = String::from_utf8_lossy(header);
+        let text = text.trim_start_matches('\u{feff}').trim_start();
+
+        if text.starts_with("<svg") || text.starts_with("<?xml") {
+            Some("image/svg+xml")
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, io::Write};
+
+    use super::*;
+    use crate::config::UploadMethod;
+
+    /// Return the asset files of the specified file names by index, sorted as in
+    /// [`scan_indices`].
+    fn indices_of(files: &[&str]) -> BTreeMap<usize, Vec<String>> {
+        let mut indices: BTreeMap<usize, Vec<String>> = BTreeMap::new();
+
+        for file in files {
+            let index = file.split('.').next().unwrap().parse().unwrap();
+            indices.entry(index).or_default().push(file.to_string());
+        }
+
+        for files in indices.values_mut() {
+            files.sort();
+        }
+
+        indices
+    }
+
+    #[test]
+    fn gap_in_asset_indices() {
+        let indices = indices_of(&["0.png", "0.json", "1.png", "1.json", "3.png", "3.json"]);
+        let errors = validate_indices(&indices, 4);
+
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(errors[0], AssetError::MissingIndex(2)));
+    }
+
+    #[test]
+    fn duplicate_asset_indices() {
+        let indices = indices_of(&["0.png", "0.json", "1.png", "01.png", "1.json", "2.png"]);
+        let errors = validate_indices(&indices, 2);
+
+        assert_eq!(errors.len(), 2);
+        assert!(matches!(
+            &errors[0],
+            AssetError::DuplicateIndex(1, files) if files == "01.png, 1.json, 1.png"
+        ));
+        assert!(matches!(
+            &errors[1],
+            AssetError::UnexpectedIndex(2, 2, files) if files == "2.png"
+        ));
+    }
+
+    #[test]
+    fn unknown_media_type_is_a_warning() {
+        let dir = env::temp_dir().join(format!("sugar-media-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let config = ConfigData::default();
+
+        // content that is not detected, e.g., a format without a known signature
+        let unknown = dir.join("0.tga");
+        fs::write(&unknown, b"\x00\x00\x02\x00 targa").unwrap();
+        let unknown_errors = validate_media(0, &unknown, "tga", &config);
+
+        // the content of a png with a jpg extension
+        let mismatch = dir.join("1.jpg");
+        fs::write(&mismatch, b"\x89PNG\r\n\x1a\n").unwrap();
+        let mismatch_errors = validate_media(1, &mismatch, "jpg", &config);
+
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(unknown_errors.len(), 1);
+        assert!(matches!(
+            unknown_errors[0],
+            AssetError::UnknownMediaType(0, _)
+        ));
+        assert!(unknown_errors[0].is_warning());
+
+        assert_eq!(mismatch_errors.len(), 1);
+        assert!(matches!(
+            &mismatch_errors[0],
+            AssetError::MediaTypeMismatch(1, _, media_type) if media_type == "image/png"
+        ));
+        assert!(!mismatch_errors[0].is_warning());
+    }
+
+    #[test]
+    fn media_file_too_large() {
+        let dir = env::temp_dir().join(format!("sugar-large-media-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let config = ConfigData {
+            upload_method: UploadMethod::NftStorage,
+            ..ConfigData::default()
+        };
+        let max_file_size = config.upload_method.max_file_size().unwrap();
+
+        // a png one byte over the limit (the file is sparse, so nothing is written)
+        let large = dir.join("3.png");
+        let mut file = File::create(&large).unwrap();
+        file.write_all(b"\x89PNG\r\n\x1a\n").unwrap();
+        file.set_len(max_file_size + 1).unwrap();
+        let large_errors = validate_media(3, &large, "png", &config);
+
+        // a png at the limit
+        file.set_len(max_file_size).unwrap();
+        let limit_errors = validate_media(3, &large, "png", &config);
+
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(large_errors.len(), 1);
+        assert!(matches!(
+            &large_errors[0],
+            AssetError::FileTooLarge(3, _, size, upload_method, max)
+                if *size == max_file_size + 1
+                    && upload_method == "nft.storage"
+                    && *max == max_file_size
+        ));
+        assert!(!large_errors[0].is_warning());
+        assert!(limit_errors.is_empty());
+    }
+
+    #[test]
+    fn scan_asset_directory() {
+        let dir = env::temp_dir().join(format!("sugar-assets-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        for file in [
+            "0.png",
+            "0.json",
+            "2.png",
+            "2.json",
+            "collection.png",
+            ".DS_Store",
+        ] {
+            fs::write(dir.join(file), b"").unwrap();
+        }
+
+        let indices = scan_indices(&dir);
+        fs::remove_dir_all(&dir).unwrap();
+
+        // the collection and hidden files are not assets
+        assert_eq!(
+            indices.unwrap(),
+            indices_of(&["0.json", "0.png", "2.json", "2.png"])
+        );
+    }
+}
@@ -35,3 +35,52 @@ pub enum ValidateError {
     #[error("Missing collection field")]
     MissingCollection,
 }
+
+#[derive(Debug, Error)]
+pub enum AssetError {
+    #[error("Asset {0}: missing file '{1}'")]
+    MissingFile(usize, String),
+
+    #[error("Asset {0}: invalid metadata file: {1}")]
+    InvalidMetadata(usize, String),
+
+    #[error("Asset {0}: missing 'image' value in metadata")]
+    MissingImage(usize),
+
+    #[error(
+        "Asset {0}: mismatch value for \"{1}\": expected \"{2}\" (from config), found \"{3}\""
+    )]
+    MismatchValue(usize, String, String, String),
+
+    #[error("Asset {0}: creators' share adds up to {1}, it must be 100")]
+    InvalidCreatorShare(usize, u32),
+
+    #[error("Asset {0}: file '{1}' has {2} bytes, {3} accepts files up to {4} bytes")]
+    FileTooLarge(usize, String, u64, String, u64),
+
+    #[error("Asset {0}: file '{1}' contains {2} data, which does not match its extension")]
+    MediaTypeMismatch(usize, String, String),
+
+    #[error("Asset {0}: the media type of file '{1}' could not be detected from its content")]
+    UnknownMediaType(usize, String),
+
+    #[error("Asset {0}: missing media and metadata files (gap in the asset indices)")]
+    MissingIndex(usize),
+
+    #[error("Asset {0}: several files have the same index: {1}")]
+    DuplicateIndex(usize, String),
+
+    #[error("Asset {0}: index is out of the {1} item(s) of the config (files: {2})")]
+    UnexpectedIndex(usize, u64, String),
+
+    #[error("Asset {0}: metadata file has the same content as asset {1}")]
+    DuplicateMetadata(usize, usize),
+}
+
+impl AssetError {
+    /// Return `true` for the problems that are reported without failing the validation:
+    /// a media type that is not detected may still be valid for the upload method.
+    pub fn is_warning(&self) -> bool {
+        matches!(self, AssetError::UnknownMediaType(_, _))
+    }
+}
@@ -63,6 +63,14 @@ pub struct Collection {
 pub struct Property {
     pub files: Vec<FileAttr>,
     pub category: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub creators: Option<Vec<CreatorAttr>>,
+}
+
+#[derive(Debug, Clone, Deserialize, Default, Serialize)]
+pub struct CreatorAttr {
+    pub address: String,
+    pub share: u8,
 }
 
 #[derive(Debug, Clone, Deserialize, Default, Serialize)]
@@ -1,8 +1,10 @@
+pub mod assets;
 pub mod errors;
 pub mod format;
 pub mod parser;
 pub mod process;
 
+pub use assets::*;
 pub use errors::*;
 pub use format::*;
 pub use parser::*;
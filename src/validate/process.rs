@@ -36,7 +36,7 @@ pub fn process_validate(args: ValidateArgs) -> Result<()> {
     let path = assets_dir.join("*.json");
     let pattern = path.to_str().ok_or(ValidateError::InvalidAssetsDirectory)?;
 
-    let (paths, errors): (Vec<_>, Vec<_>) = glob(pattern)?.into_iter().partition(Result::is_ok);
+    let (paths, errors): (Vec<_>, Vec<_>) = glob(pattern)?.partition(Result::is_ok);
 
     let pb = spinner_with_style();
     pb.enable_steady_tick(120);
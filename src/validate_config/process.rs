@@ -0,0 +1,198 @@
+use anchor_client::solana_sdk::signature::Signer;
+use console::style;
+use serde::Serialize;
+
+use crate::common::*;
+use crate::config::{parse_config_data, ConfigData, ConfigFormat, TreasuryConfig};
+use crate::rpc_calls::timed;
+use crate::setup::{check_rpc_health, get_keypair, get_rpc_url, setup_rpc_client};
+use crate::utils::{check_treasury_token_account, get_cluster};
+
+pub struct ValidateConfigArgs {
+    pub config: String,
+    pub keypair: Option<String>,
+    pub rpc_url: Option<String>,
+    pub config_format: Option<ConfigFormat>,
+    pub json: bool,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct ValidationReport {
+    pub valid: bool,
+    pub signer: Option<String>,
+    pub error_count: usize,
+    pub warning_count: usize,
+    pub errors: Vec<String>,
+    pub warnings: Vec<String>,
+}
+
+impl ValidationReport {
+    fn error<T: ToString>(&mut self, message: T) {
+        self.errors.push(message.to_string());
+    }
+
+    fn warning<T: ToString>(&mut self, message: T) {
+        self.warnings.push(message.to_string());
+    }
+
+    fn finalize(&mut self) {
+        self.error_count = self.errors.len();
+        self.warning_count = self.warnings.len();
+        self.valid = self.errors.is_empty();
+    }
+}
+
+pub fn process_validate_config(args: ValidateConfigArgs) -> Result<()> {
+    if !args.json {
+        println!(
+            "{} {}Validating config file",
+            style("[1/2]").bold().dim(),
+            LOOKING_GLASS_EMOJI
+        );
+    }
+
+    let mut report = ValidationReport::default();
+
+    let config_data = match parse_config_data(&args.config, args.config_format) {
+        Ok(config_data) => {
+            if let Err(errors) = config_data.validate() {
+                for err in errors {
+                    report.error(err);
+                }
+            }
+            for warning in config_data.warnings() {
+                report.warning(warning);
+            }
+            Some(config_data)
+        }
+        Err(err) => {
+            report.error(err);
+            None
+        }
+    };
+
+    check_keypair(args.keypair, &mut report);
+
+    if !args.json {
+        println!(
+            "\n{} {}Checking RPC and accounts (no transactions are sent)",
+            style("[2/2]").bold().dim(),
+            COMPUTER_EMOJI
+        );
+    }
+
+    match get_rpc_url(args.rpc_url) {
+        Ok(rpc_url) => check_chain(&rpc_url, config_data.as_ref(), &mut report),
+        Err(err) => report.error(err),
+    }
+
+    report.finalize();
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        // the JSON report is the whole output, so we exit directly with the
+        // appropriate status code
+        std::process::exit(if report.valid { 0 } else { 1 });
+    }
+
+    if let Some(signer) = &report.signer {
+        println!("{} {}", style("Signer:").bold(), signer);
+    }
+
+    for err in &report.errors {
+        println!("{} {}", style("error:").red().bold(), err);
+    }
+
+    for warning in &report.warnings {
+        println!("{} {}", style("warning:").yellow().bold(), warning);
+    }
+
+    println!(
+        "\n{} error(s), {} warning(s) found",
+        report.error_count, report.warning_count
+    );
+
+    if report.valid {
+        Ok(())
+    } else {
+        Err(anyhow!("Invalid config file '{}'", args.config))
+    }
+}
+
+/// Check that the keypair file exists (or the seed phrase is valid) and it is a valid
+/// keypair, reporting its public key as the signer.
+fn check_keypair(keypair: Option<String>, report: &mut ValidationReport) {
+    match get_keypair(keypair) {
+        Ok((keypair, _)) => report.signer = Some(keypair.pubkey().to_string()),
+        Err(err) => report.error(err),
+    }
+}
+
+/// Check that the RPC is healthy and that the treasury account exists.
+fn check_chain(rpc_url: &str, config_data: Option<&ConfigData>, report: &mut ValidationReport) {
+    let rpc_timeout_secs = config_data.and_then(|c| c.rpc_timeout_secs);
+    let rpc_client = match setup_rpc_client(rpc_url, rpc_timeout_secs) {
+        Ok(rpc_client) => rpc_client,
+        Err(err) => {
+            report.error(err);
+            return;
+        }
+    };
+
+    match check_rpc_health(rpc_url) {
+        Ok(health) => {
+            if let Some(reason) = health.unhealthy {
+                report.error(format!(
+                    "RPC '{}' (version {}) is not healthy: {}",
+                    rpc_url, health.version, reason
+                ));
+                return;
+            }
+        }
+        Err(err) => {
+            report.error(format!("RPC '{}' is not reachable: {}", rpc_url, err));
+            return;
+        }
+    }
+
+    let config_data = match config_data {
+        Some(config_data) => config_data,
+        None => return,
+    };
+
+    match setup_rpc_client(rpc_url, rpc_timeout_secs).and_then(get_cluster) {
+        Ok(cluster) => {
+            for warning in config_data.cluster_warnings(&cluster) {
+                report.warning(warning);
+            }
+        }
+        Err(err) => report.warning(err),
+    }
+
+    match config_data.treasury {
+        TreasuryConfig::Spl {
+            token,
+            account: Some(spl_token_account),
+        } => {
+            if let Err(err) = check_treasury_token_account(&rpc_client, &spl_token_account, &token)
+            {
+                report.error(err);
+            }
+        }
+        TreasuryConfig::Sol(sol_treasury_account) => match timed("Get treasury account", || {
+            rpc_client.get_account_with_commitment(&sol_treasury_account, rpc_client.commitment())
+        }) {
+            Ok(response) if response.value.is_some() => (),
+            // a system account does not need to exist to receive funds
+            Ok(_) => report.warning(format!(
+                "Treasury account '{}' does not exist on chain",
+                sol_treasury_account
+            )),
+            Err(err) => report.error(format!(
+                "Failed to fetch treasury account '{}': {}",
+                sol_treasury_account, err
+            )),
+        },
+        _ => (),
+    }
+}
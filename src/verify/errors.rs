@@ -6,4 +6,8 @@ pub enum VerifyError {
     FailedToGetAccountData(String),
     #[error("{0} mismatch (expected='{1}', found='{2}')")]
     Mismatch(String, String, String),
+    #[error("Failed to fetch metadata from '{0}' ({1}).")]
+    UriNotAvailable(String, String),
+    #[error("Invalid metadata at '{0}': not a JSON file.")]
+    InvalidMetadata(String),
 }
@@ -1,21 +1,36 @@
 use anchor_lang::AccountDeserialize;
 use console::style;
+use futures::stream::{self, StreamExt};
 use std::{thread, time::Duration};
 
-use mpl_candy_machine::CandyMachine;
+use mpl_candy_machine::{CandyMachine, ConfigLine};
 
 use crate::cache::*;
-use crate::candy_machine::ID as CANDY_MACHINE_ID;
 use crate::common::*;
-use crate::config::Cluster;
-use crate::constants::{CANDY_EMOJI, PAPER_EMOJI};
+use crate::config::{parser::get_placeholder_settings, Cluster, ConfigFormat};
+use crate::constants::{CANDY_EMOJI, LOOKING_GLASS_EMOJI, PAPER_EMOJI};
+use crate::rpc_calls::timed;
+use crate::upload::decode_content;
 use crate::utils::*;
 use crate::verify::VerifyError;
 
+/// Gateway used to fetch `ipfs://` URIs.
+const IPFS_GATEWAY: &str = "https://ipfs.io/ipfs/";
+
+/// Timeout (in seconds) of each metadata request.
+const FETCH_TIMEOUT: u64 = 30;
+
+/// First bytes of a gzip compressed file.
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
 pub struct VerifyArgs {
+    pub config: String,
+    pub config_format: Option<ConfigFormat>,
     pub keypair: Option<String>,
     pub rpc_url: Option<String>,
     pub cache: String,
+    pub check_uris: bool,
+    pub concurrency: usize,
 }
 
 #[derive(Debug)]
@@ -24,7 +39,7 @@ pub struct OnChainItem {
     pub uri: String,
 }
 
-pub fn process_verify(args: VerifyArgs) -> Result<()> {
+pub async fn process_verify(args: VerifyArgs) -> Result<()> {
     let sugar_config = sugar_setup(args.keypair, args.rpc_url)?;
 
     // loads the cache file (this needs to have been created by
@@ -43,9 +58,12 @@ pub fn process_verify(args: VerifyArgs) -> Result<()> {
         return Err(CacheError::CacheFileNotFound(args.cache).into());
     }
 
+    // the config lines of a hidden settings uri template are placeholders
+    let placeholders = get_placeholder_settings(&args.config, args.config_format)?;
+
     println!(
         "{} {}Loading candy machine",
-        style("[1/2]").bold().dim(),
+        style("[1/3]").bold().dim(),
         CANDY_EMOJI
     );
 
@@ -63,10 +81,11 @@ pub fn process_verify(args: VerifyArgs) -> Result<()> {
         }
     };
 
-    let client = setup_client(&sugar_config)?;
-    let program = client.program(CANDY_MACHINE_ID);
+    let rpc_client = setup_rpc_client(&sugar_config.rpc_url, None)?;
 
-    let data = match program.rpc().get_account_data(&candy_machine_pubkey) {
+    let data = match timed("Get candy machine", || {
+        rpc_client.get_account_data(&candy_machine_pubkey)
+    }) {
         Ok(account_data) => account_data,
         Err(err) => {
             return Err(VerifyError::FailedToGetAccountData(err.to_string()).into());
@@ -78,7 +97,7 @@ pub fn process_verify(args: VerifyArgs) -> Result<()> {
 
     println!(
         "\n{} {}Verification",
-        style("[2/2]").bold().dim(),
+        style("[2/3]").bold().dim(),
         PAPER_EMOJI
     );
 
@@ -93,16 +112,12 @@ pub fn process_verify(args: VerifyArgs) -> Result<()> {
         let step: u64 = 1_000_000 / num_items as u64;
 
         for i in 0..num_items {
-            let name_start = CONFIG_ARRAY_START
-                + STRING_LEN_SIZE
-                + CONFIG_LINE_SIZE * (i as usize)
-                + CONFIG_NAME_OFFSET;
+            let name_start =
+                CONFIG_ARRAY_START + STRING_LEN_SIZE + CONFIG_LINE_SIZE * i + CONFIG_NAME_OFFSET;
             let name_end = name_start + MAX_NAME_LENGTH;
 
-            let uri_start = CONFIG_ARRAY_START
-                + STRING_LEN_SIZE
-                + CONFIG_LINE_SIZE * (i as usize)
-                + CONFIG_URI_OFFSET;
+            let uri_start =
+                CONFIG_ARRAY_START + STRING_LEN_SIZE + CONFIG_LINE_SIZE * i + CONFIG_URI_OFFSET;
             let uri_end = uri_start + MAX_URI_LENGTH;
 
             let name_error = format!("Failed to decode name for item {}", i);
@@ -122,7 +137,15 @@ pub fn process_verify(args: VerifyArgs) -> Result<()> {
                 .get_mut(&i.to_string())
                 .expect("Failed to get item from config.");
 
-            if let Err(err) = items_match(cache_item, &on_chain_item) {
+            let expected = match &placeholders {
+                Some(placeholders) => placeholders.placeholder_config_line(i as u64),
+                None => ConfigLine {
+                    name: cache_item.config_name().to_string(),
+                    uri: cache_item.metadata_link.clone(),
+                },
+            };
+
+            if let Err(err) = items_match(&expected, &on_chain_item) {
                 cache_item.on_chain = false;
                 errors.push((i.to_string(), err.to_string()));
             }
@@ -136,18 +159,43 @@ pub fn process_verify(args: VerifyArgs) -> Result<()> {
         if !errors.is_empty() {
             cache.sync_file()?;
 
-            let total = errors.len();
             println!("\nInvalid items found: ");
 
-            for e in errors {
+            for e in &errors {
                 println!("- Item {}: {}", e.0, e.1);
             }
             println!("\nCache updated - re-run `deploy`.");
+        }
+
+        println!(
+            "\n{} {}Checking metadata URIs {}",
+            style("[3/3]").bold().dim(),
+            LOOKING_GLASS_EMOJI,
+            if args.check_uris { "" } else { "(skipping)" }
+        );
+
+        let uri_errors = if args.check_uris {
+            verify_uris(&cache.items.0, args.concurrency).await
+        } else {
+            Vec::new()
+        };
+
+        if !uri_errors.is_empty() {
+            println!("\nInvalid metadata found: ");
+
+            for e in &uri_errors {
+                println!("- Item {}: {}", e.0, e.1);
+            }
+            println!("\nCheck the uploaded files - re-run `upload` if needed.");
+        }
 
+        let total = errors.len() + uri_errors.len();
+
+        if total > 0 {
             return Err(anyhow!("{} invalid item(s) found.", total));
         }
 
-        let cluster = match get_cluster(program.rpc())? {
+        let cluster = match get_cluster(rpc_client)? {
             Cluster::Devnet => "devnet",
             Cluster::Mainnet => "mainnet",
         };
@@ -166,18 +214,18 @@ pub fn process_verify(args: VerifyArgs) -> Result<()> {
     Ok(())
 }
 
-fn items_match(cache_item: &CacheItem, on_chain_item: &OnChainItem) -> Result<()> {
-    if cache_item.name != on_chain_item.name {
+fn items_match(expected: &ConfigLine, on_chain_item: &OnChainItem) -> Result<()> {
+    if expected.name != on_chain_item.name {
         return Err(VerifyError::Mismatch(
             "name".to_string(),
-            cache_item.name.clone(),
+            expected.name.clone(),
             on_chain_item.name.clone(),
         )
         .into());
-    } else if cache_item.metadata_link != on_chain_item.uri {
+    } else if expected.uri != on_chain_item.uri {
         return Err(VerifyError::Mismatch(
             "uri".to_string(),
-            cache_item.metadata_link.clone(),
+            expected.uri.clone(),
             on_chain_item.uri.clone(),
         )
         .into());
@@ -185,3 +233,109 @@ fn items_match(cache_item: &CacheItem, on_chain_item: &OnChainItem) -> Result<()
 
     Ok(())
 }
+
+/// Fetch the metadata of every cache item (up to `concurrency` requests in parallel) and
+/// check that it matches the cache. Return the list of `(index, error)` found.
+async fn verify_uris(
+    cache_items: &IndexMap<String, CacheItem>,
+    concurrency: usize,
+) -> Vec<(String, String)> {
+    println!(
+        "Fetching {} metadata file(s): (Ctrl+C to abort)",
+        cache_items.len()
+    );
+    let pb = progress_bar_with_style(cache_items.len() as u64);
+
+    let http_client = match HttpClient::builder()
+        .timeout(Duration::from_secs(FETCH_TIMEOUT))
+        .build()
+    {
+        Ok(http_client) => http_client,
+        Err(err) => {
+            pb.abandon();
+            return vec![("-".to_string(), err.to_string())];
+        }
+    };
+
+    let mut errors: Vec<(String, String)> = stream::iter(cache_items.iter())
+        .map(|(index, item)| {
+            let http_client = &http_client;
+            async move { (index.clone(), check_metadata(http_client, item).await) }
+        })
+        .buffer_unordered(concurrency.max(1))
+        .filter_map(|(index, result)| {
+            pb.inc(1);
+            async move { result.err().map(|err| (index, err.to_string())) }
+        })
+        .collect()
+        .await;
+
+    pb.finish();
+
+    errors.sort_by_key(|(index, _)| index.parse::<u64>().unwrap_or(u64::MAX));
+    errors
+}
+
+/// Check that the metadata URI of the item resolves to a JSON file with the expected
+/// name and image.
+async fn check_metadata(http_client: &HttpClient, cache_item: &CacheItem) -> Result<()> {
+    let uri = &cache_item.metadata_link;
+    let url = match uri.strip_prefix("ipfs://") {
+        Some(cid) => format!("{}{}", IPFS_GATEWAY, cid),
+        None => uri.clone(),
+    };
+
+    let response = http_client
+        .get(&url)
+        .send()
+        .await
+        .map_err(|err| VerifyError::UriNotAvailable(uri.clone(), err.to_string()))?;
+
+    let status = response.status();
+
+    if !status.is_success() {
+        return Err(VerifyError::UriNotAvailable(uri.clone(), status.to_string()).into());
+    }
+
+    let mut content = response
+        .bytes()
+        .await
+        .map_err(|err| VerifyError::UriNotAvailable(uri.clone(), err.to_string()))?
+        .to_vec();
+
+    // the compressed metadata is decompressed here when the storage or the client did
+    // not decode it (i.e., the content still has the gzip header)
+    if let Some(encoding) = cache_item.metadata_encoding {
+        if content.starts_with(&GZIP_MAGIC) {
+            content = decode_content(&content, encoding)
+                .map_err(|_| VerifyError::InvalidMetadata(uri.clone()))?;
+        }
+    }
+
+    let metadata: Value =
+        serde_json::from_slice(&content).map_err(|_| VerifyError::InvalidMetadata(uri.clone()))?;
+
+    let name = metadata["name"].as_str().unwrap_or_default();
+
+    if name != cache_item.name {
+        return Err(VerifyError::Mismatch(
+            "metadata name".to_string(),
+            cache_item.name.clone(),
+            name.to_string(),
+        )
+        .into());
+    }
+
+    let image = metadata["image"].as_str().unwrap_or_default();
+
+    if image != cache_item.media_link {
+        return Err(VerifyError::Mismatch(
+            "metadata image".to_string(),
+            cache_item.media_link.clone(),
+            image.to_string(),
+        )
+        .into());
+    }
+
+    Ok(())
+}
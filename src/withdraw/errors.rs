@@ -0,0 +1,13 @@
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum WithdrawError {
+    #[error("Failed to get candy machine account {0}: {1}")]
+    FailedToGetAccount(String, String),
+    #[error("The keypair {2} is not the authority of candy machine {0} (authority is {1}), use the authority keypair to withdraw.")]
+    AuthorityMismatch(String, String, String),
+    #[error("Missing candy machine in cache file '{0}': use --candy-machine to withdraw from a candy machine or --all to withdraw from all candy machines of the keypair.")]
+    MissingCandyMachine(String),
+    #[error("Failed to look up the candy machines of the keypair: {0}. The RPC might not support large 'getProgramAccounts' requests: use an RPC that does or withdraw from each candy machine with --candy-machine.")]
+    ProgramAccountsFailed(String),
+}
@@ -1,6 +1,6 @@
 pub use anchor_client::{
     solana_sdk::{
-        commitment_config::{CommitmentConfig, CommitmentLevel},
+        account::Account as SolanaAccount,
         native_token::LAMPORTS_PER_SOL,
         pubkey::Pubkey,
         signature::{Keypair, Signature, Signer},
@@ -10,7 +10,7 @@ pub use anchor_client::{
     Client, Program,
 };
 use console::style;
-use solana_account_decoder::UiAccountEncoding;
+use solana_account_decoder::{UiAccountEncoding, UiDataSliceConfig};
 use solana_client::{
     rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig},
     rpc_filter::{Memcmp, MemcmpEncodedBytes, RpcFilterType},
@@ -24,16 +24,23 @@ use std::{
 use mpl_candy_machine::accounts as nft_accounts;
 use mpl_candy_machine::instruction as nft_instruction;
 
+use crate::cache::load_cache;
 use crate::candy_machine::ID as CANDY_MACHINE_ID;
 use crate::common::*;
+use crate::config::{format_sol, RetryConfig};
+use crate::rpc_calls::timed;
 use crate::setup::{setup_client, sugar_setup};
 use crate::utils::*;
+use crate::withdraw::WithdrawError;
 
 pub struct WithdrawArgs {
     pub candy_machine: Option<String>,
     pub keypair: Option<String>,
     pub rpc_url: Option<String>,
     pub list: bool,
+    pub all: bool,
+    pub cache: String,
+    pub dry_run: bool,
 }
 
 pub fn process_withdraw(args: WithdrawArgs) -> Result<()> {
@@ -56,78 +63,94 @@ pub fn process_withdraw(args: WithdrawArgs) -> Result<()> {
         "\n{} {}{} funds",
         style("[2/2]").bold().dim(),
         WITHDRAW_EMOJI,
-        if args.list { "Listing" } else { "Retrieving" }
+        if args.list || args.dry_run {
+            "Listing"
+        } else {
+            "Retrieving"
+        }
     );
 
     // the --list flag takes precedence; even if a candy machine id is passed
-    // as an argument, we will list the candy machines (no draining happens)
-    let candy_machine = if args.list { None } else { args.candy_machine };
+    // as an argument, we will list the candy machines (no draining happens);
+    // without --all, the candy machine defaults to the one from the cache
+    let candy_machine = if args.list || args.all {
+        None
+    } else {
+        match args.candy_machine {
+            Some(candy_machine) => Some(candy_machine),
+            None => {
+                let cache = load_cache(&args.cache, false)?;
+
+                if cache.program.candy_machine.is_empty() {
+                    return Err(WithdrawError::MissingCandyMachine(args.cache).into());
+                }
+
+                Some(cache.program.candy_machine)
+            }
+        }
+    };
 
     // (2) Retrieving data for listing/draining
 
     match &candy_machine {
         Some(candy_machine) => {
             let candy_machine = Pubkey::from_str(candy_machine)?;
+            let lamports = check_withdraw(&program, &candy_machine, &payer)?;
 
-            let pb = spinner_with_style();
-            pb.set_message("Draining candy machine...");
+            if args.dry_run {
+                print_reclaimable(&[(candy_machine, lamports)]);
+            } else {
+                let pb = spinner_with_style();
+                pb.set_message("Draining candy machine...");
 
-            do_withdraw(Rc::new(program), candy_machine, payer)?;
+                timed("Withdraw", || {
+                    do_withdraw(Rc::new(program), candy_machine, payer)
+                })?;
 
-            pb.finish_with_message("Done");
+                pb.finish_with_message("Done");
+            }
         }
         None => {
-            let config = RpcProgramAccountsConfig {
-                filters: Some(vec![RpcFilterType::Memcmp(Memcmp {
-                    offset: 8, // key
-                    bytes: MemcmpEncodedBytes::Base58(payer.to_string()),
-                    encoding: None,
-                })]),
-                account_config: RpcAccountInfoConfig {
-                    encoding: Some(UiAccountEncoding::Base64),
-                    data_slice: None,
-                    commitment: Some(CommitmentConfig {
-                        commitment: CommitmentLevel::Confirmed,
-                    }),
-                },
-                with_context: None,
-            };
-
             let pb = spinner_with_style();
             pb.set_message("Looking up candy machines...");
 
             let program = Rc::new(program);
-            let accounts = program
-                .rpc()
-                .get_program_accounts_with_config(&program.id(), config)?;
+            let accounts = match get_authority_candy_machines(&program, &payer) {
+                Ok(accounts) => accounts,
+                Err(err) => {
+                    pb.finish_and_clear();
+                    return Err(err);
+                }
+            };
 
             pb.finish_and_clear();
 
-            let mut total = 0.0f64;
-
-            accounts.iter().for_each(|account| {
-                let (_pubkey, account) = account;
-                total += account.lamports as f64;
-            });
+            let total: u64 = accounts.iter().map(|(_, account)| account.lamports).sum();
 
             println!(
                 "Found {} candy machines, total amount: ◎ {}",
                 accounts.len(),
-                total / LAMPORTS_PER_SOL as f64
+                format_sol(total)
             );
 
             if accounts.is_empty() {
                 // nothing else to do, we just say goodbye
                 println!("\n{}", style("[Completed]").bold().dim());
+            } else if args.dry_run {
+                let reclaimable: Vec<(Pubkey, u64)> = accounts
+                    .iter()
+                    .map(|(pubkey, account)| (*pubkey, account.lamports))
+                    .collect();
+                print_reclaimable(&reclaimable);
             } else if args.list {
                 println!("\n{:48} Balance", "Candy Machine ID");
                 println!("{:-<61}", "-");
 
                 for (pubkey, account) in accounts {
                     println!(
-                        "{:48} {:>12.8}",
+                        "{:48} {:>12}",
                         pubkey.to_string(),
-                        account.lamports as f64 / LAMPORTS_PER_SOL as f64
+                        format_sol(account.lamports)
                     );
                 }
 
@@ -145,19 +168,33 @@ pub fn process_withdraw(args: WithdrawArgs) -> Result<()> {
 
                 if let Some('Y') = s.chars().next() {
                     let pb = progress_bar_with_style(accounts.len() as u64);
+                    let retry_config = RetryConfig::default();
                     let mut not_drained = 0;
-
-                    accounts.iter().for_each(|account| {
-                        let (candy_machine, _account) = account;
-                        do_withdraw(program.clone(), *candy_machine, payer).unwrap_or_else(|e| {
-                            not_drained += 1;
-                            error!("Error: {}", e);
-                        });
+                    let mut reclaimed = 0;
+
+                    // the candy machines are drained in sequence, retrying the transient
+                    // errors of each withdraw
+                    for (candy_machine, account) in &accounts {
+                        match with_retry(&retry_config, "Withdraw", || {
+                            do_withdraw(program.clone(), *candy_machine, payer)
+                        }) {
+                            Ok(()) => reclaimed += account.lamports,
+                            Err(err) => {
+                                not_drained += 1;
+                                error!("Failed to drain candy machine {}: {}", candy_machine, err);
+                            }
+                        }
                         pb.inc(1);
-                    });
+                    }
 
                     pb.finish();
 
+                    println!(
+                        "Reclaimed ◎ {} from {} candy machine(s)",
+                        format_sol(reclaimed),
+                        accounts.len() - not_drained
+                    );
+
                     if not_drained > 0 {
                         println!(
                             "{}",
@@ -179,15 +216,89 @@ pub fn process_withdraw(args: WithdrawArgs) -> Result<()> {
     Ok(())
 }
 
+/// Return the candy machine accounts of the authority. Only the balance of the accounts
+/// is needed, so the account data is not requested to keep the response small.
+fn get_authority_candy_machines(
+    program: &Program,
+    authority: &Pubkey,
+) -> Result<Vec<(Pubkey, SolanaAccount)>> {
+    let config = RpcProgramAccountsConfig {
+        filters: Some(vec![RpcFilterType::Memcmp(Memcmp {
+            offset: 8, // key
+            bytes: MemcmpEncodedBytes::Base58(authority.to_string()),
+            encoding: None,
+        })]),
+        account_config: RpcAccountInfoConfig {
+            encoding: Some(UiAccountEncoding::Base64),
+            data_slice: Some(UiDataSliceConfig {
+                offset: 0,
+                length: 0,
+            }),
+            commitment: Some(program.timed_rpc().commitment()),
+        },
+        with_context: None,
+    };
+
+    // public RPCs often reject (or time out on) program account requests of large
+    // programs, which cannot be paginated
+    timed("Get candy machines", || {
+        program
+            .timed_rpc()
+            .get_program_accounts_with_config(&program.id(), config)
+    })
+    .map_err(|err| WithdrawError::ProgramAccountsFailed(err.to_string()).into())
+}
+
 fn setup_withdraw(keypair: Option<String>, rpc_url: Option<String>) -> Result<(Program, Pubkey)> {
     let sugar_config = sugar_setup(keypair, rpc_url)?;
-    let client = setup_client(&sugar_config)?;
+    let client = setup_client(&sugar_config, None)?;
     let program = client.program(CANDY_MACHINE_ID);
     let payer = program.payer();
 
     Ok((program, payer))
 }
 
+/// Check that the payer is the authority of the candy machine, returning the lamports
+/// held by the candy machine account (which are all transferred back on withdraw).
+fn check_withdraw(program: &Program, candy_machine: &Pubkey, payer: &Pubkey) -> Result<u64> {
+    let account = timed("Get candy machine", || {
+        program.timed_rpc().get_account(candy_machine)
+    })
+    .map_err(|err| WithdrawError::FailedToGetAccount(candy_machine.to_string(), err.to_string()))?;
+    let state: CandyMachine = CandyMachine::try_deserialize(&mut account.data.as_slice())?;
+
+    if state.authority != *payer {
+        return Err(WithdrawError::AuthorityMismatch(
+            candy_machine.to_string(),
+            state.authority.to_string(),
+            payer.to_string(),
+        )
+        .into());
+    }
+
+    Ok(account.lamports)
+}
+
+/// Print the lamports that would be reclaimed (no transaction is sent).
+fn print_reclaimable(accounts: &[(Pubkey, u64)]) {
+    println!("\n{:48} Reclaimable", "Candy Machine ID");
+    println!("{:-<61}", "-");
+
+    let mut total = 0;
+
+    for (pubkey, lamports) in accounts {
+        println!("{:48} {:>12}", pubkey.to_string(), format_sol(*lamports));
+        total += lamports;
+    }
+
+    println!("{:-<61}", "-");
+    println!("{:48} {:>12}", "Total", format_sol(total));
+    println!(
+        "\n{}",
+        style("Dry run: no transaction was sent.").bold().dim()
+    );
+}
+
 fn do_withdraw(program: Rc<Program>, candy_machine: Pubkey, payer: Pubkey) -> Result<()> {
     program
         .request()
@@ -196,7 +307,7 @@ fn do_withdraw(program: Rc<Program>, candy_machine: Pubkey, payer: Pubkey) -> Re
             authority: payer,
         })
         .args(nft_instruction::WithdrawFunds {})
-        .send()?;
+        .send_timed(&[])?;
 
     Ok(())
 }
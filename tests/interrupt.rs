@@ -0,0 +1,133 @@
+use std::{
+    collections::HashMap,
+    env, fs,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use sugar_cli::cache::Cache;
+use sugar_cli::config::ConfigData;
+use sugar_cli::upload::{upload_data, AssetPair, DataType, MockUploader};
+
+/// Number of assets of the test collection.
+const ASSETS: usize = 6;
+
+/// Number of concurrent uploads, so the collection is uploaded in batches.
+const BATCH_SIZE: usize = 2;
+
+/// Time each upload takes.
+const UPLOAD_TIME: Duration = Duration::from_millis(300);
+
+/// Return the assets of the collection, adding their items to the cache.
+fn test_assets(cache: &mut Cache) -> HashMap<usize, AssetPair> {
+    let mut assets = HashMap::new();
+
+    for index in 0..ASSETS {
+        let pair = AssetPair {
+            name: format!("Item #{}", index),
+            metadata: format!("assets/{}.json", index),
+            metadata_hash: format!("metadata-{}", index),
+            media: format!("assets/{}.png", index),
+            media_hash: format!("media-{}", index),
+        };
+        cache
+            .items
+            .0
+            .insert(index.to_string(), pair.clone().into_cache_item());
+        assets.insert(index, pair);
+    }
+
+    assets
+}
+
+/// Return an uploader where every upload takes `UPLOAD_TIME`.
+fn slow_uploader() -> MockUploader {
+    (0..ASSETS).fold(MockUploader::new(BATCH_SIZE), |uploader, index| {
+        uploader.with_delay(&index.to_string(), UPLOAD_TIME)
+    })
+}
+
+/// Return the indices of the items without a media link.
+fn pending_indices(cache: &Cache) -> Vec<usize> {
+    (0..ASSETS)
+        .filter(|index| cache.items.0[&index.to_string()].media_link.is_empty())
+        .collect()
+}
+
+#[tokio::test]
+async fn resume_upload_after_interrupt() {
+    let path = env::temp_dir().join(format!("sugar-interrupt-{}.json", std::process::id()));
+    let mut cache = Cache::new();
+    cache.file_path = path.to_string_lossy().to_string();
+    let assets = test_assets(&mut cache);
+    let indices: Vec<usize> = (0..ASSETS).collect();
+
+    // Ctrl+C is pressed while the first batch is being uploaded
+    let interrupted = Arc::new(AtomicBool::new(false));
+    let handler = interrupted.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(UPLOAD_TIME / 3).await;
+        handler.store(true, Ordering::SeqCst);
+    });
+
+    let uploader = Arc::new(slow_uploader());
+    let result = upload_data(
+        uploader.clone(),
+        &ConfigData::default(),
+        &assets,
+        &mut cache,
+        &indices,
+        DataType::Media,
+        interrupted,
+    )
+    .await;
+
+    // the uploads in flight complete, but no new batch is started
+    let error = result.expect_err("the upload was interrupted");
+    assert!(error.to_string().contains("re-run the upload to resume"));
+    assert_eq!(uploader.uploads().len(), BATCH_SIZE);
+
+    // the cache file is intact and has the links of the completed uploads
+    let saved = Cache::load(&cache.file_path, false).unwrap();
+    assert_eq!(saved.items.0.len(), ASSETS);
+    let pending = pending_indices(&saved);
+    assert_eq!(pending.len(), ASSETS - BATCH_SIZE);
+
+    for index in 0..ASSETS {
+        let item = &saved.items.0[&index.to_string()];
+        assert_eq!(item.media_hash, format!("media-{}", index));
+
+        if !pending.contains(&index) {
+            assert_eq!(item.media_link, MockUploader::link(&index.to_string()));
+        }
+    }
+
+    // a new run only uploads the remaining assets
+    let mut resumed = saved;
+    let uploader = Arc::new(slow_uploader());
+    let errors = upload_data(
+        uploader.clone(),
+        &ConfigData::default(),
+        &assets,
+        &mut resumed,
+        &pending,
+        DataType::Media,
+        Arc::new(AtomicBool::new(false)),
+    )
+    .await
+    .unwrap();
+    assert!(errors.is_empty());
+
+    let mut uploads = uploader.uploads();
+    uploads.sort();
+    let expected: Vec<String> = pending.iter().map(|index| index.to_string()).collect();
+    assert_eq!(uploads, expected);
+
+    let saved = Cache::load(&resumed.file_path, false).unwrap();
+    assert!(pending_indices(&saved).is_empty());
+
+    fs::remove_file(path).unwrap();
+}